@@ -1 +1,10 @@
-pub const PAGE_SIZE: usize = 4096;
\ No newline at end of file
+pub const PAGE_SIZE: usize = 4096;
+//MAPPING TABLE
+pub const MAPPING_TABLE_CAPACITY: usize = 1 << 16;
+pub const CONSOLIDATION_THRESHOLD: usize = 8;
+//LSS
+pub const FLUSH_BUFFER_SIZE: usize = 1 << 20;
+pub const ENTRY_TAG_SIZE: usize = 1;
+pub const PAGE_ID_SIZE: usize = 8;
+pub const PAGE_ENTRY_SIZE: usize = ENTRY_TAG_SIZE + PAGE_ID_SIZE + PAGE_SIZE;
+pub const COMMIT_ENTRY_SIZE: usize = ENTRY_TAG_SIZE + PAGE_ID_SIZE;
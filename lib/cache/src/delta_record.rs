@@ -0,0 +1,30 @@
+use super::buf_config::*;
+
+/// Byte range update prepended to a page chain, the page image is the base with all its deltas applied oldest first.
+#[derive(Debug, Clone)]
+pub struct DeltaRecord {
+    offset: usize,
+    data: Vec<u8>,
+}
+
+impl DeltaRecord {
+    pub fn new(offset: usize, data: &[u8]) -> Option<Self> {
+        if offset + data.len() > PAGE_SIZE {
+            None
+        } else {
+            Some(DeltaRecord{offset, data: data.to_vec()})
+        }
+    }
+
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn apply(&self, page: &mut [u8; PAGE_SIZE]) {
+        page[self.offset..self.offset + self.data.len()].copy_from_slice(&self.data);
+    }
+}
@@ -13,6 +13,7 @@ impl FileIo {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(false)
             .open(file)
             .expect("Cannot open file");
         FileIo {file: f}
@@ -27,26 +28,24 @@ impl FileIo {
         self.file.sync_all()?;
         Ok(())
     }
-    fn _read_at(&mut self, pos: u64 , mut data: &mut [u8]) -> std::io::Result<()> {
+    fn _read_at(&mut self, pos: u64 , data: &mut [u8]) -> std::io::Result<()> {
         self.file.seek(SeekFrom::Start(pos))?;
-        self.file.read_exact(&mut data)?;
+        self.file.read_exact(data)?;
         Ok(())
     }
 
     pub fn write_at(&mut self, pos: u64, data: &[u8]) {
-        match self._write_at(pos, data) {
-            Err(_msg) => {
-                error!("writing file");
-            },
-            _ => {}
+        if let Err(_msg) = self._write_at(pos, data) {
+            error!("writing file");
         }
     }
+    /// Same as `write_at` but gives the error to the caller instead of logging it.
+    pub fn try_write_at(&mut self, pos: u64, data: &[u8]) -> std::io::Result<()> {
+        self._write_at(pos, data)
+    }
     pub fn read_at(&mut self, pos: u64 , data: &mut [u8]) {
-        match self._read_at(pos, data) {
-            Err(msg) => {
-                error!("reading file {}", msg);
-            },
-            _ => {}
+        if let Err(msg) = self._read_at(pos, data) {
+            error!("reading file {}", msg);
         }
     }
     pub fn get_file_len(&self) -> u64 {
@@ -58,4 +57,10 @@ impl FileIo {
             Ok(md) => {md.len()}
         }
     }
+
+    pub fn truncate(&mut self, len: u64) {
+        if let Err(msg) = self.file.set_len(len) {
+            error!("truncating file {}", msg);
+        }
+    }
 }
\ No newline at end of file
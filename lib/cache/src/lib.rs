@@ -1,2 +1,9 @@
 #![allow(dead_code)]
-extern crate log;
\ No newline at end of file
+extern crate log;
+
+pub mod buf_config;
+pub mod delta_record;
+pub mod file_io;
+pub mod lss;
+pub mod page;
+pub mod ring_buffer;
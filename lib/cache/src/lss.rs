@@ -1,3 +1,379 @@
+use super::buf_config::*;
+use super::delta_record::DeltaRecord;
+use super::file_io::FileIo;
+use super::page::*;
+use super::ring_buffer::RingBuffer;
+use std::ptr;
+use std::sync::Mutex;
+
+const PAGE_ENTRY_TAG: u8 = 1;
+const COMMIT_TAG: u8 = 2;
+
+/// LLAMA cache: pages live in a latch-free mapping table as delta chains and are flushed
+/// as consolidated images to a log-structured file.
+/// A flush appends a batch of page entries followed by a commit entry, batches without
+/// a commit entry are ignored and truncated when the store is opened.
+/// Updates, reads, consolidation and eviction only need a shared borrow,
+/// flushing and garbage collection need an exclusive one. The log is never cleaned.
 pub struct Lss {
-    
-}
\ No newline at end of file
+    mapping_table: MappingTable,
+    file: Mutex<FileIo>,
+    flush_buffer: RingBuffer,
+    tail: LssAddress,
+    retired: RetiredList<PageNode>,
+}
+
+impl Lss {
+    pub fn new(file: &str) -> Self {
+        Lss::with_capacity(file, MAPPING_TABLE_CAPACITY, FLUSH_BUFFER_SIZE)
+    }
+
+    pub fn with_capacity(file: &str, nb_pages: usize, flush_buffer_size: usize) -> Self {
+        let mut lss = Lss{mapping_table: MappingTable::new(nb_pages), file: Mutex::new(FileIo::new(file)),
+            flush_buffer: RingBuffer::new(std::cmp::max(flush_buffer_size, PAGE_ENTRY_SIZE)), tail: 0, retired: RetiredList::new(free_chain)};
+        lss.recover();
+        lss
+    }
+
+    fn get_file_mut(&mut self) -> &mut FileIo {
+        match self.file.get_mut() {
+            Ok(file) => file,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn recover(&mut self) {
+        let file_len = self.get_file_mut().get_file_len();
+        let mut address = 0;
+        let mut pending = Vec::new();
+        let mut header = [0u8; COMMIT_ENTRY_SIZE];
+        while address + COMMIT_ENTRY_SIZE as u64 <= file_len {
+            self.get_file_mut().read_at(address, &mut header);
+            let value = u64_from_bytes(&header[ENTRY_TAG_SIZE..]);
+            if header[0] == PAGE_ENTRY_TAG && address + PAGE_ENTRY_SIZE as u64 <= file_len {
+                pending.push((value, address));
+                address += PAGE_ENTRY_SIZE as u64;
+            } else if header[0] == COMMIT_TAG && value == pending.len() as u64 {
+                address += COMMIT_ENTRY_SIZE as u64;
+                for (pid, entry_address) in pending.drain(..) {
+                    self.install_flushed(pid, entry_address);
+                }
+                self.tail = address;
+            } else {
+                break;
+            }
+        }
+        if self.tail < file_len {
+            let tail = self.tail;
+            self.get_file_mut().truncate(tail);
+        }
+    }
+
+    fn install_flushed(&mut self, pid: PageId, address: LssAddress) {
+        if let Some(head) = self.mapping_table.get(pid) {
+            let node = PageNode::Flushed{address}.into_ptr();
+            if self.mapping_table.compare_and_swap(pid, head, node) {
+                self.mapping_table.reserve(pid);
+                unsafe {
+                    free_chain(head);
+                }
+            } else {
+                unsafe {
+                    free_chain(node);
+                }
+            }
+        }
+    }
+
+    fn read_entry(&self, address: LssAddress) -> [u8; PAGE_SIZE] {
+        let mut data = [0u8; PAGE_SIZE];
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        file.read_at(address + (ENTRY_TAG_SIZE + PAGE_ID_SIZE) as u64, &mut data);
+        data
+    }
+
+    /// Rebuilds the page image from a chain, `head` must be a live chain.
+    fn materialize(&self, head: *mut PageNode) -> [u8; PAGE_SIZE] {
+        let mut deltas = Vec::new();
+        let mut node = head;
+        let mut page = loop {
+            if node.is_null() {
+                break [0u8; PAGE_SIZE];
+            }
+            match unsafe { &*node } {
+                PageNode::Base{data, ..} => break **data,
+                PageNode::Delta{record, next, ..} => {
+                    deltas.push(record);
+                    node = *next;
+                },
+                PageNode::Flushed{address} => break self.read_entry(*address),
+            }
+        };
+        for record in deltas.iter().rev() {
+            record.apply(&mut page);
+        }
+        page
+    }
+
+    pub fn allocate(&self) -> Option<PageId> {
+        self.mapping_table.allocate()
+    }
+
+    /// Number of pages the mapping table can hold.
+    pub fn get_capacity(&self) -> u64 {
+        self.mapping_table.capacity() as u64
+    }
+
+    pub fn get_pages_count(&self) -> u64 {
+        self.mapping_table.get_next_pid()
+    }
+
+    /// Returns None for out of range pages and pages that were never written.
+    /// A flushed page is loaded back in memory.
+    pub fn read(&self, pid: PageId) -> Option<[u8; PAGE_SIZE]> {
+        let head = self.mapping_table.get(pid)?;
+        if head.is_null() {
+            return None;
+        }
+        let page = self.materialize(head);
+        if let PageNode::Flushed{address} = unsafe { &*head } {
+            let node = PageNode::Base{data: Box::new(page), address: Some(*address)}.into_ptr();
+            if self.mapping_table.compare_and_swap(pid, head, node) {
+                self.retired.push(head);
+            } else {
+                unsafe {
+                    free_chain(node);
+                }
+            }
+        }
+        Some(page)
+    }
+
+    pub fn update(&self, pid: PageId, record: DeltaRecord) -> Option<()> {
+        let node = PageNode::Delta{record, next: ptr::null_mut(), chain_len: 0}.into_ptr();
+        loop {
+            let head = match self.mapping_table.get(pid) {
+                Some(head) => head,
+                None => {
+                    unsafe {
+                        free_chain(node);
+                    }
+                    return None;
+                }
+            };
+            let len = unsafe {
+                if let PageNode::Delta{ref mut next, chain_len: ref mut len, ..} = *node {
+                    *next = head;
+                    *len = chain_len(head) + 1;
+                }
+                chain_len(node)
+            };
+            if self.mapping_table.compare_and_swap(pid, head, node) {
+                self.mapping_table.reserve(pid);
+                self.mapping_table.set_dirty(pid);
+                if len >= CONSOLIDATION_THRESHOLD {
+                    self.consolidate(pid);
+                }
+                return Some(());
+            }
+        }
+    }
+
+    /// Saves a full page image as a delta record holding only the changed byte range.
+    pub fn write(&self, pid: PageId, data: &[u8; PAGE_SIZE]) -> Option<()> {
+        let current = self.read(pid).unwrap_or([0u8; PAGE_SIZE]);
+        let first = data.iter().zip(current.iter()).position(|(new, old)| new != old);
+        let last = data.iter().zip(current.iter()).rposition(|(new, old)| new != old);
+        match (first, last) {
+            (Some(first), Some(last)) => self.update(pid, DeltaRecord::new(first, &data[first..=last])?),
+            _ => self.mapping_table.get(pid).map(|_| ()),
+        }
+    }
+
+    /// Replaces the delta chain with a new base, gives up if another thread changed the page meanwhile.
+    pub fn consolidate(&self, pid: PageId) -> bool {
+        let head = match self.mapping_table.get(pid) {
+            Some(head) => head,
+            None => return false,
+        };
+        if unsafe { chain_len(head) } == 0 {
+            return true;
+        }
+        let node = PageNode::Base{data: Box::new(self.materialize(head)), address: None}.into_ptr();
+        self.install(pid, head, node)
+    }
+
+    /// Drops the in memory image of a clean page, it will be read back from the log.
+    pub fn evict(&self, pid: PageId) -> bool {
+        let head = match self.mapping_table.get(pid) {
+            Some(head) => head,
+            None => return false,
+        };
+        if head.is_null() {
+            return false;
+        }
+        match unsafe { &*head } {
+            PageNode::Base{address: Some(address), ..} => {
+                let node = PageNode::Flushed{address: *address}.into_ptr();
+                self.install(pid, head, node)
+            },
+            _ => false,
+        }
+    }
+
+    fn install(&self, pid: PageId, head: *mut PageNode, node: *mut PageNode) -> bool {
+        if self.mapping_table.compare_and_swap(pid, head, node) {
+            self.retired.push(head);
+            true
+        } else {
+            unsafe {
+                free_chain(node);
+            }
+            false
+        }
+    }
+
+    fn push_entry(&mut self, entry: &[u8]) -> std::io::Result<LssAddress> {
+        if !self.flush_buffer.push(entry) {
+            self.write_flush_buffer()?;
+            self.flush_buffer.push(entry);
+        }
+        Ok(self.tail + (self.flush_buffer.len() - entry.len()) as u64)
+    }
+
+    /// The tail only moves once the data is written, a failed write is overwritten by the next one.
+    fn write_flush_buffer(&mut self) -> std::io::Result<()> {
+        let data = self.flush_buffer.drain();
+        let tail = self.tail;
+        self.get_file_mut().try_write_at(tail, &data)?;
+        self.tail += data.len() as u64;
+        Ok(())
+    }
+
+    /// Appends the consolidated image of every dirty page to the log as one batch.
+    /// When the batch cannot be written its pages stay dirty so that the next flush writes them again.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        let mut flushed = Vec::new();
+        let mut entry = vec![0u8; PAGE_ENTRY_SIZE];
+        let mut result = Ok(());
+        for pid in 0..self.mapping_table.get_next_pid() {
+            if !self.mapping_table.take_dirty(pid) {
+                continue;
+            }
+            if let Some(head) = self.mapping_table.get(pid) {
+                let page = self.materialize(head);
+                entry[0] = PAGE_ENTRY_TAG;
+                entry[ENTRY_TAG_SIZE..ENTRY_TAG_SIZE + PAGE_ID_SIZE].copy_from_slice(&pid.to_be_bytes());
+                entry[ENTRY_TAG_SIZE + PAGE_ID_SIZE..].copy_from_slice(&page);
+                match self.push_entry(&entry) {
+                    Ok(address) => flushed.push((pid, head, page, address)),
+                    Err(e) => {
+                        self.mapping_table.set_dirty(pid);
+                        result = Err(e);
+                        break;
+                    },
+                }
+            }
+        }
+        if flushed.is_empty() {
+            return result;
+        }
+        let mut commit = [0u8; COMMIT_ENTRY_SIZE];
+        commit[0] = COMMIT_TAG;
+        commit[ENTRY_TAG_SIZE..].copy_from_slice(&(flushed.len() as u64).to_be_bytes());
+        if result.is_ok() {
+            result = self.push_entry(&commit).and_then(|_| self.write_flush_buffer());
+        }
+        if let Err(e) = result {
+            self.flush_buffer.drain();
+            for (pid, _, _, _) in flushed {
+                self.mapping_table.set_dirty(pid);
+            }
+            return Err(e);
+        }
+        for (pid, head, page, address) in flushed {
+            let node = PageNode::Base{data: Box::new(page), address: Some(address)}.into_ptr();
+            self.install(pid, head, node);
+        }
+        self.collect_garbage();
+        Ok(())
+    }
+
+    pub fn collect_garbage(&mut self) {
+        self.retired.collect();
+    }
+}
+
+fn u64_from_bytes(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; PAGE_ID_SIZE];
+    buf.copy_from_slice(&bytes[..PAGE_ID_SIZE]);
+    u64::from_be_bytes(buf)
+}
+
+#[cfg(test)]
+mod test_lss {
+    use super::*;
+
+    fn build_file_path_and_rm_old(file_name: &str) -> String {
+        let dir = std::env::temp_dir().join("test_lss");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(file_name);
+        if file.exists() {
+            std::fs::remove_file(&file).unwrap();
+        }
+        String::from(file.to_str().unwrap())
+    }
+
+    #[test]
+    fn test_deltas_and_consolidation() {
+        let file = build_file_path_and_rm_old("test_deltas_and_consolidation.db");
+        let lss = Lss::new(&file);
+        let pid = lss.allocate().unwrap();
+        assert!(lss.read(pid).is_none());
+        for count in 0..2 * CONSOLIDATION_THRESHOLD {
+            lss.update(pid, DeltaRecord::new(count, &[count as u8 + 1]).unwrap()).unwrap();
+            assert!(unsafe { chain_len(lss.mapping_table.get(pid).unwrap()) } < CONSOLIDATION_THRESHOLD);
+        }
+        let page = lss.read(pid).unwrap();
+        for count in 0..2 * CONSOLIDATION_THRESHOLD {
+            assert_eq!(page[count], count as u8 + 1);
+        }
+        assert!(DeltaRecord::new(PAGE_SIZE - 1, &[1, 2]).is_none());
+    }
+
+    #[test]
+    fn test_flush_and_recover() {
+        let file = build_file_path_and_rm_old("test_flush_and_recover.db");
+        {
+            let mut lss = Lss::with_capacity(&file, 16, PAGE_ENTRY_SIZE);
+            for count in 0..4u8 {
+                let pid = lss.allocate().unwrap();
+                lss.update(pid, DeltaRecord::new(10, &[count, count]).unwrap()).unwrap();
+            }
+            lss.flush().unwrap();
+            assert!(lss.evict(3));
+            assert_eq!(lss.read(3).unwrap()[10], 3);
+            lss.update(1, DeltaRecord::new(0, &[42]).unwrap()).unwrap();
+            lss.flush().unwrap();
+            lss.update(2, DeltaRecord::new(0, &[43]).unwrap()).unwrap();
+        }
+        let file_len = std::fs::metadata(&file).unwrap().len();
+        {
+            let mut io = FileIo::new(&file);
+            let mut entry = vec![0u8; PAGE_ENTRY_SIZE];
+            entry[0] = PAGE_ENTRY_TAG;
+            io.write_at(file_len, &entry);
+        }
+        let lss = Lss::new(&file);
+        assert_eq!(std::fs::metadata(&file).unwrap().len(), file_len);
+        assert_eq!(lss.get_pages_count(), 4);
+        let page = lss.read(1).unwrap();
+        assert_eq!(page[0], 42);
+        assert_eq!(page[10..12], [1, 1]);
+        assert_eq!(lss.read(2).unwrap()[0], 0);
+        assert_eq!(lss.read(3).unwrap()[11], 3);
+    }
+}
@@ -0,0 +1,166 @@
+use super::buf_config::*;
+use super::delta_record::DeltaRecord;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+
+pub type PageId = u64;
+pub type LssAddress = u64;
+
+/// A page state in the mapping table: a chain of deltas ending on an in memory base,
+/// on a stub pointing to the page image in the log-structured store or on nothing for a zeroed page.
+pub enum PageNode {
+    Base { data: Box<[u8; PAGE_SIZE]>, address: Option<LssAddress> },
+    Delta { record: DeltaRecord, next: *mut PageNode, chain_len: usize },
+    Flushed { address: LssAddress },
+}
+
+impl PageNode {
+    pub fn into_ptr(self) -> *mut PageNode {
+        Box::into_raw(Box::new(self))
+    }
+}
+
+/// Number of deltas on top of the chain.
+///
+/// # Safety
+/// `node` must be null or point to a live node.
+pub unsafe fn chain_len(node: *mut PageNode) -> usize {
+    if node.is_null() {
+        return 0;
+    }
+    match *node {
+        PageNode::Delta{chain_len, ..} => chain_len,
+        _ => 0,
+    }
+}
+
+/// Frees a node and every node below it.
+///
+/// # Safety
+/// The caller must be the only owner of the chain and no reader may still walk it.
+pub unsafe fn free_chain(mut node: *mut PageNode) {
+    while !node.is_null() {
+        let boxed = Box::from_raw(node);
+        node = match *boxed {
+            PageNode::Delta{next, ..} => next,
+            _ => ptr::null_mut(),
+        };
+    }
+}
+
+/// Maps logical page ids to their current state, every state change is a single compare and swap.
+pub struct MappingTable {
+    entries: Vec<AtomicPtr<PageNode>>,
+    dirty: Vec<AtomicBool>,
+    next_pid: AtomicU64,
+}
+
+impl MappingTable {
+    pub fn new(capacity: usize) -> Self {
+        MappingTable{entries: (0..capacity).map(|_| AtomicPtr::new(ptr::null_mut())).collect(),
+            dirty: (0..capacity).map(|_| AtomicBool::new(false)).collect(),
+            next_pid: AtomicU64::new(0)}
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, pid: PageId) -> Option<*mut PageNode> {
+        self.entries.get(pid as usize).map(|entry| entry.load(Ordering::Acquire))
+    }
+
+    pub fn compare_and_swap(&self, pid: PageId, current: *mut PageNode, new: *mut PageNode) -> bool {
+        self.entries.get(pid as usize)
+            .map(|entry| entry.compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire).is_ok())
+            .unwrap_or(false)
+    }
+
+    pub fn allocate(&self) -> Option<PageId> {
+        let pid = self.next_pid.fetch_add(1, Ordering::AcqRel);
+        if (pid as usize) < self.capacity() {
+            Some(pid)
+        } else {
+            self.next_pid.fetch_sub(1, Ordering::AcqRel);
+            None
+        }
+    }
+
+    /// Makes sure the next allocated id is greater than `pid`.
+    pub fn reserve(&self, pid: PageId) {
+        self.next_pid.fetch_max(pid + 1, Ordering::AcqRel);
+    }
+
+    pub fn get_next_pid(&self) -> PageId {
+        self.next_pid.load(Ordering::Acquire)
+    }
+
+    pub fn set_dirty(&self, pid: PageId) {
+        if let Some(flag) = self.dirty.get(pid as usize) {
+            flag.store(true, Ordering::Release);
+        }
+    }
+
+    pub fn take_dirty(&self, pid: PageId) -> bool {
+        self.dirty.get(pid as usize).map(|flag| flag.swap(false, Ordering::AcqRel)).unwrap_or(false)
+    }
+}
+
+impl Drop for MappingTable {
+    fn drop(&mut self) {
+        for entry in &self.entries {
+            unsafe {
+                free_chain(entry.swap(ptr::null_mut(), Ordering::AcqRel));
+            }
+        }
+    }
+}
+
+struct Retired<T> {
+    node: *mut T,
+    next: *mut Retired<T>,
+}
+
+/// Chains replaced in a mapping table, readers may still walk them so they are only freed
+/// when the table is exclusively borrowed.
+pub struct RetiredList<T> {
+    head: AtomicPtr<Retired<T>>,
+    free: unsafe fn(*mut T),
+}
+
+impl <T> RetiredList<T> {
+    /// `free` releases a retired chain.
+    pub fn new(free: unsafe fn(*mut T)) -> Self {
+        RetiredList{head: AtomicPtr::new(ptr::null_mut()), free}
+    }
+
+    pub fn push(&self, node: *mut T) {
+        let retired = Box::into_raw(Box::new(Retired{node, next: ptr::null_mut()}));
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            unsafe {
+                (*retired).next = head;
+            }
+            if self.head.compare_exchange(head, retired, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                break;
+            }
+        }
+    }
+
+    pub fn collect(&mut self) {
+        let mut curr = self.head.swap(ptr::null_mut(), Ordering::AcqRel);
+        while !curr.is_null() {
+            let retired = unsafe { Box::from_raw(curr) };
+            unsafe {
+                (self.free)(retired.node);
+            }
+            curr = retired.next;
+        }
+    }
+}
+
+impl <T> Drop for RetiredList<T> {
+    fn drop(&mut self) {
+        self.collect();
+    }
+}
@@ -0,0 +1,80 @@
+use std::cmp::min;
+
+/// Fixed capacity byte ring, bytes are popped in the order they were pushed.
+pub struct RingBuffer {
+    data: Vec<u8>,
+    head: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer{data: vec![0u8; std::cmp::max(capacity, 1)], head: 0, len: 0}
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn free_space(&self) -> usize {
+        self.capacity() - self.len
+    }
+
+    /// Pushes all the bytes or none of them when they do not fit.
+    pub fn push(&mut self, bytes: &[u8]) -> bool {
+        if bytes.len() > self.free_space() {
+            return false;
+        }
+        let capacity = self.capacity();
+        let tail = (self.head + self.len) % capacity;
+        let first = min(bytes.len(), capacity - tail);
+        self.data[tail..tail + first].copy_from_slice(&bytes[..first]);
+        self.data[..bytes.len() - first].copy_from_slice(&bytes[first..]);
+        self.len += bytes.len();
+        true
+    }
+
+    pub fn pop(&mut self, out: &mut [u8]) -> usize {
+        let capacity = self.capacity();
+        let count = min(out.len(), self.len);
+        let first = min(count, capacity - self.head);
+        out[..first].copy_from_slice(&self.data[self.head..self.head + first]);
+        out[first..count].copy_from_slice(&self.data[..count - first]);
+        self.head = (self.head + count) % capacity;
+        self.len -= count;
+        count
+    }
+
+    pub fn drain(&mut self) -> Vec<u8> {
+        let mut res = vec![0u8; self.len];
+        self.pop(&mut res);
+        res
+    }
+}
+
+#[cfg(test)]
+mod test_ring_buffer {
+    use super::*;
+
+    #[test]
+    fn test_wrap_around() {
+        let mut rb = RingBuffer::new(8);
+        assert!(rb.push(&[1, 2, 3, 4, 5]));
+        let mut out = [0u8; 3];
+        assert_eq!(rb.pop(&mut out), 3);
+        assert_eq!(out, [1, 2, 3]);
+        assert!(rb.push(&[6, 7, 8, 9, 10]));
+        assert!(!rb.push(&[11, 12]));
+        assert_eq!(rb.len(), 7);
+        assert_eq!(rb.drain(), vec![4, 5, 6, 7, 8, 9, 10]);
+        assert!(rb.is_empty());
+    }
+}
@@ -1,6 +1,21 @@
 //PAGING
-pub const PAGE_SIZE: usize = 4096;
+/// Page size of the files created when the database settings do not choose one.
+pub const DEFAULT_PAGE_SIZE: usize = 4096;
+/// Page sizes are powers of two in this range, the smallest one still holds a B+Tree node.
+pub const MIN_PAGE_SIZE: usize = 4096;
+pub const MAX_PAGE_SIZE: usize = 65536;
+pub const PAGE_CHECKSUM_SIZE: usize = 4;
 pub const PAGE_COUNTER_SIZE: usize = 8;
+pub const PAGE_SIZE_FIELD_SIZE: usize = 4;
+pub const PAGER_HEADER_SIZE: usize = PAGE_CHECKSUM_SIZE + PAGE_COUNTER_SIZE + PAGE_SIZE_FIELD_SIZE + FORMAT_MAGIC_SIZE + FORMAT_VERSION_SIZE;
+//FORMAT
+/// Marks the header page of the files written since the format is versioned.
+pub const FORMAT_MAGIC: [u8; FORMAT_MAGIC_SIZE] = *b"OGDB";
+pub const FORMAT_MAGIC_SIZE: usize = 4;
+pub const FORMAT_VERSION_SIZE: usize = 4;
+/// Version 0 has 4096 bytes pages without checksum nor header magic, property keys stored with the properties
+/// and unversioned node and relationship records, version 1 is the current layout.
+pub const FORMAT_VERSION: u32 = 1;
 pub const FIRST_FREE_PAGE_PTR: usize = 8;
 //RECORDS
 pub const RECORDS_COUNTER_SIZE: usize = 8;
@@ -9,53 +24,46 @@ pub const FREE_LIST_ITEM_COUNTER_SIZE: usize = 4;
 pub const NEXT_PAGE_PTR: usize = 8;
 pub const NEXT_FREE_PAGE_PTR: usize = 8;
 pub const HEADER_FLAGS: usize = 1;
-pub const HEADER_SIZE: usize = 21;
+pub const HEADER_SIZE: usize = PAGE_CHECKSUM_SIZE + HEADER_FLAGS + NEXT_FREE_PAGE_PTR + NEXT_PAGE_PTR + FREE_LIST_ITEM_COUNTER_SIZE;
+pub const LAYOUT_FIELD_SIZE: usize = 4;
+//VERSIONS
+pub const VERSION_SIZE: usize = 8;
+pub const VERSION_PTR_SIZE: usize = 8;
 //BTREE
-//PAGE PAYLOAD SIZE 4071 BYTES
-//UNUSED SPACE 226 BYTES
+/// Only the page size is chosen by the settings. The B+Tree cells, the property blocks and the dyn payloads
+/// are sized by the format version, a file written with other sizes has another record size in its header
+/// and is rejected as incompatible when opened.
 pub const NB_CELL: usize = 66;
 pub const NODE_PTR_SIZE: usize = 8;
 pub const KEY_SIZE: usize = 45;
 pub const CELL_HEADER_SIZE: usize = 1;
 pub const FREE_CELLS_NEXT_NODE_PTR_SIZE: usize = 8;
-pub const CELL_SIZE: usize = 58;
-pub const BTREE_NODE_RECORD_SIZE: usize = 3845;
 pub const OVERFLOW_CELL_PTR_SIZE: usize = 4;
+pub const CELL_SIZE: usize = KEY_SIZE + NODE_PTR_SIZE + CELL_HEADER_SIZE + OVERFLOW_CELL_PTR_SIZE;
 pub const BTREE_NODE_HEADER_SIZE: usize = 1;
-pub const BTREE_NB_RECORDS_PER_PAGE: usize = 1;
-pub const BTREE_NB_PAGES_PER_RECORD: usize = 0;
+pub const BTREE_NODE_RECORD_SIZE: usize = BTREE_NODE_HEADER_SIZE + CELL_SIZE * NB_CELL + NODE_PTR_SIZE + FREE_CELLS_NEXT_NODE_PTR_SIZE;
 //NODES
-//PAGE PAYLOAD SIZE 3495 BYTES
-//UNUSED SPACE 15 BYTES
 pub const NODE_HEADER_SIZE: usize = 1;
 pub const NODE_ID_SIZE: usize = 8;
-pub const NODE_RECORD_SIZE: usize = 24;
-pub const NODE_NB_RECORDS_PER_PAGE: usize = 145;
-pub const NODE_NB_PAGES_PER_RECORD: usize = 0;
+pub const LABELS_ID_SIZE: usize = 8;
+pub const NODE_RECORD_SIZE: usize = 2 * RELATIONSHIP_ID_SIZE + PROPERTY_ID_SIZE + LABELS_ID_SIZE + VERSION_SIZE + VERSION_PTR_SIZE;
 //RELATIONSHIPS
-//PAGE PAYLOAD SIZE 3763 BYTES
-//UNUSED SPACE 19 BYTES
 pub const RELATIONSHIP_HEADER_SIZE: usize = 1;
 pub const RELATIONSHIP_ID_SIZE: usize = 8;
-pub const RELATIONSHIP_RECORD_SIZE: usize = 48;
-pub const RELATIONSHIP_NB_RECORDS_PER_PAGE: usize = 78;
-pub const RELATIONSHIP_NB_PAGES_PER_RECORD: usize = 0;
 pub const RELATIONSHIP_TYPE_SIZE: usize = 8;
+pub const RELATIONSHIP_RECORD_SIZE: usize = 2 * NODE_ID_SIZE + RELATIONSHIP_TYPE_SIZE + 2 * RELATIONSHIP_ID_SIZE + PROPERTY_ID_SIZE + VERSION_SIZE + VERSION_PTR_SIZE;
 //PROPERTIES
-//PAGE PAYLOAD SIZE 3723 BYTES
-//UNUSED SPACE 3 BYTES
 pub const PROPERTY_HEADER_SIZE: usize = 1;
 pub const PROPERTY_ID_SIZE: usize = 8;
+pub const PROPERTY_TYPE_SIZE: usize = 1;
+pub const PROPERTY_KEY_ID_SIZE: usize = 8;
 pub const PROPERTY_BLOCK_SIZE: usize = 24;
-pub const PROPERTY_RECORD_SIZE: usize = 42;
-pub const PROPERTY_NB_RECORDS_PER_PAGE: usize = 88;
-pub const PROPERTY_NB_PAGES_PER_RECORD: usize = 0;
+pub const PROPERTY_RECORD_SIZE: usize = PROPERTY_HEADER_SIZE + PROPERTY_KEY_ID_SIZE + PROPERTY_TYPE_SIZE + PROPERTY_BLOCK_SIZE + PROPERTY_ID_SIZE;
 //DYN STORE
-//PAGE PAYLOAD SIZE 3955 BYTES
-//UNUSED SPACE 85 BYTES
 pub const DYN_HEADER_SIZE: usize = 1;
 pub const DYN_ID_SIZE: usize = 8;
 pub const DYN_PAYLOAD_SIZE: usize = 120;
-pub const DYN_RECORD_SIZE: usize = 129;
-pub const DYN_NB_RECORDS_PER_PAGE: usize = 30;
-pub const DYN_NB_PAGES_PER_RECORD: usize = 0;
+pub const DYN_RECORD_SIZE: usize = DYN_HEADER_SIZE + DYN_ID_SIZE + DYN_PAYLOAD_SIZE;
+//TOKENS
+pub const TOKEN_ID_SIZE: usize = 8;
+pub const TOKEN_RECORD_SIZE: usize = DYN_ID_SIZE;
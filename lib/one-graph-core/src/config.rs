@@ -1,5 +1,34 @@
 pub const NODES_FILE_NAME: &str = "nodes.db";
 pub const RELATIONSHIPS_FILE_NAME: &str = "relationships.db";
 pub const PROPERTIES_FILE_NAME: &str = "properties.db";
+pub const NODES_HISTORY_FILE_NAME: &str = "nodes-history.db";
+pub const RELATIONSHIPS_HISTORY_FILE_NAME: &str = "relationships-history.db";
 pub const DYN_FILE_NAME: &str = "dyn.db";
-pub const NODES_LABELS_INDEX_FILE_NAME: &str = "nodes-index.db";
\ No newline at end of file
+pub const NODES_LABELS_INDEX_FILE_NAME: &str = "nodes-index.db";
+pub const NODES_LABELS_BW_INDEX_FILE_NAME: &str = "nodes-index.bw.db";
+pub const LABELS_FILE_NAME: &str = "labels.db";
+pub const LABELS_NAMES_FILE_NAME: &str = "labels-names.db";
+pub const NODES_LABELS_FILE_NAME: &str = "nodes-labels.db";
+pub const RELATIONSHIP_TYPES_FILE_NAME: &str = "relationship-types.db";
+pub const RELATIONSHIP_TYPES_NAMES_FILE_NAME: &str = "relationship-types-names.db";
+pub const PROPERTY_KEYS_FILE_NAME: &str = "property-keys.db";
+pub const PROPERTY_KEYS_NAMES_FILE_NAME: &str = "property-keys-names.db";
+pub const PROPERTY_INDEXES_FILE_NAME: &str = "property-indexes.db";
+pub const PROPERTY_INDEXES_NAMES_FILE_NAME: &str = "property-indexes-names.db";
+pub const PROPERTY_INDEX_FILE_PREFIX: &str = "property-index-";
+pub const CONSTRAINTS_FILE_NAME: &str = "constraints.db";
+pub const CONSTRAINTS_NAMES_FILE_NAME: &str = "constraints-names.db";
+pub const WAL_FILE_NAME: &str = "wal.db";
+/// Directory of the database where a migration writes the new files before they replace the old ones.
+pub const MIGRATION_DIR_NAME: &str = "migration.tmp";
+
+/// Default number of pages each store keeps in its page cache.
+pub const PAGE_CACHE_CAPACITY: usize = 1024;
+
+pub const DEFAULT_DATA_DIR: &str = "data";
+pub const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:8182";
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+/// Seconds the server waits for the open connections to close when it shuts down.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: usize = 10;
+/// Prefix of the environment variables overriding the settings of the configuration file.
+pub const CONFIG_ENV_PREFIX: &str = "OG_";
@@ -0,0 +1,55 @@
+use std::fmt;
+use super::model::constraint::ConstraintError;
+use super::graph_engine::transaction::TransactionError;
+
+/// Failure of a graph operation, carried from the database files up to the query front-ends.
+#[derive(Debug, Clone)]
+pub enum GraphError {
+    /// A database file could not be read or written, with the cause reported by the system.
+    Io(String),
+    /// A page of the file, named with its id, failed its checksum.
+    Corrupted(String, u64),
+    /// The file, named first, has a page or record geometry this build cannot use, with the reason.
+    Incompatible(String, String),
+    /// A record links to a record that is missing or cannot be decoded, `og check` reports the details.
+    Inconsistent(String),
+    NodeNotFound(u64),
+    RelationshipNotFound(u64),
+    /// The node still has relationships, it can only be deleted with them.
+    NodeHasRelationships(u64),
+    Constraint(ConstraintError),
+    Transaction(TransactionError),
+    /// The query or request cannot be parsed or uses an unsupported feature.
+    InvalidRequest(String),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::Io(cause) => write!(f, "i/o error: {}", cause),
+            GraphError::Corrupted(file, page_id) => write!(f, "page {} of {} is corrupted", page_id, file),
+            GraphError::Incompatible(file, reason) => write!(f, "{} cannot be opened: {}", file, reason),
+            GraphError::Inconsistent(cause) => write!(f, "inconsistent database: {}", cause),
+            GraphError::NodeNotFound(node_id) => write!(f, "node {} not found", node_id),
+            GraphError::RelationshipNotFound(rel_id) => write!(f, "relationship {} not found", rel_id),
+            GraphError::NodeHasRelationships(node_id) => write!(f, "node {} still has relationships", node_id),
+            GraphError::Constraint(e) => write!(f, "{}", e),
+            GraphError::Transaction(e) => write!(f, "transaction error: {:?}", e),
+            GraphError::InvalidRequest(cause) => write!(f, "invalid request: {}", cause),
+        }
+    }
+}
+
+impl From<ConstraintError> for GraphError {
+    fn from(error: ConstraintError) -> Self {
+        GraphError::Constraint(error)
+    }
+}
+
+impl From<TransactionError> for GraphError {
+    fn from(error: TransactionError) -> Self {
+        GraphError::Transaction(error)
+    }
+}
+
+pub type GraphResult<T> = std::result::Result<T, GraphError>;
@@ -1,78 +1,212 @@
 mod model;
+pub mod transaction;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 use crate::graph::EdgeIndex;
 use crate::graph::NodeIndex;
 
 use super::model::*;
+use super::model::constraint::*;
+use super::model::stats::GraphStats;
+use super::error::*;
 use super::repository::graph_repository::GraphRepository;
+use super::repository::checker::ConsistencyChecker;
+use super::repository::migration::{copy_dir, migrate_dir};
 use self::model::*;
+use self::transaction::*;
 use super::matcher::vf2::sub_graph_isomorphism;
 use super::graph::traits::*;
 
 pub struct GraphEngine {
     repository: Rc<RefCell<GraphRepository>>,
+    active_transaction: Option<TransactionId>,
+    transactions_counter: TransactionId,
 }
 
 fn compare_relationships(r0: &Relationship, r1: &Relationship) -> bool {
-    let mut res = true;
-    for p0 in r0.get_properties_ref() {
-        if !r1.get_properties_ref().contains(p0) {
-            res = false;
-            break;
-        }
+    if r0.get_labels_ref().iter().any(|l| !r1.get_labels_ref().contains(l)) {
+        return false;
     }
-    res
+    contains_properties(r0.get_properties_ref(), r1.get_properties_ref())
+}
+
+fn contains_properties(pattern_props: &[Property], props: &[Property]) -> bool {
+    pattern_props.iter().all(|p0| props.iter().any(|p1| p1.get_name() == p0.get_name() && p1.get_value() == p0.get_value()))
+}
+
+fn unmatched_node() -> GraphError {
+    GraphError::Inconsistent(String::from("a matched node has no id"))
+}
+
+fn properties_in_ranges(ranges: &[PropertyRange], props: &[Property]) -> bool {
+    ranges.iter().all(|range| props.iter().any(|p| p.get_name().as_deref() == Some(range.get_name()) && p.get_value().as_ref().map(|v| range.contains(v)).unwrap_or(false)))
 }
 
 impl GraphEngine {
-    pub fn new(ctx: &init::InitContext) -> Self {
-        GraphEngine{repository: Rc::new(RefCell::new(GraphRepository::new(ctx)))}
+    /// Opens the database, fails when its write ahead log cannot be recovered.
+    pub fn new(ctx: &init::InitContext) -> GraphResult<Self> {
+        let repository = GraphRepository::new(ctx)?;
+        Ok(GraphEngine{repository: Rc::new(RefCell::new(repository)), active_transaction: None, transactions_counter: 0})
+    }
+
+    pub fn begin(&mut self) -> TransactionResult<Transaction> {
+        if self.active_transaction.is_some() {
+            return Err(TransactionError::AlreadyStarted);
+        }
+        self.transactions_counter += 1;
+        self.active_transaction = Some(self.transactions_counter);
+        Ok(Transaction::new(self.transactions_counter))
     }
 
-    pub fn create_graph(&mut self, graph: &PropertyGraph) -> Option<PropertyGraph> {
-        self.repository.borrow_mut().create_graph(graph)
+    fn end_transaction(&mut self, tx: &Transaction) -> TransactionResult<()> {
+        match self.active_transaction {
+            None => Err(TransactionError::NotStarted),
+            Some(id) if id != tx.get_id() => Err(TransactionError::InvalidTransaction),
+            _ => {
+                self.active_transaction = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Ends the transaction and makes its changes durable, fails when they cannot be logged.
+    /// The changes of a failed commit are dropped, the next transaction starts from the committed state.
+    pub fn commit(&mut self, tx: Transaction) -> GraphResult<()> {
+        self.end_transaction(&tx)?;
+        let mut repository = self.repository.borrow_mut();
+        let synced = repository.sync();
+        if synced.is_err() {
+            repository.rollback();
+        }
+        synced
     }
 
-    pub fn create_node(&mut self, node: &Node) -> Option<Node> {
-        self.repository.borrow_mut().create_node(node)
+    pub fn rollback(&mut self, tx: Transaction) -> TransactionResult<()> {
+        self.end_transaction(&tx)?;
+        self.repository.borrow_mut().rollback();
+        Ok(())
+    }
+
+    pub fn get_last_version(&mut self) -> u64 {
+        self.repository.borrow_mut().get_last_version()
+    }
+
+    pub fn is_in_transaction(&self) -> bool {
+        self.active_transaction.is_some()
+    }
+
+    pub fn commit_or_rollback<T>(&mut self, tx: Transaction, res: GraphResult<T>) -> GraphResult<T> {
+        match res {
+            Ok(value) => {
+                self.commit(tx)?;
+                Ok(value)
+            },
+            Err(e) => {
+                self.rollback(tx)?;
+                Err(e)
+            },
+        }
+    }
+
+    /// Runs the write in the active transaction, or in its own transaction committed when the write succeeds.
+    fn write<T, F>(&mut self, f: F) -> GraphResult<T> where F: FnOnce(&mut GraphRepository) -> GraphResult<T> {
+        if self.is_in_transaction() {
+            return f(&mut self.repository.borrow_mut());
+        }
+        let tx = self.begin()?;
+        let res = f(&mut self.repository.borrow_mut());
+        self.commit_or_rollback(tx, res)
+    }
+
+    pub fn create_graph(&mut self, graph: &PropertyGraph) -> GraphResult<PropertyGraph> {
+        self.write(|repository| repository.create_graph(graph))
+    }
+
+    pub fn create_node(&mut self, node: &Node) -> GraphResult<Node> {
+        self.write(|repository| repository.create_node(node))
     }
     
-    pub fn create_relationship(&mut self, rel: &Relationship, source_id: u64, target_id: u64) -> Option<Relationship> {
-        self.repository.borrow_mut().create_relationship(rel, source_id, target_id)
+    pub fn create_relationship(&mut self, rel: &Relationship, source_id: u64, target_id: u64) -> GraphResult<Relationship> {
+        self.write(|repository| repository.create_relationship(rel, source_id, target_id))
+    }
+
+    pub fn delete_node(&mut self, node_id: u64) -> GraphResult<()> {
+        self.write(|repository| repository.delete_node(node_id))
+    }
+
+    pub fn detach_delete_node(&mut self, node_id: u64) -> GraphResult<()> {
+        self.write(|repository| repository.detach_delete_node(node_id))
+    }
+
+    pub fn delete_relationship(&mut self, rel_id: u64) -> GraphResult<()> {
+        self.write(|repository| repository.delete_relationship(rel_id))
+    }
+
+    pub fn set_node_property(&mut self, node_id: u64, prop: &Property) -> GraphResult<()> {
+        self.write(|repository| repository.set_node_property(node_id, prop))
+    }
+
+    pub fn remove_node_property(&mut self, node_id: u64, name: &str) -> GraphResult<bool> {
+        self.write(|repository| repository.remove_node_property(node_id, name))
     }
 
-    pub fn match_pattern(&mut self, pattern: &PropertyGraph) -> Option<Vec<PropertyGraph>> {
-        let mut graph_proxy = GraphProxy::new(self.repository.clone(), pattern);
+    pub fn replace_node_properties(&mut self, node_id: u64, props: &[Property]) -> GraphResult<()> {
+        self.write(|repository| repository.replace_node_properties(node_id, props))
+    }
+
+    pub fn set_relationship_property(&mut self, rel_id: u64, prop: &Property) -> GraphResult<()> {
+        self.write(|repository| repository.set_relationship_property(rel_id, prop))
+    }
+
+    pub fn remove_relationship_property(&mut self, rel_id: u64, name: &str) -> GraphResult<bool> {
+        self.write(|repository| repository.remove_relationship_property(rel_id, name))
+    }
+
+    pub fn replace_relationship_properties(&mut self, rel_id: u64, props: &[Property]) -> GraphResult<()> {
+        self.write(|repository| repository.replace_relationship_properties(rel_id, props))
+    }
+
+    pub fn create_node_property_index(&mut self, label: &str, property: &str) -> GraphResult<()> {
+        self.write(|repository| repository.create_node_property_index(label, property))
+    }
+
+    pub fn create_relationship_property_index(&mut self, rel_type: &str, property: &str) -> GraphResult<()> {
+        self.write(|repository| repository.create_relationship_property_index(rel_type, property))
+    }
+
+    /// Returns false when the constraint already existed.
+    pub fn create_constraint(&mut self, constraint: &Constraint) -> GraphResult<bool> {
+        self.write(|repository| repository.create_constraint(constraint))
+    }
+
+    pub fn list_constraints(&mut self) -> GraphResult<Vec<Constraint>> {
+        self.repository.borrow_mut().list_constraints()
+    }
+
+    pub fn get_stats(&mut self) -> GraphResult<GraphStats> {
+        self.repository.borrow_mut().get_stats()
+    }
+
+    pub fn match_pattern(&mut self, pattern: &PropertyGraph, as_of: Option<u64>) -> GraphResult<Vec<PropertyGraph>> {
+        let mut graph_proxy = GraphProxy::new(self.repository.clone(), pattern, as_of)?;
         let mut res = Vec::new();
         sub_graph_isomorphism(pattern, &mut graph_proxy, 
         |n0, n1| {
-            if n0.get_id() == n1.get_id() {
-                return true;
+            if n0.get_id().is_some() {
+                return n0.get_id() == n1.get_id();
             }
-            let mut res = true;
-            for p0 in n0.get_properties_ref() {
-                if !n1.get_properties_ref().contains(p0) {
-                    res = false;
-                    break;
-                }
+            if n0.get_labels_ref().iter().any(|l| !n1.get_labels_ref().contains(l)) {
+                return false;
             }
-            res
+            contains_properties(n0.get_properties_ref(), n1.get_properties_ref()) && properties_in_ranges(n0.get_property_ranges_ref(), n1.get_properties_ref())
         },
         |e0, e1| {
-            if e0.get_id() == e1.get_id() {
-                return true;
+            if e0.get_id().is_some() {
+                return e0.get_id() == e1.get_id();
             }
-            let mut res = true;
-            for p0 in e0.get_properties_ref() {
-                if !e1.get_properties_ref().contains(p0) {
-                    res = false;
-                    break;
-                }
-            }
-            res
+            compare_relationships(e0, e1)
         },
         |map0, _map1, gpattern, proxy| {
             let mut res_match = PropertyGraph::new();
@@ -103,10 +237,22 @@ impl GraphEngine {
             res.push(res_match);
             Some(true)
         });
-        Some(res)
+        match graph_proxy.take_error() {
+            Some(e) => Err(e),
+            None => Ok(res),
+        }
+    }
+
+    pub fn match_pattern_and_create(&mut self, pattern: &PropertyGraph) -> GraphResult<Vec<PropertyGraph>> {
+        if self.is_in_transaction() {
+            return self.match_and_create(pattern);
+        }
+        let tx = self.begin()?;
+        let res = self.match_and_create(pattern);
+        self.commit_or_rollback(tx, res)
     }
 
-    pub fn match_pattern_and_create(&mut self, pattern: &PropertyGraph) -> Option<Vec<PropertyGraph>> {
+    fn match_and_create(&mut self, pattern: &PropertyGraph) -> GraphResult<Vec<PropertyGraph>> {
         let mut match_pattern = PropertyGraph::new();
         let mut map_nodes_ids = HashMap::new();
         for nid in pattern.get_nodes_ids() {
@@ -124,41 +270,516 @@ impl GraphEngine {
             }
         }
 
-        let mut res = self.match_pattern(&match_pattern)?;
+        let mut res = self.match_pattern(&match_pattern, None)?;
 
         for matched_graph in &mut res {
             for re in pattern.get_relationships_and_edges() {
                 if *re.0.get_status() == Status::Create {
                     let source_index = map_nodes_ids[&re.1.source];
                     let target_index = map_nodes_ids[&re.1.target];
-                    let source = matched_graph.get_node_ref(&source_index).get_id()?;
-                    let target = matched_graph.get_node_ref(&target_index).get_id()?;
+                    let source = matched_graph.get_node_ref(&source_index).get_id().ok_or_else(unmatched_node)?;
+                    let target = matched_graph.get_node_ref(&target_index).get_id().ok_or_else(unmatched_node)?;
                     let res = self.create_relationship(re.0, source, target)?;
                     matched_graph.add_relationship(res, source_index, target_index);
                 }
             }
         }
-        Some(res)
+        Ok(res)
     }
 
+    pub fn node_history(&mut self, node_id: u64, from: Option<u64>, to: Option<u64>) -> GraphResult<Vec<NodeVersion>> {
+        self.repository.borrow_mut().node_history(node_id, from, to)
+    }
+
+    pub fn relationship_history(&mut self, rel_id: u64, from: Option<u64>, to: Option<u64>) -> GraphResult<Vec<RelationshipVersion>> {
+        self.repository.borrow_mut().relationship_history(rel_id, from, to)
+    }
+
+    /// Frees the elements deleted at or before the version, their history included.
+    pub fn compact(&mut self, version: u64) -> GraphResult<usize> {
+        self.write(|repository| repository.compact(version))
+    }
 
     pub fn retrieve_graph() {
 
     }
 
-    pub fn sync(&mut self) {
-        self.repository.borrow_mut().sync();
+    /// Flushes the committed changes, fails while a transaction is open as it would commit part of it.
+    pub fn sync(&mut self) -> GraphResult<()> {
+        if self.is_in_transaction() {
+            return Err(TransactionError::AlreadyStarted.into());
+        }
+        self.repository.borrow_mut().sync()
     }
 }
 
+/// Checks the stores of a database that is not opened by an engine, `repair` fixes what can be fixed in place.
+pub fn check_database(ctx: &init::InitContext, repair: bool) -> GraphResult<check::CheckReport> {
+    Ok(ConsistencyChecker::new(ctx).check(repair)?)
+}
+
+/// Upgrades the store files of a database that is not opened by an engine to the current format version,
+/// in place or in a copy made in `target_dir` which leaves the database untouched.
+pub fn migrate_database(ctx: &init::InitContext, target_dir: Option<&str>) -> GraphResult<migration::MigrationReport> {
+    match target_dir {
+        Some(target_dir) => {
+            copy_dir(ctx.get_db_dir(), target_dir)?;
+            let mut target_ctx = ctx.clone();
+            target_ctx.set_db_dir(target_dir);
+            migrate_dir(&target_ctx)
+        },
+        None => migrate_dir(ctx),
+    }
+}
+
+fn to_properties_map(props: &[Property]) -> BTreeMap<String, PropertyValue> {
+    props.iter().filter_map(|p| Some((p.get_name().clone()?, p.get_value().clone()?))).collect()
+}
+
+fn from_properties_map(props: &BTreeMap<String, PropertyValue>) -> Vec<Property> {
+    props.iter().map(|(name, value)| {
+        let mut prop = Property::new();
+        prop.set_name(name);
+        prop.set_value(Some(value.clone()));
+        prop
+    }).collect()
+}
+
+/// Reads every node and relationship of a database that is not opened by an engine.
+pub fn export_database(ctx: &init::InitContext) -> GraphResult<dump::GraphDump> {
+    let mut repository = GraphRepository::new(ctx)?;
+    let mut node_ids: Vec<u64> = repository.fetch_all_nodes_ids(None)?.into_iter().collect();
+    node_ids.sort_unstable();
+    let mut graph_dump = dump::GraphDump::default();
+    for node_id in node_ids {
+        let (node, _) = repository.retrieve_node_by_id(node_id, None)?;
+        graph_dump.nodes.push(dump::NodeDump{id: node_id, labels: node.get_labels_ref().clone(), properties: to_properties_map(node.get_properties_ref())});
+        for rel_id in repository.fetch_outbound_relationships_ids(node_id, None)? {
+            let (rel, edge) = repository.retrieve_relationship_by_id(rel_id, None)?;
+            graph_dump.relationships.push(dump::RelationshipDump{id: rel_id, labels: rel.get_labels_ref().clone(), source: edge.source, target: edge.target,
+                properties: to_properties_map(rel.get_properties_ref())});
+        }
+    }
+    graph_dump.relationships.sort_by_key(|rel| rel.id);
+    Ok(graph_dump)
+}
+
+/// Creates the nodes and relationships of the dump in a single transaction, they get new ids.
+pub fn import_database(ctx: &init::InitContext, graph_dump: &dump::GraphDump) -> GraphResult<()> {
+    let mut engine = GraphEngine::new(ctx)?;
+    let tx = engine.begin()?;
+    let imported = import_graph(&mut engine, graph_dump);
+    engine.commit_or_rollback(tx, imported)
+}
+
+fn import_graph(engine: &mut GraphEngine, graph_dump: &dump::GraphDump) -> GraphResult<()> {
+    let mut node_ids = HashMap::new();
+    for node_dump in &graph_dump.nodes {
+        let mut node = Node::new();
+        node.set_labels(node_dump.labels.clone());
+        node.set_properties(from_properties_map(&node_dump.properties));
+        let created = engine.create_node(&node)?;
+        node_ids.insert(node_dump.id, created.get_id().ok_or_else(|| GraphError::Inconsistent(String::from("a created node has no id")))?);
+    }
+    for rel_dump in &graph_dump.relationships {
+        let node_id = |id: u64| node_ids.get(&id).copied().ok_or_else(|| GraphError::InvalidRequest(format!("relationship {} connects node {} which is not in the dump", rel_dump.id, id)));
+        let (source, target) = (node_id(rel_dump.source)?, node_id(rel_dump.target)?);
+        let mut rel = Relationship::new();
+        rel.set_labels(rel_dump.labels.clone());
+        rel.set_properties(from_properties_map(&rel_dump.properties));
+        engine.create_relationship(&rel, source, target)?;
+    }
+    Ok(())
+}
 
 
 #[cfg(test)]
 mod test_cache {
     use super::*;
+    use super::super::test_utils::*;
+
+    fn build_person(name: &str) -> Node {
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        let mut prop = Property::new();
+        prop.set_name("name");
+        prop.set_value(Some(PropertyValue::PString(String::from(name))));
+        node.get_properties_mut().push(prop);
+        node
+    }
+
+    fn count_persons(engine: &mut GraphEngine) -> usize {
+        let mut pattern = PropertyGraph::new();
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        pattern.add_node(node);
+        engine.match_pattern(&pattern, None).unwrap().len()
+    }
+
     #[test]
     fn test_add_prop_graphs() {
         
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_commit() {
+        let dir = build_dir_path_and_rm_old("test_engine_commit").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        {
+            let mut engine = GraphEngine::new(&ctx).unwrap();
+            let tx = engine.begin().unwrap();
+            assert!(engine.begin().is_err());
+            engine.create_node(&build_person("alice")).unwrap();
+            engine.create_node(&build_person("bob")).unwrap();
+            engine.commit(tx).unwrap();
+            assert!(!engine.is_in_transaction());
+        }
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        assert_eq!(count_persons(&mut engine), 2);
+    }
+
+    #[test]
+    fn test_autocommit() {
+        let dir = build_dir_path_and_rm_old("test_engine_autocommit").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        {
+            let mut engine = GraphEngine::new(&ctx).unwrap();
+            engine.create_node(&build_person("alice")).unwrap();
+            assert!(!engine.is_in_transaction());
+            let tx = engine.begin().unwrap();
+            engine.create_node(&build_person("bob")).unwrap();
+            assert!(matches!(engine.sync(), Err(GraphError::Transaction(TransactionError::AlreadyStarted))));
+            engine.rollback(tx).unwrap();
+            assert!(engine.sync().is_ok());
+        }
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        assert_eq!(count_persons(&mut engine), 1);
+    }
+
+    #[test]
+    fn test_match_as_of() {
+        let dir = build_dir_path_and_rm_old("test_engine_match_as_of").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let tx = engine.begin().unwrap();
+        engine.create_node(&build_person("alice")).unwrap();
+        engine.commit(tx).unwrap();
+        let v1 = engine.get_last_version();
+
+        let tx = engine.begin().unwrap();
+        engine.create_node(&build_person("bob")).unwrap();
+        engine.commit(tx).unwrap();
+
+        let mut pattern = PropertyGraph::new();
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        pattern.add_node(node);
+        assert_eq!(engine.match_pattern(&pattern, Some(v1)).unwrap().len(), 1);
+        assert_eq!(engine.match_pattern(&pattern, Some(v1 - 1)).unwrap().len(), 0);
+        assert_eq!(engine.match_pattern(&pattern, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_match_labels() {
+        let dir = build_dir_path_and_rm_old("test_engine_match_labels").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let mut graph = PropertyGraph::new();
+        let alice = graph.add_node(build_person("alice"));
+        let mut paris = Node::new();
+        paris.get_labels_mut().push(String::from("City"));
+        let paris = graph.add_node(paris);
+        graph.add_relationship(Relationship::new(), alice, paris);
+        let bob = graph.add_node(build_person("bob"));
+        let mut london = Node::new();
+        london.get_labels_mut().push(String::from("City"));
+        let london = graph.add_node(london);
+        graph.add_relationship(Relationship::new(), london, bob);
+        let tx = engine.begin().unwrap();
+        engine.create_graph(&graph).unwrap();
+        engine.commit(tx).unwrap();
+
+        let mut pattern = PropertyGraph::new();
+        let mut person = Node::new();
+        person.get_labels_mut().push(String::from("Person"));
+        let person = pattern.add_node(person);
+        let mut city = Node::new();
+        city.get_labels_mut().push(String::from("City"));
+        let city = pattern.add_node(city);
+        pattern.add_relationship(Relationship::new(), person, city);
+        let res = engine.match_pattern(&pattern, None).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].get_nodes()[0].get_labels_ref(), &vec![String::from("Person")]);
+        assert_eq!(res[0].get_nodes()[1].get_labels_ref(), &vec![String::from("City")]);
+    }
+
+    #[test]
+    fn test_match_and_create_links_edges() {
+        let dir = build_dir_path_and_rm_old("test_engine_match_and_create_links_edges").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let tx = engine.begin().unwrap();
+        let alice = engine.create_node(&build_person("alice")).unwrap().get_id();
+        let mut cities = Vec::new();
+        for _ in 0..3 {
+            let mut city = Node::new();
+            city.get_labels_mut().push(String::from("City"));
+            cities.push(engine.create_node(&city).unwrap().get_id());
+        }
+        engine.commit(tx).unwrap();
+
+        for city in &cities {
+            let mut pattern = PropertyGraph::new();
+            let mut source = Node::new();
+            source.set_id(alice);
+            source.set_status(Status::Match);
+            let source = pattern.add_node(source);
+            let mut target = Node::new();
+            target.set_id(*city);
+            target.set_status(Status::Match);
+            let target = pattern.add_node(target);
+            let mut rel = Relationship::new();
+            rel.set_status(Status::Create);
+            pattern.add_relationship(rel, source, target);
+            assert_eq!(engine.match_pattern_and_create(&pattern).unwrap().len(), 1);
+        }
+
+        let mut pattern = PropertyGraph::new();
+        let mut person = Node::new();
+        person.get_labels_mut().push(String::from("Person"));
+        let person = pattern.add_node(person);
+        let mut city = Node::new();
+        city.get_labels_mut().push(String::from("City"));
+        let city = pattern.add_node(city);
+        pattern.add_relationship(Relationship::new(), person, city);
+        assert_eq!(engine.match_pattern(&pattern, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_match_relationship_types() {
+        let dir = build_dir_path_and_rm_old("test_engine_match_relationship_types").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let mut graph = PropertyGraph::new();
+        let alice = graph.add_node(build_person("alice"));
+        let bob = graph.add_node(build_person("bob"));
+        let carol = graph.add_node(build_person("carol"));
+        let mut knows = Relationship::new();
+        knows.set_labels(vec![String::from("KNOWS")]);
+        graph.add_relationship(knows, alice, bob);
+        let mut likes = Relationship::new();
+        likes.set_labels(vec![String::from("LIKES")]);
+        graph.add_relationship(likes, alice, carol);
+        let tx = engine.begin().unwrap();
+        engine.create_graph(&graph).unwrap();
+        engine.commit(tx).unwrap();
+
+        let mut pattern = PropertyGraph::new();
+        let mut src = Node::new();
+        src.get_labels_mut().push(String::from("Person"));
+        let source = pattern.add_node(src);
+        let mut target = Node::new();
+        target.get_labels_mut().push(String::from("Person"));
+        let target = pattern.add_node(target);
+        let mut rel = Relationship::new();
+        rel.set_labels(vec![String::from("KNOWS")]);
+        pattern.add_relationship(rel, source, target);
+        let res = engine.match_pattern(&pattern, None).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].get_relationships()[0].get_labels_ref(), &vec![String::from("KNOWS")]);
+        assert!(*res[0].get_nodes()[1].get_properties_ref()[0].get_value() == Some(PropertyValue::PString(String::from("bob"))));
+    }
+
+    #[test]
+    fn test_rollback() {
+        let dir = build_dir_path_and_rm_old("test_engine_rollback").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let tx = engine.begin().unwrap();
+        engine.create_node(&build_person("alice")).unwrap();
+        engine.commit(tx).unwrap();
+
+        let tx = engine.begin().unwrap();
+        engine.create_node(&build_person("bob")).unwrap();
+        assert_eq!(count_persons(&mut engine), 2);
+        engine.rollback(tx).unwrap();
+        assert_eq!(count_persons(&mut engine), 1);
+
+        let mut reopened = GraphEngine::new(&ctx).unwrap();
+        assert_eq!(count_persons(&mut reopened), 1);
+    }
+
+    #[test]
+    fn test_match_property_ranges() {
+        let dir = build_dir_path_and_rm_old("test_engine_match_property_ranges").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        engine.create_node_property_index("Person", "name").unwrap();
+        for name in &["alice", "bob", "carol", "dave"] {
+            engine.create_node(&build_person(name)).unwrap();
+        }
+        engine.sync().unwrap();
+
+        let mut pattern = PropertyGraph::new();
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        let lower = PropertyValue::PString(String::from("bob"));
+        let upper = PropertyValue::PString(String::from("dave"));
+        node.add_property_range(PropertyRange::new("name", Some((lower, true)), Some((upper, false))));
+        pattern.add_node(node);
+        assert_eq!(engine.match_pattern(&pattern, None).unwrap().len(), 2);
+        let version = engine.get_last_version();
+        assert_eq!(engine.match_pattern(&pattern, Some(version)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_match_relationship_property_index() {
+        let dir = build_dir_path_and_rm_old("test_engine_match_relationship_property_index").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        engine.create_relationship_property_index("KNOWS", "since").unwrap();
+        let build_knows = |since: i64| {
+            let mut rel = Relationship::new();
+            rel.get_labels_mut().push(String::from("KNOWS"));
+            let mut prop = Property::new();
+            prop.set_name("since");
+            prop.set_value(Some(PropertyValue::PInteger(since)));
+            rel.get_properties_mut().push(prop);
+            rel
+        };
+        let mut graph = PropertyGraph::new();
+        for since in &[2000, 2010, 2020] {
+            let source = graph.add_node(Node::new());
+            let target = graph.add_node(Node::new());
+            graph.add_relationship(build_knows(*since), source, target);
+        }
+        engine.create_graph(&graph).unwrap();
+        engine.sync().unwrap();
+
+        let mut pattern = PropertyGraph::new();
+        let source = pattern.add_node(Node::new());
+        let target = pattern.add_node(Node::new());
+        pattern.add_relationship(build_knows(2010), source, target);
+        let proxy = GraphProxy::new(engine.repository.clone(), &pattern, None).unwrap();
+        assert_eq!(proxy.nodes_len(), 2);
+        assert_eq!(engine.match_pattern(&pattern, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_constraints() {
+        let dir = build_dir_path_and_rm_old("test_engine_constraints").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let unique = Constraint::new(ConstraintKind::Unique, ElementKind::Node, "Person", "name");
+        let exists = Constraint::new(ConstraintKind::Exists, ElementKind::Node, "Person", "name");
+        let tx = engine.begin().unwrap();
+        engine.create_node(&build_person("alice")).unwrap();
+        engine.create_node(&build_person("alice")).unwrap();
+        assert!(matches!(engine.create_constraint(&unique), Err(GraphError::Constraint(ConstraintError::UniqueViolation(_)))));
+        engine.rollback(tx).unwrap();
+        assert!(engine.repository.borrow_mut().fetch_nodes_ids_with_indexed_properties(&build_person("alice"), None).unwrap().is_none());
+
+        let tx = engine.begin().unwrap();
+        engine.create_node(&build_person("alice")).unwrap();
+        assert!(engine.create_constraint(&unique).unwrap());
+        assert!(engine.create_constraint(&exists).unwrap());
+        assert!(!engine.create_constraint(&unique).unwrap());
+        assert!(matches!(engine.create_constraint(&Constraint::new(ConstraintKind::Unique, ElementKind::Relationship, "KNOWS", "since")),
+            Err(GraphError::Constraint(ConstraintError::Unsupported(_)))));
+        engine.commit(tx).unwrap();
+
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let constraints = engine.list_constraints().unwrap();
+        assert_eq!(constraints.len(), 2);
+        assert!(constraints.contains(&unique) && constraints.contains(&exists));
+        assert!(matches!(engine.create_node(&build_person("alice")), Err(GraphError::Constraint(ConstraintError::UniqueViolation(_)))));
+        let mut anonymous = Node::new();
+        anonymous.get_labels_mut().push(String::from("Person"));
+        assert!(matches!(engine.create_node(&anonymous), Err(GraphError::Constraint(ConstraintError::ExistenceViolation(_)))));
+
+        let mut graph = PropertyGraph::new();
+        graph.add_node(build_person("bob"));
+        graph.add_node(build_person("bob"));
+        assert!(matches!(engine.create_graph(&graph), Err(GraphError::Constraint(ConstraintError::UniqueViolation(_)))));
+
+        let bob = engine.create_node(&build_person("bob")).unwrap().get_id().unwrap();
+        let mut name = Property::new();
+        name.set_name("name");
+        name.set_value(Some(PropertyValue::PString(String::from("alice"))));
+        assert!(matches!(engine.set_node_property(bob, &name), Err(GraphError::Constraint(ConstraintError::UniqueViolation(_)))));
+        assert!(matches!(engine.remove_node_property(bob, "name"), Err(GraphError::Constraint(ConstraintError::ExistenceViolation(_)))));
+        name.set_value(Some(PropertyValue::PString(String::from("bob"))));
+        engine.set_node_property(bob, &name).unwrap();
+        assert_eq!(count_persons(&mut engine), 2);
+    }
+
+    #[test]
+    fn test_match_unlabeled_nodes() {
+        let dir = build_dir_path_and_rm_old("test_engine_match_unlabeled_nodes").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut engine = GraphEngine::new(&ctx).unwrap();
+        let mut graph = PropertyGraph::new();
+        let alice = graph.add_node(build_person("alice"));
+        let place = graph.add_node(Node::new());
+        graph.add_relationship(Relationship::new(), alice, place);
+        engine.create_graph(&graph).unwrap();
+        let deleted = engine.create_node(&Node::new()).unwrap().get_id().unwrap();
+        engine.create_node(&Node::new()).unwrap();
+        engine.delete_node(deleted).unwrap();
+
+        let mut any = PropertyGraph::new();
+        any.add_node(Node::new());
+        assert_eq!(engine.match_pattern(&any, None).unwrap().len(), 3);
+
+        let mut pattern = PropertyGraph::new();
+        let mut person = Node::new();
+        person.get_labels_mut().push(String::from("Person"));
+        let source = pattern.add_node(person);
+        let target = pattern.add_node(Node::new());
+        pattern.add_relationship(Relationship::new(), source, target);
+        let matched = engine.match_pattern(&pattern, None).unwrap();
+        assert_eq!(matched.len(), 1);
+        assert!(matched[0].get_nodes().iter().all(|n| n.get_id().is_some()));
+    }
+
+    #[test]
+    fn test_export_import() {
+        let dir = build_dir_path_and_rm_old("test_engine_export").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        {
+            let mut engine = GraphEngine::new(&ctx).unwrap();
+            let mut graph = PropertyGraph::new();
+            let alice = graph.add_node(build_person("alice"));
+            let bob = graph.add_node(build_person("bob"));
+            let mut knows = Relationship::new();
+            knows.get_labels_mut().push(String::from("KNOWS"));
+            let mut since = Property::new();
+            since.set_name("since");
+            since.set_value(Some(PropertyValue::PInteger(2020)));
+            knows.get_properties_mut().push(since);
+            graph.add_relationship(knows, alice, bob);
+            graph.add_relationship(Relationship::new(), bob, alice);
+            engine.create_graph(&graph).unwrap();
+            engine.create_node(&Node::new()).unwrap();
+            engine.sync().unwrap();
+        }
+        let graph_dump = export_database(&ctx).unwrap();
+        assert_eq!(graph_dump.nodes.len(), 3);
+        assert_eq!(graph_dump.relationships.len(), 2);
+        assert_eq!(graph_dump.count_labels().get("Person"), Some(&2));
+        let knows = graph_dump.relationships.iter().find(|rel| rel.labels == vec![String::from("KNOWS")]).unwrap();
+        assert_eq!(knows.properties.get("since"), Some(&PropertyValue::PInteger(2020)));
+
+        let copy_dir = build_dir_path_and_rm_old("test_engine_import").unwrap();
+        let copy_ctx = init::InitContext::new(&copy_dir);
+        import_database(&copy_ctx, &graph_dump).unwrap();
+        let copy_dump = export_database(&copy_ctx).unwrap();
+        assert_eq!(copy_dump.nodes.len(), 3);
+        assert_eq!(copy_dump.count_relationship_types(), graph_dump.count_relationship_types());
+
+        let mut dangling = graph_dump.clone();
+        dangling.nodes.retain(|node| node.id != knows.source);
+        assert!(matches!(import_database(&copy_ctx, &dangling), Err(GraphError::InvalidRequest(_))));
+        assert_eq!(export_database(&copy_ctx).unwrap().nodes.len(), 3);
+    }
+}
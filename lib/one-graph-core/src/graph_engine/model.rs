@@ -1,11 +1,13 @@
 use super::super::model::*;
 use super::super::graph::traits::*;
 use super::super::repository::graph_repository::*;
+use super::super::error::*;
 
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 
 #[derive(Copy, Clone, Debug)]
 pub struct ProxyNodeId {
@@ -82,6 +84,19 @@ pub struct InnerEdgeData<NID: MemGraphId, EID: MemGraphId> {
     pub next_inbound_edge: Option<EID>,
 }
 
+/// First failure met while the matcher walks the proxy, the graph traits can only answer None.
+type ErrorSlot = Rc<RefCell<Option<GraphError>>>;
+
+fn keep_error<T>(slot: &ErrorSlot, res: GraphResult<T>) -> Option<T> {
+    match res {
+        Ok(value) => Some(value),
+        Err(e) => {
+            slot.borrow_mut().get_or_insert(e);
+            None
+        }
+    }
+}
+
 pub struct GraphProxy {
     nodes: Vec<Node>,
     relationships: Vec<Relationship>,
@@ -91,6 +106,8 @@ pub struct GraphProxy {
     retrieved_nodes_ids: Vec<ProxyNodeId>,
     map_vertices: Rc<RefCell<HashMap<u64, (ProxyNodeId, DbVertexData)>>>,
     map_edges: Rc<RefCell<HashMap<u64, (ProxyRelationshipId, DbEdgeData)>>>,
+    as_of: Option<u64>,
+    error: ErrorSlot,
 }
 
 
@@ -104,13 +121,13 @@ impl GrowableGraphContainerTrait<ProxyNodeId, ProxyRelationshipId, Node, Relatio
             let mut res = 0;
             if let Some(ndata) = ondata {
                 vertex_exists = true;
-                if ndata.0.get_index() < self.nodes.len() {
+                if ndata.0.get_index() < self.nodes.len() && self.nodes[ndata.0.get_index()].get_id().is_some() {
                     res = ndata.0.get_index();
                     retrieve = false;
                 }
             }
             if retrieve {
-                let rnode = self.repository.borrow_mut().retrieve_node_by_id(id.get_store_id())?;
+                let rnode = keep_error(&self.error, self.repository.borrow_mut().retrieve_node_by_id(id.get_store_id(), self.as_of))?;
                 let pid = self.add_node(&rnode, !vertex_exists)?;
                 self.map_vertices.borrow_mut().insert(pid.get_store_id(), (pid, rnode.1));
                 res = pid.get_index();
@@ -128,15 +145,15 @@ impl GrowableGraphContainerTrait<ProxyNodeId, ProxyRelationshipId, Node, Relatio
             let mut res = 0;
             if let Some(rdata) = ordata {
                 edge_exists = true;
-                if rdata.0.get_index() < self.relationships.len() {
+                if rdata.0.get_index() < self.relationships.len() && self.relationships[rdata.0.get_index()].get_id().is_some() {
                     res = rdata.0.get_index();
                     retrieve = false;
                 }
             }
             if retrieve {
-                let rrel = self.repository.borrow_mut().retrieve_relationship_by_id(id.get_store_id())?;
-                let sdata = get_or_retrieve_vertex_data(self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), rrel.1.source)?;
-                let tdata = get_or_retrieve_vertex_data(self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), rrel.1.target)?;
+                let rrel = keep_error(&self.error, self.repository.borrow_mut().retrieve_relationship_by_id(id.get_store_id(), self.as_of))?;
+                let sdata = keep_error(&self.error, get_or_retrieve_vertex_data(self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), rrel.1.source, self.as_of))?;
+                let tdata = keep_error(&self.error, get_or_retrieve_vertex_data(self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), rrel.1.target, self.as_of))?;
                 let pid = self.add_relationship(sdata.0, tdata.0, &rrel.0, !edge_exists)?;
                 self.map_edges.borrow_mut().insert(pid.get_store_id(), (pid, rrel.1));
                 res = pid.get_index();
@@ -155,6 +172,8 @@ pub struct InEdges {
     vertices: Rc<RefCell<Vec<InnerVertexData<ProxyRelationshipId>>>>,
     map_edges: Rc<RefCell<HashMap<u64, (ProxyRelationshipId, DbEdgeData)>>>,
     map_vertices: Rc<RefCell<HashMap<u64, (ProxyNodeId, DbVertexData)>>>,
+    as_of: Option<u64>,
+    error: ErrorSlot,
 }
 
 impl Iterator for InEdges {
@@ -171,8 +190,8 @@ impl Iterator for InEdges {
                     self.current_edge_index = curr_edge.next_inbound_edge;
                     Some(rdata.0)
                 } else {
-                    let edge_data = self.repository.borrow_mut().retrieve_edge_data_by_id(edge_index.get_store_id())?;
-                    let pid = add_edge(self.edges.clone(), self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), &edge_data, edge_index.get_store_id())?;
+                    let edge_data = keep_error(&self.error, self.repository.borrow_mut().retrieve_edge_data_by_id(edge_index.get_store_id(), self.as_of))?;
+                    let pid = keep_error(&self.error, add_edge(self.edges.clone(), self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), &edge_data, edge_index.get_store_id(), self.as_of))?;
                     self.map_edges.borrow_mut().insert(edge_index.get_store_id(), (pid, edge_data));
                     let edges = self.edges.borrow();
                     let curr_edge = edges.get(pid.get_index())?;
@@ -194,34 +213,29 @@ fn add_vertex(vertices: Rc<RefCell<Vec<InnerVertexData<ProxyRelationshipId>>>>,
 }
 
 
-fn get_or_retrieve_vertex_data(vertices: Rc<RefCell<Vec<InnerVertexData<ProxyRelationshipId>>>>, map_vertices: Rc<RefCell<HashMap<u64, (ProxyNodeId, DbVertexData)>>>, repository: Rc<RefCell<GraphRepository>>, id: u64) -> Option<(ProxyNodeId, InnerVertexData<ProxyRelationshipId>)> {
+fn get_or_retrieve_vertex_data(vertices: Rc<RefCell<Vec<InnerVertexData<ProxyRelationshipId>>>>, map_vertices: Rc<RefCell<HashMap<u64, (ProxyNodeId, DbVertexData)>>>, repository: Rc<RefCell<GraphRepository>>, id: u64, as_of: Option<u64>) -> GraphResult<(ProxyNodeId, InnerVertexData<ProxyRelationshipId>)> {
     let ovdata = map_vertices.borrow().get(&id).map(|data| *data);
     if let Some(vdata) = ovdata {
-        vertices.borrow().get(vdata.0.get_index()).map(|v| (vdata.0, *v))
+        Ok((vdata.0, vertices.borrow()[vdata.0.get_index()]))
     } else {
-        let vdata = repository.borrow_mut().retrieve_vertex_data_by_id(id)?;
+        let vdata = repository.borrow_mut().retrieve_vertex_data_by_id(id, as_of)?;
         let pid = add_vertex(vertices.clone(), id, vdata);
         map_vertices.borrow_mut().insert(id, (pid.0, vdata));
-        Some(pid)
+        Ok(pid)
     }
 }
 
-fn add_edge(edges: Rc<RefCell<Vec<InnerEdgeData<ProxyNodeId, ProxyRelationshipId>>>>, vertices: Rc<RefCell<Vec<InnerVertexData<ProxyRelationshipId>>>>, map_vertices: Rc<RefCell<HashMap<u64, (ProxyNodeId, DbVertexData)>>>, repository: Rc<RefCell<GraphRepository>>, db_edge_data: &DbEdgeData, rel_db_id: u64) -> Option<ProxyRelationshipId> {
+fn add_edge(edges: Rc<RefCell<Vec<InnerEdgeData<ProxyNodeId, ProxyRelationshipId>>>>, vertices: Rc<RefCell<Vec<InnerVertexData<ProxyRelationshipId>>>>, map_vertices: Rc<RefCell<HashMap<u64, (ProxyNodeId, DbVertexData)>>>, repository: Rc<RefCell<GraphRepository>>, db_edge_data: &DbEdgeData, rel_db_id: u64, as_of: Option<u64>) -> GraphResult<ProxyRelationshipId> {
     let index = edges.borrow().len();
     
-    let source_data = get_or_retrieve_vertex_data(vertices.clone(), map_vertices.clone(), repository.clone(), db_edge_data.source)?;
-    let target_data = get_or_retrieve_vertex_data(vertices.clone(), map_vertices.clone(), repository.clone(), db_edge_data.target)?;
+    let source_data = get_or_retrieve_vertex_data(vertices.clone(), map_vertices.clone(), repository.clone(), db_edge_data.source, as_of)?;
+    let target_data = get_or_retrieve_vertex_data(vertices.clone(), map_vertices.clone(), repository.clone(), db_edge_data.target, as_of)?;
     {
         edges.borrow_mut().push(InnerEdgeData{source: source_data.0, target: target_data.0,
             next_inbound_edge: db_edge_data.next_inbound_edge.map(|id| ProxyRelationshipId::new_db(id)), 
             next_outbound_edge: db_edge_data.next_outbound_edge.map(|id| ProxyRelationshipId::new_db(id))});
     }
-    let pid = ProxyRelationshipId::new(index, rel_db_id);
-    {let ms = &mut vertices.borrow_mut()[source_data.0.get_index()];
-    ms.first_outbound_edge = Some(pid);}
-    {let mt = &mut vertices.borrow_mut()[target_data.0.get_index()];
-    mt.first_inbound_edge = Some(pid);}
-    Some(pid)
+    Ok(ProxyRelationshipId::new(index, rel_db_id))
 }
 
 pub struct OutEdges {
@@ -231,6 +245,8 @@ pub struct OutEdges {
     vertices: Rc<RefCell<Vec<InnerVertexData<ProxyRelationshipId>>>>,
     map_edges: Rc<RefCell<HashMap<u64, (ProxyRelationshipId, DbEdgeData)>>>,
     map_vertices: Rc<RefCell<HashMap<u64, (ProxyNodeId, DbVertexData)>>>,
+    as_of: Option<u64>,
+    error: ErrorSlot,
 }
 
 impl Iterator for OutEdges {
@@ -247,8 +263,8 @@ impl Iterator for OutEdges {
                     self.current_edge_index = curr_edge.next_outbound_edge;
                     Some(rdata.0)
                 } else {
-                    let edge_data = self.repository.borrow_mut().retrieve_edge_data_by_id(edge_index.get_store_id())?;
-                    let pid = add_edge(self.edges.clone(), self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), &edge_data, edge_index.get_store_id())?;
+                    let edge_data = keep_error(&self.error, self.repository.borrow_mut().retrieve_edge_data_by_id(edge_index.get_store_id(), self.as_of))?;
+                    let pid = keep_error(&self.error, add_edge(self.edges.clone(), self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), &edge_data, edge_index.get_store_id(), self.as_of))?;
                     self.map_edges.borrow_mut().insert(edge_index.get_store_id(), (pid, edge_data));
                     let edges = self.edges.borrow();
                     let curr_edge = edges.get(pid.get_index())?;
@@ -267,14 +283,16 @@ impl GrowableGraphIteratorTrait<ProxyNodeId, ProxyRelationshipId> for GraphProxy
         let pid = &self.map_vertices.borrow_mut()[&source.get_store_id()];
         let first_outbound_edge = self.vertices.borrow()[pid.0.get_index()].first_outbound_edge;
         OutEdges{ edges: self.edges.clone(), current_edge_index: first_outbound_edge, repository: self.repository.clone(),
-            map_vertices: self.map_vertices.clone(), map_edges: self.map_edges.clone(), vertices: self.vertices.clone() }
+            map_vertices: self.map_vertices.clone(), map_edges: self.map_edges.clone(), vertices: self.vertices.clone(), as_of: self.as_of,
+            error: self.error.clone() }
     }
 
     fn in_edges(&mut self, target: &ProxyNodeId) -> Self::InIt {
         let pid = &self.map_vertices.borrow_mut()[&target.get_store_id()];
         let first_inbound_edge = self.vertices.borrow()[pid.0.get_index()].first_inbound_edge;
         InEdges{ edges: self.edges.clone(), current_edge_index: first_inbound_edge, repository: self.repository.clone(),
-            map_edges: self.map_edges.clone(), vertices: self.vertices.clone(), map_vertices: self.map_vertices.clone() }
+            map_edges: self.map_edges.clone(), vertices: self.vertices.clone(), map_vertices: self.map_vertices.clone(), as_of: self.as_of,
+            error: self.error.clone() }
     }
     fn in_degree(&mut self, node: &ProxyNodeId) -> usize {
         self.in_edges(node).count()
@@ -308,44 +326,116 @@ impl GrowableGraphTrait<ProxyNodeId, ProxyRelationshipId> for GraphProxy {
 
 }
 
-fn extract_nodes_labels(pattern: &PropertyGraph) -> Vec<String> {
-    let mut res = Vec::new();
-    for node in pattern.get_nodes() {
-        node.get_labels_ref().iter().for_each(|l| res.push(l.to_owned()));
+/// Largest pattern distance from a seeded node to a node that could not be seeded,
+/// None when such a node is not connected to any seeded node.
+fn unbound_nodes_depth(pattern: &PropertyGraph, seeded: &[bool]) -> Option<usize> {
+    let mut depths: Vec<Option<usize>> = seeded.iter().map(|&s| if s {Some(0)} else {None}).collect();
+    let edges = pattern.get_edges();
+    let mut depth = 0;
+    loop {
+        let mut reached = false;
+        for edge in &edges {
+            let (source, target) = (edge.source.get_index(), edge.target.get_index());
+            for &(from, to) in &[(source, target), (target, source)] {
+                if depths[from] == Some(depth) && depths[to].is_none() {
+                    depths[to] = Some(depth + 1);
+                    reached = true;
+                }
+            }
+        }
+        if !reached {
+            break;
+        }
+        depth += 1;
+    }
+    if depths.iter().any(|d| d.is_none()) {
+        None
+    } else {
+        Some(depth)
     }
-    res
 }
 
-fn retrieve_db_nodes_ids(repository: Rc<RefCell<GraphRepository>>, labels: &Vec<String>) -> Vec<ProxyNodeId> {
-    let db_node_ids = repository.borrow_mut().fetch_nodes_ids_with_labels(labels);
-    let mut res = Vec::new();
-    for id in db_node_ids {
-        res.push(ProxyNodeId::new_db(id))
+/// Candidates of a pattern node from its id, from a property index when one covers its constraints,
+/// from the endpoints of an incident relationship found by a property index, from the labels index otherwise.
+/// None when the node has neither id, indexed constraints nor labels.
+fn seed_node(repo: &mut GraphRepository, pattern: &PropertyGraph, index: usize, node: &Node, as_of: Option<u64>) -> GraphResult<Option<HashSet<u64>>> {
+    if let Some(nid) = node.get_id() {
+        let mut ids = HashSet::new();
+        if repo.is_node_visible(nid, as_of)? {
+            ids.insert(nid);
+        }
+        return Ok(Some(ids));
+    }
+    if let Some(ids) = repo.fetch_nodes_ids_with_indexed_properties(node, as_of)? {
+        return Ok(Some(ids));
+    }
+    for (rel, edge) in pattern.get_relationships_and_edges() {
+        if edge.source.get_index() == index || edge.target.get_index() == index {
+            if let Some(ids) = repo.fetch_relationships_nodes_ids_with_indexed_properties(rel, as_of)? {
+                return Ok(Some(ids));
+            }
+        }
+    }
+    if node.get_labels_ref().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(repo.fetch_nodes_ids_with_labels(node.get_labels_ref(), as_of)?))
     }
-    res
 }
 
-impl GraphProxy {
-    pub fn new(repo: Rc<RefCell<GraphRepository>>, pattern: &PropertyGraph) -> Self {
-        let labels = extract_nodes_labels(pattern);
-        let mut ids = retrieve_db_nodes_ids(repo.clone(), &labels);
-        for n_index in pattern.get_nodes_ids() {
-            if let Some(nid) = pattern.get_node_ref(&n_index).get_id() {
-                ids.push(ProxyNodeId::new_db(nid))
+/// Seeds the candidates of each pattern node with `seed_node`. Nodes that cannot be seeded are matched against
+/// the neighbourhood of the seeded candidates, or against every stored node when they are not connected to a seeded node.
+fn retrieve_db_nodes_ids(repository: Rc<RefCell<GraphRepository>>, pattern: &PropertyGraph, as_of: Option<u64>) -> GraphResult<Vec<ProxyNodeId>> {
+    let mut repo = repository.borrow_mut();
+    let mut db_node_ids = HashSet::new();
+    let mut seeded = Vec::new();
+    for (index, node) in pattern.get_nodes().iter().enumerate() {
+        let seeds = seed_node(&mut repo, pattern, index, node, as_of)?;
+        seeded.push(seeds.is_some());
+        db_node_ids.extend(seeds.unwrap_or_default());
+    }
+    match unbound_nodes_depth(pattern, &seeded) {
+        Some(depth) => {
+            let mut frontier: Vec<u64> = db_node_ids.iter().cloned().collect();
+            for _ in 0..depth {
+                let mut next = Vec::new();
+                for id in frontier {
+                    for neighbour in repo.fetch_neighbours_ids(id, as_of)? {
+                        if db_node_ids.insert(neighbour) {
+                            next.push(neighbour);
+                        }
+                    }
+                }
+                frontier = next;
             }
-        }
-        GraphProxy{repository: repo, nodes: Vec::new(),
+        },
+        None => db_node_ids = repo.fetch_all_nodes_ids(as_of)?,
+    }
+    Ok(db_node_ids.into_iter().map(ProxyNodeId::new_db).collect())
+}
+
+impl GraphProxy {
+    pub fn new(repo: Rc<RefCell<GraphRepository>>, pattern: &PropertyGraph, as_of: Option<u64>) -> GraphResult<Self> {
+        let ids = retrieve_db_nodes_ids(repo.clone(), pattern, as_of)?;
+        Ok(GraphProxy{repository: repo, nodes: Vec::new(),
             relationships: Vec::new(),
             retrieved_nodes_ids: ids, vertices: Rc::new(RefCell::new(Vec::new())),
             edges: Rc::new(RefCell::new(Vec::new())),
             map_vertices: Rc::new(RefCell::new(HashMap::new())),
             map_edges: Rc::new(RefCell::new(HashMap::new())),
-        }
+            as_of,
+            error: Rc::new(RefCell::new(None)),
+        })
+    }
+
+    /// The failure that cut the walk of the matcher short, if any.
+    pub fn take_error(&self) -> Option<GraphError> {
+        self.error.borrow_mut().take()
     }
 
     fn add_edge(&mut self, rel_db_id: u64) -> Option<ProxyRelationshipId> {
-        let db_edge_data = self.repository.borrow_mut().retrieve_edge_data_by_id(rel_db_id)?;
-        add_edge(self.edges.clone(), self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), &db_edge_data, rel_db_id)
+        let db_edge_data = keep_error(&self.error, self.repository.borrow_mut().retrieve_edge_data_by_id(rel_db_id, self.as_of))?;
+        keep_error(&self.error, add_edge(self.edges.clone(), self.vertices.clone(), self.map_vertices.clone(), self.repository.clone(), &db_edge_data, rel_db_id, self.as_of))
     }
 
     fn add_vertex(&mut self, db_id: u64, vdata: DbVertexData) -> (ProxyNodeId, InnerVertexData<ProxyRelationshipId>) {
@@ -361,10 +451,10 @@ impl GraphProxy {
                 self.map_vertices.borrow()[&id].0
             }
         };
-        while pid.get_index() > self.nodes.len() {
+        while pid.get_index() >= self.nodes.len() {
             self.nodes.push(Node::new());
         }
-        self.nodes.insert(pid.get_index(), node.0.clone());
+        self.nodes[pid.get_index()] = node.0.clone();
         Some(pid)
     }
 
@@ -377,10 +467,10 @@ impl GraphProxy {
                 self.map_edges.borrow()[&id].0
             }
         };
-        while pid.get_index() > self.relationships.len() {
+        while pid.get_index() >= self.relationships.len() {
             self.relationships.push(Relationship::new());
         }
-        self.relationships.insert(pid.get_index(), rel.clone());
+        self.relationships[pid.get_index()] = rel.clone();
         Some(pid)
     }
 }
@@ -0,0 +1,29 @@
+pub type TransactionId = u64;
+
+#[derive(Debug, Clone)]
+pub enum TransactionError {
+    AlreadyStarted,
+    NotStarted,
+    InvalidTransaction,
+}
+
+pub type TransactionResult<T> = std::result::Result<T, TransactionError>;
+
+/// Handle on the transaction opened by `GraphEngine::begin`.
+/// Changes made while it is open stay in the repository page caches
+/// until `GraphEngine::commit` logs them atomically, or `GraphEngine::rollback` drops them.
+/// A write made while no transaction is open runs in its own transaction, committed when the write succeeds.
+#[derive(Debug)]
+pub struct Transaction {
+    id: TransactionId,
+}
+
+impl Transaction {
+    pub(crate) fn new(id: TransactionId) -> Self {
+        Transaction{id}
+    }
+
+    pub fn get_id(&self) -> TransactionId {
+        self.id
+    }
+}
@@ -1,11 +1,13 @@
 #![allow(dead_code)]
 extern crate log;
+extern crate cache;
 extern crate serde;
 extern crate toml;
 #[macro_use]
 extern crate bson;
 
 pub mod test_utils;
+pub mod error;
 mod config;
 mod buf_config;
 pub mod graph;
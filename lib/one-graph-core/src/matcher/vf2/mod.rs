@@ -61,6 +61,8 @@ impl <'g0, 'g1, NID0, NID1, EID0, EID1, N0, R0, N1, R1, VCOMP, ECOMP, Graph0, Gr
             let last =  self.match_continuation.pop();
             if let Some(back) = last {
                 self.state.pop(&back.0, &back.1);
+                self.first_candidate_0 = Some(back.0);
+                self.curr_candidate_1_index = self.graph_1_ids.iter().position(|id| *id == back.1).unwrap_or(self.graph_1_ids.len());
             }
         }
 
@@ -0,0 +1,100 @@
+use std::fmt;
+
+/// Inconsistency found in a store file, ids are record ids unless they are page ids.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The header page cannot be used, with the reason, the file was not checked.
+    UnusableHeader(String),
+    /// The page does not match its checksum, its records were not checked.
+    CorruptedPage(u64),
+    /// The free list of the page holds out of range or duplicated slots.
+    InvalidFreeList(u64),
+    /// The page has free slots but is not linked in the free pages chain.
+    UnlinkedFreePage(u64),
+    /// The free pages chain loops or goes through a page without free slots.
+    BrokenFreePagesChain(u64),
+    /// The header records counter does not match the records in use.
+    WrongRecordsCounter{stored: u64, counted: u64},
+    /// The relationships chain of the node goes through a record that is not in use.
+    DanglingRelationship{node: u64, relationship: u64},
+    /// The relationships chain of the node comes back to a relationship it already went through.
+    RelationshipChainCycle{node: u64, relationship: u64},
+    /// The relationships chain of the node goes through a relationship of another node.
+    MisplacedRelationship{node: u64, relationship: u64},
+    /// The source or target of the relationship is not in use.
+    MissingNode{relationship: u64, node: u64},
+    /// The relationship cannot be reached from the chains of its source and target.
+    UnlinkedRelationship(u64),
+    /// The properties chain of the node or relationship loops or goes through a record that is not in use.
+    BrokenPropertyChain{owner: u64, property: u64},
+    /// The property value lives in a dynamic record that is not in use.
+    MissingPropertyValue{property: u64, record: u64},
+    /// The property key is not a token of the property keys store.
+    MissingPropertyKey{property: u64, key: u64},
+    /// The name of the token lives in a dynamic record that cannot be loaded.
+    MissingTokenName{token: u64, record: u64},
+    /// The versions of the node or relationship loop or go through a history record that is not in use.
+    BrokenHistoryChain{owner: u64, version: u64},
+    /// The history record is not a version of any node or relationship.
+    UnlinkedHistoryVersion(u64),
+    /// The dynamic record continues with a record that is not in use or loops.
+    BrokenDynChain(u64),
+    /// The label index references a node that is not in use.
+    DanglingLabelIndexEntry(u64),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::UnusableHeader(reason) => write!(f, "header page cannot be used: {}", reason),
+            Problem::CorruptedPage(page) => write!(f, "page {} is corrupted", page),
+            Problem::InvalidFreeList(page) => write!(f, "page {} has an invalid free list", page),
+            Problem::UnlinkedFreePage(page) => write!(f, "page {} has free records but is not in the free pages chain", page),
+            Problem::BrokenFreePagesChain(page) => write!(f, "free pages chain is broken at page {}", page),
+            Problem::WrongRecordsCounter{stored, counted} => write!(f, "records counter is {} but {} records are in use", stored, counted),
+            Problem::DanglingRelationship{node, relationship} => write!(f, "node {} chains relationship {} which is not in use", node, relationship),
+            Problem::RelationshipChainCycle{node, relationship} => write!(f, "relationships chain of node {} loops at relationship {}", node, relationship),
+            Problem::MisplacedRelationship{node, relationship} => write!(f, "node {} chains relationship {} of another node", node, relationship),
+            Problem::MissingNode{relationship, node} => write!(f, "relationship {} connects node {} which is not in use", relationship, node),
+            Problem::UnlinkedRelationship(relationship) => write!(f, "relationship {} is not linked from its nodes", relationship),
+            Problem::BrokenPropertyChain{owner, property} => write!(f, "properties chain of {} is broken at property {}", owner, property),
+            Problem::MissingPropertyValue{property, record} => write!(f, "property {} value record {} is not in use", property, record),
+            Problem::MissingPropertyKey{property, key} => write!(f, "property {} key token {} is not in use", property, key),
+            Problem::MissingTokenName{token, record} => write!(f, "token {} name record {} cannot be loaded", token, record),
+            Problem::BrokenHistoryChain{owner, version} => write!(f, "versions of {} are broken at history record {}", owner, version),
+            Problem::UnlinkedHistoryVersion(version) => write!(f, "history record {} is not a version of any record", version),
+            Problem::BrokenDynChain(record) => write!(f, "dynamic record {} has a broken next record", record),
+            Problem::DanglingLabelIndexEntry(node) => write!(f, "label index references node {} which is not in use", node),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// Name of the store file the problem was found in.
+    pub file: String,
+    pub problem: Problem,
+    pub repaired: bool,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}{}", self.file, self.problem, if self.repaired {" (repaired)"} else {""})
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    pub findings: Vec<Finding>,
+}
+
+impl CheckReport {
+    pub fn is_consistent(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Findings left as they were, either because repair was not requested or because they cannot be repaired.
+    pub fn get_unrepaired(&self) -> Vec<&Finding> {
+        self.findings.iter().filter(|finding| !finding.repaired).collect()
+    }
+}
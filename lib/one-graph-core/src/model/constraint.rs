@@ -0,0 +1,66 @@
+use super::ElementKind;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConstraintKind {
+    /// No two nodes with the label share a value of the property.
+    Unique,
+    /// Every element with the label or relationship type has the property.
+    Exists,
+}
+
+impl ConstraintKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ConstraintKind::Unique => "unique",
+            ConstraintKind::Exists => "exists",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unique" => Some(ConstraintKind::Unique),
+            "exists" => Some(ConstraintKind::Exists),
+            _ => None,
+        }
+    }
+}
+
+/// Schema constraint on a property of the nodes with a label or of the relationships with a type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Constraint {
+    pub kind: ConstraintKind,
+    pub target: ElementKind,
+    pub label: String,
+    pub property: String,
+}
+
+impl Constraint {
+    pub fn new(kind: ConstraintKind, target: ElementKind, label: &str, property: &str) -> Self {
+        Constraint{kind, target, label: String::from(label), property: String::from(property)}
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}.{}", self.kind.get_name(), self.target.get_name(), self.label, self.property)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConstraintError {
+    UniqueViolation(Constraint),
+    ExistenceViolation(Constraint),
+    /// Uniqueness is only supported on node labels.
+    Unsupported(Constraint),
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstraintError::UniqueViolation(c) => write!(f, "unique constraint violated: {}", c),
+            ConstraintError::ExistenceViolation(c) => write!(f, "existence constraint violated: {}", c),
+            ConstraintError::Unsupported(c) => write!(f, "unsupported constraint: {}", c),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use super::init::*;
+use super::super::config::*;
+use super::super::buf_config::DEFAULT_PAGE_SIZE;
+use super::super::repository::is_page_size_valid;
+use log::LevelFilter;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The configuration file could not be read, with the cause reported by the system.
+    Io(String),
+    /// The file is not valid TOML or has unknown or mistyped settings.
+    Parse(String),
+    /// The setting, named with its key, has a value that cannot be used, with the reason.
+    Invalid(String, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(cause) => write!(f, "cannot read the configuration: {}", cause),
+            ConfigError::Parse(cause) => write!(f, "cannot parse the configuration: {}", cause),
+            ConfigError::Invalid(key, reason) => write!(f, "invalid setting {}: {}", key, reason),
+        }
+    }
+}
+
+pub type ConfigResult<T> = std::result::Result<T, ConfigError>;
+
+/// File names of the stores in the data directory, the `[files]` table of the configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreFiles {
+    pub nodes: String,
+    pub relationships: String,
+    pub nodes_history: String,
+    pub relationships_history: String,
+    pub properties: String,
+    pub dynamic: String,
+    pub nodes_labels_index: String,
+    pub nodes_labels_bw_index: String,
+    pub labels: String,
+    pub labels_names: String,
+    pub nodes_labels: String,
+    pub relationship_types: String,
+    pub relationship_types_names: String,
+    pub property_keys: String,
+    pub property_keys_names: String,
+    pub property_indexes: String,
+    pub property_indexes_names: String,
+    /// Each property index file is named with this prefix followed by the index id.
+    pub property_index_prefix: String,
+    pub constraints: String,
+    pub constraints_names: String,
+    pub wal: String,
+}
+
+impl Default for StoreFiles {
+    fn default() -> Self {
+        StoreFiles{nodes: String::from(NODES_FILE_NAME), relationships: String::from(RELATIONSHIPS_FILE_NAME),
+            nodes_history: String::from(NODES_HISTORY_FILE_NAME), relationships_history: String::from(RELATIONSHIPS_HISTORY_FILE_NAME),
+            properties: String::from(PROPERTIES_FILE_NAME), dynamic: String::from(DYN_FILE_NAME),
+            nodes_labels_index: String::from(NODES_LABELS_INDEX_FILE_NAME), nodes_labels_bw_index: String::from(NODES_LABELS_BW_INDEX_FILE_NAME),
+            labels: String::from(LABELS_FILE_NAME), labels_names: String::from(LABELS_NAMES_FILE_NAME),
+            nodes_labels: String::from(NODES_LABELS_FILE_NAME), relationship_types: String::from(RELATIONSHIP_TYPES_FILE_NAME),
+            relationship_types_names: String::from(RELATIONSHIP_TYPES_NAMES_FILE_NAME), property_keys: String::from(PROPERTY_KEYS_FILE_NAME),
+            property_keys_names: String::from(PROPERTY_KEYS_NAMES_FILE_NAME), property_indexes: String::from(PROPERTY_INDEXES_FILE_NAME),
+            property_indexes_names: String::from(PROPERTY_INDEXES_NAMES_FILE_NAME), property_index_prefix: String::from(PROPERTY_INDEX_FILE_PREFIX),
+            constraints: String::from(CONSTRAINTS_FILE_NAME), constraints_names: String::from(CONSTRAINTS_NAMES_FILE_NAME),
+            wal: String::from(WAL_FILE_NAME)}
+    }
+}
+
+impl StoreFiles {
+    fn get_names(&self) -> Vec<(&'static str, &str)> {
+        vec![("nodes", &self.nodes), ("relationships", &self.relationships), ("nodes_history", &self.nodes_history),
+            ("relationships_history", &self.relationships_history), ("properties", &self.properties), ("dynamic", &self.dynamic),
+            ("nodes_labels_index", &self.nodes_labels_index), ("nodes_labels_bw_index", &self.nodes_labels_bw_index),
+            ("labels", &self.labels), ("labels_names", &self.labels_names), ("nodes_labels", &self.nodes_labels),
+            ("relationship_types", &self.relationship_types), ("relationship_types_names", &self.relationship_types_names),
+            ("property_keys", &self.property_keys), ("property_keys_names", &self.property_keys_names),
+            ("property_indexes", &self.property_indexes), ("property_indexes_names", &self.property_indexes_names),
+            ("property_index_prefix", &self.property_index_prefix), ("constraints", &self.constraints),
+            ("constraints_names", &self.constraints_names), ("wal", &self.wal)]
+    }
+}
+
+/// Database settings, read from a TOML file where every setting is optional:
+///
+/// ```toml
+/// data_dir = "/var/lib/og"
+/// listen_address = "127.0.0.1:8182"
+/// log_level = "info"
+/// shutdown_timeout = 10
+/// page_size = 4096
+/// page_cache_capacity = 1024
+/// sync_policy = "always"
+/// labels_index = "btree"
+/// property_indexes = "btree"
+///
+/// [files]
+/// nodes = "nodes.db"
+/// ```
+///
+/// The top level settings are overridden by the environment variables named after them
+/// in upper case with the `OG_` prefix, e.g. `OG_DATA_DIR`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DbConfig {
+    pub data_dir: String,
+    pub listen_address: String,
+    /// One of off, error, warn, info, debug and trace.
+    pub log_level: String,
+    /// Seconds the server waits for the open connections to close before the final flush.
+    pub shutdown_timeout: usize,
+    /// Page size of the files created in the data directory.
+    pub page_size: usize,
+    /// Number of pages each store keeps in its page cache.
+    pub page_cache_capacity: usize,
+    pub sync_policy: SyncPolicy,
+    /// Kind of the labels index, a database is refused when its labels index has the other kind.
+    pub labels_index: IndexKind,
+    /// Kind of the property indexes created from now on.
+    pub property_indexes: IndexKind,
+    pub files: StoreFiles,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        DbConfig{data_dir: String::from(DEFAULT_DATA_DIR), listen_address: String::from(DEFAULT_LISTEN_ADDRESS),
+            log_level: String::from(DEFAULT_LOG_LEVEL), shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT, page_size: DEFAULT_PAGE_SIZE, page_cache_capacity: PAGE_CACHE_CAPACITY,
+            sync_policy: SyncPolicy::Always, labels_index: IndexKind::BTree,
+            property_indexes: IndexKind::BTree, files: StoreFiles::default()}
+    }
+}
+
+fn invalid(key: &str, reason: &str) -> ConfigError {
+    ConfigError::Invalid(String::from(key), String::from(reason))
+}
+
+fn parse_number(key: &str, value: &str) -> ConfigResult<usize> {
+    value.parse().map_err(|_| invalid(key, "not a positive integer"))
+}
+
+fn parse_variant<T: for<'de> Deserialize<'de>>(key: &str, value: &str) -> ConfigResult<T> {
+    toml::Value::String(String::from(value)).try_into().map_err(|e| invalid(key, &e.to_string()))
+}
+
+impl DbConfig {
+    /// Reads the file, applies the environment overrides and validates the result.
+    pub fn load(file: &str) -> ConfigResult<Self> {
+        let text = std::fs::read_to_string(file).map_err(|e| ConfigError::Io(format!("{}: {}", file, e)))?;
+        let mut config = DbConfig::from_toml_str(&text)?;
+        config.apply_overrides(std::env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses the settings without validating them.
+    pub fn from_toml_str(text: &str) -> ConfigResult<Self> {
+        toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    /// Applies the variables prefixed with `OG_` that name a top level setting, the others are ignored.
+    pub fn apply_overrides<I: IntoIterator<Item = (String, String)>>(&mut self, vars: I) -> ConfigResult<()> {
+        for (name, value) in vars {
+            let key = match name.strip_prefix(CONFIG_ENV_PREFIX) {
+                Some(key) => key.to_lowercase(),
+                None => continue,
+            };
+            match key.as_str() {
+                "data_dir" => self.data_dir = value,
+                "listen_address" => self.listen_address = value,
+                "log_level" => self.log_level = value,
+                "shutdown_timeout" => self.shutdown_timeout = parse_number(&key, &value)?,
+                "page_size" => self.page_size = parse_number(&key, &value)?,
+                "page_cache_capacity" => self.page_cache_capacity = parse_number(&key, &value)?,
+                "sync_policy" => self.sync_policy = parse_variant(&key, &value)?,
+                "labels_index" => self.labels_index = parse_variant(&key, &value)?,
+                "property_indexes" => self.property_indexes = parse_variant(&key, &value)?,
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> ConfigResult<()> {
+        if self.data_dir.is_empty() {
+            return Err(invalid("data_dir", "the data directory is empty"));
+        }
+        if self.listen_address.parse::<SocketAddr>().is_err() {
+            return Err(invalid("listen_address", "not an ip address and port"));
+        }
+        if self.log_level.parse::<LevelFilter>().is_err() {
+            return Err(invalid("log_level", "not one of off, error, warn, info, debug and trace"));
+        }
+        if !is_page_size_valid(self.page_size) {
+            return Err(invalid("page_size", "not a power of two between 4096 and 65536"));
+        }
+        if self.page_cache_capacity == 0 {
+            return Err(invalid("page_cache_capacity", "the page cache holds at least one page"));
+        }
+        let mut names = HashSet::new();
+        for (key, name) in self.files.get_names() {
+            let key = format!("files.{}", key);
+            if name.is_empty() || name.contains(['/', '\\']) {
+                return Err(invalid(&key, "not a file name"));
+            }
+            if !names.insert(name) {
+                return Err(invalid(&key, "the file name is already used"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_init_context(&self) -> InitContext<'static> {
+        InitContext::from_config(self)
+    }
+
+    pub fn get_log_level(&self) -> LevelFilter {
+        self.log_level.parse().unwrap_or(LevelFilter::Info)
+    }
+
+    pub fn get_shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout as u64)
+    }
+}
+
+#[cfg(test)]
+mod test_db_config {
+    use super::*;
+
+    #[test]
+    fn test_load_settings() {
+        let text = "data_dir = \"/var/lib/og\"\npage_cache_capacity = 64\nsync_policy = \"never\"\nlabels_index = \"bwtree\"\n\n[files]\nnodes = \"n.db\"\n";
+        let mut config = DbConfig::from_toml_str(text).unwrap();
+        assert_eq!(config.page_size, DEFAULT_PAGE_SIZE);
+        assert_eq!(config.files.relationships, RELATIONSHIPS_FILE_NAME);
+        let vars = vec![(String::from("OG_PAGE_SIZE"), String::from("8192")), (String::from("OG_SYNC_POLICY"), String::from("always")),
+            (String::from("OG_SHUTDOWN_TIMEOUT"), String::from("30")), (String::from("OG_PROPERTY_INDEXES"), String::from("bwtree")),
+            (String::from("HOME"), String::from("/root"))];
+        config.apply_overrides(vars).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.get_shutdown_timeout(), Duration::from_secs(30));
+
+        let ctx = config.get_init_context();
+        assert_eq!(ctx.get_db_dir(), "/var/lib/og");
+        assert_eq!(ctx.get_page_size(), 8192);
+        assert_eq!(ctx.get_page_cache_capacity(), 64);
+        assert_eq!(ctx.get_sync_policy(), SyncPolicy::Always);
+        assert_eq!(ctx.get_nodes_labels_index_kind(), IndexKind::BwTree);
+        assert_eq!(ctx.get_property_indexes_kind(), IndexKind::BwTree);
+        assert!(ctx.get_nodes_store_path().unwrap().ends_with("n.db"));
+        assert!(ctx.get_nodes_labels_index_path().unwrap().ends_with(NODES_LABELS_BW_INDEX_FILE_NAME));
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        assert!(matches!(DbConfig::from_toml_str("page_cache = 1"), Err(ConfigError::Parse(_))));
+        assert!(matches!(DbConfig::from_toml_str("sync_policy = \"sometimes\""), Err(ConfigError::Parse(_))));
+        let mut config = DbConfig::default();
+        let override_error = config.apply_overrides(vec![(String::from("OG_PAGE_SIZE"), String::from("big"))]);
+        assert_eq!(override_error, Err(invalid("page_size", "not a positive integer")));
+
+        config.page_size = 5000;
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(key, _)) if key == "page_size"));
+        config.page_size = DEFAULT_PAGE_SIZE;
+        config.listen_address = String::from("localhost");
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(key, _)) if key == "listen_address"));
+        config.listen_address = String::from(DEFAULT_LISTEN_ADDRESS);
+        config.files.dynamic = config.files.nodes.clone();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(key, _)) if key == "files.dynamic"));
+    }
+}
@@ -0,0 +1,50 @@
+use super::PropertyValue;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Node of a dump, named by its id in the dumped database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeDump {
+    pub id: u64,
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertyValue>,
+}
+
+/// Relationship of a dump, its source and target are the ids of dumped nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelationshipDump {
+    pub id: u64,
+    pub labels: Vec<String>,
+    pub source: u64,
+    pub target: u64,
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertyValue>,
+}
+
+/// Nodes and relationships of a database, the ids are not kept when a dump is imported.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphDump {
+    pub nodes: Vec<NodeDump>,
+    pub relationships: Vec<RelationshipDump>,
+}
+
+impl GraphDump {
+    /// Number of nodes of each label.
+    pub fn count_labels(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for label in self.nodes.iter().flat_map(|node| node.labels.iter()) {
+            *counts.entry(label.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Number of relationships of each type.
+    pub fn count_relationship_types(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for label in self.relationships.iter().flat_map(|rel| rel.labels.iter()) {
+            *counts.entry(label.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
@@ -1,55 +1,332 @@
 use super::super::config::*;
+use super::super::buf_config::DEFAULT_PAGE_SIZE;
+use super::db_config::DbConfig;
+use serde::Deserialize;
+use std::borrow::Cow;
 use std::path;
 
+/// Index implementation, a B+Tree stored in the records files or a Bw-Tree stored in a log-structured file.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexKind {
+    BTree,
+    BwTree,
+}
+
+impl IndexKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            IndexKind::BTree => "btree",
+            IndexKind::BwTree => "bwtree",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "btree" => Some(IndexKind::BTree),
+            "bwtree" => Some(IndexKind::BwTree),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a commit waits for the write ahead log and the stores to reach the disk,
+/// with `Never` a commit survives a crash of the process but not of the system.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncPolicy {
+    Always,
+    Never,
+}
+
 #[derive(Debug, Clone)]
 pub struct InitContext<'a> {
     db_dir: String,
-    node_store_name: &'a str,
-    relationships_store_name: &'a str,
-    properties_store_name: &'a str,
-    dynamic_store_name: &'a str,
-    nodes_labels_index_name: &'a str,
+    node_store_name: Cow<'a, str>,
+    relationships_store_name: Cow<'a, str>,
+    nodes_history_store_name: Cow<'a, str>,
+    relationships_history_store_name: Cow<'a, str>,
+    properties_store_name: Cow<'a, str>,
+    dynamic_store_name: Cow<'a, str>,
+    nodes_labels_index_name: Cow<'a, str>,
+    nodes_labels_bw_index_name: Cow<'a, str>,
+    nodes_labels_index_kind: IndexKind,
+    labels_store_name: Cow<'a, str>,
+    labels_names_store_name: Cow<'a, str>,
+    nodes_labels_store_name: Cow<'a, str>,
+    relationship_types_store_name: Cow<'a, str>,
+    relationship_types_names_store_name: Cow<'a, str>,
+    property_keys_store_name: Cow<'a, str>,
+    property_keys_names_store_name: Cow<'a, str>,
+    property_indexes_store_name: Cow<'a, str>,
+    property_indexes_names_store_name: Cow<'a, str>,
+    constraints_store_name: Cow<'a, str>,
+    constraints_names_store_name: Cow<'a, str>,
+    property_index_prefix: Cow<'a, str>,
+    property_indexes_kind: IndexKind,
+    wal_name: Cow<'a, str>,
+    page_size: usize,
+    page_cache_capacity: usize,
+    sync_policy: SyncPolicy,
 }
 
 impl <'a> InitContext<'a> {
     pub fn new(dir: &str) -> Self {
-        InitContext{db_dir: String::from(dir), node_store_name: NODES_FILE_NAME,
-            relationships_store_name: RELATIONSHIPS_FILE_NAME, 
-            properties_store_name: PROPERTIES_FILE_NAME,
-            dynamic_store_name: DYN_FILE_NAME,
-            nodes_labels_index_name: NODES_LABELS_INDEX_FILE_NAME,
+        InitContext{db_dir: String::from(dir), node_store_name: Cow::Borrowed(NODES_FILE_NAME),
+            relationships_store_name: Cow::Borrowed(RELATIONSHIPS_FILE_NAME), 
+            nodes_history_store_name: Cow::Borrowed(NODES_HISTORY_FILE_NAME),
+            relationships_history_store_name: Cow::Borrowed(RELATIONSHIPS_HISTORY_FILE_NAME),
+            properties_store_name: Cow::Borrowed(PROPERTIES_FILE_NAME),
+            dynamic_store_name: Cow::Borrowed(DYN_FILE_NAME),
+            nodes_labels_index_name: Cow::Borrowed(NODES_LABELS_INDEX_FILE_NAME),
+            nodes_labels_bw_index_name: Cow::Borrowed(NODES_LABELS_BW_INDEX_FILE_NAME),
+            nodes_labels_index_kind: IndexKind::BTree,
+            labels_store_name: Cow::Borrowed(LABELS_FILE_NAME),
+            labels_names_store_name: Cow::Borrowed(LABELS_NAMES_FILE_NAME),
+            nodes_labels_store_name: Cow::Borrowed(NODES_LABELS_FILE_NAME),
+            relationship_types_store_name: Cow::Borrowed(RELATIONSHIP_TYPES_FILE_NAME),
+            relationship_types_names_store_name: Cow::Borrowed(RELATIONSHIP_TYPES_NAMES_FILE_NAME),
+            property_keys_store_name: Cow::Borrowed(PROPERTY_KEYS_FILE_NAME),
+            property_keys_names_store_name: Cow::Borrowed(PROPERTY_KEYS_NAMES_FILE_NAME),
+            property_indexes_store_name: Cow::Borrowed(PROPERTY_INDEXES_FILE_NAME),
+            property_indexes_names_store_name: Cow::Borrowed(PROPERTY_INDEXES_NAMES_FILE_NAME),
+            constraints_store_name: Cow::Borrowed(CONSTRAINTS_FILE_NAME),
+            constraints_names_store_name: Cow::Borrowed(CONSTRAINTS_NAMES_FILE_NAME),
+            property_index_prefix: Cow::Borrowed(PROPERTY_INDEX_FILE_PREFIX),
+            property_indexes_kind: IndexKind::BTree,
+            wal_name: Cow::Borrowed(WAL_FILE_NAME),
+            page_size: DEFAULT_PAGE_SIZE,
+            page_cache_capacity: PAGE_CACHE_CAPACITY,
+            sync_policy: SyncPolicy::Always,
         }
     }
 
+    /// Context of the database described by a validated configuration.
+    pub fn from_config(config: &DbConfig) -> InitContext<'static> {
+        let files = &config.files;
+        InitContext{db_dir: config.data_dir.clone(), node_store_name: Cow::Owned(files.nodes.clone()),
+            relationships_store_name: Cow::Owned(files.relationships.clone()),
+            nodes_history_store_name: Cow::Owned(files.nodes_history.clone()),
+            relationships_history_store_name: Cow::Owned(files.relationships_history.clone()),
+            properties_store_name: Cow::Owned(files.properties.clone()),
+            dynamic_store_name: Cow::Owned(files.dynamic.clone()),
+            nodes_labels_index_name: Cow::Owned(files.nodes_labels_index.clone()),
+            nodes_labels_bw_index_name: Cow::Owned(files.nodes_labels_bw_index.clone()),
+            nodes_labels_index_kind: config.labels_index,
+            labels_store_name: Cow::Owned(files.labels.clone()),
+            labels_names_store_name: Cow::Owned(files.labels_names.clone()),
+            nodes_labels_store_name: Cow::Owned(files.nodes_labels.clone()),
+            relationship_types_store_name: Cow::Owned(files.relationship_types.clone()),
+            relationship_types_names_store_name: Cow::Owned(files.relationship_types_names.clone()),
+            property_keys_store_name: Cow::Owned(files.property_keys.clone()),
+            property_keys_names_store_name: Cow::Owned(files.property_keys_names.clone()),
+            property_indexes_store_name: Cow::Owned(files.property_indexes.clone()),
+            property_indexes_names_store_name: Cow::Owned(files.property_indexes_names.clone()),
+            constraints_store_name: Cow::Owned(files.constraints.clone()),
+            constraints_names_store_name: Cow::Owned(files.constraints_names.clone()),
+            property_index_prefix: Cow::Owned(files.property_index_prefix.clone()),
+            property_indexes_kind: config.property_indexes,
+            wal_name: Cow::Owned(files.wal.clone()),
+            page_size: config.page_size,
+            page_cache_capacity: config.page_cache_capacity,
+            sync_policy: config.sync_policy,
+        }
+    }
+
+    pub fn get_db_dir(&self) -> &str {
+        &self.db_dir
+    }
+
+    pub fn set_db_dir(&mut self, dir: &str) {
+        self.db_dir = String::from(dir);
+    }
+
     pub fn get_nodes_store_path(&self) -> Option<String> {
         let mut file_path = path::PathBuf::new();
         file_path.push(self.db_dir.as_str());
-        file_path.push(self.node_store_name);
+        file_path.push(&*self.node_store_name);
         file_path.to_str().map(String::from)
     }
     pub fn get_relationships_store_path(&self) -> Option<String> {
         let mut file_path = path::PathBuf::new();
         file_path.push(self.db_dir.as_str());
-        file_path.push(self.relationships_store_name);
+        file_path.push(&*self.relationships_store_name);
+        file_path.to_str().map(String::from)
+    }
+    pub fn get_nodes_history_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.nodes_history_store_name);
+        file_path.to_str().map(String::from)
+    }
+    pub fn get_relationships_history_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.relationships_history_store_name);
         file_path.to_str().map(String::from)
     }
     pub fn get_properties_store_path(&self) -> Option<String> {
         let mut file_path = path::PathBuf::new();
         file_path.push(self.db_dir.as_str());
-        file_path.push(self.properties_store_name);
+        file_path.push(&*self.properties_store_name);
         file_path.to_str().map(String::from)
     }
     pub fn get_dynamic_store_path(&self) -> Option<String> {
         let mut file_path = path::PathBuf::new();
         file_path.push(self.db_dir.as_str());
-        file_path.push(self.dynamic_store_name);
+        file_path.push(&*self.dynamic_store_name);
         file_path.to_str().map(String::from)
     }
     
+    /// Path of the labels index of the configured kind.
     pub fn get_nodes_labels_index_path(&self) -> Option<String> {
+        self.get_nodes_labels_index_path_for(self.nodes_labels_index_kind)
+    }
+
+    /// Each kind of labels index has its own file.
+    pub fn get_nodes_labels_index_path_for(&self, kind: IndexKind) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        match kind {
+            IndexKind::BTree => file_path.push(&*self.nodes_labels_index_name),
+            IndexKind::BwTree => file_path.push(&*self.nodes_labels_bw_index_name),
+        }
+        file_path.to_str().map(String::from)
+    }
+
+    /// Page size of the files created in the database, existing files keep the page size they were created with.
+    pub fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size;
+    }
+
+    /// Number of pages each store keeps in its page cache.
+    pub fn get_page_cache_capacity(&self) -> usize {
+        self.page_cache_capacity
+    }
+
+    pub fn set_page_cache_capacity(&mut self, page_cache_capacity: usize) {
+        self.page_cache_capacity = page_cache_capacity;
+    }
+
+    pub fn get_sync_policy(&self) -> SyncPolicy {
+        self.sync_policy
+    }
+
+    pub fn set_sync_policy(&mut self, sync_policy: SyncPolicy) {
+        self.sync_policy = sync_policy;
+    }
+
+    pub fn get_nodes_labels_index_kind(&self) -> IndexKind {
+        self.nodes_labels_index_kind
+    }
+
+    /// The repository refuses a database whose labels index was written with the other kind.
+    pub fn set_nodes_labels_index_kind(&mut self, kind: IndexKind) {
+        self.nodes_labels_index_kind = kind;
+    }
+
+    /// Kind of the property indexes created from now on, the existing ones keep their kind.
+    pub fn get_property_indexes_kind(&self) -> IndexKind {
+        self.property_indexes_kind
+    }
+
+    pub fn set_property_indexes_kind(&mut self, kind: IndexKind) {
+        self.property_indexes_kind = kind;
+    }
+
+    pub fn get_labels_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.labels_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_labels_names_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.labels_names_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_nodes_labels_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.nodes_labels_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_relationship_types_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.relationship_types_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_relationship_types_names_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.relationship_types_names_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_property_keys_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.property_keys_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_property_keys_names_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.property_keys_names_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_property_indexes_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.property_indexes_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_property_indexes_names_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.property_indexes_names_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    /// Path prefix of the property index files, each index file is suffixed by its id.
+    pub fn get_property_index_path_prefix(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.property_index_prefix);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_constraints_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.constraints_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_constraints_names_store_path(&self) -> Option<String> {
+        let mut file_path = path::PathBuf::new();
+        file_path.push(self.db_dir.as_str());
+        file_path.push(&*self.constraints_names_store_name);
+        file_path.to_str().map(String::from)
+    }
+
+    pub fn get_wal_path(&self) -> Option<String> {
         let mut file_path = path::PathBuf::new();
         file_path.push(self.db_dir.as_str());
-        file_path.push(self.nodes_labels_index_name);
+        file_path.push(&*self.wal_name);
         file_path.to_str().map(String::from)
     }
 }
\ No newline at end of file
@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// Format upgrade of a store file, a file already at the target version has both versions equal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMigration {
+    /// Name of the store file in the database directory.
+    pub file: String,
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+impl fmt::Display for FileMigration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.from_version == self.to_version {
+            write!(f, "{}: version {} is up to date", self.file, self.to_version)
+        } else {
+            write!(f, "{}: version {} migrated to {}", self.file, self.from_version, self.to_version)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MigrationReport {
+    pub files: Vec<FileMigration>,
+}
+
+impl MigrationReport {
+    /// Files whose format changed.
+    pub fn get_migrated(&self) -> Vec<&FileMigration> {
+        self.files.iter().filter(|file| file.from_version != file.to_version).collect()
+    }
+}
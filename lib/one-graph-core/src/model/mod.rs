@@ -1,9 +1,19 @@
 use super::graph::*;
 pub mod init;
+pub mod db_config;
+pub mod constraint;
+pub mod check;
+pub mod migration;
+pub mod dump;
+pub mod stats;
+use std::cmp::Ordering;
 use std::hash::Hash;
 use std::hash::Hasher;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+/// Values are written in dumps as plain strings, numbers and booleans.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
 pub enum PropertyValue {
     PString(String),
     PInteger(i64),
@@ -52,6 +62,18 @@ impl PartialEq for PropertyValue {
 }
 impl Eq for PropertyValue {}
 
+/// Orders values of a same type, values of different types are not comparable.
+pub fn compare_values(v0: &PropertyValue, v1: &PropertyValue) -> Option<Ordering> {
+    use self::PropertyValue::*;
+    match (v0, v1) {
+        (PBool(sval), PBool(oval)) => Some(sval.cmp(oval)),
+        (PString(sval), PString(oval)) => Some(sval.cmp(oval)),
+        (PInteger(sval), PInteger(oval)) => Some(sval.cmp(oval)),
+        (PFloat(sval), PFloat(oval)) => sval.partial_cmp(oval),
+        _ => None,
+    }
+}
+
 #[derive(Hash, Eq, PartialEq, Clone)]
 pub struct Property {
     id: Option<u64>,
@@ -92,6 +114,83 @@ impl Property {
     }
 }
 
+/// Gives its new value to the property of the same name, appends the property when the list has none.
+pub fn set_in_list(props: &mut Vec<Property>, prop: &Property) {
+    match props.iter_mut().find(|p| p.get_name() == prop.get_name()) {
+        Some(p) => p.set_value(prop.get_value().clone()),
+        None => props.push(prop.clone()),
+    }
+}
+
+/// Range constraint of a pattern on a property, bounds are (value, inclusive) and a missing bound is unbounded.
+#[derive(Hash, Eq, PartialEq, Clone)]
+pub struct PropertyRange {
+    name: String,
+    lower: Option<(PropertyValue, bool)>,
+    upper: Option<(PropertyValue, bool)>,
+}
+
+impl PropertyRange {
+    pub fn new(name: &str, lower: Option<(PropertyValue, bool)>, upper: Option<(PropertyValue, bool)>) -> Self {
+        PropertyRange {name: String::from(name), lower, upper}
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_lower(&self) -> &Option<(PropertyValue, bool)> {
+        &self.lower
+    }
+
+    pub fn get_upper(&self) -> &Option<(PropertyValue, bool)> {
+        &self.upper
+    }
+
+    pub fn contains(&self, value: &PropertyValue) -> bool {
+        let above_lower = match &self.lower {
+            Some((lower, inclusive)) => match compare_values(value, lower) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Equal) => *inclusive,
+                _ => false,
+            },
+            None => true,
+        };
+        let below_upper = match &self.upper {
+            Some((upper, inclusive)) => match compare_values(value, upper) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => *inclusive,
+                _ => false,
+            },
+            None => true,
+        };
+        above_lower && below_upper
+    }
+}
+
+/// Kind of graph element a property index or a constraint applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementKind {
+    Node,
+    Relationship,
+}
+
+impl ElementKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ElementKind::Node => "node",
+            ElementKind::Relationship => "relationship",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "node" => Some(ElementKind::Node),
+            "relationship" => Some(ElementKind::Relationship),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Hash, Eq, PartialEq, Clone, Copy)]
 pub enum Status {
@@ -106,6 +205,7 @@ pub struct Node {
     id: Option<u64>,
     var: Option<String>,
     properties: Vec<Property>,
+    property_ranges: Vec<PropertyRange>,
     labels: Vec<String>,
     status: Status,
 }
@@ -113,7 +213,7 @@ pub struct Node {
 
 impl Node {
     pub fn new() -> Self {
-        Node {var: None, properties: Vec::new(), labels: Vec::new(), id:None, status: Status::Empty}
+        Node {var: None, properties: Vec::new(), property_ranges: Vec::new(), labels: Vec::new(), id:None, status: Status::Empty}
     }
 
     pub fn get_id(&self) -> Option<u64> {
@@ -147,6 +247,19 @@ impl Node {
     pub fn set_properties(&mut self, properties: Vec<Property>) {
         self.properties = properties;
     }
+
+    pub fn get_property_ranges_ref(&self) -> &Vec<PropertyRange> {
+        &self.property_ranges
+    }
+
+    pub fn add_property_range(&mut self, range: PropertyRange) {
+        self.property_ranges.push(range);
+    }
+
+    pub fn set_labels(&mut self, labels: Vec<String>) {
+        self.labels = labels;
+    }
+
     pub fn get_labels_ref(&self) -> &Vec<String> {
         &self.labels
     }
@@ -229,4 +342,20 @@ impl Relationship {
     }
 }
 
-pub type PropertyGraph = container::GraphContainer<Node, Relationship>;
\ No newline at end of file
+pub type PropertyGraph = container::GraphContainer<Node, Relationship>;
+
+#[derive(Clone)]
+pub struct NodeVersion {
+    pub version: u64,
+    pub node: Node,
+    pub outbound_relationships: Vec<u64>,
+    pub inbound_relationships: Vec<u64>,
+}
+
+#[derive(Clone)]
+pub struct RelationshipVersion {
+    pub version: u64,
+    pub relationship: Relationship,
+    pub source: u64,
+    pub target: u64,
+}
\ No newline at end of file
@@ -0,0 +1,13 @@
+use std::collections::BTreeMap;
+
+/// Counts of the current graph, deleted nodes and relationships kept for the history are left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub relationships: usize,
+    /// Number of nodes with each label.
+    pub labels: BTreeMap<String, usize>,
+    /// Number of relationships of each type.
+    pub relationship_types: BTreeMap<String, usize>,
+    pub constraints: usize,
+}
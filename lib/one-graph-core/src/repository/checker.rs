@@ -0,0 +1,531 @@
+use super::store::nodes_store::NodesStore;
+use super::store::relationships_store::RelationshipsStore;
+use super::store::properties_store::PropertiesStore;
+use super::store::dynamic_store::DynamicStore;
+use super::store::token_store::TokenStore;
+use super::store::records::TOMBSTONE;
+use super::byte_utils::u64_from_bytes;
+use super::records::RecordsManagerResult;
+use super::index::*;
+use super::graph_repository::GraphRepository;
+use super::migration::get_file_name;
+use super::super::model::init::InitContext;
+use super::super::model::check::*;
+use std::collections::{HashMap, HashSet};
+
+/// Record owning a properties chain, versions are the records of the history stores.
+#[derive(Clone, Copy)]
+enum Owner {
+    Node,
+    Relationship,
+    NodeVersion,
+    RelationshipVersion,
+}
+
+impl Owner {
+    fn get_file(self, files: &CheckedFiles) -> &str {
+        match self {
+            Owner::Node => &files.nodes,
+            Owner::Relationship => &files.relationships,
+            Owner::NodeVersion => &files.nodes_history,
+            Owner::RelationshipVersion => &files.relationships_history,
+        }
+    }
+}
+
+/// Names of the checked files as the init context resolves them, the findings are labelled with them.
+struct CheckedFiles {
+    nodes: String,
+    relationships: String,
+    nodes_history: String,
+    relationships_history: String,
+    properties: String,
+    dyn_store: String,
+    property_keys: String,
+    property_keys_names: String,
+    nodes_labels_index: String,
+}
+
+impl CheckedFiles {
+    fn new(init_ctx: &InitContext) -> Self {
+        let name = |path: Option<String>| path.map(|path| get_file_name(&path)).unwrap_or_default();
+        CheckedFiles{nodes: name(init_ctx.get_nodes_store_path()),
+            relationships: name(init_ctx.get_relationships_store_path()),
+            nodes_history: name(init_ctx.get_nodes_history_store_path()),
+            relationships_history: name(init_ctx.get_relationships_history_store_path()),
+            properties: name(init_ctx.get_properties_store_path()),
+            dyn_store: name(init_ctx.get_dynamic_store_path()),
+            property_keys: name(init_ctx.get_property_keys_store_path()),
+            property_keys_names: name(init_ctx.get_property_keys_names_store_path()),
+            nodes_labels_index: name(init_ctx.get_nodes_labels_index_path())}
+    }
+}
+
+/// History records reached with their properties and, when the chain is broken, the last record reached with the broken link.
+type HistoryWalk = (Vec<(u64, u64)>, Option<(Option<u64>, u64)>);
+
+/// Walks the history records from `first` with `load` which gives the previous version and the properties of a record.
+fn walk_history(mut load: impl FnMut(u64) -> RecordsManagerResult<(u64, u64)>, first: u64) -> HistoryWalk {
+    let mut reached = Vec::new();
+    let mut visited = HashSet::new();
+    let mut last = None;
+    let mut version_id = first;
+    while version_id != 0 {
+        match load(version_id) {
+            Ok((prev_version, next_prop_id)) if visited.insert(version_id) => {
+                reached.push((version_id, next_prop_id));
+                last = Some(version_id);
+                version_id = prev_version;
+            },
+            _ => return (reached, Some((last, version_id))),
+        }
+    }
+    (reached, None)
+}
+
+/// Offline consistency checker of the nodes, relationships, properties and dynamic stores, of their history stores,
+/// of the property keys store and of the label index.
+/// Repairs cut the broken chains at their last valid link and drop dangling index entries,
+/// the other problems are only reported.
+pub struct ConsistencyChecker {
+    nodes_store: NodesStore,
+    relationships_store: RelationshipsStore,
+    nodes_history_store: NodesStore,
+    relationships_history_store: RelationshipsStore,
+    properties_store: PropertiesStore,
+    dyn_store: DynamicStore,
+    property_keys_store: TokenStore,
+    nodes_labels_index: Box<dyn Index>,
+    files: CheckedFiles,
+    key_ids: HashSet<u64>,
+    checked_properties: HashSet<u64>,
+    repair: bool,
+    report: CheckReport,
+}
+
+impl ConsistencyChecker {
+    /// Opens the repository first so that the committed write ahead log is replayed and the indexes that are not logged
+    /// are rebuilt, the stores are then checked as the repository opens them.
+    pub fn new(init_ctx: &InitContext) -> Self {
+        let _ = GraphRepository::new(init_ctx);
+        let page_size = init_ctx.get_page_size();
+        ConsistencyChecker{nodes_store: NodesStore::new(&init_ctx.get_nodes_store_path().unwrap(), page_size),
+            relationships_store: RelationshipsStore::new(&init_ctx.get_relationships_store_path().unwrap(), page_size),
+            nodes_history_store: NodesStore::new(&init_ctx.get_nodes_history_store_path().unwrap(), page_size),
+            relationships_history_store: RelationshipsStore::new(&init_ctx.get_relationships_history_store_path().unwrap(), page_size),
+            properties_store: PropertiesStore::new(&init_ctx.get_properties_store_path().unwrap(), page_size),
+            dyn_store: DynamicStore::new(&init_ctx.get_dynamic_store_path().unwrap(), page_size),
+            property_keys_store: TokenStore::new(&init_ctx.get_property_keys_store_path().unwrap(), &init_ctx.get_property_keys_names_store_path().unwrap(), page_size),
+            nodes_labels_index: open_index(init_ctx.get_nodes_labels_index_kind(), &init_ctx.get_nodes_labels_index_path().unwrap(), page_size),
+            files: CheckedFiles::new(init_ctx),
+            key_ids: HashSet::new(), checked_properties: HashSet::new(), repair: false, report: CheckReport::default()}
+    }
+
+    fn report(&mut self, file: String, problem: Problem, repaired: bool) {
+        self.report.findings.push(Finding{file, problem, repaired});
+    }
+
+    /// Fails when the repaired stores cannot be written, the findings are then not repaired on disk.
+    pub fn check(mut self, repair: bool) -> RecordsManagerResult<CheckReport> {
+        self.repair = repair;
+        self.check_pages();
+        self.check_property_keys();
+        let node_ids = self.check_relationships();
+        let versions = self.check_histories();
+        self.check_properties(&versions);
+        self.check_dyn_chains();
+        self.check_labels_index(&node_ids);
+        if repair {
+            self.nodes_store.sync()?;
+            self.relationships_store.sync()?;
+            self.nodes_history_store.sync()?;
+            self.relationships_history_store.sync()?;
+            self.properties_store.sync()?;
+            self.dyn_store.sync()?;
+            self.nodes_labels_index.sync()?;
+        }
+        Ok(self.report)
+    }
+
+    fn check_pages(&mut self) {
+        let repair = self.repair;
+        let files = &self.files;
+        let pages = vec![(files.nodes.clone(), self.nodes_store.check_pages(repair)),
+            (files.relationships.clone(), self.relationships_store.check_pages(repair)),
+            (files.nodes_history.clone(), self.nodes_history_store.check_pages(repair)),
+            (files.relationships_history.clone(), self.relationships_history_store.check_pages(repair)),
+            (files.properties.clone(), self.properties_store.check_pages(repair)),
+            (files.dyn_store.clone(), self.dyn_store.check_pages(repair)),
+            (files.property_keys.clone(), self.property_keys_store.check_pages(repair)),
+            (files.property_keys_names.clone(), self.property_keys_store.check_names_pages(repair))];
+        for (file, problems) in pages {
+            for (problem, repaired) in problems {
+                self.report(file.clone(), problem, repaired);
+            }
+        }
+    }
+
+    /// Every property key token must have a name, the tokens are kept to check the keys of the properties.
+    fn check_property_keys(&mut self) {
+        let (key_ids, missing) = self.property_keys_store.check_names();
+        for (token, record) in missing {
+            self.report(self.files.property_keys.clone(), Problem::MissingTokenName{token, record}, false);
+        }
+        self.key_ids = key_ids.into_iter().collect();
+    }
+
+    /// Cuts the relationships chain of the node after `last`, or at its head when there is no valid link.
+    fn cut_relationships_chain(&mut self, node_id: u64, last: Option<u64>, outbound: bool) -> RecordsManagerResult<()> {
+        match last {
+            Some(rel_id) => {
+                let mut rr = self.relationships_store.load(rel_id)?;
+                if outbound {rr.next_outbound_edge = 0} else {rr.next_inbound_edge = 0}
+                self.relationships_store.save(rel_id, &rr)
+            },
+            None => {
+                let mut nr = self.nodes_store.load(node_id)?;
+                if outbound {nr.first_outbound_edge = 0} else {nr.first_inbound_edge = 0}
+                self.nodes_store.save(node_id, &nr)
+            },
+        }
+    }
+
+    /// Walks one relationships chain of the node, returns the relationships linked before the first broken link.
+    fn check_relationships_chain(&mut self, node_id: u64, first: u64, outbound: bool) -> HashSet<u64> {
+        let mut linked = HashSet::new();
+        let mut last = None;
+        let mut rel_id = first;
+        while rel_id != 0 {
+            let problem = match self.relationships_store.load(rel_id) {
+                _ if linked.contains(&rel_id) => Problem::RelationshipChainCycle{node: node_id, relationship: rel_id},
+                Err(_) => Problem::DanglingRelationship{node: node_id, relationship: rel_id},
+                Ok(rr) if (if outbound {rr.source} else {rr.target}) != node_id => Problem::MisplacedRelationship{node: node_id, relationship: rel_id},
+                Ok(rr) => {
+                    linked.insert(rel_id);
+                    last = Some(rel_id);
+                    rel_id = if outbound {rr.next_outbound_edge} else {rr.next_inbound_edge};
+                    continue;
+                },
+            };
+            let repaired = self.repair && self.cut_relationships_chain(node_id, last, outbound).is_ok();
+            self.report(self.files.relationships.clone(), problem, repaired);
+            break;
+        }
+        linked
+    }
+
+    /// Checks the edge chains of every node and the endpoints of every relationship, returns the nodes not deleted.
+    /// A relationship that is not deleted must not point to a deleted node.
+    fn check_relationships(&mut self) -> HashSet<u64> {
+        let nodes = self.nodes_store.scan().map(|(id, nr)| (id, nr.first_outbound_edge, nr.first_inbound_edge)).collect::<Vec<(u64, u64, u64)>>();
+        let live_node_ids = self.nodes_store.scan().filter(|(_, nr)| !nr.is_tombstone()).map(|(id, _)| id).collect::<HashSet<u64>>();
+        let mut outbound_linked = HashSet::new();
+        let mut inbound_linked = HashSet::new();
+        for (node_id, first_outbound, first_inbound) in &nodes {
+            outbound_linked.extend(self.check_relationships_chain(*node_id, *first_outbound, true));
+            inbound_linked.extend(self.check_relationships_chain(*node_id, *first_inbound, false));
+        }
+        let node_ids = nodes.iter().map(|(id, _, _)| *id).collect::<HashSet<u64>>();
+        let relationships = self.relationships_store.scan().map(|(id, rr)| (id, rr.source, rr.target, rr.is_tombstone())).collect::<Vec<(u64, u64, u64, bool)>>();
+        for (rel_id, source, target, deleted) in relationships {
+            let mut missing = false;
+            let known_ids = if deleted {&node_ids} else {&live_node_ids};
+            for node_id in &[source, target] {
+                if !known_ids.contains(node_id) {
+                    self.report(self.files.relationships.clone(), Problem::MissingNode{relationship: rel_id, node: *node_id}, false);
+                    missing = true;
+                }
+            }
+            if !missing && (!outbound_linked.contains(&rel_id) || !inbound_linked.contains(&rel_id)) {
+                self.report(self.files.relationships.clone(), Problem::UnlinkedRelationship(rel_id), false);
+            }
+        }
+        live_node_ids
+    }
+
+    /// Cuts the versions of the record after the history record `last`, or at the record itself when there is no valid link.
+    fn cut_history_chain(&mut self, owner: Owner, owner_id: u64, last: Option<u64>) -> RecordsManagerResult<()> {
+        match (owner, last) {
+            (Owner::Node, Some(version_id)) => {
+                let mut nr = self.nodes_history_store.load(version_id)?;
+                nr.prev_version = 0;
+                self.nodes_history_store.save(version_id, &nr)
+            },
+            (Owner::Node, None) => {
+                let mut nr = self.nodes_store.load(owner_id)?;
+                nr.prev_version = 0;
+                self.nodes_store.save(owner_id, &nr)
+            },
+            (_, Some(version_id)) => {
+                let mut rr = self.relationships_history_store.load(version_id)?;
+                rr.prev_version = 0;
+                self.relationships_history_store.save(version_id, &rr)
+            },
+            (_, None) => {
+                let mut rr = self.relationships_store.load(owner_id)?;
+                rr.prev_version = 0;
+                self.relationships_store.save(owner_id, &rr)
+            },
+        }
+    }
+
+    /// Walks the versions of every node and relationship, returns the properties chains of the versions reached.
+    /// History records that no record reaches are reported as unlinked.
+    fn check_histories(&mut self) -> Vec<(Owner, u64, u64)> {
+        let mut versions = Vec::new();
+        let nodes = self.nodes_store.scan().map(|(id, nr)| (Owner::Node, id, nr.prev_version)).collect::<Vec<(Owner, u64, u64)>>();
+        let relationships = self.relationships_store.scan().map(|(id, rr)| (Owner::Relationship, id, rr.prev_version)).collect::<Vec<(Owner, u64, u64)>>();
+        let mut nodes_reached = HashSet::new();
+        let mut relationships_reached = HashSet::new();
+        for (owner, owner_id, first) in nodes.into_iter().chain(relationships) {
+            let (reached, broken) = match owner {
+                Owner::Node => walk_history(|id| self.nodes_history_store.load(id).map(|nr| (nr.prev_version, nr.next_prop_id)), first),
+                _ => walk_history(|id| self.relationships_history_store.load(id).map(|rr| (rr.prev_version, rr.next_prop_id)), first),
+            };
+            let (version_owner, reached_ids) = match owner {
+                Owner::Node => (Owner::NodeVersion, &mut nodes_reached),
+                _ => (Owner::RelationshipVersion, &mut relationships_reached),
+            };
+            reached_ids.extend(reached.iter().map(|(id, _)| *id));
+            versions.extend(reached.into_iter().filter(|(_, next_prop_id)| *next_prop_id != TOMBSTONE).map(|(id, next_prop_id)| (version_owner, id, next_prop_id)));
+            if let Some((last, version)) = broken {
+                let repaired = self.repair && self.cut_history_chain(owner, owner_id, last).is_ok();
+                self.report(String::from(version_owner.get_file(&self.files)), Problem::BrokenHistoryChain{owner: owner_id, version}, repaired);
+            }
+        }
+        let unlinked_nodes = self.nodes_history_store.scan().map(|(id, _)| id).filter(|id| !nodes_reached.contains(id)).collect::<Vec<u64>>();
+        let unlinked_relationships = self.relationships_history_store.scan().map(|(id, _)| id).filter(|id| !relationships_reached.contains(id)).collect::<Vec<u64>>();
+        for version_id in unlinked_nodes {
+            self.report(self.files.nodes_history.clone(), Problem::UnlinkedHistoryVersion(version_id), false);
+        }
+        for version_id in unlinked_relationships {
+            self.report(self.files.relationships_history.clone(), Problem::UnlinkedHistoryVersion(version_id), false);
+        }
+        versions
+    }
+
+    /// Cuts the properties chain after `last`, or detaches it from its owner when there is no valid link.
+    fn cut_properties_chain(&mut self, owner: Owner, owner_id: u64, last: Option<u64>) -> RecordsManagerResult<()> {
+        match (owner, last) {
+            (_, Some(prop_id)) => {
+                let mut pr = self.properties_store.load(prop_id)?;
+                pr.next_prop_id = 0;
+                self.properties_store.save(prop_id, &pr)
+            },
+            (Owner::Node, None) => {
+                let mut nr = self.nodes_store.load(owner_id)?;
+                nr.next_prop_id = 0;
+                self.nodes_store.save(owner_id, &nr)
+            },
+            (Owner::Relationship, None) => {
+                let mut rr = self.relationships_store.load(owner_id)?;
+                rr.next_prop_id = 0;
+                self.relationships_store.save(owner_id, &rr)
+            },
+            (Owner::NodeVersion, None) => {
+                let mut nr = self.nodes_history_store.load(owner_id)?;
+                nr.next_prop_id = 0;
+                self.nodes_history_store.save(owner_id, &nr)
+            },
+            (Owner::RelationshipVersion, None) => {
+                let mut rr = self.relationships_history_store.load(owner_id)?;
+                rr.next_prop_id = 0;
+                self.relationships_history_store.save(owner_id, &rr)
+            },
+        }
+    }
+
+    /// Versions share the properties chains that did not change, the key and the value of a property are checked once.
+    fn check_properties_chain(&mut self, owner: Owner, owner_id: u64, first: u64) {
+        let mut visited = HashSet::new();
+        let mut last = None;
+        let mut prop_id = first;
+        while prop_id != 0 {
+            let record = if visited.insert(prop_id) {
+                self.properties_store.load(prop_id).ok().filter(|pr| pr.in_use)
+            } else {
+                None
+            };
+            match record {
+                Some(pr) => {
+                    if self.checked_properties.insert(prop_id) {
+                        if !self.key_ids.contains(&pr.key_id) {
+                            self.report(self.files.properties.clone(), Problem::MissingPropertyKey{property: prop_id, key: pr.key_id}, false);
+                        }
+                        if !pr.full_inlined {
+                            let value_id = u64_from_bytes(&pr.prop_block[..std::mem::size_of::<u64>()]);
+                            if !self.dyn_store.load(value_id).map(|dr| dr.in_use).unwrap_or(false) {
+                                self.report(self.files.properties.clone(), Problem::MissingPropertyValue{property: prop_id, record: value_id}, false);
+                            }
+                        }
+                    }
+                    last = Some(prop_id);
+                    prop_id = pr.next_prop_id;
+                },
+                None => {
+                    let repaired = self.repair && self.cut_properties_chain(owner, owner_id, last).is_ok();
+                    self.report(String::from(owner.get_file(&self.files)), Problem::BrokenPropertyChain{owner: owner_id, property: prop_id}, repaired);
+                    break;
+                },
+            }
+        }
+    }
+
+    fn check_properties(&mut self, versions: &[(Owner, u64, u64)]) {
+        let nodes = self.nodes_store.scan().filter(|(_, nr)| !nr.is_tombstone()).map(|(id, nr)| (Owner::Node, id, nr.next_prop_id)).collect::<Vec<(Owner, u64, u64)>>();
+        let relationships = self.relationships_store.scan().filter(|(_, rr)| !rr.is_tombstone()).map(|(id, rr)| (Owner::Relationship, id, rr.next_prop_id)).collect::<Vec<(Owner, u64, u64)>>();
+        for (owner, owner_id, first) in nodes.into_iter().chain(relationships).chain(versions.iter().copied()) {
+            self.check_properties_chain(owner, owner_id, first);
+        }
+    }
+
+    /// Every `has_next` must lead to a record in use and no chain may loop.
+    fn check_dyn_chains(&mut self) {
+        let records = self.dyn_store.scan().filter(|(_, dr)| dr.in_use).map(|(id, dr)| (id, dr.has_next, dr.next)).collect::<Vec<(u64, bool, u64)>>();
+        let used = records.iter().map(|(id, _, _)| *id).collect::<HashSet<u64>>();
+        let nexts = records.iter().filter(|(_, has_next, _)| *has_next).map(|(id, _, next)| (*id, *next)).collect::<HashMap<u64, u64>>();
+        let mut broken = records.iter().filter(|(_, has_next, next)| *has_next && !used.contains(next)).map(|(id, _, _)| *id).collect::<Vec<u64>>();
+        let mut done = HashSet::new();
+        for (id, _, _) in &records {
+            let mut path = HashSet::new();
+            let mut curr = *id;
+            while let Some(next) = nexts.get(&curr) {
+                if done.contains(&curr) {
+                    break;
+                }
+                if !path.insert(curr) {
+                    broken.push(curr);
+                    break;
+                }
+                curr = *next;
+            }
+            done.extend(path);
+        }
+        broken.sort();
+        broken.dedup();
+        for id in broken {
+            self.report(self.files.dyn_store.clone(), Problem::BrokenDynChain(id), false);
+        }
+    }
+
+    fn check_labels_index(&mut self, node_ids: &HashSet<u64>) {
+        let mut indexed = self.nodes_labels_index.range(None, None, true).unwrap_or_default().into_iter().map(|(_, id)| id).collect::<Vec<u64>>();
+        indexed.sort();
+        indexed.dedup();
+        for node_id in indexed {
+            if !node_ids.contains(&node_id) {
+                let repaired = self.repair && self.nodes_labels_index.delete(node_id).is_some();
+                self.report(self.files.nodes_labels_index.clone(), Problem::DanglingLabelIndexEntry(node_id), repaired);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_checker {
+    use super::*;
+    use super::super::graph_repository::GraphRepository;
+    use super::super::super::model::*;
+    use super::super::super::test_utils::*;
+    use super::super::super::model::db_config::DbConfig;
+    use super::super::super::buf_config::DEFAULT_PAGE_SIZE;
+
+    fn build_person(name: &str) -> Node {
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        let mut prop = Property::new();
+        prop.set_name("name");
+        prop.set_value(Some(PropertyValue::PString(String::from(name))));
+        node.get_properties_mut().push(prop);
+        node
+    }
+
+    #[test]
+    fn test_check_and_repair() {
+        let dir = build_dir_path_and_rm_old("test_check_and_repair").unwrap();
+        let ctx = InitContext::new(&dir);
+        let (bob, carol, knows_carol) = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let alice = repo.create_node(&build_person("alice")).unwrap().get_id().unwrap();
+            let bob = repo.create_node(&build_person("bob, whose name does not fit in a property block")).unwrap().get_id().unwrap();
+            let carol = repo.create_node(&build_person("carol")).unwrap().get_id().unwrap();
+            repo.create_relationship(&Relationship::new(), alice, bob).unwrap();
+            let knows_carol = repo.create_relationship(&Relationship::new(), alice, carol).unwrap().get_id().unwrap();
+            repo.sync().unwrap();
+            (bob, carol, knows_carol)
+        };
+        assert!(ConsistencyChecker::new(&ctx).check(false).unwrap().is_consistent());
+
+        {
+            let mut nodes_store = NodesStore::new(&ctx.get_nodes_store_path().unwrap(), DEFAULT_PAGE_SIZE);
+            nodes_store.delete(carol).unwrap();
+            let mut nr = nodes_store.load(bob).unwrap();
+            nr.next_prop_id = 999;
+            nodes_store.save(bob, &nr).unwrap();
+            nodes_store.sync().unwrap();
+        }
+
+        let expected = [Problem::BrokenPropertyChain{owner: bob, property: 999},
+            Problem::DanglingLabelIndexEntry(carol),
+            Problem::MissingNode{relationship: knows_carol, node: carol}];
+        let report = ConsistencyChecker::new(&ctx).check(false).unwrap();
+        assert_eq!(report.findings.len(), expected.len());
+        assert!(expected.iter().all(|p| report.findings.iter().any(|f| &f.problem == p && !f.repaired)));
+
+        let report = ConsistencyChecker::new(&ctx).check(true).unwrap();
+        assert_eq!(report.get_unrepaired().len(), 1);
+        assert_eq!(report.get_unrepaired()[0].problem, Problem::MissingNode{relationship: knows_carol, node: carol});
+
+        let report = ConsistencyChecker::new(&ctx).check(false).unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].problem, Problem::MissingNode{relationship: knows_carol, node: carol});
+    }
+
+    #[test]
+    fn test_check_histories_and_property_keys() {
+        let mut config = DbConfig{data_dir: build_dir_path_and_rm_old("test_check_histories_and_property_keys").unwrap(), ..DbConfig::default()};
+        config.files.nodes_history = String::from("nodes_versions.db");
+        config.files.properties = String::from("props.db");
+        let ctx = InitContext::from_config(&config);
+        let alice = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let alice = repo.create_node(&build_person("alice")).unwrap().get_id().unwrap();
+            repo.sync().unwrap();
+            let mut age = Property::new();
+            age.set_name("age");
+            age.set_value(Some(PropertyValue::PInteger(30)));
+            repo.set_node_property(alice, &age).unwrap();
+            repo.sync().unwrap();
+            alice
+        };
+        assert!(ConsistencyChecker::new(&ctx).check(false).unwrap().is_consistent());
+
+        let page_size = DEFAULT_PAGE_SIZE;
+        let first_prop_id = {
+            let mut nodes_store = NodesStore::new(&ctx.get_nodes_store_path().unwrap(), page_size);
+            let mut nodes_history_store = NodesStore::new(&ctx.get_nodes_history_store_path().unwrap(), page_size);
+            let nr = nodes_store.load(alice).unwrap();
+            let mut prev = nodes_history_store.load(nr.prev_version).unwrap();
+            prev.prev_version = 999;
+            nodes_history_store.save(nr.prev_version, &prev).unwrap();
+            nodes_history_store.sync().unwrap();
+            nr.next_prop_id
+        };
+        {
+            let mut properties_store = PropertiesStore::new(&ctx.get_properties_store_path().unwrap(), page_size);
+            let mut pr = properties_store.load(first_prop_id).unwrap();
+            pr.key_id = 999;
+            properties_store.save(first_prop_id, &pr).unwrap();
+            properties_store.sync().unwrap();
+        }
+
+        let report = ConsistencyChecker::new(&ctx).check(true).unwrap();
+        assert_eq!(report.findings.len(), 2);
+        assert!(report.findings.iter().any(|f| f.problem == Problem::BrokenHistoryChain{owner: alice, version: 999} && f.repaired && f.file == "nodes_versions.db"));
+        assert_eq!(report.get_unrepaired()[0].problem, Problem::MissingPropertyKey{property: first_prop_id, key: 999});
+
+        let report = ConsistencyChecker::new(&ctx).check(false).unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].file, "props.db");
+    }
+}
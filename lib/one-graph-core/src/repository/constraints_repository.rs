@@ -0,0 +1,92 @@
+use super::property_indexes_repository::DEFINITION_SEPARATOR;
+use super::store::token_store::TokenStore;
+use super::records::RecordsManagerResult;
+use super::wal::*;
+use super::super::model::*;
+use super::super::model::constraint::*;
+use super::super::error::*;
+
+fn constraint_to_token(constraint: &Constraint) -> String {
+    format!("{}{}{}{}{}{}{}", constraint.kind.get_name(), DEFINITION_SEPARATOR, constraint.target.get_name(), DEFINITION_SEPARATOR,
+        constraint.label, DEFINITION_SEPARATOR, constraint.property)
+}
+
+fn constraint_from_token(token: &str) -> Option<Constraint> {
+    let mut parts = token.splitn(4, DEFINITION_SEPARATOR);
+    let kind = ConstraintKind::from_name(parts.next()?)?;
+    let target = ElementKind::from_name(parts.next()?)?;
+    Some(Constraint::new(kind, target, parts.next()?, parts.next()?))
+}
+
+/// Declared constraints, stored as the tokens of a catalog store.
+pub struct ConstraintsRepository {
+    catalog: TokenStore,
+    constraints: Vec<Constraint>,
+    loaded: bool,
+}
+
+impl ConstraintsRepository {
+    pub fn new(catalog_file: &str, catalog_names_file: &str, page_size: usize) -> Self {
+        ConstraintsRepository {catalog: TokenStore::new(catalog_file, catalog_names_file, page_size), constraints: Vec::new(), loaded: false}
+    }
+
+    fn load_constraints(&mut self) -> GraphResult<()> {
+        if self.loaded {
+            return Ok(());
+        }
+        for (constraint_id, token) in self.catalog.get_tokens()? {
+            let constraint = constraint_from_token(&token).ok_or_else(|| GraphError::Inconsistent(format!("invalid constraint {}", constraint_id)))?;
+            self.constraints.push(constraint);
+        }
+        self.loaded = true;
+        Ok(())
+    }
+
+    /// Declares a constraint, returns false when it already existed.
+    pub fn create(&mut self, constraint: &Constraint) -> GraphResult<bool> {
+        self.load_constraints()?;
+        if self.constraints.contains(constraint) {
+            return Ok(false);
+        }
+        self.catalog.get_or_create(&constraint_to_token(constraint))?;
+        self.constraints.push(constraint.clone());
+        Ok(true)
+    }
+
+    pub fn list(&mut self) -> GraphResult<Vec<Constraint>> {
+        self.load_constraints()?;
+        Ok(self.constraints.clone())
+    }
+
+    /// Constraints applying to an element of the given kind carrying one of the labels.
+    pub fn applying_to(&mut self, target: ElementKind, labels: &[String]) -> GraphResult<Vec<Constraint>> {
+        self.load_constraints()?;
+        Ok(self.constraints.iter().filter(|c| c.target == target && labels.contains(&c.label)).cloned().collect())
+    }
+
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.catalog.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.catalog.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.catalog.set_cache_capacity(capacity);
+    }
+
+    pub fn discard(&mut self) {
+        self.catalog.discard();
+        self.constraints.clear();
+        self.loaded = false;
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.catalog.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.catalog.mark_clean()
+    }
+}
@@ -1,29 +1,395 @@
 use super::store::*;
+use super::store::token_store::TokenStore;
 use super::properties_repository::*;
+use super::labels_repository::*;
+use super::property_indexes_repository::*;
+use super::constraints_repository::*;
+use super::records::{RecordsManagerError, RecordsManagerResult};
+use super::wal::*;
 use super::super::model::*;
-use super::super::repository::index::b_tree::*;
+use super::super::model::constraint::*;
+use super::super::model::stats::GraphStats;
+use super::super::error::*;
+use super::super::repository::index::*;
 use self::records::*;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
 use super::super::graph::traits::*;
 use super::super::graph::*;
+use log::error;
 
 pub struct GraphRepository {
     nodes_store: nodes_store::NodesStore,
     relationships_store: relationships_store::RelationshipsStore,
+    nodes_history_store: nodes_store::NodesStore,
+    relationships_history_store: relationships_store::RelationshipsStore,
     properties_repository: PropertiesRespository,
-    nodes_labels_index: BTreeIndex,
+    labels_repository: LabelsRepository,
+    relationship_types_store: TokenStore,
+    nodes_labels_index: Box<dyn Index>,
+    property_indexes: PropertyIndexesRepository,
+    constraints: ConstraintsRepository,
+    wal: WriteAheadLog,
+    pending_version: Option<u64>,
 }
 
+fn is_visible(version: u64, as_of: Option<u64>) -> bool {
+    match as_of {
+        Some(t) => version <= t,
+        None => true,
+    }
+}
+
+fn is_in_range(version: u64, next_version: Option<u64>, from: Option<u64>, to: Option<u64>) -> bool {
+    let starts_before_end = match to {
+        Some(t) => version <= t,
+        None => true,
+    };
+    let ends_after_start = match (from, next_version) {
+        (Some(f), Some(next)) => next > f,
+        _ => true,
+    };
+    starts_before_end && ends_after_start
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// A record looked up with an id given by the caller is reported missing with that id.
+fn or_not_found<T>(res: RecordsManagerResult<T>, not_found: GraphError) -> GraphResult<T> {
+    match res {
+        Err(RecordsManagerError::NotFound) => Err(not_found),
+        res => Ok(res?),
+    }
+}
+
+/// Record of a scanned id, None when the id is not in use.
+fn if_in_use<T>(res: RecordsManagerResult<T>) -> GraphResult<Option<T>> {
+    match res {
+        Err(RecordsManagerError::NotFound) => Ok(None),
+        res => Ok(Some(res?)),
+    }
+}
+
+fn labels_index_failure() -> GraphError {
+    GraphError::Inconsistent(String::from("the labels index cannot be updated"))
+}
+
+/// Each kind of labels index has its own file, a non empty file of the other kind belongs to the labels index of the database.
+fn check_labels_index_kind(init_ctx: &init::InitContext) -> GraphResult<()> {
+    let kind = init_ctx.get_nodes_labels_index_kind();
+    let other_kind = match kind {
+        init::IndexKind::BTree => init::IndexKind::BwTree,
+        init::IndexKind::BwTree => init::IndexKind::BTree,
+    };
+    let other_file = init_ctx.get_nodes_labels_index_path_for(other_kind).unwrap_or_default();
+    if std::fs::metadata(&other_file).map(|md| md.len() > 0).unwrap_or(false) {
+        let reason = format!("the labels index is a {} index, the database is opened with a {} index", other_kind.get_name(), kind.get_name());
+        error!("{} {}", other_file, reason);
+        return Err(GraphError::Incompatible(other_file, reason));
+    }
+    Ok(())
+}
+
+fn find_value<'a>(props: &'a [Property], name: &str) -> Option<&'a PropertyValue> {
+    props.iter().find(|p| p.get_name().as_deref() == Some(name)).and_then(|p| p.get_value().as_ref())
+}
+
+fn remove_from_list(props: &mut Vec<Property>, name: &str) -> bool {
+    let len = props.len();
+    props.retain(|p| p.get_name().as_deref() != Some(name));
+    props.len() != len
+}
+
+
 impl GraphRepository {
-    pub fn new(init_ctx: &init::InitContext) -> Self {
-        GraphRepository {nodes_store: nodes_store::NodesStore::new(&init_ctx.get_nodes_store_path().unwrap()),
-            relationships_store: relationships_store::RelationshipsStore::new(&init_ctx.get_relationships_store_path().unwrap()),
-            properties_repository: PropertiesRespository::new(&init_ctx.get_properties_store_path().unwrap(), &init_ctx.get_dynamic_store_path().unwrap()),
-            nodes_labels_index: BTreeIndex::new(&init_ctx.get_nodes_labels_index_path().unwrap())}
+    /// Replays the committed write ahead log first, fails when the log cannot be read or is corrupted.
+    /// A log torn by a crash before its commit is discarded, its transaction never happened.
+    /// The indexes that are not logged are rebuilt from the stores once a log was replayed.
+    /// Fails as well when the labels index of the database has another kind than the configured one.
+    pub fn new(init_ctx: &init::InitContext) -> GraphResult<Self> {
+        check_labels_index_kind(init_ctx)?;
+        let mut wal = WriteAheadLog::with_sync_policy(&init_ctx.get_wal_path().unwrap(), init_ctx.get_sync_policy())?;
+        let replayed = match wal.recover() {
+            Ok(replayed) => replayed,
+            Err(WalError::Incomplete) => false,
+            Err(e) => {
+                error!("the write ahead log cannot be recovered: {:?}", e);
+                return Err(e.into());
+            },
+        };
+        let page_size = init_ctx.get_page_size();
+        let mut repository = GraphRepository {wal: wal, nodes_store: nodes_store::NodesStore::new(&init_ctx.get_nodes_store_path().unwrap(), page_size),
+            relationships_store: relationships_store::RelationshipsStore::new(&init_ctx.get_relationships_store_path().unwrap(), page_size),
+            nodes_history_store: nodes_store::NodesStore::new(&init_ctx.get_nodes_history_store_path().unwrap(), page_size),
+            relationships_history_store: relationships_store::RelationshipsStore::new(&init_ctx.get_relationships_history_store_path().unwrap(), page_size),
+            properties_repository: PropertiesRespository::new(&init_ctx.get_properties_store_path().unwrap(), &init_ctx.get_dynamic_store_path().unwrap(),
+                &init_ctx.get_property_keys_store_path().unwrap(), &init_ctx.get_property_keys_names_store_path().unwrap(), page_size),
+            labels_repository: LabelsRepository::new(&init_ctx.get_labels_store_path().unwrap(), &init_ctx.get_labels_names_store_path().unwrap(), &init_ctx.get_nodes_labels_store_path().unwrap(), page_size),
+            relationship_types_store: TokenStore::new(&init_ctx.get_relationship_types_store_path().unwrap(), &init_ctx.get_relationship_types_names_store_path().unwrap(), page_size),
+            nodes_labels_index: open_index(init_ctx.get_nodes_labels_index_kind(), &init_ctx.get_nodes_labels_index_path().unwrap(), page_size),
+            property_indexes: PropertyIndexesRepository::new(&init_ctx.get_property_indexes_store_path().unwrap(), &init_ctx.get_property_indexes_names_store_path().unwrap(),
+                &init_ctx.get_property_index_path_prefix().unwrap(), page_size, init_ctx.get_property_indexes_kind()),
+            constraints: ConstraintsRepository::new(&init_ctx.get_constraints_store_path().unwrap(), &init_ctx.get_constraints_names_store_path().unwrap(), page_size),
+            pending_version: None};
+        repository.set_cache_capacity(init_ctx.get_page_cache_capacity());
+        if replayed {
+            repository.rebuild_unlogged_indexes()?;
+            repository.wal.end()?;
+        }
+        Ok(repository)
+    }
+
+    /// The indexes that are not logged may miss the changes of the transaction replayed from the write ahead log,
+    /// they are emptied and filled again from the stores.
+    fn rebuild_unlogged_indexes(&mut self) -> GraphResult<()> {
+        if !self.nodes_labels_index.is_logged() {
+            for (label, node_id) in self.nodes_labels_index.range(None, None, true).ok_or_else(labels_index_failure)? {
+                self.nodes_labels_index.remove(&label, node_id).ok_or_else(labels_index_failure)?;
+            }
+            let nodes: Vec<(u64, NodeRecord)> = self.nodes_store.scan().filter(|(_, nr)| !nr.is_tombstone()).collect();
+            for (node_id, nr) in nodes {
+                for label in self.labels_repository.retrieve_list(nr.labels_id)? {
+                    self.nodes_labels_index.insert(&label, node_id).ok_or_else(labels_index_failure)?;
+                }
+            }
+            self.nodes_labels_index.sync()?;
+        }
+        for (target, label, property) in self.property_indexes.clear_unlogged_indexes()? {
+            self.fill_property_index(target, &label, &property)?;
+        }
+        Ok(self.property_indexes.sync()?)
+    }
+
+    /// Number of pages each store keeps in its page cache.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.nodes_store.set_cache_capacity(capacity);
+        self.relationships_store.set_cache_capacity(capacity);
+        self.nodes_history_store.set_cache_capacity(capacity);
+        self.relationships_history_store.set_cache_capacity(capacity);
+        self.properties_repository.set_cache_capacity(capacity);
+        self.labels_repository.set_cache_capacity(capacity);
+        self.relationship_types_store.set_cache_capacity(capacity);
+        self.nodes_labels_index.set_cache_capacity(capacity);
+        self.property_indexes.set_cache_capacity(capacity);
+        self.constraints.set_cache_capacity(capacity);
+    }
+
+    pub fn get_last_version(&mut self) -> u64 {
+        self.nodes_store.get_version()
+    }
+
+    fn current_version(&mut self) -> u64 {
+        if let Some(version) = self.pending_version {
+            return version;
+        }
+        let version = std::cmp::max(now_millis(), self.get_last_version() + 1);
+        self.pending_version = Some(version);
+        version
+    }
+
+    /// Current record of the node, the tombstone of a deleted node included.
+    fn load_node_record(&mut self, node_id: u64) -> GraphResult<NodeRecord> {
+        or_not_found(self.nodes_store.load(node_id), GraphError::NodeNotFound(node_id))
+    }
+
+    fn load_node(&mut self, node_id: u64) -> GraphResult<NodeRecord> {
+        let nr = self.load_node_record(node_id)?;
+        if nr.is_tombstone() {
+            return Err(GraphError::NodeNotFound(node_id));
+        }
+        Ok(nr)
+    }
+
+    fn load_relationship_record(&mut self, rel_id: u64) -> GraphResult<RelationshipRecord> {
+        or_not_found(self.relationships_store.load(rel_id), GraphError::RelationshipNotFound(rel_id))
+    }
+
+    fn load_relationship(&mut self, rel_id: u64) -> GraphResult<RelationshipRecord> {
+        let rr = self.load_relationship_record(rel_id)?;
+        if rr.is_tombstone() {
+            return Err(GraphError::RelationshipNotFound(rel_id));
+        }
+        Ok(rr)
+    }
+
+    /// Version of the node visible at `as_of`, None when the node was created after or deleted before.
+    fn load_node_version(&mut self, node_id: u64, as_of: Option<u64>) -> GraphResult<Option<NodeRecord>> {
+        let mut nr = self.load_node_record(node_id)?;
+        while !is_visible(nr.version, as_of) {
+            if nr.prev_version == 0 {
+                return Ok(None);
+            }
+            nr = self.nodes_history_store.load(nr.prev_version)?;
+        }
+        Ok(Some(nr).filter(|nr| !nr.is_tombstone()))
+    }
+
+    fn load_relationship_version(&mut self, rel_id: u64, as_of: Option<u64>) -> GraphResult<Option<RelationshipRecord>> {
+        let mut rr = self.load_relationship_record(rel_id)?;
+        while !is_visible(rr.version, as_of) {
+            if rr.prev_version == 0 {
+                return Ok(None);
+            }
+            rr = self.relationships_history_store.load(rr.prev_version)?;
+        }
+        Ok(Some(rr).filter(|rr| !rr.is_tombstone()))
+    }
+
+    fn save_node_version(&mut self, node_id: u64, nr: &mut NodeRecord) -> GraphResult<()> {
+        let version = self.current_version();
+        let current = self.load_node(node_id)?;
+        if current.version == version {
+            nr.prev_version = current.prev_version;
+        } else {
+            nr.prev_version = self.nodes_history_store.create(&current)?;
+        }
+        nr.version = version;
+        Ok(self.nodes_store.save(node_id, nr)?)
+    }
+
+    fn save_relationship_version(&mut self, rel_id: u64, rr: &mut RelationshipRecord) -> GraphResult<()> {
+        let version = self.current_version();
+        let current = self.load_relationship(rel_id)?;
+        if current.version == version {
+            rr.prev_version = current.prev_version;
+        } else {
+            rr.prev_version = self.relationships_history_store.create(&current)?;
+        }
+        rr.version = version;
+        Ok(self.relationships_store.save(rel_id, rr)?)
+    }
+
+    fn create_relationship_type(&mut self, rel: &Relationship) -> GraphResult<u64> {
+        match rel.get_labels_ref().first() {
+            Some(rel_type) => Ok(self.relationship_types_store.get_or_create(rel_type)?),
+            None => Ok(0),
+        }
+    }
+
+    fn retrieve_relationship_type(&mut self, type_id: u64) -> GraphResult<Vec<String>> {
+        if type_id == 0 {
+            return Ok(Vec::new());
+        }
+        let name = self.relationship_types_store.get_name(type_id)?;
+        Ok(vec![name.ok_or_else(|| GraphError::Inconsistent(format!("relationship type {} is missing", type_id)))?])
+    }
+
+    fn first_visible_relationship(&mut self, first_rel_id: u64, outbound: bool, as_of: Option<u64>) -> GraphResult<Option<u64>> {
+        let mut rel_id = first_rel_id;
+        while rel_id != 0 {
+            let rr = self.relationships_store.load(rel_id)?;
+            if self.load_relationship_version(rel_id, as_of)?.is_some() {
+                return Ok(Some(rel_id));
+            }
+            rel_id = if outbound {rr.next_outbound_edge} else {rr.next_inbound_edge};
+        }
+        Ok(None)
+    }
+
+    fn collect_visible_relationships(&mut self, first_rel_id: u64, outbound: bool, as_of: Option<u64>) -> GraphResult<Vec<u64>> {
+        let mut res = Vec::new();
+        let mut rel_id = first_rel_id;
+        while rel_id != 0 {
+            let rr = self.relationships_store.load(rel_id)?;
+            if self.load_relationship_version(rel_id, as_of)?.is_some() {
+                res.push(rel_id);
+            }
+            rel_id = if outbound {rr.next_outbound_edge} else {rr.next_inbound_edge};
+        }
+        Ok(res)
+    }
+
+    /// Every version of the node from its creation, the last one is a tombstone when it is deleted.
+    fn load_node_versions(&mut self, node_id: u64) -> GraphResult<Vec<NodeRecord>> {
+        let mut nr = self.load_node_record(node_id)?;
+        let mut res = Vec::new();
+        while nr.prev_version != 0 {
+            let prev = self.nodes_history_store.load(nr.prev_version)?;
+            res.push(nr);
+            nr = prev;
+        }
+        res.push(nr);
+        res.reverse();
+        Ok(res)
+    }
+
+    fn load_relationship_versions(&mut self, rel_id: u64) -> GraphResult<Vec<RelationshipRecord>> {
+        let mut rr = self.load_relationship_record(rel_id)?;
+        let mut res = Vec::new();
+        while rr.prev_version != 0 {
+            let prev = self.relationships_history_store.load(rr.prev_version)?;
+            res.push(rr);
+            rr = prev;
+        }
+        res.push(rr);
+        res.reverse();
+        Ok(res)
+    }
+
+    pub fn node_history(&mut self, node_id: u64, from: Option<u64>, to: Option<u64>) -> GraphResult<Vec<NodeVersion>> {
+        let current = self.load_node_record(node_id)?;
+        let versions = self.load_node_versions(node_id)?;
+        let mut res = Vec::new();
+        for (index, nr) in versions.iter().enumerate() {
+            let next_version = versions.get(index + 1).map(|next| next.version);
+            if nr.is_tombstone() || !is_in_range(nr.version, next_version, from, to) {
+                continue;
+            }
+            let mut node = Node::new();
+            node.set_id(Some(node_id));
+            node.set_properties(self.properties_repository.retrieve_list(nr.next_prop_id)?);
+            node.set_labels(self.labels_repository.retrieve_list(nr.labels_id)?);
+            let as_of = Some(nr.version);
+            res.push(NodeVersion{version: nr.version, node,
+                outbound_relationships: self.collect_visible_relationships(current.first_outbound_edge, true, as_of)?,
+                inbound_relationships: self.collect_visible_relationships(current.first_inbound_edge, false, as_of)?});
+        }
+        Ok(res)
+    }
+
+    pub fn relationship_history(&mut self, rel_id: u64, from: Option<u64>, to: Option<u64>) -> GraphResult<Vec<RelationshipVersion>> {
+        let versions = self.load_relationship_versions(rel_id)?;
+        let mut res = Vec::new();
+        for (index, rr) in versions.iter().enumerate() {
+            let next_version = versions.get(index + 1).map(|next| next.version);
+            if rr.is_tombstone() || !is_in_range(rr.version, next_version, from, to) {
+                continue;
+            }
+            let mut relationship = Relationship::new();
+            relationship.set_id(Some(rel_id));
+            relationship.set_properties(self.properties_repository.retrieve_list(rr.next_prop_id)?);
+            relationship.set_labels(self.retrieve_relationship_type(rr.relationship_type)?);
+            res.push(RelationshipVersion{version: rr.version, relationship, source: rr.source, target: rr.target});
+        }
+        Ok(res)
+    }
+
+    pub fn is_node_visible(&mut self, node_id: u64, as_of: Option<u64>) -> GraphResult<bool> {
+        match self.load_node_version(node_id, as_of) {
+            Ok(nr) => Ok(nr.is_some()),
+            Err(GraphError::NodeNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn retain_visible_nodes(&mut self, ids: HashSet<u64>, as_of: Option<u64>) -> GraphResult<HashSet<u64>> {
+        if as_of.is_none() {
+            return Ok(ids);
+        }
+        let mut res = HashSet::new();
+        for id in ids {
+            if self.is_node_visible(id, as_of)? {
+                res.insert(id);
+            }
+        }
+        Ok(res)
     }
 
-    pub fn fetch_nodes_ids_with_labels(&mut self, labels: &Vec<String>) -> HashSet<u64> {
+    pub fn fetch_nodes_ids_with_labels(&mut self, labels: &Vec<String>, as_of: Option<u64>) -> GraphResult<HashSet<u64>> {
         let mut res = HashSet::new();
         for label in labels {
             let ids = self.nodes_labels_index.search(label);
@@ -31,67 +397,281 @@ impl GraphRepository {
                 res.extend(node_ids.iter());
             }
         }
-        res
+        if as_of.is_some() {
+            res.extend(self.fetch_deleted_nodes_ids_with_labels(labels, as_of)?);
+        }
+        self.retain_visible_nodes(res, as_of)
     }
 
-    pub fn retrieve_node_by_id(&mut self, node_id: u64) -> Option<(Node, DbVertexData)> {
-        let nr = self.nodes_store.load(node_id)?;
-        let mut node = Node::new();
-        node.set_id(Some(node_id));
-        node.set_properties(self.properties_repository.retrieve_list(nr.next_prop_id)?);
-        let mut vertex = DbVertexData::new();
-        if nr.first_inbound_edge != 0 {
-            vertex.first_inbound_edge = Some(nr.first_inbound_edge);
+    /// Deleted nodes are no longer in the labels index, their labels are read from the version visible at `as_of`.
+    fn fetch_deleted_nodes_ids_with_labels(&mut self, labels: &[String], as_of: Option<u64>) -> GraphResult<HashSet<u64>> {
+        let deleted: Vec<u64> = self.nodes_store.scan().filter(|(_, nr)| nr.is_tombstone()).map(|(id, _)| id).collect();
+        let mut res = HashSet::new();
+        for node_id in deleted {
+            if let Some(nr) = self.load_node_version(node_id, as_of)? {
+                if self.labels_repository.retrieve_list(nr.labels_id)?.iter().any(|label| labels.contains(label)) {
+                    res.insert(node_id);
+                }
+            }
         }
-        if nr.first_outbound_edge != 0 {
-            vertex.first_outbound_edge = Some(nr.first_outbound_edge);
+        Ok(res)
+    }
+
+    /// Every stored node visible at `as_of`.
+    pub fn fetch_all_nodes_ids(&mut self, as_of: Option<u64>) -> GraphResult<HashSet<u64>> {
+        let res: HashSet<u64> = self.nodes_store.scan().filter(|(_, nr)| as_of.is_some() || !nr.is_tombstone()).map(|(id, _)| id).collect();
+        self.retain_visible_nodes(res, as_of)
+    }
+
+    /// Counts the node and relationship records and the entries of the labels index, the properties are not read.
+    pub fn get_stats(&mut self) -> GraphResult<GraphStats> {
+        let mut stats = GraphStats{nodes: self.nodes_store.scan().filter(|(_, nr)| !nr.is_tombstone()).count(), ..GraphStats::default()};
+        for (label, _) in self.nodes_labels_index.range(None, None, true).unwrap_or_default() {
+            *stats.labels.entry(label).or_insert(0) += 1;
+        }
+        let mut type_counts = HashMap::new();
+        for (_, rr) in self.relationships_store.scan().filter(|(_, rr)| !rr.is_tombstone()) {
+            stats.relationships += 1;
+            *type_counts.entry(rr.relationship_type).or_insert(0) += 1;
+        }
+        for (type_id, name) in self.relationship_types_store.get_tokens()? {
+            if let Some(count) = type_counts.get(&type_id) {
+                stats.relationship_types.insert(name, *count);
+            }
         }
-        Some((node, vertex))
+        stats.constraints = self.list_constraints()?.len();
+        Ok(stats)
     }
 
-    pub fn retrieve_vertex_data_by_id(&mut self, node_id: u64) -> Option<DbVertexData> {
-        let nr = self.nodes_store.load(node_id)?;
-        let mut vertex = DbVertexData::new();
-        if nr.first_inbound_edge != 0 {
-            vertex.first_inbound_edge = Some(nr.first_inbound_edge);
+    /// Ids of the relationships visible at `as_of` whose source is the node.
+    pub fn fetch_outbound_relationships_ids(&mut self, node_id: u64, as_of: Option<u64>) -> GraphResult<Vec<u64>> {
+        let nr = self.load_node_record(node_id)?;
+        self.collect_visible_relationships(nr.first_outbound_edge, true, as_of)
+    }
+
+    /// Ids of the nodes linked to a node by a relationship visible at `as_of`, in either direction.
+    pub fn fetch_neighbours_ids(&mut self, node_id: u64, as_of: Option<u64>) -> GraphResult<HashSet<u64>> {
+        let nr = self.load_node_record(node_id)?;
+        let mut res = HashSet::new();
+        for rel_id in self.collect_visible_relationships(nr.first_outbound_edge, true, as_of)? {
+            res.insert(self.relationships_store.load(rel_id)?.target);
         }
-        if nr.first_outbound_edge != 0 {
-            vertex.first_outbound_edge = Some(nr.first_outbound_edge);
+        for rel_id in self.collect_visible_relationships(nr.first_inbound_edge, false, as_of)? {
+            res.insert(self.relationships_store.load(rel_id)?.source);
         }
-        Some(vertex)
+        Ok(res)
     }
 
-    pub fn retrieve_relationship_by_id(&mut self, rel_id: u64) -> Option<(Relationship, DbEdgeData)> {
-        let rr = self.relationships_store.load(rel_id)?;
-        let mut rel = Relationship::new();
-        rel.set_id(Some(rel_id));
-        rel.set_properties(self.properties_repository.retrieve_list(rr.next_prop_id)?);
-        let mut edge = DbEdgeData::new(rr.source, rr.target);
-        if rr.next_inbound_edge != 0 {
-            edge.next_inbound_edge = Some(rr.next_inbound_edge);
+    /// Candidate ids of a pattern node taken from a property index on one of its labels,
+    /// None when no index covers its constraints. Indexes hold current values so past versions are not looked up.
+    pub fn fetch_nodes_ids_with_indexed_properties(&mut self, node: &Node, as_of: Option<u64>) -> GraphResult<Option<HashSet<u64>>> {
+        if as_of.is_some() {
+            return Ok(None);
         }
-        if rr.next_outbound_edge != 0 {
-            edge.next_outbound_edge = Some(rr.next_outbound_edge);
+        for label in node.get_labels_ref() {
+            for prop in node.get_properties_ref() {
+                if let (Some(name), Some(value)) = (prop.get_name(), prop.get_value()) {
+                    if let Some(ids) = self.property_indexes.search(ElementKind::Node, label, name, value)? {
+                        return Ok(Some(ids.into_iter().collect()));
+                    }
+                }
+            }
+            for range in node.get_property_ranges_ref() {
+                if let Some(ids) = self.property_indexes.range(ElementKind::Node, label, range)? {
+                    return Ok(Some(ids.into_iter().collect()));
+                }
+            }
         }
-        Some((rel, edge))
+        Ok(None)
     }
 
-    pub fn retrieve_edge_data_by_id(&mut self, rel_id: u64) -> Option<DbEdgeData> {
-        let rr = self.relationships_store.load(rel_id)?;
-        let mut edge = DbEdgeData::new(rr.source, rr.target);
-        if rr.next_inbound_edge != 0 {
-            edge.next_inbound_edge = Some(rr.next_inbound_edge);
+    /// Endpoints of the relationships found by a property index covering the constraints of `rel`,
+    /// None when none of its properties is indexed.
+    pub fn fetch_relationships_nodes_ids_with_indexed_properties(&mut self, rel: &Relationship, as_of: Option<u64>) -> GraphResult<Option<HashSet<u64>>> {
+        if as_of.is_some() {
+            return Ok(None);
         }
-        if rr.next_outbound_edge != 0 {
-            edge.next_outbound_edge = Some(rr.next_outbound_edge);
+        for label in rel.get_labels_ref() {
+            for prop in rel.get_properties_ref() {
+                if let (Some(name), Some(value)) = (prop.get_name(), prop.get_value()) {
+                    if let Some(ids) = self.property_indexes.search(ElementKind::Relationship, label, name, value)? {
+                        let mut nodes_ids = HashSet::new();
+                        for rel_id in ids {
+                            let rr = self.relationships_store.load(rel_id)?;
+                            nodes_ids.insert(rr.source);
+                            nodes_ids.insert(rr.target);
+                        }
+                        return Ok(Some(nodes_ids));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Current value of the property of every node with the label or every relationship with the type.
+    fn retrieve_labelled_values(&mut self, target: ElementKind, label: &str, property: &str) -> GraphResult<Vec<(u64, Option<PropertyValue>)>> {
+        let mut res = Vec::new();
+        match target {
+            ElementKind::Node => {
+                for node_id in self.nodes_labels_index.search(label).unwrap_or_default() {
+                    let nr = self.nodes_store.load(node_id)?;
+                    res.push((node_id, self.properties_repository.find_value(nr.next_prop_id, property)?));
+                }
+            },
+            ElementKind::Relationship => {
+                let type_id = match self.relationship_types_store.get_id(label)? {
+                    Some(id) => id,
+                    None => return Ok(res),
+                };
+                for rel_id in 1..=self.relationships_store.get_max_id() {
+                    if let Some(rr) = if_in_use(self.relationships_store.load(rel_id))? {
+                        if rr.relationship_type == type_id && !rr.is_tombstone() {
+                            res.push((rel_id, self.properties_repository.find_value(rr.next_prop_id, property)?));
+                        }
+                    }
+                }
+            },
         }
-        Some(edge)
+        Ok(res)
     }
 
-    pub fn retrieve_sub_graph_around(&mut self, node_id: u64) -> Option<PropertyGraph> {
+    fn create_property_index(&mut self, target: ElementKind, label: &str, property: &str) -> GraphResult<()> {
+        if !self.property_indexes.create_index(target, label, property)? {
+            return Ok(());
+        }
+        self.fill_property_index(target, label, property)
+    }
+
+    /// Indexes the current value of the property of every element with the label or type.
+    fn fill_property_index(&mut self, target: ElementKind, label: &str, property: &str) -> GraphResult<()> {
+        let labels = vec![String::from(label)];
+        for (id, value) in self.retrieve_labelled_values(target, label, property)? {
+            let mut prop = Property::new();
+            prop.set_name(property);
+            prop.set_value(value);
+            self.property_indexes.insert(target, &labels, &[prop], id)?;
+        }
+        Ok(())
+    }
+
+    pub fn create_node_property_index(&mut self, label: &str, property: &str) -> GraphResult<()> {
+        self.create_property_index(ElementKind::Node, label, property)
+    }
+
+    pub fn create_relationship_property_index(&mut self, rel_type: &str, property: &str) -> GraphResult<()> {
+        self.create_property_index(ElementKind::Relationship, rel_type, property)
+    }
+
+    /// Declares a constraint once the stored elements satisfy it, a unique constraint is backed by a property index.
+    /// Returns false when the constraint already existed.
+    pub fn create_constraint(&mut self, constraint: &Constraint) -> GraphResult<bool> {
+        if self.constraints.list()?.contains(constraint) {
+            return Ok(false);
+        }
+        match constraint.kind {
+            ConstraintKind::Unique => {
+                if constraint.target != ElementKind::Node {
+                    return Err(ConstraintError::Unsupported(constraint.clone()).into());
+                }
+                let mut values = HashSet::new();
+                for (_, value) in self.retrieve_labelled_values(constraint.target, &constraint.label, &constraint.property)? {
+                    if let Some(value) = value {
+                        if !values.insert(value) {
+                            return Err(ConstraintError::UniqueViolation(constraint.clone()).into());
+                        }
+                    }
+                }
+                self.create_property_index(constraint.target, &constraint.label, &constraint.property)?;
+            },
+            ConstraintKind::Exists => {
+                for (_, value) in self.retrieve_labelled_values(constraint.target, &constraint.label, &constraint.property)? {
+                    if value.is_none() {
+                        return Err(ConstraintError::ExistenceViolation(constraint.clone()).into());
+                    }
+                }
+            },
+        }
+        self.constraints.create(constraint)
+    }
+
+    pub fn list_constraints(&mut self) -> GraphResult<Vec<Constraint>> {
+        self.constraints.list()
+    }
+
+    /// Checks the properties an element would have against the constraints of its labels.
+    /// `element_id` is the element being updated, its own indexed value is not a duplicate.
+    /// `batch` collects the unique values of the elements created along so they cannot clash between themselves.
+    fn check_constraints(&mut self, target: ElementKind, labels: &[String], props: &[Property], element_id: Option<u64>,
+        batch: &mut HashSet<(Constraint, PropertyValue)>) -> GraphResult<()> {
+        for constraint in self.constraints.applying_to(target, labels)? {
+            let value = match find_value(props, &constraint.property) {
+                Some(value) => value,
+                None if constraint.kind == ConstraintKind::Exists => return Err(ConstraintError::ExistenceViolation(constraint).into()),
+                None => continue,
+            };
+            if constraint.kind == ConstraintKind::Unique {
+                let ids = self.property_indexes.search(target, &constraint.label, &constraint.property, value)?
+                    .ok_or_else(|| GraphError::Inconsistent(format!("no index backs the constraint {}", constraint)))?;
+                if ids.iter().any(|id| Some(*id) != element_id) || !batch.insert((constraint.clone(), value.clone())) {
+                    return Err(ConstraintError::UniqueViolation(constraint).into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn make_vertex_data(&mut self, nr: &NodeRecord, as_of: Option<u64>) -> GraphResult<DbVertexData> {
+        let mut vertex = DbVertexData::new();
+        vertex.first_inbound_edge = self.first_visible_relationship(nr.first_inbound_edge, false, as_of)?;
+        vertex.first_outbound_edge = self.first_visible_relationship(nr.first_outbound_edge, true, as_of)?;
+        Ok(vertex)
+    }
+
+    fn make_edge_data(&mut self, rr: &RelationshipRecord, as_of: Option<u64>) -> GraphResult<DbEdgeData> {
+        let mut edge = DbEdgeData::new(rr.source, rr.target);
+        edge.next_inbound_edge = self.first_visible_relationship(rr.next_inbound_edge, false, as_of)?;
+        edge.next_outbound_edge = self.first_visible_relationship(rr.next_outbound_edge, true, as_of)?;
+        Ok(edge)
+    }
+
+    /// The node as it was at `as_of`, not found when it did not exist yet.
+    pub fn retrieve_node_by_id(&mut self, node_id: u64, as_of: Option<u64>) -> GraphResult<(Node, DbVertexData)> {
+        let nr = self.load_node_record(node_id)?;
+        let vr = self.load_node_version(node_id, as_of)?.ok_or(GraphError::NodeNotFound(node_id))?;
+        let mut node = Node::new();
+        node.set_id(Some(node_id));
+        node.set_properties(self.properties_repository.retrieve_list(vr.next_prop_id)?);
+        node.set_labels(self.labels_repository.retrieve_list(vr.labels_id)?);
+        let vertex = self.make_vertex_data(&nr, as_of)?;
+        Ok((node, vertex))
+    }
+
+    pub fn retrieve_vertex_data_by_id(&mut self, node_id: u64, as_of: Option<u64>) -> GraphResult<DbVertexData> {
+        let nr = self.load_node_record(node_id)?;
+        self.make_vertex_data(&nr, as_of)
+    }
+
+    pub fn retrieve_relationship_by_id(&mut self, rel_id: u64, as_of: Option<u64>) -> GraphResult<(Relationship, DbEdgeData)> {
+        let rr = self.load_relationship_record(rel_id)?;
+        let vr = self.load_relationship_version(rel_id, as_of)?.ok_or(GraphError::RelationshipNotFound(rel_id))?;
+        let mut rel = Relationship::new();
+        rel.set_id(Some(rel_id));
+        rel.set_properties(self.properties_repository.retrieve_list(vr.next_prop_id)?);
+        rel.set_labels(self.retrieve_relationship_type(vr.relationship_type)?);
+        let edge = self.make_edge_data(&rr, as_of)?;
+        Ok((rel, edge))
+    }
+
+    pub fn retrieve_edge_data_by_id(&mut self, rel_id: u64, as_of: Option<u64>) -> GraphResult<DbEdgeData> {
+        let rr = self.load_relationship_record(rel_id)?;
+        self.make_edge_data(&rr, as_of)
+    }
+
+    pub fn retrieve_sub_graph_around(&mut self, node_id: u64) -> GraphResult<PropertyGraph> {
         let mut pg = PropertyGraph::new();
         let mut map_nodes = HashMap::new();
-        let nr = self.nodes_store.load(node_id)?;
+        let nr = self.load_node(node_id)?;
         let mut node = Node::new();
         node.set_id(Some(node_id));
         map_nodes.insert(node_id, pg.add_node(node));
@@ -134,42 +714,77 @@ impl GraphRepository {
             }
         }
 
-        Some(pg)
+        Ok(pg)
     }
 
-    pub fn create_node(&mut self, node: &Node) -> Option<Node> {
+    pub fn create_node(&mut self, node: &Node) -> GraphResult<Node> {
+        self.check_constraints(ElementKind::Node, node.get_labels_ref(), node.get_properties_ref(), None, &mut HashSet::new())?;
+        self.write_node(node)
+    }
+
+    fn write_node(&mut self, node: &Node) -> GraphResult<Node> {
         let mut nr = NodeRecord::new();
+        nr.version = self.current_version();
         nr.next_prop_id = self.properties_repository.create_list(node.get_properties_ref())?;
+        nr.labels_id = self.labels_repository.create_list(node.get_labels_ref())?;
         let nid = self.nodes_store.create(&nr)?;
         for label in node.get_labels_ref() {
-            self.nodes_labels_index.insert(label, nid);
+            self.nodes_labels_index.insert(label, nid).ok_or_else(labels_index_failure)?;
         }
+        self.property_indexes.insert(ElementKind::Node, node.get_labels_ref(), node.get_properties_ref(), nid)?;
         let mut res = node.clone();
         res.set_id(Some(nid));
-        Some(res)
+        Ok(res)
     }
     
 
-    pub fn create_relationship(&mut self, rel: &Relationship, source: u64, target: u64) -> Option<Relationship> {
+    pub fn create_relationship(&mut self, rel: &Relationship, source: u64, target: u64) -> GraphResult<Relationship> {
+        self.check_constraints(ElementKind::Relationship, rel.get_labels_ref(), rel.get_properties_ref(), None, &mut HashSet::new())?;
+        self.write_relationship(rel, source, target)
+    }
+
+    fn write_relationship(&mut self, rel: &Relationship, source: u64, target: u64) -> GraphResult<Relationship> {
         let mut rr = RelationshipRecord::new(source, target);
+        rr.version = self.current_version();
+        let mut source_nr = self.load_node(source)?;
+        rr.next_outbound_edge = source_nr.first_outbound_edge;
+        rr.next_inbound_edge = if source == target {source_nr.first_inbound_edge} else {self.load_node(target)?.first_inbound_edge};
         rr.next_prop_id = self.properties_repository.create_list(rel.get_properties_ref())?;
+        rr.relationship_type = self.create_relationship_type(rel)?;
         let rid = self.relationships_store.create(&rr)?;
+        self.property_indexes.insert(ElementKind::Relationship, rel.get_labels_ref(), rel.get_properties_ref(), rid)?;
+        self.link_to_chains(&mut source_nr, rid, source, target)?;
         let mut res = rel.clone();
         res.set_id(Some(rid));
-        Some(res)
+        Ok(res)
+    }
+
+    pub fn create_graph(&mut self, pgraph: &PropertyGraph) -> GraphResult<PropertyGraph> {
+        let mut batch = HashSet::new();
+        for node in pgraph.get_nodes() {
+            self.check_constraints(ElementKind::Node, node.get_labels_ref(), node.get_properties_ref(), None, &mut batch)?;
+        }
+        for rel in pgraph.get_relationships() {
+            self.check_constraints(ElementKind::Relationship, rel.get_labels_ref(), rel.get_properties_ref(), None, &mut batch)?;
+        }
+        self.write_graph(pgraph)
     }
 
-    pub fn create_graph(&mut self, pgraph: &PropertyGraph) -> Option<PropertyGraph> {
+    fn write_graph(&mut self, pgraph: &PropertyGraph) -> GraphResult<PropertyGraph> {
+        let version = self.current_version();
         let mut map_nodes = HashMap::new();
         let mut node_index = 0;
         let mut node_records = Vec::new();
         for node in pgraph.get_nodes() {
             let mut nr = NodeRecord::new();
+            nr.version = version;
             nr.next_prop_id = self.properties_repository.create_list(node.get_properties_ref())?;
+            nr.labels_id = self.labels_repository.create_list(node.get_labels_ref())?;
             let nid = self.nodes_store.create(&nr)?;
             for label in node.get_labels_ref() {
-                self.nodes_labels_index.insert(label, nid);
+                self.nodes_labels_index.insert(label, nid).ok_or_else(labels_index_failure)?;
             }
+            self.property_indexes.insert(ElementKind::Node, node.get_labels_ref(), node.get_properties_ref(), nid)?;
             map_nodes.insert(node_index, nid);
             node_records.push((nid, nr));
             node_index += 1;
@@ -179,11 +794,13 @@ impl GraphRepository {
         let mut map_rel = HashMap::new();
         let mut rel_records = Vec::new();
         for edge in pgraph.get_edges() {
-            let mut rr = RelationshipRecord::new(*map_nodes.get(&edge.source.get_index())?,
-             *map_nodes.get(&edge.target.get_index())?);
+            let mut rr = RelationshipRecord::new(map_nodes[&edge.source.get_index()], map_nodes[&edge.target.get_index()]);
+            rr.version = version;
             let rel = pgraph.get_relationship_ref(&edge.id);
             rr.next_prop_id = self.properties_repository.create_list(rel.get_properties_ref())?;
+            rr.relationship_type = self.create_relationship_type(rel)?;
             let rid = self.relationships_store.create(&rr)?;
+            self.property_indexes.insert(ElementKind::Relationship, rel.get_labels_ref(), rel.get_properties_ref(), rid)?;
             map_rel.insert(rel_index, rid);
             rel_records.push((rid, rr));
             rel_index += 1;
@@ -194,12 +811,12 @@ impl GraphRepository {
             let vertex = pgraph.get_inner_graph().get_vertex(NodeIndex::new(nr_index));
             let in_edge_index = vertex.get_first_inbound_edge();
             if let Some(in_edge) = in_edge_index {
-                nr.1.first_inbound_edge = *map_rel.get(&in_edge.get_index())?;
+                nr.1.first_inbound_edge = map_rel[&in_edge.get_index()];
             }
             
             let out_edge_index = vertex.get_first_outbound_edge();
             if let Some(out_edge) = out_edge_index {
-                nr.1.first_outbound_edge = *map_rel.get(&out_edge.get_index())?;
+                nr.1.first_outbound_edge = map_rel[&out_edge.get_index()];
             }
             
             self.nodes_store.save(nr.0, &nr.1)?;
@@ -210,10 +827,10 @@ impl GraphRepository {
         for rr in &mut rel_records {
             let edge = pgraph.get_inner_graph().get_edge_data(EdgeIndex::new(rr_index));
             if let Some(out_edge) = &edge.get_next_outbound_edge() {
-                rr.1.next_outbound_edge = *map_rel.get(&out_edge.get_index())?;
+                rr.1.next_outbound_edge = map_rel[&out_edge.get_index()];
             }
             if let Some(in_edge) = &edge.get_next_inbound_edge() {
-                rr.1.next_inbound_edge = *map_rel.get(&in_edge.get_index())?;
+                rr.1.next_inbound_edge = map_rel[&in_edge.get_index()];
             }
             
             self.relationships_store.save(rr.0, &rr.1)?;
@@ -233,14 +850,394 @@ impl GraphRepository {
             r_index += 1;
         }
 
-        Some(res)
+        Ok(res)
+    }
+
+    /// Writes the nodes and relationships read from a store of an older format at the ids they had there,
+    /// relationships keep their place in the chains. The stores must not hold records at these ids yet.
+    pub fn restore(&mut self, nodes: &[(u64, Node, DbVertexData)], relationships: &[(u64, Relationship, DbEdgeData)]) -> GraphResult<()> {
+        let version = self.current_version();
+        let max_node_id = nodes.iter().map(|n| n.0).max().unwrap_or(0);
+        let mut unused_node_ids = self.nodes_store.extend_to(max_node_id)?.collect::<BTreeSet<u64>>();
+        for (nid, node, vertex) in nodes {
+            if !unused_node_ids.remove(nid) {
+                return Err(GraphError::Inconsistent(format!("node {} is already used", nid)));
+            }
+            let mut nr = NodeRecord::new();
+            nr.version = version;
+            nr.next_prop_id = self.properties_repository.create_list(node.get_properties_ref())?;
+            nr.labels_id = self.labels_repository.create_list(node.get_labels_ref())?;
+            nr.first_outbound_edge = vertex.first_outbound_edge.unwrap_or(0);
+            nr.first_inbound_edge = vertex.first_inbound_edge.unwrap_or(0);
+            self.nodes_store.save(*nid, &nr)?;
+            for label in node.get_labels_ref() {
+                self.nodes_labels_index.insert(label, *nid).ok_or_else(labels_index_failure)?;
+            }
+            self.property_indexes.insert(ElementKind::Node, node.get_labels_ref(), node.get_properties_ref(), *nid)?;
+        }
+        for nid in unused_node_ids {
+            self.nodes_store.delete(nid)?;
+        }
+
+        let max_rel_id = relationships.iter().map(|r| r.0).max().unwrap_or(0);
+        let mut unused_rel_ids = self.relationships_store.extend_to(max_rel_id)?.collect::<BTreeSet<u64>>();
+        for (rid, rel, edge) in relationships {
+            if !unused_rel_ids.remove(rid) {
+                return Err(GraphError::Inconsistent(format!("relationship {} is already used", rid)));
+            }
+            let mut rr = RelationshipRecord::new(edge.source, edge.target);
+            rr.version = version;
+            rr.next_outbound_edge = edge.next_outbound_edge.unwrap_or(0);
+            rr.next_inbound_edge = edge.next_inbound_edge.unwrap_or(0);
+            rr.next_prop_id = self.properties_repository.create_list(rel.get_properties_ref())?;
+            rr.relationship_type = self.create_relationship_type(rel)?;
+            self.relationships_store.save(*rid, &rr)?;
+            self.property_indexes.insert(ElementKind::Relationship, rel.get_labels_ref(), rel.get_properties_ref(), *rid)?;
+        }
+        for rid in unused_rel_ids {
+            self.relationships_store.delete(rid)?;
+        }
+        Ok(())
+    }
+
+    /// Puts the relationship at the head of the chains of its nodes, each node gets a new version.
+    fn link_to_chains(&mut self, source_nr: &mut NodeRecord, rel_id: u64, source: u64, target: u64) -> GraphResult<()> {
+        source_nr.first_outbound_edge = rel_id;
+        if source == target {
+            source_nr.first_inbound_edge = rel_id;
+            return self.save_node_version(source, source_nr);
+        }
+        self.save_node_version(source, source_nr)?;
+        let mut target_nr = self.load_node(target)?;
+        target_nr.first_inbound_edge = rel_id;
+        self.save_node_version(target, &mut target_nr)
+    }
+
+    fn unlink_from_chain(&mut self, node_id: u64, rel_id: u64, next_rel_id: u64, outbound: bool) -> GraphResult<()> {
+        let mut nr = self.nodes_store.load(node_id)?;
+        let first = if outbound {nr.first_outbound_edge} else {nr.first_inbound_edge};
+        if first == rel_id {
+            if outbound {
+                nr.first_outbound_edge = next_rel_id;
+            } else {
+                nr.first_inbound_edge = next_rel_id;
+            }
+            return Ok(self.nodes_store.save(node_id, &nr)?);
+        }
+        let mut curr_id = first;
+        while curr_id != 0 {
+            let mut curr = self.relationships_store.load(curr_id)?;
+            let next = if outbound {curr.next_outbound_edge} else {curr.next_inbound_edge};
+            if next == rel_id {
+                if outbound {
+                    curr.next_outbound_edge = next_rel_id;
+                } else {
+                    curr.next_inbound_edge = next_rel_id;
+                }
+                return Ok(self.relationships_store.save(curr_id, &curr)?);
+            }
+            curr_id = next;
+        }
+        Err(GraphError::Inconsistent(format!("relationship {} is not in the chain of node {}", rel_id, node_id)))
+    }
+
+    /// Frees the record of the relationship and its whole history.
+    fn free_relationship(&mut self, rel_id: u64) -> GraphResult<()> {
+        let mut props = HashSet::new();
+        let mut rr = self.relationships_store.load(rel_id)?;
+        props.insert(rr.next_prop_id);
+        self.relationships_store.delete(rel_id)?;
+        while rr.prev_version != 0 {
+            let prev_id = rr.prev_version;
+            rr = self.relationships_history_store.load(prev_id)?;
+            props.insert(rr.next_prop_id);
+            self.relationships_history_store.delete(prev_id)?;
+        }
+        props.remove(&TOMBSTONE);
+        for prop_id in props {
+            self.properties_repository.delete_list(prop_id)?;
+        }
+        Ok(())
+    }
+
+    fn free_node(&mut self, node_id: u64) -> GraphResult<()> {
+        let mut props = HashSet::new();
+        let mut labels = HashSet::new();
+        let mut nr = self.nodes_store.load(node_id)?;
+        props.insert(nr.next_prop_id);
+        labels.insert(nr.labels_id);
+        self.nodes_store.delete(node_id)?;
+        while nr.prev_version != 0 {
+            let prev_id = nr.prev_version;
+            nr = self.nodes_history_store.load(prev_id)?;
+            props.insert(nr.next_prop_id);
+            labels.insert(nr.labels_id);
+            self.nodes_history_store.delete(prev_id)?;
+        }
+        props.remove(&TOMBSTONE);
+        for prop_id in props {
+            self.properties_repository.delete_list(prop_id)?;
+        }
+        for labels_id in labels {
+            self.labels_repository.delete_list(labels_id)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the history of the relationship with a tombstone, it stays in the chains of its nodes until compaction.
+    pub fn delete_relationship(&mut self, rel_id: u64) -> GraphResult<()> {
+        let rr = self.load_relationship(rel_id)?;
+        self.unindex_relationship(rel_id, &rr)?;
+        let archived = rr.version != self.current_version();
+        let mut tombstone = RelationshipRecord{next_prop_id: TOMBSTONE, ..rr};
+        self.save_relationship_version(rel_id, &mut tombstone)?;
+        if !archived {
+            self.properties_repository.delete_list(rr.next_prop_id)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the history of the node with a tombstone, its versions stay readable until compaction.
+    pub fn delete_node(&mut self, node_id: u64) -> GraphResult<()> {
+        let nr = self.load_node(node_id)?;
+        if self.first_visible_relationship(nr.first_outbound_edge, true, None)?.is_some() ||
+            self.first_visible_relationship(nr.first_inbound_edge, false, None)?.is_some() {
+            return Err(GraphError::NodeHasRelationships(node_id));
+        }
+        self.unindex_node(node_id, &nr)?;
+        let archived = nr.version != self.current_version();
+        let mut tombstone = NodeRecord{next_prop_id: TOMBSTONE, ..nr};
+        self.save_node_version(node_id, &mut tombstone)?;
+        if !archived {
+            self.properties_repository.delete_list(nr.next_prop_id)?;
+        }
+        self.nodes_labels_index.delete(node_id).ok_or_else(labels_index_failure)
+    }
+
+    /// Frees the nodes and relationships deleted at or before `version` with all their versions,
+    /// reads as of an earlier version do not find them anymore. Returns the number of freed elements.
+    pub fn compact(&mut self, version: u64) -> GraphResult<usize> {
+        let is_expired = |deleted: bool, deleted_at: u64| deleted && deleted_at <= version;
+        let rel_ids: Vec<u64> = self.relationships_store.scan().filter(|(_, rr)| is_expired(rr.is_tombstone(), rr.version)).map(|(id, _)| id).collect();
+        let node_ids: Vec<u64> = self.nodes_store.scan().filter(|(_, nr)| is_expired(nr.is_tombstone(), nr.version)).map(|(id, _)| id).collect();
+        for rel_id in &rel_ids {
+            let rr = self.relationships_store.load(*rel_id)?;
+            self.unlink_from_chain(rr.source, *rel_id, rr.next_outbound_edge, true)?;
+            self.unlink_from_chain(rr.target, *rel_id, rr.next_inbound_edge, false)?;
+            self.free_relationship(*rel_id)?;
+        }
+        let mut freed = rel_ids.len();
+        for node_id in node_ids {
+            let nr = self.nodes_store.load(node_id)?;
+            if nr.first_outbound_edge == 0 && nr.first_inbound_edge == 0 {
+                self.free_node(node_id)?;
+                freed += 1;
+            }
+        }
+        Ok(freed)
+    }
+
+    pub fn detach_delete_node(&mut self, node_id: u64) -> GraphResult<()> {
+        let nr = self.load_node(node_id)?;
+        let mut rel_ids = self.collect_visible_relationships(nr.first_outbound_edge, true, None)?;
+        for rel_id in self.collect_visible_relationships(nr.first_inbound_edge, false, None)? {
+            if !rel_ids.contains(&rel_id) {
+                rel_ids.push(rel_id);
+            }
+        }
+        for rel_id in rel_ids {
+            self.delete_relationship(rel_id)?;
+        }
+        self.delete_node(node_id)
+    }
+
+    fn unindex_node(&mut self, node_id: u64, nr: &NodeRecord) -> GraphResult<()> {
+        if !self.property_indexes.has_indexes()? {
+            return Ok(());
+        }
+        let labels = self.labels_repository.retrieve_list(nr.labels_id)?;
+        let props = self.properties_repository.retrieve_list(nr.next_prop_id)?;
+        self.property_indexes.remove(ElementKind::Node, &labels, &props, node_id)
+    }
+
+    fn unindex_relationship(&mut self, rel_id: u64, rr: &RelationshipRecord) -> GraphResult<()> {
+        if !self.property_indexes.has_indexes()? {
+            return Ok(());
+        }
+        let types = self.retrieve_relationship_type(rr.relationship_type)?;
+        let props = self.properties_repository.retrieve_list(rr.next_prop_id)?;
+        self.property_indexes.remove(ElementKind::Relationship, &types, &props, rel_id)
+    }
+
+    fn update_node_properties(&mut self, node_id: u64, props: &[Property]) -> GraphResult<()> {
+        let nr = self.load_node(node_id)?;
+        let labels = self.labels_repository.retrieve_list(nr.labels_id)?;
+        self.check_constraints(ElementKind::Node, &labels, props, Some(node_id), &mut HashSet::new())?;
+        self.write_node_properties(node_id, props)
+    }
+
+    /// Points the node at a fresh properties list. The previous list is freed unless
+    /// it is still referenced by an archived version.
+    fn write_node_properties(&mut self, node_id: u64, props: &[Property]) -> GraphResult<()> {
+        let mut nr = self.load_node(node_id)?;
+        self.unindex_node(node_id, &nr)?;
+        let old_prop_id = nr.next_prop_id;
+        let archived = nr.version != self.current_version();
+        nr.next_prop_id = self.properties_repository.create_list(props)?;
+        self.save_node_version(node_id, &mut nr)?;
+        if self.property_indexes.has_indexes()? {
+            let labels = self.labels_repository.retrieve_list(nr.labels_id)?;
+            self.property_indexes.insert(ElementKind::Node, &labels, props, node_id)?;
+        }
+        if !archived {
+            self.properties_repository.delete_list(old_prop_id)?;
+        }
+        Ok(())
+    }
+
+    fn update_relationship_properties(&mut self, rel_id: u64, props: &[Property]) -> GraphResult<()> {
+        let rr = self.load_relationship(rel_id)?;
+        let types = self.retrieve_relationship_type(rr.relationship_type)?;
+        self.check_constraints(ElementKind::Relationship, &types, props, Some(rel_id), &mut HashSet::new())?;
+        self.write_relationship_properties(rel_id, props)
+    }
+
+    fn write_relationship_properties(&mut self, rel_id: u64, props: &[Property]) -> GraphResult<()> {
+        let mut rr = self.load_relationship(rel_id)?;
+        self.unindex_relationship(rel_id, &rr)?;
+        let old_prop_id = rr.next_prop_id;
+        let archived = rr.version != self.current_version();
+        rr.next_prop_id = self.properties_repository.create_list(props)?;
+        self.save_relationship_version(rel_id, &mut rr)?;
+        if self.property_indexes.has_indexes()? {
+            let types = self.retrieve_relationship_type(rr.relationship_type)?;
+            self.property_indexes.insert(ElementKind::Relationship, &types, props, rel_id)?;
+        }
+        if !archived {
+            self.properties_repository.delete_list(old_prop_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_node_property(&mut self, node_id: u64, prop: &Property) -> GraphResult<()> {
+        let nr = self.load_node(node_id)?;
+        let mut props = self.properties_repository.retrieve_list(nr.next_prop_id)?;
+        set_in_list(&mut props, prop);
+        self.update_node_properties(node_id, &props)
+    }
+
+    pub fn remove_node_property(&mut self, node_id: u64, name: &str) -> GraphResult<bool> {
+        let nr = self.load_node(node_id)?;
+        let mut props = self.properties_repository.retrieve_list(nr.next_prop_id)?;
+        if !remove_from_list(&mut props, name) {
+            return Ok(false);
+        }
+        self.update_node_properties(node_id, &props)?;
+        Ok(true)
+    }
+
+    pub fn replace_node_properties(&mut self, node_id: u64, props: &[Property]) -> GraphResult<()> {
+        self.update_node_properties(node_id, props)
+    }
+
+    pub fn set_relationship_property(&mut self, rel_id: u64, prop: &Property) -> GraphResult<()> {
+        let rr = self.load_relationship(rel_id)?;
+        let mut props = self.properties_repository.retrieve_list(rr.next_prop_id)?;
+        set_in_list(&mut props, prop);
+        self.update_relationship_properties(rel_id, &props)
+    }
+
+    pub fn remove_relationship_property(&mut self, rel_id: u64, name: &str) -> GraphResult<bool> {
+        let rr = self.load_relationship(rel_id)?;
+        let mut props = self.properties_repository.retrieve_list(rr.next_prop_id)?;
+        if !remove_from_list(&mut props, name) {
+            return Ok(false);
+        }
+        self.update_relationship_properties(rel_id, &props)?;
+        Ok(true)
+    }
+
+    pub fn replace_relationship_properties(&mut self, rel_id: u64, props: &[Property]) -> GraphResult<()> {
+        self.update_relationship_properties(rel_id, props)
+    }
+
+    /// Logs the dirty pages of every store and applies them. The pages stay dirty until the commit succeeded,
+    /// a failed commit rolls the changes back. The log is emptied once the indexes that are not logged are written.
+    pub fn sync(&mut self) -> GraphResult<()> {
+        self.log_pages();
+        match self.wal.apply() {
+            Ok(()) => {
+                self.mark_pages_clean()?;
+                Ok(self.wal.end()?)
+            },
+            Err(e) => {
+                self.wal.clear();
+                self.rollback();
+                Err(e.into())
+            },
+        }
+    }
+
+    /// The write ahead log was applied so every store is marked clean, the first failure is returned:
+    /// it comes from a Bw-Tree index whose nodes could not be written, the log is then kept for the next recovery.
+    fn mark_pages_clean(&mut self) -> RecordsManagerResult<()> {
+        let results = vec![self.nodes_labels_index.mark_clean(),
+            self.property_indexes.mark_clean(),
+            self.constraints.mark_clean(),
+            self.relationships_store.mark_clean(),
+            self.nodes_store.mark_clean(),
+            self.relationships_history_store.mark_clean(),
+            self.nodes_history_store.mark_clean(),
+            self.properties_repository.mark_clean(),
+            self.labels_repository.mark_clean(),
+            self.relationship_types_store.mark_clean()];
+        results.into_iter().collect()
+    }
+
+    fn log_pages(&mut self) {
+        if let Some(version) = self.pending_version.take() {
+            self.nodes_store.set_version(version);
+            self.relationships_store.set_version(version);
+        }
+        self.nodes_labels_index.log_sync(&mut self.wal);
+        self.property_indexes.log_sync(&mut self.wal);
+        self.constraints.log_sync(&mut self.wal);
+        self.relationships_store.log_sync(&mut self.wal);
+        self.nodes_store.log_sync(&mut self.wal);
+        self.relationships_history_store.log_sync(&mut self.wal);
+        self.nodes_history_store.log_sync(&mut self.wal);
+        self.properties_repository.log_sync(&mut self.wal);
+        self.labels_repository.log_sync(&mut self.wal);
+        self.relationship_types_store.log_sync(&mut self.wal);
+    }
+
+    /// Reads back every page of the stores and property indexes, reports the first one failing its checksum.
+    /// Pages with uncommitted changes are not read from storage again.
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.nodes_store.verify()?;
+        self.relationships_store.verify()?;
+        self.nodes_history_store.verify()?;
+        self.relationships_history_store.verify()?;
+        self.properties_repository.verify()?;
+        self.labels_repository.verify()?;
+        self.relationship_types_store.verify()?;
+        self.nodes_labels_index.verify()?;
+        self.property_indexes.verify()?;
+        self.constraints.verify()
     }
 
-    pub fn sync(&mut self) {
-        self.nodes_labels_index.sync();
-        self.relationships_store.sync();
-        self.nodes_store.sync();
-        self.properties_repository.sync();
+    pub fn rollback(&mut self) {
+        self.pending_version = None;
+        self.nodes_labels_index.discard();
+        self.property_indexes.discard();
+        self.constraints.discard();
+        self.relationships_store.discard();
+        self.nodes_store.discard();
+        self.relationships_history_store.discard();
+        self.nodes_history_store.discard();
+        self.properties_repository.discard();
+        self.labels_repository.discard();
+        self.relationship_types_store.discard();
     }
 }
 
@@ -269,4 +1266,657 @@ impl DbEdgeData {
     fn new(source: u64, target: u64) -> Self {
         DbEdgeData{source: source, target: target, next_outbound_edge: None, next_inbound_edge: None}
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test_graph_repository {
+    use super::*;
+    use super::super::io::file_access::FileAccess;
+    use super::super::records::RecordsManagerError;
+    use super::super::super::buf_config::DEFAULT_PAGE_SIZE;
+    use super::super::super::test_utils::*;
+    use super::super::super::config::{NODES_LABELS_BW_INDEX_FILE_NAME, PROPERTY_INDEX_FILE_PREFIX};
+
+    fn build_name_property(name: &str) -> Property {
+        let mut prop = Property::new();
+        prop.set_name("name");
+        prop.set_value(Some(PropertyValue::PString(String::from(name))));
+        prop
+    }
+
+    #[test]
+    fn test_node_labels() {
+        let dir = build_dir_path_and_rm_old("test_node_labels").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let labels = vec![String::from("Person"), String::from("Parent")];
+        let (id, graph_ids) = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let mut node = Node::new();
+            node.set_labels(labels.clone());
+            let id = repo.create_node(&node).unwrap().get_id().unwrap();
+            let mut pgraph = PropertyGraph::new();
+            let mut city = Node::new();
+            city.get_labels_mut().push(String::from("City"));
+            pgraph.add_node(city);
+            pgraph.add_node(Node::new());
+            let created = repo.create_graph(&pgraph).unwrap();
+            repo.sync().unwrap();
+            (id, created.get_nodes().iter().map(|n| n.get_id().unwrap()).collect::<Vec<u64>>())
+        };
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        assert_eq!(repo.retrieve_node_by_id(id, None).unwrap().0.get_labels_ref(), &labels);
+        assert_eq!(repo.retrieve_node_by_id(graph_ids[0], None).unwrap().0.get_labels_ref(), &vec![String::from("City")]);
+        assert!(repo.retrieve_node_by_id(graph_ids[1], None).unwrap().0.get_labels_ref().is_empty());
+        assert_eq!(repo.node_history(id, None, None).unwrap()[0].node.get_labels_ref(), &labels);
+    }
+
+    #[test]
+    fn test_bw_tree_labels_index() {
+        let dir = build_dir_path_and_rm_old("test_bw_tree_labels_index").unwrap();
+        let mut ctx = init::InitContext::new(&dir);
+        ctx.set_nodes_labels_index_kind(init::IndexKind::BwTree);
+        let ids = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let mut ids = Vec::new();
+            for _ in 0..3 {
+                let mut node = Node::new();
+                node.set_labels(vec![String::from("Person")]);
+                ids.push(repo.create_node(&node).unwrap().get_id().unwrap());
+            }
+            repo.sync().unwrap();
+            repo.delete_node(ids[0]).unwrap();
+            repo.sync().unwrap();
+            ids
+        };
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let found = repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], None).unwrap();
+        assert_eq!(found, ids[1..].iter().copied().collect::<HashSet<u64>>());
+        ctx.set_nodes_labels_index_kind(init::IndexKind::BTree);
+        assert!(matches!(GraphRepository::new(&ctx), Err(GraphError::Incompatible(file, _)) if file.ends_with(NODES_LABELS_BW_INDEX_FILE_NAME)));
+        assert!(!std::path::Path::new(&ctx.get_nodes_labels_index_path().unwrap()).exists());
+    }
+
+    #[test]
+    fn test_bw_tree_property_index() {
+        let dir = build_dir_path_and_rm_old("test_bw_tree_property_index").unwrap();
+        let mut ctx = init::InitContext::new(&dir);
+        ctx.set_property_indexes_kind(init::IndexKind::BwTree);
+        let mut pattern = Node::new();
+        pattern.get_labels_mut().push(String::from("Person"));
+        pattern.add_property_range(PropertyRange::new("age", Some((PropertyValue::PInteger(30), true)), None));
+        let ids = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            repo.create_node_property_index("Person", "age").unwrap();
+            let mut ids = Vec::new();
+            for age in [20, 30, 40].iter() {
+                let mut node = Node::new();
+                node.get_labels_mut().push(String::from("Person"));
+                let mut prop = Property::new();
+                prop.set_name("age");
+                prop.set_value(Some(PropertyValue::PInteger(*age)));
+                node.get_properties_mut().push(prop);
+                ids.push(repo.create_node(&node).unwrap().get_id().unwrap());
+            }
+            repo.sync().unwrap();
+            ids
+        };
+        ctx.set_property_indexes_kind(init::IndexKind::BTree);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        repo.create_relationship_property_index("KNOWS", "name").unwrap();
+        repo.sync().unwrap();
+        let found = repo.fetch_nodes_ids_with_indexed_properties(&pattern, None).unwrap().unwrap();
+        assert_eq!(found, ids[1..].iter().copied().collect());
+        let index_files = std::fs::read_dir(&dir).unwrap().filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with(PROPERTY_INDEX_FILE_PREFIX)).collect::<Vec<String>>();
+        assert_eq!(index_files.len(), 2);
+        assert_eq!(index_files.iter().filter(|name| name.ends_with(".bw.db")).count(), 1);
+    }
+
+    #[test]
+    fn test_property_index_insert_failure() {
+        let dir = build_dir_path_and_rm_old("test_property_index_insert_failure").unwrap();
+        let mut ctx = init::InitContext::new(&dir);
+        ctx.set_property_indexes_kind(init::IndexKind::BwTree);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        repo.create_node_property_index("Person", "name").unwrap();
+        repo.sync().unwrap();
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        node.get_properties_mut().push(build_name_property(&"a".repeat(2000)));
+        assert!(matches!(repo.create_node(&node), Err(GraphError::Inconsistent(_))));
+        repo.rollback();
+        assert!(repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_node_versions() {
+        let dir = build_dir_path_and_rm_old("test_node_versions").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let mut node = Node::new();
+        node.get_properties_mut().push(build_name_property("alice"));
+        let id = repo.create_node(&node).unwrap().get_id().unwrap();
+        repo.sync().unwrap();
+        let v1 = repo.get_last_version();
+
+        let mut nr = repo.nodes_store.load(id).unwrap();
+        nr.next_prop_id = repo.properties_repository.create_list(&vec![build_name_property("bob")]).unwrap();
+        repo.save_node_version(id, &mut nr).unwrap();
+        repo.sync().unwrap();
+        let v2 = repo.get_last_version();
+        assert!(v2 > v1);
+
+        let current = repo.retrieve_node_by_id(id, None).unwrap().0;
+        assert!(*current.get_properties_ref()[0].get_value() == Some(PropertyValue::PString(String::from("bob"))));
+        let old = repo.retrieve_node_by_id(id, Some(v1)).unwrap().0;
+        assert!(*old.get_properties_ref()[0].get_value() == Some(PropertyValue::PString(String::from("alice"))));
+        assert!(matches!(repo.retrieve_node_by_id(id, Some(v1 - 1)), Err(GraphError::NodeNotFound(_))));
+
+        let history = repo.node_history(id, None, None).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].version, v1);
+        assert_eq!(history[1].version, v2);
+        assert!(*history[0].node.get_properties_ref()[0].get_value() == Some(PropertyValue::PString(String::from("alice"))));
+        assert_eq!(repo.node_history(id, Some(v2), None).unwrap().len(), 1);
+        assert_eq!(repo.node_history(id, None, Some(v1)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_relationships_as_of() {
+        let dir = build_dir_path_and_rm_old("test_relationships_as_of").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let mut pgraph = PropertyGraph::new();
+        let s = pgraph.add_node(Node::new());
+        let t = pgraph.add_node(Node::new());
+        pgraph.add_relationship(Relationship::new(), s, t);
+        let created = repo.create_graph(&pgraph).unwrap();
+        repo.sync().unwrap();
+        let v1 = repo.get_last_version();
+        let source_id = created.get_nodes()[0].get_id().unwrap();
+        assert!(repo.retrieve_vertex_data_by_id(source_id, Some(v1)).unwrap().first_outbound_edge.is_some());
+        assert!(repo.retrieve_vertex_data_by_id(source_id, Some(v1 - 1)).unwrap().first_outbound_edge.is_none());
+
+        let history = repo.node_history(source_id, None, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].outbound_relationships.len(), 1);
+        let rel_id = history[0].outbound_relationships[0];
+        let rel_history = repo.relationship_history(rel_id, None, None).unwrap();
+        assert_eq!(rel_history.len(), 1);
+        assert_eq!(rel_history[0].source, source_id);
+    }
+
+    #[test]
+    fn test_link_relationship_as_of() {
+        let dir = build_dir_path_and_rm_old("test_link_relationship_as_of").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let source = repo.create_node(&Node::new()).unwrap().get_id().unwrap();
+        let target = repo.create_node(&Node::new()).unwrap().get_id().unwrap();
+        repo.sync().unwrap();
+        let v1 = repo.get_last_version();
+        let rel_id = repo.create_relationship(&Relationship::new(), source, target).unwrap().get_id().unwrap();
+        let self_loop = repo.create_relationship(&Relationship::new(), source, source).unwrap().get_id().unwrap();
+        repo.sync().unwrap();
+        let v2 = repo.get_last_version();
+
+        assert_eq!(repo.load_node_version(source, Some(v1)).unwrap().unwrap().first_outbound_edge, 0);
+        assert_eq!(repo.load_node_version(target, Some(v1)).unwrap().unwrap().first_inbound_edge, 0);
+        assert!(repo.retrieve_vertex_data_by_id(source, Some(v1)).unwrap().first_outbound_edge.is_none());
+        assert!(repo.retrieve_vertex_data_by_id(target, Some(v1)).unwrap().first_inbound_edge.is_none());
+
+        let history = repo.node_history(source, None, None).unwrap();
+        assert_eq!(history.iter().map(|nv| nv.version).collect::<Vec<u64>>(), vec![v1, v2]);
+        assert!(history[0].outbound_relationships.is_empty());
+        assert_eq!(history[1].outbound_relationships, vec![self_loop, rel_id]);
+        assert_eq!(history[1].inbound_relationships, vec![self_loop]);
+        let history = repo.node_history(target, None, None).unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history[0].inbound_relationships.is_empty());
+        assert_eq!(history[1].inbound_relationships, vec![rel_id]);
+    }
+
+    #[test]
+    fn test_update_properties() {
+        let dir = build_dir_path_and_rm_old("test_update_properties").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let created = create_chain(&mut repo);
+        let node_id = created.get_nodes()[0].get_id().unwrap();
+        let rel_id = created.get_relationships()[0].get_id().unwrap();
+
+        repo.set_node_property(node_id, &build_name_property("alice")).unwrap();
+        let mut age = Property::new();
+        age.set_name("age");
+        age.set_value(Some(PropertyValue::PInteger(42)));
+        repo.set_node_property(node_id, &age).unwrap();
+        let intermediate = repo.nodes_store.load(node_id).unwrap().next_prop_id;
+        repo.set_node_property(node_id, &build_name_property("bob")).unwrap();
+        assert!(repo.properties_repository.retrieve_list(intermediate).is_err());
+        repo.set_relationship_property(rel_id, &build_name_property("knows")).unwrap();
+        repo.sync().unwrap();
+
+        assert_eq!(repo.remove_node_property(node_id, "age").ok(), Some(true));
+        assert_eq!(repo.remove_node_property(node_id, "unknown").ok(), Some(false));
+        repo.replace_relationship_properties(rel_id, &[age.clone()]).unwrap();
+        repo.sync().unwrap();
+
+        let node = repo.retrieve_node_by_id(node_id, None).unwrap().0;
+        assert_eq!(node.get_properties_ref().len(), 1);
+        assert_eq!(node.get_properties_ref()[0].get_value(), &Some(PropertyValue::PString(String::from("bob"))));
+        let rel = repo.retrieve_relationship_by_id(rel_id, None).unwrap().0;
+        assert_eq!(rel.get_properties_ref().len(), 1);
+        assert_eq!(rel.get_properties_ref()[0].get_name(), &Some(String::from("age")));
+
+        let history = repo.node_history(node_id, None, None).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[1].node.get_properties_ref().len(), 2);
+        assert!(repo.set_node_property(node_id + 1000, &age).is_err());
+    }
+
+    #[test]
+    fn test_relationship_types() {
+        let dir = build_dir_path_and_rm_old("test_relationship_types").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let (graph_rel, single_rel, untyped_rel) = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let mut pgraph = PropertyGraph::new();
+            let s = pgraph.add_node(Node::new());
+            let t = pgraph.add_node(Node::new());
+            let mut rel = Relationship::new();
+            rel.set_labels(vec![String::from("FRIEND_OF")]);
+            pgraph.add_relationship(rel, s, t);
+            let created = repo.create_graph(&pgraph).unwrap();
+            let source = created.get_nodes()[0].get_id().unwrap();
+            let target = created.get_nodes()[1].get_id().unwrap();
+            let mut knows = Relationship::new();
+            knows.set_labels(vec![String::from("KNOWS")]);
+            let single = repo.create_relationship(&knows, source, target).unwrap();
+            let untyped = repo.create_relationship(&Relationship::new(), source, target).unwrap();
+            repo.sync().unwrap();
+            (created.get_relationships()[0].get_id().unwrap(), single.get_id().unwrap(), untyped.get_id().unwrap())
+        };
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        assert_eq!(repo.retrieve_relationship_by_id(graph_rel, None).unwrap().0.get_labels_ref(), &vec![String::from("FRIEND_OF")]);
+        assert_eq!(repo.retrieve_relationship_by_id(single_rel, None).unwrap().0.get_labels_ref(), &vec![String::from("KNOWS")]);
+        assert!(repo.retrieve_relationship_by_id(untyped_rel, None).unwrap().0.get_labels_ref().is_empty());
+        assert_eq!(repo.relationships_store.load(single_rel).unwrap().relationship_type, repo.relationship_types_store.get_id("KNOWS").unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_read_only_sync_logs_nothing() {
+        let dir = build_dir_path_and_rm_old("test_read_only_sync_logs_nothing").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let mut person = Node::new();
+        person.get_labels_mut().push(String::from("Person"));
+        person.get_properties_mut().push(build_name_property("alice"));
+        let alice = repo.create_node(&person).unwrap().get_id().unwrap();
+        repo.sync().unwrap();
+        assert_eq!(repo.retrieve_node_by_id(alice, None).unwrap().0.get_labels_ref()[0], "Person");
+        assert_eq!(repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], None).unwrap().len(), 1);
+        repo.log_pages();
+        assert!(repo.wal.is_empty());
+    }
+
+    #[test]
+    fn test_stats() {
+        let dir = build_dir_path_and_rm_old("test_stats").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let mut person = Node::new();
+        person.get_labels_mut().push(String::from("Person"));
+        let alice = repo.create_node(&person).unwrap().get_id().unwrap();
+        let bob = repo.create_node(&person).unwrap().get_id().unwrap();
+        let carol = repo.create_node(&Node::new()).unwrap().get_id().unwrap();
+        let mut knows = Relationship::new();
+        knows.set_labels(vec![String::from("KNOWS")]);
+        repo.create_relationship(&knows, alice, bob).unwrap();
+        let knows_carol = repo.create_relationship(&knows, alice, carol).unwrap().get_id().unwrap();
+        repo.create_relationship(&Relationship::new(), bob, carol).unwrap();
+        repo.sync().unwrap();
+        repo.delete_relationship(knows_carol).unwrap();
+        repo.delete_node(alice).unwrap_err();
+        repo.detach_delete_node(alice).unwrap();
+        repo.sync().unwrap();
+
+        let stats = repo.get_stats().unwrap();
+        assert_eq!(stats.nodes, 2);
+        assert_eq!(stats.relationships, 1);
+        assert_eq!(stats.labels.into_iter().collect::<Vec<(String, usize)>>(), vec![(String::from("Person"), 1)]);
+        assert!(stats.relationship_types.is_empty());
+        assert_eq!(stats.constraints, 0);
+    }
+
+    #[test]
+    fn test_create_relationship_links_chains() {
+        let dir = build_dir_path_and_rm_old("test_create_relationship_links_chains").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let (a, b, rels) = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let created = create_chain(&mut repo);
+            let a = created.get_nodes()[0].get_id().unwrap();
+            let b = created.get_nodes()[1].get_id().unwrap();
+            let mut rels: Vec<u64> = created.get_relationships().iter().map(|r| r.get_id().unwrap()).collect();
+            rels.push(repo.create_relationship(&Relationship::new(), a, b).unwrap().get_id().unwrap());
+            repo.sync().unwrap();
+            (a, b, rels)
+        };
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let self_loop = repo.create_relationship(&Relationship::new(), a, a).unwrap().get_id().unwrap();
+        repo.sync().unwrap();
+
+        let first_out = repo.nodes_store.load(a).unwrap().first_outbound_edge;
+        let mut a_out = repo.collect_visible_relationships(first_out, true, None).unwrap();
+        a_out.sort();
+        let mut expected = vec![rels[0], rels[1], rels[3], self_loop];
+        expected.sort();
+        assert_eq!(a_out, expected);
+        let first_in = repo.nodes_store.load(a).unwrap().first_inbound_edge;
+        assert_eq!(repo.collect_visible_relationships(first_in, false, None).unwrap(), vec![self_loop]);
+        let first_in = repo.nodes_store.load(b).unwrap().first_inbound_edge;
+        let mut b_in = repo.collect_visible_relationships(first_in, false, None).unwrap();
+        b_in.sort();
+        let mut expected = vec![rels[0], rels[2], rels[3]];
+        expected.sort();
+        assert_eq!(b_in, expected);
+
+        repo.delete_relationship(rels[3]).unwrap();
+        repo.sync().unwrap();
+        let first_in = repo.nodes_store.load(b).unwrap().first_inbound_edge;
+        assert_eq!(repo.collect_visible_relationships(first_in, false, None).unwrap().len(), 2);
+    }
+
+    fn create_chain(repo: &mut GraphRepository) -> PropertyGraph {
+        let mut pgraph = PropertyGraph::new();
+        let a = pgraph.add_node(Node::new());
+        let b = pgraph.add_node(Node::new());
+        let c = pgraph.add_node(Node::new());
+        pgraph.add_relationship(Relationship::new(), a, b);
+        pgraph.add_relationship(Relationship::new(), a, c);
+        pgraph.add_relationship(Relationship::new(), c, b);
+        let created = repo.create_graph(&pgraph).unwrap();
+        repo.sync().unwrap();
+        created
+    }
+
+    #[test]
+    fn test_delete_relationship() {
+        let dir = build_dir_path_and_rm_old("test_delete_relationship").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let created = create_chain(&mut repo);
+        let a = created.get_nodes()[0].get_id().unwrap();
+        let b = created.get_nodes()[1].get_id().unwrap();
+        let rels: Vec<u64> = created.get_relationships().iter().map(|r| r.get_id().unwrap()).collect();
+
+        repo.delete_relationship(rels[0]).unwrap();
+        repo.sync().unwrap();
+        assert!(repo.retrieve_relationship_by_id(rels[0], None).is_err());
+        let first_out = repo.nodes_store.load(a).unwrap().first_outbound_edge;
+        let a_out = repo.collect_visible_relationships(first_out, true, None).unwrap();
+        assert_eq!(a_out, vec![rels[1]]);
+        let first_in = repo.nodes_store.load(b).unwrap().first_inbound_edge;
+        let b_in = repo.collect_visible_relationships(first_in, false, None).unwrap();
+        assert_eq!(b_in, vec![rels[2]]);
+        assert!(matches!(repo.delete_relationship(rels[0]), Err(GraphError::RelationshipNotFound(_))));
+    }
+
+    #[test]
+    fn test_delete_node() {
+        let dir = build_dir_path_and_rm_old("test_delete_node").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        node.get_properties_mut().push(build_name_property("alice"));
+        let created = create_chain(&mut repo);
+        let a = created.get_nodes()[0].get_id().unwrap();
+        let b = created.get_nodes()[1].get_id().unwrap();
+        let c = created.get_nodes()[2].get_id().unwrap();
+        let alice = repo.create_node(&node).unwrap().get_id().unwrap();
+        repo.sync().unwrap();
+
+        assert!(matches!(repo.delete_node(b), Err(GraphError::NodeHasRelationships(_))));
+        repo.rollback();
+        repo.delete_node(alice).unwrap();
+        repo.sync().unwrap();
+        assert!(matches!(repo.retrieve_node_by_id(alice, None), Err(GraphError::NodeNotFound(_))));
+        assert!(repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], None).unwrap().is_empty());
+
+        repo.detach_delete_node(b).unwrap();
+        repo.sync().unwrap();
+        assert!(repo.retrieve_node_by_id(b, None).is_err());
+        let first_out = repo.nodes_store.load(a).unwrap().first_outbound_edge;
+        let a_out = repo.collect_visible_relationships(first_out, true, None).unwrap();
+        assert_eq!(a_out.len(), 1);
+        assert!(repo.retrieve_vertex_data_by_id(c, None).unwrap().first_outbound_edge.is_none());
+        assert!(repo.retrieve_vertex_data_by_id(c, None).unwrap().first_inbound_edge.is_some());
+
+        let version = repo.get_last_version();
+        assert_eq!(repo.compact(version).unwrap(), 4);
+        repo.sync().unwrap();
+        assert_eq!(repo.nodes_store.load(c).unwrap().first_outbound_edge, 0);
+        assert!(repo.nodes_store.load(b).is_err());
+        assert!(repo.nodes_store.load(alice).is_err());
+        assert!(repo.retrieve_vertex_data_by_id(c, None).unwrap().first_inbound_edge.is_some());
+    }
+
+    #[test]
+    fn test_deleted_node_as_of() {
+        let dir = build_dir_path_and_rm_old("test_deleted_node_as_of").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let created = create_chain(&mut repo);
+        let a = created.get_nodes()[0].get_id().unwrap();
+        let b = created.get_nodes()[1].get_id().unwrap();
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from("Person"));
+        node.get_properties_mut().push(build_name_property("alice"));
+        let alice = repo.create_node(&node).unwrap().get_id().unwrap();
+        repo.sync().unwrap();
+        let before = repo.get_last_version();
+
+        repo.detach_delete_node(b).unwrap();
+        repo.delete_node(alice).unwrap();
+        repo.sync().unwrap();
+        let deleted = repo.get_last_version();
+        assert!(matches!(repo.retrieve_node_by_id(alice, None), Err(GraphError::NodeNotFound(_))));
+        assert!(matches!(repo.retrieve_node_by_id(alice, Some(deleted)), Err(GraphError::NodeNotFound(_))));
+        assert!(matches!(repo.set_node_property(alice, &build_name_property("bob")), Err(GraphError::NodeNotFound(_))));
+        assert!(!repo.fetch_all_nodes_ids(None).unwrap().contains(&alice));
+
+        let old = repo.retrieve_node_by_id(alice, Some(before)).unwrap().0;
+        assert_eq!(old.get_properties_ref()[0].get_value(), &Some(PropertyValue::PString(String::from("alice"))));
+        assert!(repo.fetch_all_nodes_ids(Some(before)).unwrap().contains(&alice));
+        assert_eq!(repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], Some(before)).unwrap(), [alice].iter().copied().collect());
+        assert!(repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], None).unwrap().is_empty());
+        assert_eq!(repo.fetch_outbound_relationships_ids(a, Some(before)).unwrap().len(), 2);
+        assert_eq!(repo.fetch_outbound_relationships_ids(a, None).unwrap().len(), 1);
+        assert_eq!(repo.fetch_neighbours_ids(b, Some(before)).unwrap().len(), 2);
+
+        let history = repo.node_history(alice, None, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(repo.node_history(alice, Some(deleted), None).unwrap().is_empty());
+        let rel_id = created.get_relationships()[0].get_id().unwrap();
+        assert_eq!(repo.relationship_history(rel_id, None, None).unwrap().len(), 1);
+
+        assert_eq!(repo.compact(before).unwrap(), 0);
+        assert_eq!(repo.compact(deleted).unwrap(), 4);
+        repo.sync().unwrap();
+        assert!(matches!(repo.retrieve_node_by_id(alice, Some(before)), Err(GraphError::NodeNotFound(_))));
+        assert!(matches!(repo.relationship_history(rel_id, None, None), Err(GraphError::RelationshipNotFound(_))));
+        assert_eq!(repo.fetch_outbound_relationships_ids(a, Some(before)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_property_indexes() {
+        let dir = build_dir_path_and_rm_old("test_property_indexes").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let build_person = |age: i64| {
+            let mut node = Node::new();
+            node.get_labels_mut().push(String::from("Person"));
+            let mut prop = Property::new();
+            prop.set_name("age");
+            prop.set_value(Some(PropertyValue::PInteger(age)));
+            node.get_properties_mut().push(prop);
+            node
+        };
+        let mut pattern = Node::new();
+        pattern.get_labels_mut().push(String::from("Person"));
+        pattern.add_property_range(PropertyRange::new("age", Some((PropertyValue::PInteger(30), false)), None));
+        let (young, old, older) = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let young = repo.create_node(&build_person(20)).unwrap().get_id().unwrap();
+            let old = repo.create_node(&build_person(40)).unwrap().get_id().unwrap();
+            assert!(repo.fetch_nodes_ids_with_indexed_properties(&pattern, None).unwrap().is_none());
+            repo.create_node_property_index("Person", "age").unwrap();
+            let older = repo.create_node(&build_person(60)).unwrap().get_id().unwrap();
+            let ids = repo.fetch_nodes_ids_with_indexed_properties(&pattern, None).unwrap().unwrap();
+            assert_eq!(ids, [old, older].iter().copied().collect());
+            let version = repo.current_version();
+            assert!(repo.fetch_nodes_ids_with_indexed_properties(&pattern, Some(version)).unwrap().is_none());
+
+            let source = repo.create_node(&Node::new()).unwrap().get_id().unwrap();
+            let mut knows = Relationship::new();
+            knows.get_labels_mut().push(String::from("KNOWS"));
+            knows.get_properties_mut().push(build_name_property("friend"));
+            let rel = repo.create_relationship(&knows, source, young).unwrap().get_id().unwrap();
+            repo.create_relationship_property_index("KNOWS", "name").unwrap();
+            let friend = PropertyValue::PString(String::from("friend"));
+            assert_eq!(repo.property_indexes.search(ElementKind::Relationship, "KNOWS", "name", &friend).unwrap().unwrap(), vec![rel]);
+            let ids = repo.fetch_relationships_nodes_ids_with_indexed_properties(&knows, None).unwrap().unwrap();
+            assert_eq!(ids, [source, young].iter().copied().collect());
+            repo.sync().unwrap();
+            (young, old, older)
+        };
+
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        let mut age = Property::new();
+        age.set_name("age");
+        age.set_value(Some(PropertyValue::PInteger(35)));
+        repo.set_node_property(young, &age).unwrap();
+        repo.delete_node(older).unwrap();
+        repo.sync().unwrap();
+        let ids = repo.fetch_nodes_ids_with_indexed_properties(&pattern, None).unwrap().unwrap();
+        assert_eq!(ids, [young, old].iter().copied().collect());
+
+        let mut equal = Node::new();
+        equal.get_labels_mut().push(String::from("Person"));
+        equal.get_properties_mut().push(age);
+        let ids = repo.fetch_nodes_ids_with_indexed_properties(&equal, None).unwrap().unwrap();
+        assert_eq!(ids, [young].iter().copied().collect());
+    }
+
+    /// Logs the pages of the pending changes and drops the repository before the checkpoint, as a crash would.
+    fn crash_before_checkpoint(mut repo: GraphRepository, label: &str) -> u64 {
+        let mut node = Node::new();
+        node.get_labels_mut().push(String::from(label));
+        let id = repo.create_node(&node).unwrap().get_id().unwrap();
+        repo.log_pages();
+        repo.wal.write_log().unwrap();
+        id
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        let dir = build_dir_path_and_rm_old("test_missing_directory").unwrap();
+        let ctx = init::InitContext::new(&format!("{}/missing", dir));
+        assert!(matches!(GraphRepository::new(&ctx), Err(GraphError::Io(_))));
+    }
+
+    #[test]
+    fn test_crash_recovery() {
+        let dir = build_dir_path_and_rm_old("test_crash_recovery").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let wal_file = ctx.get_wal_path().unwrap();
+        let committed_id = crash_before_checkpoint(GraphRepository::new(&ctx).unwrap(), "Person");
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        assert_eq!(repo.retrieve_node_by_id(committed_id, None).unwrap().0.get_labels_ref()[0], "Person");
+        assert_eq!(FileAccess::new(&wal_file).unwrap().get_file_len(), 0);
+
+        let torn_id = crash_before_checkpoint(repo, "City");
+        let log_len = FileAccess::new(&wal_file).unwrap().get_file_len();
+        std::fs::OpenOptions::new().write(true).open(&wal_file).unwrap().set_len(log_len - 1).unwrap();
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        assert!(matches!(repo.retrieve_node_by_id(torn_id, None), Err(GraphError::NodeNotFound(_))));
+        assert_eq!(repo.retrieve_node_by_id(committed_id, None).unwrap().0.get_labels_ref()[0], "Person");
+
+        crash_before_checkpoint(repo, "Country");
+        FileAccess::new(&wal_file).unwrap().write_at(10, &[0xFF]).unwrap();
+        assert!(matches!(GraphRepository::new(&ctx), Err(GraphError::Inconsistent(_))));
+        assert!(matches!(GraphRepository::new(&ctx), Err(GraphError::Inconsistent(_))));
+    }
+
+    #[test]
+    fn test_bw_tree_indexes_rebuilt_on_recovery() {
+        let dir = build_dir_path_and_rm_old("test_bw_tree_indexes_rebuilt_on_recovery").unwrap();
+        let mut ctx = init::InitContext::new(&dir);
+        ctx.set_nodes_labels_index_kind(init::IndexKind::BwTree);
+        ctx.set_property_indexes_kind(init::IndexKind::BwTree);
+        let mut pattern = Node::new();
+        pattern.get_labels_mut().push(String::from("Person"));
+        pattern.get_properties_mut().push(build_name_property("Alice"));
+        let (deleted_id, id) = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            repo.create_node_property_index("Person", "name").unwrap();
+            let deleted_id = repo.create_node(&pattern).unwrap().get_id().unwrap();
+            repo.sync().unwrap();
+            repo.delete_node(deleted_id).unwrap();
+            let id = repo.create_node(&pattern).unwrap().get_id().unwrap();
+            repo.log_pages();
+            repo.wal.apply().unwrap();
+            (deleted_id, id)
+        };
+        assert!(FileAccess::new(&ctx.get_wal_path().unwrap()).unwrap().get_file_len() > 0);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        assert_eq!(FileAccess::new(&ctx.get_wal_path().unwrap()).unwrap().get_file_len(), 0);
+        assert!(matches!(repo.retrieve_node_by_id(deleted_id, None), Err(GraphError::NodeNotFound(_))));
+        let expected = [id].iter().copied().collect::<HashSet<u64>>();
+        assert_eq!(repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], None).unwrap(), expected);
+        assert_eq!(repo.fetch_nodes_ids_with_indexed_properties(&pattern, None).unwrap().unwrap(), expected);
+        repo.verify().unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_nodes_page() {
+        let dir = build_dir_path_and_rm_old("test_corrupted_nodes_page").unwrap();
+        let ctx = init::InitContext::new(&dir);
+        let id = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let mut node = Node::new();
+            node.get_labels_mut().push(String::from("Person"));
+            let id = repo.create_node(&node).unwrap().get_id().unwrap();
+            repo.sync().unwrap();
+            assert!(repo.verify().is_ok());
+            id
+        };
+        let nodes_file = ctx.get_nodes_store_path().unwrap();
+        FileAccess::new(&nodes_file).unwrap().write_at(DEFAULT_PAGE_SIZE as u64 + 200, &[0xFF]).unwrap();
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        assert!(matches!(repo.nodes_store.load(id), Err(RecordsManagerError::Corrupted(_, 1))));
+        assert!(matches!(repo.retrieve_node_by_id(id, None), Err(GraphError::Corrupted(_, 1))));
+        match repo.verify() {
+            Err(RecordsManagerError::Corrupted(file, page_id)) => assert!(file == nodes_file && page_id == 1),
+            _ => panic!("the nodes page should be corrupted"),
+        }
+    }
+
+    #[test]
+    fn test_page_size() {
+        let dir = build_dir_path_and_rm_old("test_repository_page_size").unwrap();
+        let mut ctx = init::InitContext::new(&dir);
+        ctx.set_page_size(2 * DEFAULT_PAGE_SIZE);
+        let id = {
+            let mut repo = GraphRepository::new(&ctx).unwrap();
+            let mut node = Node::new();
+            node.get_labels_mut().push(String::from("Person"));
+            let id = repo.create_node(&node).unwrap().get_id().unwrap();
+            repo.sync().unwrap();
+            id
+        };
+        let ctx = init::InitContext::new(&dir);
+        let mut repo = GraphRepository::new(&ctx).unwrap();
+        assert_eq!(repo.retrieve_node_by_id(id, None).unwrap().0.get_labels_ref()[0], "Person");
+        assert!(repo.verify().is_ok());
+    }
+}
@@ -1,5 +1,7 @@
 use super::store::*;
 use super::model::*;
+use super::super::records::RecordsManagerResult;
+use super::super::wal::*;
 
 pub type DataPtr = u64;
 pub struct BTreeIndex {
@@ -14,15 +16,57 @@ fn get_node_ptr(not_found_index: usize, node: &BTreeNode) -> Option<NodeId> {
     Some(node_ptr)
 }
 
+/// Keys are ordered byte wise so encoded typed keys keep their order and prefixes group together.
 fn binary_search_keys(keys: &Vec<&str>, value: &str) -> Result<usize, usize> {
-    keys.binary_search_by(|&probe|{
-        Ord::cmp(&probe.len(), &value.len()).then(probe.cmp(value))
-    })
+    keys.binary_search(&value)
+}
+
+/// Walks the leaves through their sibling pointers, yielding keys in order up to the upper bound.
+pub struct RangeCursor<'a> {
+    node_store: &'a mut BTreeNodeStore,
+    leaf: Option<BTreeNode>,
+    cell_index: usize,
+    upper: Option<String>,
+    inclusive: bool,
+}
+
+impl <'a> RangeCursor<'a> {
+    fn is_past_upper(&self, key: &str) -> bool {
+        match &self.upper {
+            Some(upper) if self.inclusive => key > upper.as_str(),
+            Some(upper) => key >= upper.as_str(),
+            None => false,
+        }
+    }
+}
+
+impl <'a> Iterator for RangeCursor<'a> {
+    type Item = (String, Vec<DataPtr>);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let leaf = self.leaf.as_ref()?;
+            if self.cell_index < leaf.get_cells_ref().len() {
+                let cell = leaf.get_cell_ref(self.cell_index);
+                self.cell_index += 1;
+                if self.is_past_upper(cell.get_key()) {
+                    self.leaf = None;
+                    return None;
+                }
+                if !cell.get_data_ptrs_ref().is_empty() {
+                    return Some((cell.get_key().clone(), cell.get_data_ptrs_ref().clone()));
+                }
+            } else {
+                let next_leaf = leaf.get_node_ptr().and_then(|id| self.node_store.retrieve_node(id));
+                self.leaf = next_leaf;
+                self.cell_index = 0;
+            }
+        }
+    }
 }
 
 impl BTreeIndex {
-    pub fn new(file: &str) -> Self {
-        BTreeIndex{node_store: BTreeNodeStore::new(file)}
+    pub fn new(file: &str, page_size: usize) -> Self {
+        BTreeIndex{node_store: BTreeNodeStore::new(file, page_size)}
     }
 
     fn tree_search(&mut self, value: &str, node: &BTreeNode) -> Option<Vec<DataPtr>> {
@@ -50,7 +94,8 @@ impl BTreeIndex {
     }
 
 
-    fn split_leaf_node(&mut self, value: &str, data_ptr: u64, node: &mut BTreeNode, new_cell_index: usize) -> Option<BTreeNode> {
+    /// The outer option fails when a node cannot be stored, the inner one holds the new node to link in the parent.
+    fn split_leaf_node(&mut self, value: &str, data_ptr: u64, node: &mut BTreeNode, new_cell_index: usize) -> Option<Option<BTreeNode>> {
         node.insert_cell(new_cell_index, Cell::new_leaf(value, data_ptr));
         let split = node.get_cells_ref().len() / 2;
         let mut new_node_cells = Vec::new();
@@ -81,14 +126,14 @@ impl BTreeIndex {
             let mut new_root = BTreeNode::new(false, true, vec![middle_key]);
             new_root.set_node_ptr(node.get_id());
             self.node_store.create(&mut new_root)?;
-            None
+            Some(None)
         } else {
             self.node_store.save(node)?;
-            Some(new)
+            Some(Some(new))
         }
     }
 
-    fn split_interior_node(&mut self, new_key: &str, new_node_ptr: Option<NodeId>, node: &mut BTreeNode, new_cell_index: usize) -> Option<BTreeNode> {
+    fn split_interior_node(&mut self, new_key: &str, new_node_ptr: Option<NodeId>, node: &mut BTreeNode, new_cell_index: usize) -> Option<Option<BTreeNode>> {
         node.insert_cell(new_cell_index, Cell::new_ptr(new_key, new_node_ptr));
         let split = node.get_cells_ref().len() / 2;
         let mut new_node_cells = Vec::new();
@@ -102,7 +147,7 @@ impl BTreeIndex {
         let new_first_cell = &new_node_cells[0];
         let mut middle_key = Cell::new_ptr(&new_first_cell.get_key(), None);
         let mut new = BTreeNode::new(false, false, new_node_cells);
-        self.node_store.create(&mut new);
+        self.node_store.create(&mut new)?;
 
         if node.is_root() {
             node.set_is_root(false);
@@ -111,14 +156,15 @@ impl BTreeIndex {
             let mut new_root = BTreeNode::new(false, true, vec![middle_key]);
             new_root.set_node_ptr(node.get_id());
             self.node_store.create(&mut new_root)?;
-            None
+            Some(None)
         } else {
             self.node_store.save(node)?;
-            Some(new)
+            Some(Some(new))
         }
     }
 
-    fn insert_or_update_key_ptrs(&mut self, value: &str, data_ptr: u64, node: &mut BTreeNode) -> Option<BTreeNode> {
+    /// Same result as the splits: None when a node cannot be stored, then the new node to link in the parent if any.
+    fn insert_or_update_key_ptrs(&mut self, value: &str, data_ptr: u64, node: &mut BTreeNode) -> Option<Option<BTreeNode>> {
         let keys = node.get_keys();
         let res = binary_search_keys(&keys, value);
         match res {
@@ -126,7 +172,7 @@ impl BTreeIndex {
                 if node.is_leaf() {
                     node.get_cell_mut(found).append_data_ptr(data_ptr);
                     self.node_store.save(node)?;
-                    None
+                    Some(None)
                 } else {
                     let mut child = node.get_cell_ref(found).get_node_ptr().and_then(|id| self.node_store.retrieve_node(id))?;
                     self.insert_or_update_key_ptrs(value, data_ptr, &mut child)
@@ -139,12 +185,15 @@ impl BTreeIndex {
                     } else {
                         node.insert_cell(not_found, Cell::new_leaf(value, data_ptr));
                         self.node_store.save(node)?;
-                        None
+                        Some(None)
                     }
                 } else {
                     let node_ptr = get_node_ptr(not_found, &node)?;
                     let mut child = self.node_store.retrieve_node(node_ptr)?;
-                    let split_node = self.insert_or_update_key_ptrs(value, data_ptr, &mut child)?;
+                    let split_node = match self.insert_or_update_key_ptrs(value, data_ptr, &mut child)? {
+                        Some(split_node) => split_node,
+                        None => return Some(None),
+                    };
                     let first_cell = split_node.get_cell_ref(0);
                     let first_cell_key = first_cell.get_key();
                     let first_split_cell_key_search = binary_search_keys(&keys, first_cell_key);
@@ -155,10 +204,10 @@ impl BTreeIndex {
                             } else {
                                 node.insert_cell(not_found, Cell::new_ptr(first_cell.get_key(), split_node.get_id()));
                                 self.node_store.save(node)?;
-                                None
+                                Some(None)
                             }
                         },
-                        _ => {None}
+                        _ => Some(None),
                     }
                     
                 }
@@ -173,15 +222,102 @@ impl BTreeIndex {
 
     pub fn insert(&mut self, value: &str, data_ptr: u64) -> Option<()> {
         let mut root = self.node_store.load_or_create_root_node()?;
-        self.insert_or_update_key_ptrs(value, data_ptr, &mut root).map(|_node|())
+        self.insert_or_update_key_ptrs(value, data_ptr, &mut root).map(|_split_node| ())
     }
 
-    pub fn delete(&mut self, value: u64) {
+    fn first_leaf(&mut self) -> Option<BTreeNode> {
+        let mut node = self.node_store.load_or_create_root_node()?;
+        while !node.is_leaf() {
+            node = node.get_node_ptr().and_then(|id| self.node_store.retrieve_node(id))?;
+        }
+        Some(node)
+    }
+
+    fn find_leaf(&mut self, value: &str) -> Option<BTreeNode> {
+        let mut node = self.node_store.load_or_create_root_node()?;
+        while !node.is_leaf() {
+            let node_ptr = match binary_search_keys(&node.get_keys(), value) {
+                Ok(found) => node.get_cell_ref(found).get_node_ptr()?,
+                Err(not_found) => get_node_ptr(not_found, &node)?,
+            };
+            node = self.node_store.retrieve_node(node_ptr)?;
+        }
+        Some(node)
+    }
+
+    /// Cursor over the keys between `lower` and `upper`, a missing bound is unbounded.
+    /// `inclusive` applies to both bounds.
+    pub fn range(&mut self, lower: Option<&str>, upper: Option<&str>, inclusive: bool) -> Option<RangeCursor<'_>> {
+        let (leaf, cell_index) = match lower {
+            Some(value) => {
+                let leaf = self.find_leaf(value)?;
+                let cell_index = match binary_search_keys(&leaf.get_keys(), value) {
+                    Ok(found) if inclusive => found,
+                    Ok(found) => found + 1,
+                    Err(not_found) => not_found,
+                };
+                (leaf, cell_index)
+            },
+            None => (self.first_leaf()?, 0),
+        };
+        Some(RangeCursor{node_store: &mut self.node_store, leaf: Some(leaf), cell_index, upper: upper.map(String::from), inclusive})
+    }
 
+    /// Removes a data pointer from a single key, unlike `delete` which scans every leaf.
+    pub fn remove(&mut self, value: &str, data_ptr: DataPtr) -> Option<()> {
+        let mut leaf = self.find_leaf(value)?;
+        if let Ok(found) = binary_search_keys(&leaf.get_keys(), value) {
+            if leaf.get_cell_ref(found).get_data_ptrs_ref().contains(&data_ptr) {
+                leaf.get_cell_mut(found).delete_data_ptr(data_ptr);
+                self.node_store.save(&mut leaf)?;
+            }
+        }
+        Some(())
     }
 
-    pub fn sync(&mut self) {
-        self.node_store.sync();
+    pub fn delete(&mut self, value: u64) -> Option<()> {
+        let mut leaf = self.first_leaf()?;
+        loop {
+            let mut changed = false;
+            for index in 0..leaf.get_cells_ref().len() {
+                if leaf.get_cell_ref(index).get_data_ptrs_ref().contains(&value) {
+                    leaf.get_cell_mut(index).delete_data_ptr(value);
+                    changed = true;
+                }
+            }
+            if changed {
+                self.node_store.save(&mut leaf)?;
+            }
+            match leaf.get_node_ptr() {
+                Some(next_id) => leaf = self.node_store.retrieve_node(next_id)?,
+                None => break,
+            }
+        }
+        Some(())
+    }
+
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.node_store.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.node_store.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.node_store.set_cache_capacity(capacity);
+    }
+
+    pub fn discard(&mut self) {
+        self.node_store.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.node_store.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.node_store.mark_clean()
     }
 }
 
@@ -189,14 +325,15 @@ impl BTreeIndex {
 mod test_b_tree {
     use super::*;
     use super::super::super::super::test_utils::*;
+    use super::super::super::super::buf_config::DEFAULT_PAGE_SIZE;
     #[test]
     fn test_insert() {
         let file = build_file_path_and_rm_old("b_tree", "test_insert.db").unwrap();
-        let mut index = BTreeIndex::new(&file);
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
         let key = "a short key";
-        index.insert(key, 42);
+        index.insert(key, 42).unwrap();
         let long_key = "a long key a long key a long key a long key a long key a long key a long key a long key a long key a long key a long key a long key a long key ";
-        index.insert(long_key, 87968567);
+        index.insert(long_key, 87968567).unwrap();
 
 
         let data_ptrs = index.search(key);
@@ -208,7 +345,7 @@ mod test_b_tree {
             assert!(false);
         }
 
-        index.sync();
+        index.sync().unwrap();
 
         index.insert(key, 56);
 
@@ -230,13 +367,13 @@ mod test_b_tree {
     #[test]
     fn test_root_split() {
         let file = build_file_path_and_rm_old("b_tree", "test_root_split.db").unwrap();
-        let mut index = BTreeIndex::new(&file);
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
 
         for i in 0..1000 {
-            index.insert(&format!("key # {}", i), i);
+            index.insert(&format!("key # {}", i), i).unwrap();
         }
 
-        index.sync();
+        index.sync().unwrap();
 
         for i in 0..1000 {
             let optrs = index.search(&format!("key # {}", i));
@@ -250,4 +387,69 @@ mod test_b_tree {
         }
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_many_data_ptrs() {
+        let file = build_file_path_and_rm_old("b_tree", "test_many_data_ptrs.db").unwrap();
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
+        for i in 1..101 {
+            index.insert("Person", i);
+        }
+        index.sync().unwrap();
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
+        let ptrs = index.search("Person").unwrap();
+        assert_eq!(ptrs.len(), 100);
+        assert!(ptrs.contains(&100));
+    }
+
+    #[test]
+    fn test_delete() {
+        let file = build_file_path_and_rm_old("b_tree", "test_delete.db").unwrap();
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
+        for i in 0..1000 {
+            index.insert(&format!("key # {}", i % 100), i).unwrap();
+        }
+        for i in 0..1000 {
+            if i % 3 == 0 {
+                index.delete(i).unwrap();
+            }
+        }
+        index.sync().unwrap();
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
+        for k in 0..100 {
+            let ptrs = index.search(&format!("key # {}", k)).unwrap();
+            for i in (k..1000).step_by(100) {
+                assert_eq!(ptrs.contains(&i), i % 3 != 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_range() {
+        use super::super::key::IndexKey;
+        let file = build_file_path_and_rm_old("b_tree", "test_range.db").unwrap();
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
+        for age in -500..500 {
+            index.insert(&IndexKey::Integer(age).encode(), (age + 500) as u64);
+        }
+        for name in &["John", "Joe", "Jim", "Karl", "Jo"] {
+            index.insert(&IndexKey::String(name.to_string()).encode(), name.len() as u64);
+        }
+        index.sync().unwrap();
+        let mut index = BTreeIndex::new(&file, DEFAULT_PAGE_SIZE);
+
+        let (_, upper) = IndexKey::Integer(0).type_bounds();
+        let lower = IndexKey::Integer(30).encode();
+        let ptrs = index.range(Some(&lower), Some(&upper), false).unwrap().flat_map(|(_, ptrs)| ptrs).collect::<Vec<u64>>();
+        assert_eq!(ptrs, (531..1000).collect::<Vec<u64>>());
+
+        let upper = IndexKey::Integer(-490).encode();
+        let ptrs = index.range(None, Some(&upper), true).unwrap().flat_map(|(_, ptrs)| ptrs).collect::<Vec<u64>>();
+        assert_eq!(ptrs, (0..11).collect::<Vec<u64>>());
+
+        let (lower, upper) = IndexKey::prefix_bounds("Jo");
+        let keys = index.range(Some(&lower), Some(&upper), true).unwrap().map(|(key, _)| key).collect::<Vec<String>>();
+        let expected = ["Jo", "Joe", "John"].iter().map(|name| IndexKey::String(name.to_string()).encode()).collect::<Vec<String>>();
+        assert_eq!(keys, expected);
+    }
+}
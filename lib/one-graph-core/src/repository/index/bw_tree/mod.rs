@@ -0,0 +1,530 @@
+mod model;
+
+use self::model::*;
+use super::super::wal::*;
+use super::b_tree::DataPtr;
+use super::super::records::{RecordsManagerError, RecordsManagerResult};
+use cache::buf_config::{CONSOLIDATION_THRESHOLD, FLUSH_BUFFER_SIZE, MAPPING_TABLE_CAPACITY, PAGE_SIZE};
+use cache::lss::Lss;
+use cache::page::RetiredList;
+use log::error;
+use std::ops::Bound;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+
+/// Path of inner nodes with their chosen child, then the leaf id, chain head and consolidated view.
+type LeafLocation = (Vec<(PageId, PageId)>, PageId, *mut BwNode, BwBase);
+
+/// Lock-free index built as a B-link tree of delta chains: writers prepend insert, delete and split deltas
+/// with a compare and swap on the mapping table, readers follow right links when a node was split under them.
+/// Nodes are persisted one per page in a log-structured store which is its own log, they are written
+/// once the write ahead log applied the other stores and before it is emptied: the repository rebuilds
+/// the index when it replays the log after a crash in between.
+/// Empty nodes are never merged.
+/// Replaced chains are freed on `sync` and `discard` which need an exclusive borrow.
+pub struct BwTreeIndex {
+    mapping_table: Vec<AtomicPtr<BwNode>>,
+    dirty: Vec<AtomicBool>,
+    next_pid: AtomicU64,
+    lss: Lss,
+    retired: RetiredList<BwNode>,
+    file: String,
+}
+
+fn make_start_key(bound: Bound<&str>) -> EntryKey {
+    match bound {
+        Bound::Included(key) => (key.as_bytes().to_vec(), 0),
+        Bound::Excluded(key) => (key.as_bytes().to_vec(), u64::MAX),
+        Bound::Unbounded => min_key(),
+    }
+}
+
+fn is_before_end(key: &[u8], bound: Bound<&str>) -> bool {
+    match bound {
+        Bound::Included(end) => key <= end.as_bytes(),
+        Bound::Excluded(end) => key < end.as_bytes(),
+        Bound::Unbounded => true,
+    }
+}
+
+fn is_after_start(key: &[u8], bound: Bound<&str>) -> bool {
+    match bound {
+        Bound::Excluded(start) => key > start.as_bytes(),
+        _ => true,
+    }
+}
+
+impl BwTreeIndex {
+    pub fn new(file: &str) -> Self {
+        BwTreeIndex::with_capacity(file, MAPPING_TABLE_CAPACITY)
+    }
+
+    /// The index holds at most `nb_nodes` nodes.
+    pub fn with_capacity(file: &str, nb_nodes: usize) -> Self {
+        let lss = Lss::with_capacity(file, nb_nodes, FLUSH_BUFFER_SIZE);
+        let next_pid = std::cmp::max(lss.get_pages_count(), ROOT_PAGE_ID + 1);
+        BwTreeIndex{mapping_table: (0..nb_nodes).map(|_| AtomicPtr::new(ptr::null_mut())).collect(),
+            dirty: (0..nb_nodes).map(|_| AtomicBool::new(false)).collect(),
+            next_pid: AtomicU64::new(next_pid), lss, retired: RetiredList::new(free_chain), file: String::from(file)}
+    }
+
+    fn get_entry(&self, pid: PageId) -> Option<&AtomicPtr<BwNode>> {
+        self.mapping_table.get(pid as usize)
+    }
+
+    /// Returns the head of the node chain, loading the node from the store on first access.
+    fn load_head(&self, pid: PageId) -> Option<*mut BwNode> {
+        let entry = self.get_entry(pid)?;
+        let head = entry.load(Ordering::Acquire);
+        if !head.is_null() {
+            return Some(head);
+        }
+        let base = self.lss.read(pid).map(|page| BwBase::from_page(&page)).unwrap_or_else(BwBase::empty_leaf);
+        let node = BwNode::Base(base).into_ptr();
+        match entry.compare_exchange(head, node, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => Some(node),
+            Err(current) => {
+                unsafe {
+                    free_chain(node);
+                }
+                Some(current)
+            }
+        }
+    }
+
+    fn install(&self, pid: PageId, head: *mut BwNode, node: *mut BwNode) -> bool {
+        let installed = self.get_entry(pid)
+            .map(|entry| entry.compare_exchange(head, node, Ordering::AcqRel, Ordering::Acquire).is_ok())
+            .unwrap_or(false);
+        if installed {
+            self.dirty[pid as usize].store(true, Ordering::Release);
+        } else {
+            unsafe {
+                drop(Box::from_raw(node));
+            }
+        }
+        installed
+    }
+
+    fn retire(&self, node: *mut BwNode) {
+        self.retired.push(node);
+    }
+
+    /// Fails once every id of the mapping table is used, the id counter never goes past its capacity.
+    fn allocate(&self, base: BwBase) -> Option<PageId> {
+        let capacity = self.mapping_table.len() as u64;
+        let pid = self.next_pid.fetch_update(Ordering::AcqRel, Ordering::Acquire, |pid| if pid < capacity { Some(pid + 1) } else { None });
+        let pid = match pid {
+            Ok(pid) => pid,
+            Err(_) => {
+                error!("the bw-tree mapping table is full");
+                return None;
+            }
+        };
+        let entry = self.get_entry(pid)?;
+        entry.store(BwNode::Base(base).into_ptr(), Ordering::Release);
+        self.dirty[pid as usize].store(true, Ordering::Release);
+        Some(pid)
+    }
+
+    /// Frees a node that was allocated but never linked in the tree, its id is lost.
+    fn release(&self, pid: PageId) {
+        if let Some(entry) = self.get_entry(pid) {
+            self.dirty[pid as usize].store(false, Ordering::Release);
+            unsafe {
+                free_chain(entry.swap(ptr::null_mut(), Ordering::AcqRel));
+            }
+        }
+    }
+
+    /// Descends to the leaf owning the key, the path holds each inner node with the child chosen in it.
+    fn find(&self, key: &EntryKey) -> Option<LeafLocation> {
+        let mut path = Vec::new();
+        let mut pid = ROOT_PAGE_ID;
+        loop {
+            let head = self.load_head(pid)?;
+            let view = unsafe { materialize(head) };
+            if view.is_out_of_range(key) {
+                pid = view.right?;
+                continue;
+            }
+            if view.is_leaf {
+                return Some((path, pid, head, view));
+            }
+            let child = view.find_child(key)?;
+            path.push((pid, child));
+            pid = child;
+        }
+    }
+
+    fn consolidate(&self, pid: PageId, head: *mut BwNode) {
+        if unsafe { chain_len(head) } < CONSOLIDATION_THRESHOLD {
+            return;
+        }
+        let base = unsafe { materialize(head) };
+        if self.install(pid, head, BwNode::Base(base).into_ptr()) {
+            self.retire(head);
+        }
+    }
+
+    /// Moves the upper half of a full node to a new right sibling, the root keeps its id
+    /// and becomes an inner node over two new children.
+    fn split(&self, pid: PageId, head: *mut BwNode, view: &BwBase) -> Option<()> {
+        let middle = view.keys.len() / 2;
+        let separator = view.keys[middle].clone();
+        let right_children = if view.is_leaf { Vec::new() } else { view.children[middle..].to_vec() };
+        let right_base = BwBase{is_leaf: view.is_leaf, keys: view.keys[middle..].to_vec(), children: right_children,
+            high_key: view.high_key.clone(), right: view.right};
+        let right_pid = self.allocate(right_base)?;
+        if pid == ROOT_PAGE_ID {
+            let left_children = if view.is_leaf { Vec::new() } else { view.children[..middle].to_vec() };
+            let left_base = BwBase{is_leaf: view.is_leaf, keys: view.keys[..middle].to_vec(), children: left_children,
+                high_key: Some(separator.clone()), right: Some(right_pid)};
+            let left_pid = match self.allocate(left_base) {
+                Some(left_pid) => left_pid,
+                None => {
+                    self.release(right_pid);
+                    return None;
+                }
+            };
+            let root = BwBase{is_leaf: false, keys: vec![min_key(), separator], children: vec![left_pid, right_pid], high_key: None, right: None};
+            if self.install(pid, head, BwNode::Base(root).into_ptr()) {
+                self.retire(head);
+            } else {
+                self.release(left_pid);
+                self.release(right_pid);
+            }
+            return Some(());
+        }
+        let split = BwNode::Split{separator: separator.clone(), right: right_pid, next: head, chain_len: unsafe { chain_len(head) } + 1};
+        if self.install(pid, head, split.into_ptr()) {
+            self.post_index_entry(pid, &separator, right_pid)
+        } else {
+            self.release(right_pid);
+            Some(())
+        }
+    }
+
+    /// Links the new right sibling in the parent of the split node, gives up when another thread already did.
+    fn post_index_entry(&self, pid: PageId, separator: &EntryKey, right_pid: PageId) -> Option<()> {
+        loop {
+            let (path, _, _, _) = self.find(separator)?;
+            let parent_pid = match path.iter().rev().find(|(_, child)| *child == pid) {
+                Some((parent_pid, _)) => *parent_pid,
+                None => return Some(()),
+            };
+            let head = self.load_head(parent_pid)?;
+            let view = unsafe { materialize(head) };
+            if view.is_out_of_range(separator) {
+                continue;
+            }
+            if view.keys.binary_search(separator).is_ok() {
+                return Some(());
+            }
+            if view.size() + entry_size(separator, false) > PAGE_SIZE {
+                self.split(parent_pid, head, &view)?;
+                continue;
+            }
+            let node = BwNode::Insert{key: separator.clone(), child: Some(right_pid), next: head, chain_len: unsafe { chain_len(head) } + 1};
+            let node_ptr = node.into_ptr();
+            if self.install(parent_pid, head, node_ptr) {
+                self.consolidate(parent_pid, node_ptr);
+                return Some(());
+            }
+        }
+    }
+
+    pub fn insert(&self, key: &str, data_ptr: DataPtr) -> Option<()> {
+        if key.len() > MAX_KEY_SIZE {
+            error!("index key longer than {} bytes", MAX_KEY_SIZE);
+            return None;
+        }
+        let entry_key = (key.as_bytes().to_vec(), data_ptr);
+        loop {
+            let (_, pid, head, view) = self.find(&entry_key)?;
+            if view.keys.binary_search(&entry_key).is_ok() {
+                return Some(());
+            }
+            if view.size() + entry_size(&entry_key, true) > PAGE_SIZE {
+                self.split(pid, head, &view)?;
+                continue;
+            }
+            let node = BwNode::Insert{key: entry_key.clone(), child: None, next: head, chain_len: unsafe { chain_len(head) } + 1};
+            let node_ptr = node.into_ptr();
+            if self.install(pid, head, node_ptr) {
+                self.consolidate(pid, node_ptr);
+                return Some(());
+            }
+        }
+    }
+
+    pub fn remove(&self, key: &str, data_ptr: DataPtr) -> Option<()> {
+        let entry_key = (key.as_bytes().to_vec(), data_ptr);
+        loop {
+            let (_, pid, head, view) = self.find(&entry_key)?;
+            if view.keys.binary_search(&entry_key).is_err() {
+                return Some(());
+            }
+            let node = BwNode::Delete{key: entry_key.clone(), next: head, chain_len: unsafe { chain_len(head) } + 1};
+            let node_ptr = node.into_ptr();
+            if self.install(pid, head, node_ptr) {
+                self.consolidate(pid, node_ptr);
+                return Some(());
+            }
+        }
+    }
+
+    /// Scans the leaves from the start bound following the right links, keys are compared as bytes.
+    pub fn range(&self, start: Bound<&str>, end: Bound<&str>) -> Option<Vec<(String, DataPtr)>> {
+        let start_key = make_start_key(start);
+        let (_, _, _, mut view) = self.find(&start_key)?;
+        let mut res = Vec::new();
+        loop {
+            for key in view.keys.iter().filter(|key| **key >= start_key) {
+                if !is_before_end(&key.0, end) {
+                    return Some(res);
+                }
+                if is_after_start(&key.0, start) {
+                    res.push((String::from_utf8(key.0.clone()).ok()?, key.1));
+                }
+            }
+            match view.right {
+                Some(right) => view = unsafe { materialize(self.load_head(right)?) },
+                None => return Some(res),
+            }
+        }
+    }
+
+    pub fn search(&self, key: &str) -> Option<Vec<DataPtr>> {
+        let ptrs = self.range(Bound::Included(key), Bound::Included(key))?.into_iter().map(|(_, ptr)| ptr).collect::<Vec<DataPtr>>();
+        if ptrs.is_empty() {
+            None
+        } else {
+            Some(ptrs)
+        }
+    }
+
+    /// Removes the data pointer under every key, all the leaves are scanned.
+    pub fn delete(&self, data_ptr: DataPtr) -> Option<()> {
+        for (key, _) in self.range(Bound::Unbounded, Bound::Unbounded)?.iter().filter(|(_, ptr)| *ptr == data_ptr) {
+            self.remove(key, data_ptr)?;
+        }
+        Some(())
+    }
+
+    /// A node that cannot be written stays dirty so that the next sync writes it again.
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        for pid in 0..self.next_pid.load(Ordering::Acquire) {
+            if !self.dirty[pid as usize].swap(false, Ordering::AcqRel) {
+                continue;
+            }
+            let head = self.mapping_table[pid as usize].load(Ordering::Acquire);
+            if !head.is_null() && self.lss.write(pid, &unsafe { materialize(head) }.to_page()).is_none() {
+                error!("index node {} is out of the lss mapping table", pid);
+                self.dirty[pid as usize].store(true, Ordering::Release);
+                return Err(RecordsManagerError::Full(self.file.clone()));
+            }
+        }
+        let flushed = self.lss.flush().map_err(|e| RecordsManagerError::Io(format!("{}: {}", self.file, e)));
+        self.retired.collect();
+        flushed
+    }
+
+    /// Drops every change since the last sync, nodes are loaded again from the store.
+    pub fn discard(&mut self) {
+        for pid in 0..self.next_pid.load(Ordering::Acquire) {
+            self.release(pid);
+        }
+        self.next_pid.store(std::cmp::max(self.lss.get_pages_count(), ROOT_PAGE_ID + 1), Ordering::Release);
+        self.retired.collect();
+    }
+
+    /// Walks each level from its first node along the right links, starting with the root.
+    /// The keys must increase across the nodes of a level and stay below the high key of their node,
+    /// a node has a right sibling exactly when it has a high key and every child of a level is a node of the next one.
+    pub fn verify(&self) -> RecordsManagerResult<()> {
+        let mut expected = vec![ROOT_PAGE_ID];
+        let mut nb_visited = 0;
+        loop {
+            let mut pid = expected[0];
+            let mut visited = Vec::new();
+            let mut children = Vec::new();
+            let mut last_key: Option<EntryKey> = None;
+            let mut low_key: Option<EntryKey> = None;
+            let mut is_leaf = None;
+            loop {
+                let corrupted = || RecordsManagerError::Corrupted(self.file.clone(), pid);
+                nb_visited += 1;
+                if nb_visited > self.mapping_table.len() {
+                    error!("index node {} is linked in a cycle", pid);
+                    return Err(corrupted());
+                }
+                let view = unsafe { materialize(self.load_head(pid).ok_or_else(corrupted)?) };
+                if *is_leaf.get_or_insert(view.is_leaf) != view.is_leaf || (!view.is_leaf && (view.keys.is_empty() || view.children.len() != view.keys.len())) {
+                    error!("index node {} does not have the shape of its level", pid);
+                    return Err(corrupted());
+                }
+                for key in &view.keys {
+                    let is_too_low = last_key.as_ref().map(|last| key <= last).unwrap_or(false) || low_key.as_ref().map(|low| key < low).unwrap_or(false);
+                    if is_too_low || view.is_out_of_range(key) {
+                        error!("index node {} has keys out of order", pid);
+                        return Err(corrupted());
+                    }
+                    last_key = Some(key.clone());
+                }
+                visited.push(pid);
+                children.extend_from_slice(&view.children);
+                match (view.right, view.high_key) {
+                    (Some(right), Some(high_key)) => {
+                        if low_key.as_ref().map(|low| high_key <= *low).unwrap_or(false) {
+                            error!("index node {} has a high key out of order", pid);
+                            return Err(corrupted());
+                        }
+                        low_key = Some(high_key);
+                        pid = right;
+                    },
+                    (None, None) => break,
+                    _ => {
+                        error!("index node {} has a right link without high key or the opposite", pid);
+                        return Err(corrupted());
+                    },
+                }
+            }
+            if let Some(missing) = expected.iter().find(|expected_pid| !visited.contains(expected_pid)) {
+                error!("index node {} is not linked in its level", missing);
+                return Err(RecordsManagerError::Corrupted(self.file.clone(), *missing));
+            }
+            if children.is_empty() {
+                return Ok(());
+            }
+            expected = children;
+        }
+    }
+
+    /// Nothing is logged, the nodes are written to the LSS by `mark_clean`.
+    pub fn log_sync(&mut self, _wal: &mut WriteAheadLog) {
+    }
+
+    /// Called once the write ahead log applied the other stores, the log is only emptied once the nodes are written.
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.sync()
+    }
+}
+
+impl Drop for BwTreeIndex {
+    fn drop(&mut self) {
+        self.discard();
+    }
+}
+
+#[cfg(test)]
+mod test_bw_tree {
+    use super::*;
+    use super::super::super::super::test_utils::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_insert_and_splits() {
+        let file = build_file_path_and_rm_old("bw_tree", "test_insert_and_splits.db").unwrap();
+        {
+            let mut index = BwTreeIndex::new(&file);
+            for i in 0..2000 {
+                index.insert(&format!("key # {}", i % 500), i).unwrap();
+            }
+            index.insert("Person", 1).unwrap();
+            assert!(index.insert(&"k".repeat(MAX_KEY_SIZE + 1), 1).is_none());
+            index.sync().unwrap();
+            index.insert("Person", 2).unwrap();
+            index.discard();
+        }
+        let index = BwTreeIndex::new(&file);
+        for k in 0..500 {
+            let ptrs = index.search(&format!("key # {}", k)).unwrap();
+            assert_eq!(ptrs, vec![k, k + 500, k + 1000, k + 1500]);
+        }
+        assert_eq!(index.search("Person").unwrap(), vec![1]);
+        assert!(index.search("City").is_none());
+    }
+
+    #[test]
+    fn test_range_and_delete() {
+        let file = build_file_path_and_rm_old("bw_tree", "test_range_and_delete.db").unwrap();
+        let index = BwTreeIndex::new(&file);
+        for i in 0..1000u64 {
+            index.insert(&format!("{:04}", i), i).unwrap();
+        }
+        let range = index.range(Bound::Excluded("0100"), Bound::Included("0200")).unwrap();
+        assert_eq!(range.len(), 100);
+        assert_eq!(range[0], (String::from("0101"), 101));
+        assert_eq!(range[99], (String::from("0200"), 200));
+        index.delete(150).unwrap();
+        index.remove("0151", 151).unwrap();
+        assert!(index.search("0150").is_none());
+        assert_eq!(index.range(Bound::Included("0100"), Bound::Excluded("0200")).unwrap().len(), 98);
+        assert_eq!(index.range(Bound::Unbounded, Bound::Unbounded).unwrap().len(), 998);
+    }
+
+    #[test]
+    fn test_full_mapping_table() {
+        let file = build_file_path_and_rm_old("bw_tree", "test_full_mapping_table.db").unwrap();
+        let inserted = {
+            let mut index = BwTreeIndex::with_capacity(&file, 4);
+            let inserted = (0..10000u64).take_while(|i| index.insert(&format!("key # {:05}", i), *i).is_some()).count() as u64;
+            assert!(inserted < 10000);
+            assert!(index.insert("key # 99999", 99999).is_none());
+            index.sync().unwrap();
+            inserted
+        };
+        let mut index = BwTreeIndex::with_capacity(&file, 4);
+        assert_eq!(index.range(Bound::Unbounded, Bound::Unbounded).unwrap().len() as u64, inserted);
+        index.discard();
+    }
+
+    #[test]
+    fn test_root_split_without_room() {
+        let file = build_file_path_and_rm_old("bw_tree", "test_root_split_without_room.db").unwrap();
+        let index = BwTreeIndex::with_capacity(&file, 2);
+        assert!((0..1000u64).take_while(|i| index.insert(&format!("key # {:04}", i), *i).is_some()).count() < 1000);
+        assert!(index.mapping_table[1].load(Ordering::Acquire).is_null());
+        assert!(!index.dirty[1].load(Ordering::Acquire));
+        index.verify().unwrap();
+    }
+
+    #[test]
+    fn test_verify() {
+        let file = build_file_path_and_rm_old("bw_tree", "test_verify.db").unwrap();
+        let index = BwTreeIndex::new(&file);
+        for i in 0..2000u64 {
+            index.insert(&format!("{:04}", i), i).unwrap();
+        }
+        index.verify().unwrap();
+        let (_, pid, head, mut view) = index.find(&(b"1000".to_vec(), 1000)).unwrap();
+        view.keys.reverse();
+        assert!(index.install(pid, head, BwNode::Base(view).into_ptr()));
+        index.retire(head);
+        assert!(matches!(index.verify(), Err(RecordsManagerError::Corrupted(_, corrupted)) if corrupted == pid));
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let file = build_file_path_and_rm_old("bw_tree", "test_concurrent_writers.db").unwrap();
+        let index = Arc::new(BwTreeIndex::new(&file));
+        let writers = (0..4u64).map(|t| {
+            let index = index.clone();
+            thread::spawn(move || {
+                for i in 0..500u64 {
+                    index.insert(&format!("key # {}", i), t * 1000 + i).unwrap();
+                    index.search(&format!("key # {}", i)).unwrap();
+                }
+            })
+        }).collect::<Vec<_>>();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        for i in 0..500u64 {
+            let ptrs = index.search(&format!("key # {}", i)).unwrap();
+            assert_eq!(ptrs, vec![i, 1000 + i, 2000 + i, 3000 + i]);
+        }
+    }
+}
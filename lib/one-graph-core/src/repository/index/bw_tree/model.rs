@@ -0,0 +1,244 @@
+use cache::buf_config::PAGE_SIZE;
+use std::ptr;
+
+pub type PageId = u64;
+/// Index keys are ordered by key bytes then data pointer so duplicated keys spread over several leaves.
+pub type EntryKey = (Vec<u8>, u64);
+
+pub const ROOT_PAGE_ID: PageId = 0;
+pub const MAX_KEY_SIZE: usize = 1024;
+
+const INITIALIZED_FLAG: u8 = 0b0000_0001;
+const LEAF_FLAG: u8 = 0b0000_0010;
+const HIGH_KEY_FLAG: u8 = 0b0000_0100;
+const NODE_HEADER_SIZE: usize = 1 + 8 + 2;
+const KEY_LEN_SIZE: usize = 2;
+const PTR_SIZE: usize = 8;
+
+pub fn min_key() -> EntryKey {
+    (Vec::new(), 0)
+}
+
+pub fn entry_size(key: &EntryKey, is_leaf: bool) -> usize {
+    KEY_LEN_SIZE + key.0.len() + PTR_SIZE + if is_leaf { 0 } else { PTR_SIZE }
+}
+
+/// Consolidated node: a leaf holds index entries, an inner node holds the lowest key of each child.
+/// Keys greater or equal to the high key moved to the right sibling.
+#[derive(Clone)]
+pub struct BwBase {
+    pub is_leaf: bool,
+    pub keys: Vec<EntryKey>,
+    pub children: Vec<PageId>,
+    pub high_key: Option<EntryKey>,
+    pub right: Option<PageId>,
+}
+
+impl BwBase {
+    pub fn empty_leaf() -> Self {
+        BwBase{is_leaf: true, keys: Vec::new(), children: Vec::new(), high_key: None, right: None}
+    }
+
+    pub fn size(&self) -> usize {
+        let keys_size: usize = self.keys.iter().map(|key| entry_size(key, self.is_leaf)).sum();
+        NODE_HEADER_SIZE + keys_size + self.high_key.as_ref().map(|key| entry_size(key, true)).unwrap_or(0)
+    }
+
+    pub fn is_out_of_range(&self, key: &EntryKey) -> bool {
+        self.high_key.as_ref().map(|high_key| key >= high_key).unwrap_or(false)
+    }
+
+    pub fn find_child(&self, key: &EntryKey) -> Option<PageId> {
+        let index = match self.keys.binary_search(key) {
+            Ok(found) => found,
+            Err(not_found) => not_found.saturating_sub(1),
+        };
+        self.children.get(index).copied()
+    }
+
+    fn insert(&mut self, key: &EntryKey, child: Option<PageId>) {
+        if let Err(index) = self.keys.binary_search(key) {
+            self.keys.insert(index, key.clone());
+            if let Some(child_id) = child {
+                self.children.insert(index, child_id);
+            }
+        }
+    }
+
+    fn delete(&mut self, key: &EntryKey) {
+        if let Ok(index) = self.keys.binary_search(key) {
+            self.keys.remove(index);
+            if !self.is_leaf {
+                self.children.remove(index);
+            }
+        }
+    }
+
+    fn split(&mut self, separator: &EntryKey, right: PageId) {
+        let index = match self.keys.binary_search(separator) {
+            Ok(found) => found,
+            Err(not_found) => not_found,
+        };
+        self.keys.truncate(index);
+        if !self.is_leaf {
+            self.children.truncate(index);
+        }
+        self.high_key = Some(separator.clone());
+        self.right = Some(right);
+    }
+
+    pub fn to_page(&self) -> [u8; PAGE_SIZE] {
+        let mut page = [0u8; PAGE_SIZE];
+        let mut flags = INITIALIZED_FLAG;
+        if self.is_leaf {
+            flags |= LEAF_FLAG;
+        }
+        if self.high_key.is_some() {
+            flags |= HIGH_KEY_FLAG;
+        }
+        page[0] = flags;
+        page[1..9].copy_from_slice(&self.right.unwrap_or(0).to_be_bytes());
+        page[9..11].copy_from_slice(&(self.keys.len() as u16).to_be_bytes());
+        let mut offset = NODE_HEADER_SIZE;
+        if let Some(high_key) = &self.high_key {
+            offset = write_key(&mut page, offset, high_key);
+        }
+        for (index, key) in self.keys.iter().enumerate() {
+            offset = write_key(&mut page, offset, key);
+            if !self.is_leaf {
+                page[offset..offset + PTR_SIZE].copy_from_slice(&self.children[index].to_be_bytes());
+                offset += PTR_SIZE;
+            }
+        }
+        page
+    }
+
+    pub fn from_page(page: &[u8; PAGE_SIZE]) -> Self {
+        if page[0] & INITIALIZED_FLAG == 0 {
+            return BwBase::empty_leaf();
+        }
+        let is_leaf = page[0] & LEAF_FLAG != 0;
+        let right = read_u64(&page[1..9]);
+        let mut len_buf = [0u8; 2];
+        len_buf.copy_from_slice(&page[9..11]);
+        let nb_keys = u16::from_be_bytes(len_buf) as usize;
+        let mut offset = NODE_HEADER_SIZE;
+        let mut high_key = None;
+        if page[0] & HIGH_KEY_FLAG != 0 {
+            let (key, next) = read_key(page, offset);
+            high_key = Some(key);
+            offset = next;
+        }
+        let mut keys = Vec::with_capacity(nb_keys);
+        let mut children = Vec::new();
+        for _ in 0..nb_keys {
+            let (key, next) = read_key(page, offset);
+            keys.push(key);
+            offset = next;
+            if !is_leaf {
+                children.push(read_u64(&page[offset..offset + PTR_SIZE]));
+                offset += PTR_SIZE;
+            }
+        }
+        BwBase{is_leaf, keys, children, high_key, right: if right == 0 { None } else { Some(right) }}
+    }
+}
+
+fn read_u64(buf: &[u8]) -> u64 {
+    let mut bytes = [0u8; PTR_SIZE];
+    bytes.copy_from_slice(&buf[..PTR_SIZE]);
+    u64::from_be_bytes(bytes)
+}
+
+fn write_key(page: &mut [u8], offset: usize, key: &EntryKey) -> usize {
+    let key_end = offset + KEY_LEN_SIZE + key.0.len();
+    page[offset..offset + KEY_LEN_SIZE].copy_from_slice(&(key.0.len() as u16).to_be_bytes());
+    page[offset + KEY_LEN_SIZE..key_end].copy_from_slice(&key.0);
+    page[key_end..key_end + PTR_SIZE].copy_from_slice(&key.1.to_be_bytes());
+    key_end + PTR_SIZE
+}
+
+fn read_key(page: &[u8], offset: usize) -> (EntryKey, usize) {
+    let mut len_buf = [0u8; KEY_LEN_SIZE];
+    len_buf.copy_from_slice(&page[offset..offset + KEY_LEN_SIZE]);
+    let key_end = offset + KEY_LEN_SIZE + u16::from_be_bytes(len_buf) as usize;
+    let key = page[offset + KEY_LEN_SIZE..key_end].to_vec();
+    ((key, read_u64(&page[key_end..key_end + PTR_SIZE])), key_end + PTR_SIZE)
+}
+
+/// Mapping table state of a node: a chain of deltas ending on a base.
+/// An insert delta with a child is an index entry posted to an inner node after a split.
+pub enum BwNode {
+    Base(BwBase),
+    Insert { key: EntryKey, child: Option<PageId>, next: *mut BwNode, chain_len: usize },
+    Delete { key: EntryKey, next: *mut BwNode, chain_len: usize },
+    Split { separator: EntryKey, right: PageId, next: *mut BwNode, chain_len: usize },
+}
+
+impl BwNode {
+    pub fn into_ptr(self) -> *mut BwNode {
+        Box::into_raw(Box::new(self))
+    }
+
+    fn get_next(&self) -> *mut BwNode {
+        match self {
+            BwNode::Base(_) => ptr::null_mut(),
+            BwNode::Insert{next, ..} | BwNode::Delete{next, ..} | BwNode::Split{next, ..} => *next,
+        }
+    }
+}
+
+/// Number of deltas on top of the chain.
+///
+/// # Safety
+/// `node` must be null or point to a live node.
+pub unsafe fn chain_len(node: *mut BwNode) -> usize {
+    if node.is_null() {
+        return 0;
+    }
+    match *node {
+        BwNode::Base(_) => 0,
+        BwNode::Insert{chain_len, ..} | BwNode::Delete{chain_len, ..} | BwNode::Split{chain_len, ..} => chain_len,
+    }
+}
+
+/// Rebuilds the consolidated node, deltas are applied oldest first.
+///
+/// # Safety
+/// `head` must point to a live chain ending on a base.
+pub unsafe fn materialize(head: *mut BwNode) -> BwBase {
+    let mut deltas = Vec::new();
+    let mut node = head;
+    let mut base = loop {
+        if node.is_null() {
+            break BwBase::empty_leaf();
+        }
+        match &*node {
+            BwNode::Base(base) => break base.clone(),
+            delta => {
+                deltas.push(delta);
+                node = delta.get_next();
+            },
+        }
+    };
+    for delta in deltas.iter().rev() {
+        match delta {
+            BwNode::Insert{key, child, ..} => base.insert(key, *child),
+            BwNode::Delete{key, ..} => base.delete(key),
+            BwNode::Split{separator, right, ..} => base.split(separator, *right),
+            BwNode::Base(_) => {},
+        }
+    }
+    base
+}
+
+/// Frees a node and every node below it.
+///
+/// # Safety
+/// The caller must be the only owner of the chain and no reader may still walk it.
+pub unsafe fn free_chain(mut node: *mut BwNode) {
+    while !node.is_null() {
+        let boxed = Box::from_raw(node);
+        node = boxed.get_next();
+    }
+}
@@ -0,0 +1,164 @@
+use super::super::super::model::PropertyValue;
+
+const BOOL_TAG: u8 = 0x10;
+const INTEGER_TAG: u8 = 0x20;
+const FLOAT_TAG: u8 = 0x30;
+const STRING_TAG: u8 = 0x40;
+const TUPLE_TAG: u8 = 0x50;
+
+const STRING_ESCAPE: u8 = 0xFF;
+const SIGN_BIT: u64 = 1 << 63;
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Typed index key, values of a same type sort like their natural order once encoded.
+/// Different types never mix: booleans < integers < floats < strings < tuples.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexKey {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Tuple(Vec<IndexKey>),
+}
+
+fn push_string_bytes(buf: &mut Vec<u8>, value: &str) {
+    for &byte in value.as_bytes() {
+        buf.push(byte);
+        if byte == 0 {
+            buf.push(STRING_ESCAPE);
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        hex.push(HEX_DIGITS[(byte & 0x0F) as usize] as char);
+    }
+    hex
+}
+
+impl IndexKey {
+    pub fn from_value(value: &PropertyValue) -> Self {
+        match value {
+            PropertyValue::PBool(bval) => IndexKey::Bool(*bval),
+            PropertyValue::PInteger(ival) => IndexKey::Integer(*ival),
+            PropertyValue::PFloat(fval) => IndexKey::Float(*fval),
+            PropertyValue::PString(sval) => IndexKey::String(sval.clone()),
+        }
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            IndexKey::Bool(_) => BOOL_TAG,
+            IndexKey::Integer(_) => INTEGER_TAG,
+            IndexKey::Float(_) => FLOAT_TAG,
+            IndexKey::String(_) => STRING_TAG,
+            IndexKey::Tuple(_) => TUPLE_TAG,
+        }
+    }
+
+    fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.push(self.tag());
+        match self {
+            IndexKey::Bool(value) => buf.push(*value as u8),
+            IndexKey::Integer(value) => buf.extend_from_slice(&((*value as u64) ^ SIGN_BIT).to_be_bytes()),
+            IndexKey::Float(value) => {
+                let bits = value.to_bits();
+                let ordered = if bits & SIGN_BIT != 0 { !bits } else { bits ^ SIGN_BIT };
+                buf.extend_from_slice(&ordered.to_be_bytes());
+            },
+            IndexKey::String(value) => {
+                //a zero byte is escaped so the terminator sorts a string before any longer one
+                push_string_bytes(buf, value);
+                buf.extend_from_slice(&[0, 0]);
+            },
+            IndexKey::Tuple(values) => {
+                for value in values {
+                    value.write_bytes(buf);
+                }
+                buf.push(0);
+            },
+        }
+    }
+
+    /// Order-preserving bytes of the key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_bytes(&mut buf);
+        buf
+    }
+
+    /// Index key string, hex digits sort like the nibbles they stand for
+    /// so encoded keys keep the byte order and stay NUL free in the tree cells.
+    pub fn encode(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    /// Bounds enclosing every key of this key type, a bound is never a key itself
+    /// so they can be used with inclusive or exclusive ranges.
+    pub fn type_bounds(&self) -> (String, String) {
+        (to_hex(&[self.tag()]), to_hex(&[self.tag() + 1]))
+    }
+
+    /// Bounds enclosing every string key starting with `prefix`.
+    pub fn prefix_bounds(prefix: &str) -> (String, String) {
+        let mut buf = vec![STRING_TAG];
+        push_string_bytes(&mut buf, prefix);
+        let lower = to_hex(&buf);
+        //'g' sorts after every hex digit
+        let upper = format!("{}g", lower);
+        (lower, upper)
+    }
+}
+
+#[cfg(test)]
+mod test_index_key {
+    use super::*;
+    #[test]
+    fn test_order_preserving() {
+        let integers = [i64::MIN, -300, -1, 0, 1, 30, 256, i64::MAX];
+        for pair in integers.windows(2) {
+            assert!(IndexKey::Integer(pair[0]).encode() < IndexKey::Integer(pair[1]).encode());
+        }
+        let floats = [f64::NEG_INFINITY, -2.5, -0.1, 0.0, 0.1, 2.5, 1e300, f64::INFINITY];
+        for pair in floats.windows(2) {
+            assert!(IndexKey::Float(pair[0]).encode() < IndexKey::Float(pair[1]).encode());
+        }
+        let strings = ["", "a", "a\0", "a\0b", "ab", "b", "ba"];
+        for pair in strings.windows(2) {
+            assert!(IndexKey::String(pair[0].to_string()).encode() < IndexKey::String(pair[1].to_string()).encode());
+        }
+        assert!(IndexKey::Bool(false).encode() < IndexKey::Bool(true).encode());
+
+        let tuples = [
+            IndexKey::Tuple(vec![IndexKey::String("a".to_string()), IndexKey::Integer(2)]),
+            IndexKey::Tuple(vec![IndexKey::String("a".to_string()), IndexKey::Integer(10)]),
+            IndexKey::Tuple(vec![IndexKey::String("ab".to_string()), IndexKey::Integer(-5)]),
+        ];
+        for pair in tuples.windows(2) {
+            assert!(pair[0].encode() < pair[1].encode());
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        let (lower, upper) = IndexKey::Integer(0).type_bounds();
+        for value in &[i64::MIN, 0, i64::MAX] {
+            let key = IndexKey::Integer(*value).encode();
+            assert!(lower < key && key < upper);
+        }
+        assert!(IndexKey::Float(-1.0).encode() > upper);
+
+        let (lower, upper) = IndexKey::prefix_bounds("Jo");
+        for name in &["Jo", "John", "Joe"] {
+            let key = IndexKey::String(name.to_string()).encode();
+            assert!(lower < key && key < upper);
+        }
+        for name in &["J", "Jim", "Jp", "Karl"] {
+            let key = IndexKey::String(name.to_string()).encode();
+            assert!(key < lower || key > upper);
+        }
+    }
+}
@@ -1,3 +1,128 @@
 mod model;
 mod store;
-pub mod b_tree;
\ No newline at end of file
+pub mod b_tree;
+pub mod bw_tree;
+pub mod key;
+
+use self::b_tree::*;
+use self::bw_tree::*;
+use super::records::RecordsManagerResult;
+use super::wal::*;
+use std::ops::Bound;
+use super::super::model::init::IndexKind;
+
+/// Operations shared by the index implementations, keys map to lists of data pointers.
+pub trait Index {
+    fn insert(&mut self, key: &str, data_ptr: DataPtr) -> Option<()>;
+    fn search(&mut self, key: &str) -> Option<Vec<DataPtr>>;
+    /// Keys between `lower` and `upper` with each of their data pointers in key order, `inclusive` applies to both bounds.
+    fn range(&mut self, lower: Option<&str>, upper: Option<&str>, inclusive: bool) -> Option<Vec<(String, DataPtr)>>;
+    /// Removes a data pointer from a single key.
+    fn remove(&mut self, key: &str, data_ptr: DataPtr) -> Option<()>;
+    fn delete(&mut self, data_ptr: DataPtr) -> Option<()>;
+    fn sync(&mut self) -> RecordsManagerResult<()>;
+    /// Checks the integrity of the pages backing the index.
+    fn verify(&mut self) -> RecordsManagerResult<()>;
+    fn discard(&mut self);
+    fn log_sync(&mut self, wal: &mut WriteAheadLog);
+    fn mark_clean(&mut self) -> RecordsManagerResult<()>;
+    fn set_cache_capacity(&mut self, capacity: usize);
+    /// False when the pages are not written in the write ahead log, the index is then rebuilt after a crash.
+    fn is_logged(&self) -> bool;
+}
+
+fn to_bound(key: Option<&str>, inclusive: bool) -> Bound<&str> {
+    match key {
+        Some(value) if inclusive => Bound::Included(value),
+        Some(value) => Bound::Excluded(value),
+        None => Bound::Unbounded,
+    }
+}
+
+impl Index for BTreeIndex {
+    fn insert(&mut self, key: &str, data_ptr: DataPtr) -> Option<()> {
+        BTreeIndex::insert(self, key, data_ptr)
+    }
+    fn search(&mut self, key: &str) -> Option<Vec<DataPtr>> {
+        BTreeIndex::search(self, key)
+    }
+    fn range(&mut self, lower: Option<&str>, upper: Option<&str>, inclusive: bool) -> Option<Vec<(String, DataPtr)>> {
+        let entries = BTreeIndex::range(self, lower, upper, inclusive)?;
+        Some(entries.flat_map(|(key, ptrs)| ptrs.into_iter().map(move |ptr| (key.clone(), ptr))).collect())
+    }
+    fn remove(&mut self, key: &str, data_ptr: DataPtr) -> Option<()> {
+        BTreeIndex::remove(self, key, data_ptr)
+    }
+    fn delete(&mut self, data_ptr: DataPtr) -> Option<()> {
+        BTreeIndex::delete(self, data_ptr)
+    }
+    fn sync(&mut self) -> RecordsManagerResult<()> {
+        BTreeIndex::sync(self)
+    }
+    fn verify(&mut self) -> RecordsManagerResult<()> {
+        BTreeIndex::verify(self)
+    }
+    fn discard(&mut self) {
+        BTreeIndex::discard(self)
+    }
+    fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        BTreeIndex::log_sync(self, wal)
+    }
+    fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        BTreeIndex::mark_clean(self)
+    }
+    fn set_cache_capacity(&mut self, capacity: usize) {
+        BTreeIndex::set_cache_capacity(self, capacity)
+    }
+    fn is_logged(&self) -> bool {
+        true
+    }
+}
+
+impl Index for BwTreeIndex {
+    fn insert(&mut self, key: &str, data_ptr: DataPtr) -> Option<()> {
+        BwTreeIndex::insert(self, key, data_ptr)
+    }
+    fn search(&mut self, key: &str) -> Option<Vec<DataPtr>> {
+        BwTreeIndex::search(self, key)
+    }
+    fn range(&mut self, lower: Option<&str>, upper: Option<&str>, inclusive: bool) -> Option<Vec<(String, DataPtr)>> {
+        BwTreeIndex::range(self, to_bound(lower, inclusive), to_bound(upper, inclusive))
+    }
+    fn remove(&mut self, key: &str, data_ptr: DataPtr) -> Option<()> {
+        BwTreeIndex::remove(self, key, data_ptr)
+    }
+    fn delete(&mut self, data_ptr: DataPtr) -> Option<()> {
+        BwTreeIndex::delete(self, data_ptr)
+    }
+    fn sync(&mut self) -> RecordsManagerResult<()> {
+        BwTreeIndex::sync(self)
+    }
+    fn verify(&mut self) -> RecordsManagerResult<()> {
+        BwTreeIndex::verify(self)
+    }
+    fn discard(&mut self) {
+        BwTreeIndex::discard(self)
+    }
+    fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        BwTreeIndex::log_sync(self, wal)
+    }
+    fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        BwTreeIndex::mark_clean(self)
+    }
+    /// Every node of the Bw-Tree stays in its mapping table.
+    fn set_cache_capacity(&mut self, _capacity: usize) {
+    }
+    /// The nodes are written in the LSS once the write ahead log committed.
+    fn is_logged(&self) -> bool {
+        false
+    }
+}
+
+/// The Bw-Tree pages have the size of the LSS pages whatever `page_size` is.
+pub fn open_index(kind: IndexKind, file: &str, page_size: usize) -> Box<dyn Index> {
+    match kind {
+        IndexKind::BTree => Box::new(BTreeIndex::new(file, page_size)),
+        IndexKind::BwTree => Box::new(BwTreeIndex::new(file)),
+    }
+}
@@ -3,10 +3,13 @@ mod records;
 use self::records::*;
 use super::super::super::buf_config::*;
 use super::model::*;
+use super::super::wal::*;
 use super::super::records::*;
 
 type CellPos = (NodeId, CellId);
 
+const NB_DATA_PTRS_PER_CELL: usize = (KEY_SIZE - 2) / NODE_PTR_SIZE;
+
 pub struct BTreeNodeStore {
     records_manager: RecordsManager,
 }
@@ -29,14 +32,37 @@ fn append_list_ptr(ptrs: &mut Vec<u64>, buf: &[u8]) {
     }
 }
 
+fn build_list_ptr_cell_records(data_ptrs: &[NodeId]) -> Vec<CellRecord> {
+    let mut cell_records = Vec::new();
+    for chunk in data_ptrs.chunks(NB_DATA_PTRS_PER_CELL) {
+        let mut cell_record = CellRecord::new();
+        cell_record.set_is_active();
+        cell_record.set_is_list_ptr();
+        cell_record.key[..2].copy_from_slice(&(chunk.len() as u16).to_be_bytes());
+        let mut data_ptr_offset = 2;
+        for data_ptr in chunk {
+            cell_record.key[data_ptr_offset..data_ptr_offset+NODE_PTR_SIZE].copy_from_slice(&data_ptr.to_be_bytes());
+            data_ptr_offset += NODE_PTR_SIZE;
+        }
+        cell_records.push(cell_record);
+    }
+    if cell_records.is_empty() {
+        let mut cell_record = CellRecord::new();
+        cell_record.set_is_active();
+        cell_record.set_is_list_ptr();
+        cell_records.push(cell_record);
+    }
+    cell_records
+}
+
 enum CellLoadRes {
     InteriorCell(NodeId),
     LeafCell(Vec<NodeId>),
 }
 
 impl BTreeNodeStore {
-    pub fn new(file: &str) -> Self {
-        BTreeNodeStore{records_manager: RecordsManager::new(file, BTREE_NODE_RECORD_SIZE, BTREE_NB_RECORDS_PER_PAGE, BTREE_NB_PAGES_PER_RECORD)}
+    pub fn new(file: &str, page_size: usize) -> Self {
+        BTreeNodeStore{records_manager: RecordsManager::new(file, BTREE_NODE_RECORD_SIZE, page_size)}
     }
 
     fn retrieve_overflow_cells(&mut self, cell_record: &CellRecord, vkey: &mut Vec<u8>) -> Option<CellLoadRes> {
@@ -113,7 +139,7 @@ impl BTreeNodeStore {
             }
             if curr_node_id != cell.node_ptr {
                 self.save_node_record(curr_node_id, &curr_node_record)?;
-                curr_node_record = self.load_node_record(curr_node_id)?;
+                curr_node_record = self.load_node_record(cell.node_ptr)?;
             }
             curr_cell_id = cell.overflow_cell_ptr;
             curr_node_id = cell.node_ptr;
@@ -223,33 +249,8 @@ impl BTreeNodeStore {
             cell_records.push(cell_record);
         }
         
-        if cell.get_data_ptrs_ref().len() > 0 {
-            let mut data_ptr_offset = 2;
-            let mut cell_record = CellRecord::new();
-            cell_record.set_is_active();
-            cell_record.set_is_list_ptr();
-            let mut data_ptr_count: u16 = 0;
-            let mut whole_data_ptr_count = 0;
-            for data_ptr in cell.get_data_ptrs_ref() {
-                cell_record.key[data_ptr_offset..data_ptr_offset+8].copy_from_slice(&data_ptr.to_be_bytes());
-                data_ptr_offset += NODE_PTR_SIZE;
-                data_ptr_count += 1;
-                whole_data_ptr_count += 1;
-                if data_ptr_offset > KEY_SIZE {
-                    cell_record.key[..2].copy_from_slice(&data_ptr_count.to_be_bytes());
-                    cell_records.push(cell_record);
-                    cell_record = CellRecord::new();
-                    cell_record.set_is_active();
-                    cell_record.set_is_list_ptr();
-                    data_ptr_offset = 2;
-                    data_ptr_count = 0;
-                } else if whole_data_ptr_count == cell.get_data_ptrs_ref().len() {
-                    cell_record.key[..2].copy_from_slice(&data_ptr_count.to_be_bytes());
-                    cell_records.push(cell_record);
-                    break;
-                }
-            }
-            
+        if !cell.get_data_ptrs_ref().is_empty() {
+            cell_records.extend(build_list_ptr_cell_records(cell.get_data_ptrs_ref()));
         }
 
         let nb_records = cell_records.len();
@@ -329,21 +330,21 @@ impl BTreeNodeStore {
         if has_overflow {
             let mut curr_node = self.load_node_record(curr_node_id)?;
             while has_overflow {
-                let overflow_cell = &curr_node.cells[curr_overflow_cell_id as usize];
+                let overflow_cell = curr_node.cells[curr_overflow_cell_id as usize];
                 has_overflow = overflow_cell.has_overflow();
-                curr_node_id = overflow_cell.node_ptr;
                 curr_overflow_cell_id = overflow_cell.overflow_cell_ptr;
-                cells.push(*overflow_cell);
-                if curr_node_id != overflow_cell.node_ptr {
-                    curr_node = self.load_node_record(curr_node_id)?;
+                cells.push(overflow_cell);
+                if has_overflow && curr_node_id != overflow_cell.node_ptr {
+                    curr_node = self.load_node_record(overflow_cell.node_ptr)?;
                 }
+                curr_node_id = overflow_cell.node_ptr;
             }
         }
         
         Some(cells)
     }
 
-    fn update_cell_data_ptrs(&mut self, root_cell_record: &CellRecord, data_ptrs: &Vec<NodeId>) -> Option<()> {
+    fn update_cell_data_ptrs(&mut self, root_cell_record: &CellRecord, data_ptrs: &[NodeId]) -> Option<()> {
         let overflow_cell_records = self.load_overflow_cell_records(root_cell_record)?;
         let mut list_ptr_cells = Vec::new();
         let mut prev_cell_record = *root_cell_record;
@@ -354,59 +355,38 @@ impl BTreeNodeStore {
                 prev_cell_record = *cell_record;
             }
         }
-        
-        list_ptr_cells.reverse();
 
-        let mut cells_to_create = Vec::new();
-        let mut cells_to_update = Vec::new();
-        let mut data_ptr_offset = 2;
-        let mut data_ptr_count: u16 = 0;
-        let mut whole_data_ptr_count = 0;
-        let mut curr_list_ptr_cell = list_ptr_cells.pop()?;
-        let mut to_create= false;
-        for data_ptr in data_ptrs {
-            curr_list_ptr_cell.key[data_ptr_offset..data_ptr_offset+NODE_PTR_SIZE].copy_from_slice(&data_ptr.to_be_bytes());
-            data_ptr_offset += NODE_PTR_SIZE;
-            data_ptr_count += 1;
-            whole_data_ptr_count += 1;
-            if data_ptr_offset > KEY_SIZE {
-                curr_list_ptr_cell.key.copy_from_slice(&data_ptr_count.to_be_bytes());
-                if to_create {
-                    cells_to_create.push(curr_list_ptr_cell);
-                } else {
-                    cells_to_update.push(curr_list_ptr_cell);
-                }
-                data_ptr_offset = 2;
-                data_ptr_count = 0;
-                curr_list_ptr_cell = {
-                    if let Some(cell) = list_ptr_cells.pop() {
-                        to_create = false;
-                        cell
-                    } else {
-                        to_create = true;
-                        let mut new_cell = CellRecord::new();
-                        new_cell.set_is_active();
-                        new_cell.set_is_list_ptr();
-                        new_cell
-                    }
-                };
-            } else if whole_data_ptr_count == data_ptrs.len() {
-                curr_list_ptr_cell.key[..2].copy_from_slice(&data_ptr_count.to_be_bytes());
-                if to_create {
-                    cells_to_create.push(curr_list_ptr_cell);
-                } else {
-                    cells_to_update.push(curr_list_ptr_cell);
-                }
-                break;
-            }
+        let new_cells = build_list_ptr_cell_records(data_ptrs);
+        let nb_updated_cells = std::cmp::min(new_cells.len(), list_ptr_cells.len());
+        if nb_updated_cells == 0 {
+            return None;
         }
 
-        cells_to_update.reverse();
-        let mut last_updated_cell_pos = self.update_overflow_cells(&cells_to_update, &prev_cell_record)?;
-        if cells_to_create.len() > 0 {
+        //overwrite old cells in place, keeping their links
+        let mut cells_to_update = Vec::new();
+        for index in 0..nb_updated_cells {
+            let old_cell = &list_ptr_cells[index];
+            let mut cell = new_cells[index];
+            cell.header = old_cell.header;
+            cell.node_ptr = old_cell.node_ptr;
+            cell.overflow_cell_ptr = old_cell.overflow_cell_ptr;
+            cells_to_update.push(cell);
+        }
+        let last_old_cell = list_ptr_cells[nb_updated_cells - 1];
+        if new_cells.len() < list_ptr_cells.len() {
+            cells_to_update.last_mut()?.set_no_overflow();
+        }
+
+        let last_updated_cell_pos = self.update_overflow_cells(&cells_to_update, &prev_cell_record)?;
+        if new_cells.len() > nb_updated_cells {
+            let mut cells_to_create = new_cells[nb_updated_cells..].to_vec();
+            cells_to_create.reverse();
+            for cell in &mut cells_to_create[1..] {
+                cell.set_has_overflow();
+            }
             let created_first_cell_pos = self.create_overflow_cells(&mut cells_to_create)?;
-            //link last updated cell to created cells
-            let last_updated_node =  &mut last_updated_cell_pos.2;
+            //link last updated cell to created cells, reloading its node as created cells may share it
+            let mut last_updated_node = self.load_node_record(last_updated_cell_pos.0)?;
             let last_updated_cell = &mut last_updated_node.cells[last_updated_cell_pos.1 as usize];
             last_updated_cell.set_has_overflow();
             last_updated_cell.node_ptr = created_first_cell_pos.0;
@@ -415,14 +395,14 @@ impl BTreeNodeStore {
         }
 
         //disable unused cells
-        if list_ptr_cells.len() > 0 {
-            self.delete_cell_records(&list_ptr_cells, last_updated_cell_pos.0, last_updated_cell_pos.1)?;
+        if list_ptr_cells.len() > nb_updated_cells {
+            self.delete_cell_records(&list_ptr_cells[nb_updated_cells..], last_old_cell.node_ptr, last_old_cell.overflow_cell_ptr)?;
         }
         
         Some(())
     }
 
-    fn delete_cell_records(&mut self, cell_records_to_delete: &Vec<CellRecord>, first_cell_node_id: NodeId, first_cell_id: CellId) -> Option<()> {
+    fn delete_cell_records(&mut self, cell_records_to_delete: &[CellRecord], first_cell_node_id: NodeId, first_cell_id: CellId) -> Option<()> {
         let mut curr_node_id = first_cell_node_id;
         let mut curr_cell_id = first_cell_id;
         for cell in cell_records_to_delete {
@@ -460,6 +440,10 @@ impl BTreeNodeStore {
         } else {
             self.set_root_node_ptr(id);
         }
+        if let Some(next_id) = node.get_node_ptr() {
+            main_node_record.set_has_next_node();
+            main_node_record.ptr = next_id;
+        }
         let mut cells_context = Vec::new();
         for index in 0..main_node_record.cells.len() {
             if main_node_record.cells[index].is_active() {
@@ -557,8 +541,28 @@ impl BTreeNodeStore {
         self.records_manager.is_empty()
     }
 
-    pub fn sync(&mut self) {
-        self.records_manager.sync();
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.records_manager.set_cache_capacity(capacity);
+    }
+
+    pub fn discard(&mut self) {
+        self.records_manager.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.records_manager.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.mark_clean()
     }
 }
 
@@ -591,7 +595,7 @@ mod test_btree_node_store {
         let file = build_file_path_and_rm_old("b_tree_nodes", "test_create.db").unwrap();
         let long_key = "blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6
         blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6blabla6";
-        let mut store = BTreeNodeStore::new(&file);
+        let mut store = BTreeNodeStore::new(&file, DEFAULT_PAGE_SIZE);
         let mut cells = Vec::new();
         cells.push(Cell::new_ptr("blabla1", Some(1)));
         cells.push(Cell::new_ptr("blabla2", Some(2)));
@@ -602,9 +606,9 @@ mod test_btree_node_store {
         let mut node = BTreeNode::new(false, false, cells);
         node.set_node_ptr(Some(42));
         store.create(&mut node);
-        store.sync();
+        store.sync().unwrap();
 
-        let mut load_store = BTreeNodeStore::new(&file);
+        let mut load_store = BTreeNodeStore::new(&file, DEFAULT_PAGE_SIZE);
         let load =  node.get_id().and_then(|id| load_store.retrieve_node(id));
 
         if let Some(loaded) = &load {
@@ -626,7 +630,7 @@ mod test_btree_node_store {
     #[test]
     fn test_update_ptrs() {
         let file = build_file_path_and_rm_old("b_tree_nodes", "test_update_ptrs.db").unwrap();
-        let mut store = BTreeNodeStore::new(&file);
+        let mut store = BTreeNodeStore::new(&file, DEFAULT_PAGE_SIZE);
 
         let long_key = "blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3blabla3";
         let mut cells = Vec::new();
@@ -638,9 +642,9 @@ mod test_btree_node_store {
         let mut node = BTreeNode::new(true, false, cells);
 
         store.create(&mut node);
-        store.sync();
+        store.sync().unwrap();
 
-        let mut load_store = BTreeNodeStore::new(&file);
+        let mut load_store = BTreeNodeStore::new(&file, DEFAULT_PAGE_SIZE);
         let mut loaded =  node.get_id().and_then(|id| load_store.retrieve_node(id));
 
         if let Some(load) = &mut loaded {
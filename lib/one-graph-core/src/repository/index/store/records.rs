@@ -27,6 +27,9 @@ impl CellRecord {
     pub fn set_has_overflow(&mut self) {
         self.header = self.header | HAS_OVERFLOW_CELL_FLAG;
     }
+    pub fn set_no_overflow(&mut self) {
+        self.header &= !HAS_OVERFLOW_CELL_FLAG;
+    }
     pub fn is_active(&self) -> bool {
         self.header & IS_ACTIVE_CELL_FLAG > 0
     }
@@ -8,14 +8,16 @@ pub struct FileAccess {
 }
 
 impl FileAccess {
-    pub fn new(file: &str) -> Self {
-        let f = std::fs::OpenOptions::new()
+    pub fn new(file: &str) -> std::io::Result<Self> {
+        let res = std::fs::OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open(file)
-            .expect("Cannot open file");
-        FileAccess {file: f}
+            .open(file);
+        if let Err(msg) = &res {
+            error!("opening file {} {}", file, msg);
+        }
+        res.map(|f| FileAccess {file: f})
     }
     fn _write_at(&mut self, pos: u64, data: &[u8]) -> std::io::Result<()> {
         let mut written = 0;
@@ -24,7 +26,6 @@ impl FileAccess {
             let bytes_written = self.file.write(&data[written..])?;
             written += bytes_written;
         }
-        self.file.sync_all()?;
         Ok(())
     }
     fn _read_at(&mut self, pos: u64 , mut data: &mut [u8]) -> std::io::Result<()> {
@@ -33,21 +34,34 @@ impl FileAccess {
         Ok(())
     }
 
-    pub fn write_at(&mut self, pos: u64, data: &[u8]) {
-        match self._write_at(pos, data) {
-            Err(_msg) => {
-                error!("writing file");
-            },
-            _ => {}
+    pub fn write_at(&mut self, pos: u64, data: &[u8]) -> std::io::Result<()> {
+        let res = self._write_at(pos, data);
+        if let Err(msg) = &res {
+            error!("writing file {}", msg);
         }
+        res
     }
-    pub fn read_at(&mut self, pos: u64 , data: &mut [u8]) {
-        match self._read_at(pos, data) {
-            Err(msg) => {
-                error!("reading file {}", msg);
-            },
-            _ => {}
+    /// Fails when the buffer cannot be filled entirely, e.g. when reading past the end of a truncated file.
+    pub fn read_at(&mut self, pos: u64 , data: &mut [u8]) -> std::io::Result<()> {
+        let res = self._read_at(pos, data);
+        if let Err(msg) = &res {
+            error!("reading file {}", msg);
+        }
+        res
+    }
+    pub fn sync(&mut self) -> std::io::Result<()> {
+        let res = self.file.sync_all();
+        if let Err(msg) = &res {
+            error!("syncing file {}", msg);
+        }
+        res
+    }
+    pub fn truncate(&mut self) -> std::io::Result<()> {
+        let res = self.file.set_len(0);
+        if let Err(msg) = &res {
+            error!("truncating file {}", msg);
         }
+        res
     }
     pub fn get_file_len(&self) -> u64 {
         match self.file.metadata() {
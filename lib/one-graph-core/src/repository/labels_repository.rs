@@ -0,0 +1,106 @@
+use super::store::*;
+use super::store::token_store::TokenStore;
+use super::byte_utils::*;
+use super::records::RecordsManagerResult;
+use super::super::error::*;
+use super::wal::*;
+use super::super::buf_config::*;
+
+pub struct LabelsRepository {
+    tokens: TokenStore,
+    lists_store: dynamic_store::DynamicStore,
+}
+
+impl LabelsRepository {
+    pub fn new(tokens_file: &str, names_file: &str, lists_file: &str, page_size: usize) -> Self {
+        LabelsRepository {tokens: TokenStore::new(tokens_file, names_file, page_size), lists_store: dynamic_store::DynamicStore::new(lists_file, page_size)}
+    }
+
+    pub fn create_list(&mut self, labels: &[String]) -> GraphResult<u64> {
+        if labels.is_empty() {
+            return Ok(0);
+        }
+        let mut data = Vec::with_capacity(labels.len() * TOKEN_ID_SIZE);
+        for label in labels {
+            data.extend_from_slice(&u64_to_bytes(self.tokens.get_or_create(label)?));
+        }
+        Ok(self.lists_store.save_data(&data)?)
+    }
+
+    pub fn retrieve_list(&mut self, labels_id: u64) -> GraphResult<Vec<String>> {
+        let mut res = Vec::new();
+        if labels_id == 0 {
+            return Ok(res);
+        }
+        let data = self.lists_store.load_data(labels_id)?;
+        for chunk in data.chunks(TOKEN_ID_SIZE) {
+            let token_id = u64_from_bytes(chunk);
+            if token_id == 0 {
+                break;
+            }
+            res.push(self.tokens.get_name(token_id)?.ok_or_else(|| GraphError::Inconsistent(format!("label {} is missing", token_id)))?);
+        }
+        Ok(res)
+    }
+
+    pub fn delete_list(&mut self, labels_id: u64) -> GraphResult<()> {
+        if labels_id == 0 {
+            return Ok(());
+        }
+        Ok(self.lists_store.delete_data(labels_id)?)
+    }
+
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.tokens.sync()?;
+        self.lists_store.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.tokens.verify()?;
+        self.lists_store.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.tokens.set_cache_capacity(capacity);
+        self.lists_store.set_cache_capacity(capacity);
+    }
+
+    pub fn discard(&mut self) {
+        self.tokens.discard();
+        self.lists_store.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.tokens.log_sync(wal);
+        self.lists_store.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.tokens.mark_clean()?;
+        self.lists_store.mark_clean()
+    }
+}
+
+#[cfg(test)]
+mod test_labels_repo {
+    use super::*;
+    use super::super::super::test_utils::*;
+
+    #[test]
+    fn test_labels_list() {
+        let tokens_file = build_file_path_and_rm_old("test_labels_list", "labels.db").unwrap();
+        let names_file = build_file_path_and_rm_old("test_labels_list", "labels-names.db").unwrap();
+        let lists_file = build_file_path_and_rm_old("test_labels_list", "nodes-labels.db").unwrap();
+        let mut repo = LabelsRepository::new(&tokens_file, &names_file, &lists_file, DEFAULT_PAGE_SIZE);
+        let mut labels = Vec::new();
+        for i in 0..20 {
+            labels.push(format!("Label{}", i));
+        }
+        let id = repo.create_list(&labels).unwrap();
+        assert_eq!(repo.retrieve_list(id).unwrap(), labels);
+        assert_eq!(repo.create_list(&[]).unwrap(), 0);
+        assert!(repo.retrieve_list(0).unwrap().is_empty());
+        repo.delete_list(id).unwrap();
+        assert!(repo.retrieve_list(id).is_err());
+    }
+}
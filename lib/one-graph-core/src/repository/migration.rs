@@ -0,0 +1,529 @@
+use super::pager::*;
+use super::io::file_access::FileAccess;
+use super::byte_utils::u64_from_bytes;
+use super::graph_repository::{GraphRepository, DbEdgeData, DbVertexData};
+use super::super::buf_config::*;
+use super::super::config::*;
+use super::super::error::{GraphError, GraphResult};
+use super::super::model::*;
+use super::super::model::init::{IndexKind, InitContext};
+use super::super::model::migration::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use log::info;
+
+//FORMAT VERSION 0
+/// Version 0 files have 4096 bytes pages without checksum, their header page starts with the page count.
+const V0_PAGE_SIZE: usize = 4096;
+/// Records per page were computed with a next page pointer that data pages do not have.
+const V0_HEADER_SIZE: usize = HEADER_FLAGS + NEXT_FREE_PAGE_PTR + NEXT_PAGE_PTR + FREE_LIST_ITEM_COUNTER_SIZE;
+/// Data pages start with the flags, the next free page and the free list length, then the free list and the records.
+const V0_FREE_LIST_LEN_OFFSET: usize = HEADER_FLAGS + NEXT_FREE_PAGE_PTR;
+const V0_FREE_LIST_OFFSET: usize = V0_FREE_LIST_LEN_OFFSET + FREE_LIST_ITEM_COUNTER_SIZE;
+const V0_NODE_RECORD_SIZE: usize = 2 * RELATIONSHIP_ID_SIZE + PROPERTY_ID_SIZE;
+const V0_RELATIONSHIP_RECORD_SIZE: usize = 2 * NODE_ID_SIZE + RELATIONSHIP_TYPE_SIZE + 2 * RELATIONSHIP_ID_SIZE + PROPERTY_ID_SIZE;
+const V0_PROPERTY_BLOCK_SIZE: usize = 24;
+const V0_PROPERTY_RECORD_SIZE: usize = PROPERTY_HEADER_SIZE + PROPERTY_TYPE_SIZE + PROPERTY_KEY_ID_SIZE + V0_PROPERTY_BLOCK_SIZE + PROPERTY_ID_SIZE;
+const V0_DYN_PAYLOAD_SIZE: usize = 120;
+const V0_DYN_RECORD_SIZE: usize = DYN_HEADER_SIZE + DYN_ID_SIZE + V0_DYN_PAYLOAD_SIZE;
+const V0_NB_CELL: usize = 66;
+const V0_KEY_SIZE: usize = 45;
+const V0_CELL_SIZE: usize = CELL_HEADER_SIZE + NODE_PTR_SIZE + OVERFLOW_CELL_PTR_SIZE + V0_KEY_SIZE;
+const V0_CELLS_OFFSET: usize = BTREE_NODE_HEADER_SIZE + NODE_PTR_SIZE + FREE_CELLS_NEXT_NODE_PTR_SIZE;
+const V0_BTREE_NODE_RECORD_SIZE: usize = V0_CELLS_OFFSET + V0_CELL_SIZE * V0_NB_CELL;
+
+const V0_IN_USE_FLAG: u8 = 0b0000_0001;
+const V0_FULL_INLINED_FLAG: u8 = 0b0000_0010;
+const V0_KEY_INLINED_FLAG: u8 = 0b0000_0100;
+const V0_HAS_NEXT_FLAG: u8 = 0b0000_1000;
+const V0_HAS_OVERFLOW_CELL_FLAG: u8 = 0b1000_0000;
+const V0_IS_ACTIVE_CELL_FLAG: u8 = 0b0100_0000;
+const V0_IS_LIST_PTR_CELL_FLAG: u8 = 0b0010_0000;
+const V0_IS_LEAF_NODE_FLAG: u8 = 0b1000_0000;
+const V0_IS_OVERFLOW_NODE_FLAG: u8 = 0b0010_0000;
+
+/// Records per page as version 0 computed them, as many as fit in the page along with their free list.
+fn v0_nb_records_per_page(record_size: usize) -> usize {
+    let page_payload_size = V0_PAGE_SIZE - V0_HEADER_SIZE;
+    let mut nb_records = page_payload_size / record_size;
+    while nb_records > 0 && page_payload_size - nb_records * record_size < nb_records * FREE_LIST_PTR_SIZE {
+        nb_records -= 1;
+    }
+    nb_records
+}
+
+/// Records file written by format version 0, read one page at a time.
+struct V0RecordsFile {
+    file_name: String,
+    io: FileAccess,
+    record_size: usize,
+    nb_records_per_page: usize,
+    page_count: u64,
+    page: Option<(PageId, Vec<u8>)>,
+}
+
+impl V0RecordsFile {
+    fn open(file: &str, record_size: usize) -> GraphResult<Self> {
+        let file_name = Path::new(file).file_name().and_then(|name| name.to_str()).map(String::from).unwrap_or_default();
+        let mut io = FileAccess::new(file).map_err(|e| GraphError::Io(format!("{}: {}", file_name, e)))?;
+        let page_count = if io.get_file_len() == 0 {
+            0
+        } else {
+            let mut counter = [0u8; PAGE_COUNTER_SIZE];
+            io.read_at(0, &mut counter).map_err(|_| GraphError::Corrupted(file_name.clone(), HEADER_PAGE_ID))?;
+            u64::from_be_bytes(counter)
+        };
+        if (page_count + 1) * V0_PAGE_SIZE as u64 > io.get_file_len() && page_count > 0 {
+            return Err(GraphError::Corrupted(file_name, HEADER_PAGE_ID));
+        }
+        Ok(V0RecordsFile{file_name, io, record_size, nb_records_per_page: v0_nb_records_per_page(record_size), page_count, page: None})
+    }
+
+    fn get_max_id(&self) -> u64 {
+        self.page_count * self.nb_records_per_page as u64
+    }
+
+    /// Record at the id, `None` when it is free.
+    fn load(&mut self, id: u64) -> GraphResult<Option<Vec<u8>>> {
+        if id == 0 || id > self.get_max_id() {
+            return Ok(None);
+        }
+        let nb_records_per_page = self.nb_records_per_page;
+        let page_id = 1 + (id - 1) / nb_records_per_page as u64;
+        let slot = ((id - 1) % nb_records_per_page as u64) as usize;
+        if self.page.as_ref().map(|page| page.0) != Some(page_id) {
+            let mut data = vec![0u8; V0_PAGE_SIZE];
+            self.io.read_at(page_id * V0_PAGE_SIZE as u64, &mut data).map_err(|_| GraphError::Corrupted(self.file_name.clone(), page_id))?;
+            self.page = Some((page_id, data));
+        }
+        let page = &self.page.as_ref().unwrap().1;
+        let mut len_bytes = [0u8; FREE_LIST_ITEM_COUNTER_SIZE];
+        len_bytes.copy_from_slice(&page[V0_FREE_LIST_LEN_OFFSET..V0_FREE_LIST_OFFSET]);
+        let free_list_len = u32::from_be_bytes(len_bytes) as usize;
+        if free_list_len > nb_records_per_page {
+            return Err(GraphError::Corrupted(self.file_name.clone(), page_id));
+        }
+        let is_free = page[V0_FREE_LIST_OFFSET..V0_FREE_LIST_OFFSET + free_list_len * FREE_LIST_PTR_SIZE]
+            .chunks(FREE_LIST_PTR_SIZE)
+            .any(|ptr| u32::from_be_bytes([ptr[0], ptr[1], ptr[2], ptr[3]]) as usize == slot);
+        if is_free {
+            return Ok(None);
+        }
+        let offset = V0_FREE_LIST_OFFSET + nb_records_per_page * FREE_LIST_PTR_SIZE + slot * self.record_size;
+        Ok(Some(page[offset..offset + self.record_size].to_vec()))
+    }
+
+    /// Record referenced by another one, a free record means the file is inconsistent.
+    fn load_used(&mut self, id: u64) -> GraphResult<Vec<u8>> {
+        let file_name = self.file_name.clone();
+        self.load(id)?.ok_or_else(|| GraphError::Inconsistent(format!("{} has no record {}", file_name, id)))
+    }
+}
+
+/// String stored up to its first zero byte, with the index of that byte.
+fn read_v0_string(data: &[u8]) -> GraphResult<(String, usize)> {
+    let end = data.iter().position(|&byte| byte == 0).unwrap_or(data.len());
+    let string = String::from_utf8(data[..end].to_vec()).map_err(|_| GraphError::Inconsistent(String::from("a version 0 string is not valid UTF-8")))?;
+    Ok((string, end))
+}
+
+/// Property value of version 0, booleans inlined with their key were written two bytes after its end.
+fn read_v0_value(prop_type: u8, data: &[u8], bool_offset: usize) -> GraphResult<PropertyValue> {
+    let too_short = || GraphError::Inconsistent(format!("a version 0 property of type {} is truncated", prop_type));
+    match prop_type {
+        0 => Ok(PropertyValue::PString(read_v0_string(data)?.0)),
+        1 => Ok(PropertyValue::PInteger(u64_from_bytes(data.get(..8).ok_or_else(too_short)?) as i64)),
+        2 => Ok(PropertyValue::PFloat(f64::from_bits(u64_from_bytes(data.get(..8).ok_or_else(too_short)?)))),
+        3 => Ok(PropertyValue::PBool(*data.get(bool_offset).ok_or_else(too_short)? != 0)),
+        _ => Err(GraphError::Inconsistent(format!("unknown version 0 property type {}", prop_type))),
+    }
+}
+
+/// Nodes and relationships of a version 0 database with their ids and chains.
+type V0Graph = (Vec<(u64, Node, DbVertexData)>, Vec<(u64, Relationship, DbEdgeData)>);
+
+struct V0Cell {
+    header: u8,
+    node_ptr: u64,
+    overflow_cell_ptr: usize,
+    key: Vec<u8>,
+}
+
+fn read_v0_cell(node: &[u8], cell_id: usize) -> Option<V0Cell> {
+    if cell_id >= V0_NB_CELL {
+        return None;
+    }
+    let cell = &node[V0_CELLS_OFFSET + cell_id * V0_CELL_SIZE..V0_CELLS_OFFSET + (cell_id + 1) * V0_CELL_SIZE];
+    let overflow_offset = CELL_HEADER_SIZE + NODE_PTR_SIZE;
+    let key_offset = overflow_offset + OVERFLOW_CELL_PTR_SIZE;
+    Some(V0Cell{header: cell[0], node_ptr: u64_from_bytes(&cell[CELL_HEADER_SIZE..overflow_offset]),
+        overflow_cell_ptr: u32::from_be_bytes([cell[overflow_offset], cell[overflow_offset + 1], cell[overflow_offset + 2], cell[overflow_offset + 3]]) as usize,
+        key: cell[key_offset..].to_vec()})
+}
+
+/// Database written by format version 0: property keys are stored with the properties or in the dynamic store,
+/// labels only in the label index and relationships have no type.
+struct V0Database {
+    nodes: V0RecordsFile,
+    relationships: V0RecordsFile,
+    properties: V0RecordsFile,
+    dynamic: V0RecordsFile,
+    labels_index: V0RecordsFile,
+}
+
+impl V0Database {
+    fn open(paths: &V0Paths) -> GraphResult<Self> {
+        Ok(V0Database{nodes: V0RecordsFile::open(&paths.nodes, V0_NODE_RECORD_SIZE)?,
+            relationships: V0RecordsFile::open(&paths.relationships, V0_RELATIONSHIP_RECORD_SIZE)?,
+            properties: V0RecordsFile::open(&paths.properties, V0_PROPERTY_RECORD_SIZE)?,
+            dynamic: V0RecordsFile::open(&paths.dynamic, V0_DYN_RECORD_SIZE)?,
+            labels_index: V0RecordsFile::open(&paths.labels_index, V0_BTREE_NODE_RECORD_SIZE)?})
+    }
+
+    /// Data chained over dynamic records, the last one is padded with zeros.
+    fn load_data(&mut self, id: u64) -> GraphResult<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut next = id;
+        for _ in 0..=self.dynamic.get_max_id() {
+            let record = self.dynamic.load_used(next)?;
+            data.extend_from_slice(&record[DYN_HEADER_SIZE + DYN_ID_SIZE..]);
+            if record[0] & V0_HAS_NEXT_FLAG == 0 {
+                return Ok(data);
+            }
+            next = u64_from_bytes(&record[DYN_HEADER_SIZE..DYN_HEADER_SIZE + DYN_ID_SIZE]);
+        }
+        Err(GraphError::Inconsistent(format!("the dynamic records from {} loop", id)))
+    }
+
+    /// Properties are fully inlined when the key and the value fit in the block, the key alone when it is short,
+    /// otherwise both are stored in the dynamic store.
+    fn load_property(&mut self, record: &[u8]) -> GraphResult<Property> {
+        let flags = record[0];
+        let prop_type = record[PROPERTY_HEADER_SIZE];
+        let key_offset = PROPERTY_HEADER_SIZE + PROPERTY_TYPE_SIZE;
+        let block_offset = key_offset + PROPERTY_KEY_ID_SIZE;
+        let block = &record[block_offset..block_offset + V0_PROPERTY_BLOCK_SIZE];
+        let mut prop = Property::new();
+        if flags & V0_FULL_INLINED_FLAG != 0 {
+            let (name, end) = read_v0_string(block)?;
+            prop.set_name(&name);
+            prop.set_value(Some(read_v0_value(prop_type, block.get(end + 1..).unwrap_or_default(), 2)?));
+        } else {
+            let (name, value_ptr) = if flags & V0_KEY_INLINED_FLAG != 0 {
+                let (name, end) = read_v0_string(block)?;
+                let value_ptr = block.get(end + 1..end + 1 + DYN_ID_SIZE).ok_or_else(|| GraphError::Inconsistent(format!("the key {} is too long to be inlined", name)))?;
+                (name, u64_from_bytes(value_ptr))
+            } else {
+                let key = self.load_data(u64_from_bytes(&record[key_offset..block_offset]))?;
+                (read_v0_string(&key)?.0, u64_from_bytes(&block[..DYN_ID_SIZE]))
+            };
+            prop.set_name(&name);
+            let data = self.load_data(value_ptr)?;
+            prop.set_value(Some(read_v0_value(prop_type, &data, 0)?));
+        }
+        Ok(prop)
+    }
+
+    fn load_properties(&mut self, first_prop_id: u64) -> GraphResult<Vec<Property>> {
+        let mut props = Vec::new();
+        let mut prop_id = first_prop_id;
+        while prop_id != 0 {
+            if props.len() as u64 > self.properties.get_max_id() {
+                return Err(GraphError::Inconsistent(format!("the properties from {} loop", first_prop_id)));
+            }
+            let record = self.properties.load_used(prop_id)?;
+            if record[0] & V0_IN_USE_FLAG == 0 {
+                return Err(GraphError::Inconsistent(format!("property {} is not in use", prop_id)));
+            }
+            props.push(self.load_property(&record)?);
+            let next_offset = V0_PROPERTY_RECORD_SIZE - PROPERTY_ID_SIZE;
+            prop_id = u64_from_bytes(&record[next_offset..]);
+        }
+        Ok(props)
+    }
+
+    /// Key and data pointers of a leaf cell, the key continues in overflow cells followed by the cells listing the pointers.
+    fn load_leaf_cell(&mut self, first: V0Cell) -> GraphResult<(String, Vec<u64>)> {
+        let mut key = first.key.clone();
+        let mut ptrs = Vec::new();
+        let mut cell = first;
+        for _ in 0..=self.labels_index.get_max_id() * V0_NB_CELL as u64 {
+            if cell.header & V0_HAS_OVERFLOW_CELL_FLAG == 0 {
+                let end = key.iter().position(|&byte| byte == 0).unwrap_or(key.len());
+                let name = read_v0_string(&key[..end])?.0;
+                return Ok((name, ptrs));
+            }
+            let node = self.labels_index.load_used(cell.node_ptr)?;
+            cell = read_v0_cell(&node, cell.overflow_cell_ptr).ok_or_else(|| GraphError::Corrupted(self.labels_index.file_name.clone(), cell.node_ptr))?;
+            if cell.header & V0_IS_LIST_PTR_CELL_FLAG != 0 {
+                let count = u16::from_be_bytes([cell.key[0], cell.key[1]]) as usize;
+                let list = cell.key.get(2..2 + count * NODE_PTR_SIZE).ok_or_else(|| GraphError::Corrupted(self.labels_index.file_name.clone(), cell.node_ptr))?;
+                ptrs.extend(list.chunks(NODE_PTR_SIZE).map(u64_from_bytes));
+            } else {
+                let end = key.iter().position(|&byte| byte == 0).unwrap_or(key.len());
+                key.truncate(end);
+                key.extend_from_slice(&cell.key);
+            }
+        }
+        Err(GraphError::Inconsistent(String::from("the overflow cells of the label index loop")))
+    }
+
+    /// Labels of each node, read from the cells of the leaves of the label index.
+    fn load_labels(&mut self) -> GraphResult<BTreeMap<u64, BTreeSet<String>>> {
+        let mut labels = BTreeMap::new();
+        for node_id in 1..=self.labels_index.get_max_id() {
+            let node = match self.labels_index.load(node_id)? {
+                Some(node) => node,
+                None => continue,
+            };
+            if node[0] & V0_IS_LEAF_NODE_FLAG == 0 || node[0] & V0_IS_OVERFLOW_NODE_FLAG != 0 {
+                continue;
+            }
+            for cell_id in 0..V0_NB_CELL {
+                let cell = read_v0_cell(&node, cell_id).unwrap();
+                if cell.header & V0_IS_ACTIVE_CELL_FLAG == 0 {
+                    continue;
+                }
+                let (label, ptrs) = self.load_leaf_cell(cell)?;
+                for ptr in ptrs {
+                    labels.entry(ptr).or_insert_with(BTreeSet::new).insert(label.clone());
+                }
+            }
+        }
+        Ok(labels)
+    }
+
+    fn read_graph(&mut self) -> GraphResult<V0Graph> {
+        let mut labels = self.load_labels()?;
+        let non_zero = |id: u64| if id == 0 {None} else {Some(id)};
+        let mut nodes = Vec::new();
+        for node_id in 1..=self.nodes.get_max_id() {
+            if let Some(record) = self.nodes.load(node_id)? {
+                let mut node = Node::new();
+                node.set_id(Some(node_id));
+                node.set_properties(self.load_properties(u64_from_bytes(&record[2 * RELATIONSHIP_ID_SIZE..]))?);
+                node.get_labels_mut().extend(labels.remove(&node_id).unwrap_or_default());
+                let vertex = DbVertexData{first_outbound_edge: non_zero(u64_from_bytes(&record[..RELATIONSHIP_ID_SIZE])),
+                    first_inbound_edge: non_zero(u64_from_bytes(&record[RELATIONSHIP_ID_SIZE..2 * RELATIONSHIP_ID_SIZE]))};
+                nodes.push((node_id, node, vertex));
+            }
+        }
+        if let Some(node_id) = labels.keys().next() {
+            return Err(GraphError::Inconsistent(format!("the label index holds the missing node {}", node_id)));
+        }
+        let mut relationships = Vec::new();
+        for rel_id in 1..=self.relationships.get_max_id() {
+            if let Some(record) = self.relationships.load(rel_id)? {
+                let field = |index: usize| u64_from_bytes(&record[index * NODE_ID_SIZE..(index + 1) * NODE_ID_SIZE]);
+                let mut rel = Relationship::new();
+                rel.set_id(Some(rel_id));
+                rel.set_properties(self.load_properties(field(5))?);
+                let edge = DbEdgeData{source: field(0), target: field(1), next_outbound_edge: non_zero(field(3)), next_inbound_edge: non_zero(field(4))};
+                relationships.push((rel_id, rel, edge));
+            }
+        }
+        Ok((nodes, relationships))
+    }
+}
+
+/// Files of a version 0 database, it had no other one.
+struct V0Paths {
+    nodes: String,
+    relationships: String,
+    properties: String,
+    dynamic: String,
+    labels_index: String,
+}
+
+impl V0Paths {
+    /// The label index of version 0 is a B+Tree, it keeps the default name when the database now uses a Bw-Tree.
+    fn new(ctx: &InitContext) -> Self {
+        let labels_index = if ctx.get_nodes_labels_index_kind() == IndexKind::BTree {
+            ctx.get_nodes_labels_index_path().unwrap_or_default()
+        } else {
+            Path::new(ctx.get_db_dir()).join(NODES_LABELS_INDEX_FILE_NAME).to_str().map(String::from).unwrap_or_default()
+        };
+        V0Paths{nodes: ctx.get_nodes_store_path().unwrap_or_default(), relationships: ctx.get_relationships_store_path().unwrap_or_default(),
+            properties: ctx.get_properties_store_path().unwrap_or_default(), dynamic: ctx.get_dynamic_store_path().unwrap_or_default(),
+            labels_index}
+    }
+
+    fn to_vec(&self) -> Vec<&String> {
+        vec![&self.nodes, &self.relationships, &self.properties, &self.dynamic, &self.labels_index]
+    }
+}
+
+pub(crate) fn get_file_name(path: &str) -> String {
+    Path::new(path).file_name().and_then(|name| name.to_str()).map(String::from).unwrap_or_default()
+}
+
+/// Format version of the database read from its nodes store, `None` when the database has no node store yet.
+/// Files written before the versioned header have no format magic, they are version 0.
+fn read_database_version(nodes_path: &str) -> GraphResult<Option<u32>> {
+    let is_empty = fs::metadata(nodes_path).map(|md| md.len() == 0).unwrap_or(true);
+    if is_empty {
+        return Ok(None);
+    }
+    let mut header = [0u8; PAGER_HEADER_SIZE];
+    let mut io = FileAccess::new(nodes_path).map_err(|e| GraphError::Io(format!("{}: {}", get_file_name(nodes_path), e)))?;
+    io.read_at(0, &mut header).map_err(|_| GraphError::Corrupted(get_file_name(nodes_path), HEADER_PAGE_ID))?;
+    Ok(Some(read_format_version(&header).unwrap_or(0)))
+}
+
+/// Rewrites a version 0 database record by record: the new files are written in a directory of the database and then replace the old ones.
+/// Pages get their checksum, node and relationship records the version fields, property keys go to the property keys store
+/// and the label index is built again with the current key order. Nodes and relationships keep their ids.
+fn migrate_v0(ctx: &InitContext) -> GraphResult<Vec<String>> {
+    let paths = V0Paths::new(ctx);
+    let (nodes, relationships) = V0Database::open(&paths)?.read_graph()?;
+    let migration_dir = Path::new(ctx.get_db_dir()).join(MIGRATION_DIR_NAME);
+    if migration_dir.exists() {
+        fs::remove_dir_all(&migration_dir).map_err(|e| GraphError::Io(e.to_string()))?;
+    }
+    fs::create_dir_all(&migration_dir).map_err(|e| GraphError::Io(e.to_string()))?;
+    let mut migration_ctx = ctx.clone();
+    migration_ctx.set_db_dir(migration_dir.to_str().ok_or_else(|| GraphError::Io(format!("invalid path {:?}", migration_dir)))?);
+    {
+        let mut repository = GraphRepository::new(&migration_ctx)?;
+        repository.restore(&nodes, &relationships)?;
+        repository.sync()?;
+    }
+    for path in paths.to_vec() {
+        if Path::new(path).exists() {
+            fs::remove_file(path).map_err(|e| GraphError::Io(e.to_string()))?;
+        }
+    }
+    for entry in fs::read_dir(&migration_dir).map_err(|e| GraphError::Io(e.to_string()))? {
+        let entry = entry.map_err(|e| GraphError::Io(e.to_string()))?;
+        fs::rename(entry.path(), Path::new(ctx.get_db_dir()).join(entry.file_name())).map_err(|e| GraphError::Io(e.to_string()))?;
+    }
+    fs::remove_dir(&migration_dir).map_err(|e| GraphError::Io(e.to_string()))?;
+    info!("{} nodes and {} relationships migrated from format version 0 to {}", nodes.len(), relationships.len(), FORMAT_VERSION);
+    Ok(paths.to_vec().into_iter().map(|path| get_file_name(path)).collect())
+}
+
+/// Upgrades the database described by the context to the current format version in place.
+/// The files of a version 0 database are only replaced once the new ones are written and synced.
+pub fn migrate_dir(ctx: &InitContext) -> GraphResult<MigrationReport> {
+    let nodes_path = ctx.get_nodes_store_path().unwrap_or_default();
+    let version = match read_database_version(&nodes_path)? {
+        Some(version) => version,
+        None => return Ok(MigrationReport::default()),
+    };
+    if version > FORMAT_VERSION {
+        return Err(GraphError::Incompatible(get_file_name(&nodes_path), format!("format version {} is newer than {}", version, FORMAT_VERSION)));
+    }
+    let files = if version == 0 {
+        migrate_v0(ctx)?
+    } else {
+        V0Paths::new(ctx).to_vec().into_iter().filter(|path| Path::new(path).exists()).map(|path| get_file_name(path)).collect()
+    };
+    Ok(MigrationReport{files: files.into_iter().map(|file| FileMigration{file, from_version: version, to_version: FORMAT_VERSION}).collect()})
+}
+
+/// Copies the files of the database directory into `target_dir` which must not hold any file yet.
+pub fn copy_dir(dir: &str, target_dir: &str) -> GraphResult<()> {
+    let is_used = fs::read_dir(target_dir).map(|mut entries| entries.next().is_some()).unwrap_or(false);
+    if is_used {
+        return Err(GraphError::InvalidRequest(format!("target directory {} is not empty", target_dir)));
+    }
+    fs::create_dir_all(target_dir).map_err(|e| GraphError::Io(e.to_string()))?;
+    for entry in fs::read_dir(dir).map_err(|e| GraphError::Io(e.to_string()))? {
+        let entry = entry.map_err(|e| GraphError::Io(e.to_string()))?;
+        if entry.path().is_file() {
+            fs::copy(entry.path(), Path::new(target_dir).join(entry.file_name())).map_err(|e| GraphError::Io(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_migration {
+    use super::*;
+    use super::super::super::model::init::InitContext;
+    use super::super::super::test_utils::*;
+    use super::super::super::graph_engine::{check_database, migrate_database};
+
+    /// Database written by the code of format version 0, its content is described in the README of the fixtures.
+    fn v0_fixture_dir() -> String {
+        format!("{}/fixtures/v0", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn find_value<'a>(node: &'a Node, name: &str) -> Option<&'a PropertyValue> {
+        node.get_properties_ref().iter().find(|p| p.get_name().as_deref() == Some(name)).and_then(|p| p.get_value().as_ref())
+    }
+
+    #[test]
+    fn test_v0_records_per_page() {
+        assert_eq!(v0_nb_records_per_page(V0_NODE_RECORD_SIZE), 145);
+        assert_eq!(v0_nb_records_per_page(V0_RELATIONSHIP_RECORD_SIZE), 78);
+        assert_eq!(v0_nb_records_per_page(V0_PROPERTY_RECORD_SIZE), 88);
+        assert_eq!(v0_nb_records_per_page(V0_DYN_RECORD_SIZE), 30);
+        assert_eq!(v0_nb_records_per_page(V0_BTREE_NODE_RECORD_SIZE), 1);
+    }
+
+    #[test]
+    fn test_migrate_v0_checksums() {
+        let dir = build_dir_path_and_rm_old("test_migrate_v0_checksums").unwrap();
+        copy_dir(&v0_fixture_dir(), &dir).unwrap();
+        let report = migrate_dir(&InitContext::new(&dir)).unwrap();
+        assert!(report.get_migrated().iter().all(|migration| migration.from_version == 0));
+        for entry in fs::read_dir(&dir).unwrap() {
+            let data = fs::read(entry.unwrap().path()).unwrap();
+            if data.is_empty() {
+                continue;
+            }
+            let page_size = read_page_size(&data);
+            assert_eq!(read_format_version(&data), Some(FORMAT_VERSION));
+            assert!(data.chunks(page_size).all(is_page_valid));
+        }
+    }
+
+    #[test]
+    fn test_migrate_v0_database() {
+        let dir = build_dir_path_and_rm_old("test_migrate_v0_database").unwrap();
+        let target_dir = build_dir_path_and_rm_old("test_migrate_v0_database_target").unwrap();
+        copy_dir(&v0_fixture_dir(), &dir).unwrap();
+        let ctx = InitContext::new(&dir);
+        assert!(matches!(GraphRepository::new(&ctx).unwrap().retrieve_node_by_id(145, None), Err(GraphError::Incompatible(_, _))));
+
+        let report = migrate_database(&ctx, Some(&target_dir)).unwrap();
+        assert_eq!(report.get_migrated().len(), 5);
+        let target_ctx = InitContext::new(&target_dir);
+        let mut repo = GraphRepository::new(&target_ctx).unwrap();
+        assert!(repo.verify().is_ok());
+        let persons = repo.fetch_nodes_ids_with_labels(&vec![String::from("Person")], None).unwrap();
+        assert_eq!(persons, [145, 144].iter().copied().collect());
+        let employees = repo.fetch_nodes_ids_with_labels(&vec![String::from("Employee")], None).unwrap();
+        assert_eq!(employees, [142].iter().copied().collect());
+        let long_label = String::from("AVeryLongLabelNameThatDoesNotFitInASingleBTreeCell");
+        assert_eq!(repo.fetch_nodes_ids_with_labels(&vec![long_label], None).unwrap(), [144].iter().copied().collect());
+
+        let alice = repo.retrieve_node_by_id(145, None).unwrap().0;
+        assert_eq!(find_value(&alice, "name"), Some(&PropertyValue::PString(String::from("Alice"))));
+        assert_eq!(find_value(&alice, "age"), Some(&PropertyValue::PInteger(30)));
+        assert!(matches!(find_value(&alice, "score"), Some(PropertyValue::PFloat(score)) if *score == 1.5));
+        assert_eq!(find_value(&alice, "active"), Some(&PropertyValue::PBool(true)));
+        let bob = repo.retrieve_node_by_id(144, None).unwrap().0;
+        assert_eq!(bob.get_labels_ref().len(), 2);
+        assert_eq!(find_value(&bob, "bio"), Some(&PropertyValue::PString(String::from("a value too long to be inlined with its key"))));
+        assert_eq!(find_value(&bob, "description"), Some(&PropertyValue::PString("0123456789".repeat(15))));
+        assert_eq!(find_value(&bob, "a_property_key_longer_than_the_block"), Some(&PropertyValue::PInteger(-42)));
+        assert!(repo.retrieve_node_by_id(143, None).unwrap().0.get_properties_ref().is_empty());
+        assert!(repo.retrieve_node_by_id(141, None).is_err());
+
+        assert_eq!(repo.fetch_outbound_relationships_ids(145, None).unwrap(), vec![78]);
+        let (knows, edge) = repo.retrieve_relationship_by_id(78, None).unwrap();
+        assert_eq!((edge.source, edge.target), (145, 144));
+        assert_eq!(knows.get_properties_ref()[0].get_value(), &Some(PropertyValue::PInteger(2010)));
+        assert_eq!(repo.fetch_neighbours_ids(143, None).unwrap(), [144, 145].iter().copied().collect());
+        assert!(check_database(&target_ctx, false).unwrap().is_consistent());
+        assert!(migrate_database(&ctx, Some(&target_dir)).is_err());
+
+        assert_eq!(migrate_database(&ctx, None).unwrap().get_migrated().len(), 5);
+        assert!(!Path::new(&dir).join(MIGRATION_DIR_NAME).exists());
+        assert_eq!(GraphRepository::new(&ctx).unwrap().retrieve_node_by_id(142, None).unwrap().0.get_labels_ref()[0], "Employee");
+        assert!(migrate_database(&ctx, None).unwrap().get_migrated().is_empty());
+    }
+}
@@ -4,5 +4,12 @@ mod records;
 mod index;
 mod store;
 mod io;
+mod wal;
 mod properties_repository;
-pub mod graph_repository;
\ No newline at end of file
+mod labels_repository;
+mod property_indexes_repository;
+mod constraints_repository;
+pub mod checker;
+pub mod migration;
+pub mod graph_repository;
+pub use self::pager::is_page_size_valid;
@@ -0,0 +1,240 @@
+use super::*;
+use cache::lss::Lss;
+use cache::buf_config::{FLUSH_BUFFER_SIZE, PAGE_SIZE as LSS_PAGE_SIZE};
+use std::convert::TryInto;
+use log::error;
+
+/// Page provider backed by the LLAMA log-structured store, the header page is stored as page 0.
+/// Pages touched since the last sync are copied in working frames and saved back as delta records,
+/// the LSS flushes each sync as a single batch. It is not transactional: nothing is written in the write ahead log,
+/// so the graph repository does not use it, its stores are all logged in the write ahead log.
+/// Pages have the size of the LSS pages, 4096 bytes, a pager opened with another page size refuses every page
+/// with an `Incompatible` error.
+pub struct LssPager {
+    file: String,
+    lss: Lss,
+    frames: HashMap<PageId, Frame>,
+    header_page: HeaderPage,
+    header_error: Option<PagerError>,
+}
+
+/// A header page that was never written belongs to a new file and gets the LSS page size.
+fn load_header_page(lss: &Lss, file: &str) -> (HeaderPage, Option<PagerError>) {
+    match read_page(lss, file, HEADER_PAGE_ID) {
+        Ok(data) if data.iter().all(|&byte| byte == 0) => {
+            let mut header_page = HeaderPage::create(LSS_PAGE_SIZE);
+            header_page.dirty = true;
+            (header_page, None)
+        },
+        Ok(data) => match check_header(file, &data) {
+            None if read_page_size(&data) == LSS_PAGE_SIZE => (HeaderPage::new(data), None),
+            None => {
+                let reason = format!("unsupported page size {}", read_page_size(&data));
+                (HeaderPage::create(LSS_PAGE_SIZE), Some(PagerError::Incompatible(String::from(file), reason)))
+            },
+            Some(e) => (HeaderPage::create(LSS_PAGE_SIZE), Some(e)),
+        },
+        Err(e) => (HeaderPage::create(LSS_PAGE_SIZE), Some(e)),
+    }
+}
+
+/// The configured page size must be the one of the LSS pages.
+fn check_page_size(file: &str, page_size: usize) -> Option<PagerError> {
+    if page_size == LSS_PAGE_SIZE {
+        return None;
+    }
+    let reason = format!("page size {} is not supported by the log-structured store, its pages have {} bytes", page_size, LSS_PAGE_SIZE);
+    error!("{} {}", file, reason);
+    Some(PagerError::Incompatible(String::from(file), reason))
+}
+
+impl LssPager {
+    pub fn new(file: &str, page_size: usize) -> Self {
+        LssPager::with_lss(file, page_size, Lss::new(file))
+    }
+
+    /// The LSS holds at most `nb_pages` pages, the header page included.
+    pub fn with_capacity(file: &str, page_size: usize, nb_pages: usize) -> Self {
+        LssPager::with_lss(file, page_size, Lss::with_capacity(file, nb_pages, FLUSH_BUFFER_SIZE))
+    }
+
+    fn with_lss(file: &str, page_size: usize, lss: Lss) -> Self {
+        let (header_page, header_error) = match check_page_size(file, page_size) {
+            Some(e) => (HeaderPage::create(LSS_PAGE_SIZE), Some(e)),
+            None => load_header_page(&lss, file),
+        };
+        LssPager{file: String::from(file), lss, frames: HashMap::new(), header_page, header_error}
+    }
+
+    fn save(&self, pid: PageId, data: &mut [u8]) -> PagerResult<()> {
+        seal_page(data);
+        let page: Option<&[u8; LSS_PAGE_SIZE]> = (&data[..]).try_into().ok();
+        if page.and_then(|page| self.lss.write(pid, page)).is_none() {
+            error!("page {} is out of the lss mapping table", pid);
+            return Err(PagerError::Full(self.file.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Pages missing from the mapping table were never written and are read as zeros.
+fn read_page(lss: &Lss, file: &str, pid: PageId) -> PagerResult<Vec<u8>> {
+    let data = lss.read(pid).unwrap_or([0u8; LSS_PAGE_SIZE]);
+    if is_page_valid(&data) {
+        Ok(data.to_vec())
+    } else {
+        error!("page {} of {} is corrupted", pid, file);
+        Err(PagerError::Corrupted(String::from(file), pid))
+    }
+}
+
+impl PageProvider for LssPager {
+    fn get_header_page_mut(&mut self) -> &mut HeaderPage {
+        &mut self.header_page
+    }
+
+    fn get_header_page_ref(&self) -> &HeaderPage {
+        &self.header_page
+    }
+
+    fn get_file(&self) -> &str {
+        &self.file
+    }
+
+    fn get_page_size(&self) -> usize {
+        LSS_PAGE_SIZE
+    }
+
+    fn get_header_error(&self) -> Option<PagerError> {
+        self.header_error.clone()
+    }
+
+    fn load_page(&mut self, pid: PageId) -> PagerResult<Page<'_>> {
+        if let Some(e) = &self.header_error {
+            return Err(e.clone());
+        }
+        if pid == HEADER_PAGE_ID || self.header_page.get_page_count() < pid {
+            return Err(PagerError::PageNotFound(pid));
+        }
+        if !self.frames.contains_key(&pid) {
+            let data = read_page(&self.lss, &self.file, pid)?;
+            self.frames.insert(pid, Frame::new(data, false));
+        }
+        Ok(Page::new(pid, &mut self.header_page, self.frames.get_mut(&pid).unwrap()))
+    }
+
+    fn append(&mut self) -> PagerResult<Page<'_>> {
+        let next_pid = self.header_page.get_page_count() + 1;
+        if next_pid >= self.lss.get_capacity() {
+            return Err(PagerError::Full(self.file.clone()));
+        }
+        self.header_page.set_page_count(next_pid);
+        self.frames.insert(next_pid, Frame::new(vec![0u8; LSS_PAGE_SIZE], true));
+        Ok(Page::new(next_pid, &mut self.header_page, self.frames.get_mut(&next_pid).unwrap()))
+    }
+
+    /// The frames are dropped once the LSS flushed them, they are kept when a page cannot be saved.
+    fn sync(&mut self) -> PagerResult<()> {
+        if self.header_error.is_some() {
+            return Ok(());
+        }
+        if self.header_page.dirty {
+            let mut header_data = std::mem::take(&mut self.header_page.data);
+            let saved = self.save(HEADER_PAGE_ID, &mut header_data);
+            self.header_page.data = header_data;
+            saved?;
+            self.header_page.dirty = false;
+        }
+        let mut frames = std::mem::take(&mut self.frames);
+        for (pid, frame) in frames.iter_mut().filter(|(_, frame)| frame.dirty) {
+            if let Err(e) = self.save(*pid, &mut frame.data) {
+                self.frames = frames;
+                return Err(e);
+            }
+            frame.dirty = false;
+        }
+        self.lss.flush().map_err(|e| PagerError::Io(format!("{}: {}", self.file, e)))
+    }
+
+    fn discard(&mut self) {
+        self.frames.clear();
+        let (header_page, header_error) = load_header_page(&self.lss, &self.file);
+        self.header_page = header_page;
+        self.header_error = header_error;
+    }
+
+    /// Only the pages touched since the last sync are kept in frames, the LSS caches the others.
+    fn set_capacity(&mut self, _capacity: usize) {
+    }
+
+    /// Nothing is logged, the pages are only saved to the LSS by `sync`.
+    fn log_sync(&mut self, _wal: &mut WriteAheadLog) {
+    }
+
+    /// Saves the pages as `sync` does.
+    fn mark_clean(&mut self) -> PagerResult<()> {
+        self.sync()
+    }
+}
+
+#[cfg(test)]
+mod test_lss_pager {
+    use super::*;
+    use super::super::super::records::{RecordsManager, RecordsManagerError};
+    use super::super::super::super::test_utils::*;
+
+    #[test]
+    fn test_records_on_lss() {
+        let file = build_file_path_and_rm_old("test_lss_pager", "test_records_on_lss.db").unwrap();
+        let ids = {
+            let mut rm = RecordsManager::with_page_provider(LssPager::new(&file, LSS_PAGE_SIZE), NODE_RECORD_SIZE);
+            let ids = (0..200u8).map(|count| rm.create(&[count; NODE_RECORD_SIZE]).unwrap()).collect::<Vec<u64>>();
+            rm.sync().unwrap();
+            rm.save(ids[0], &[255u8; NODE_RECORD_SIZE]).unwrap();
+            rm.discard();
+            rm.delete(ids[1]).unwrap();
+            rm.sync().unwrap();
+            ids
+        };
+        let mut rm = RecordsManager::with_page_provider(LssPager::new(&file, LSS_PAGE_SIZE), NODE_RECORD_SIZE);
+        let mut data = [0u8; NODE_RECORD_SIZE];
+        rm.load(ids[0], &mut data).unwrap();
+        assert_eq!(data, [0u8; NODE_RECORD_SIZE]);
+        assert!(rm.load(ids[1], &mut data).is_err());
+        rm.load(ids[199], &mut data).unwrap();
+        assert_eq!(data, [199u8; NODE_RECORD_SIZE]);
+    }
+
+    #[test]
+    fn test_full_lss() {
+        let file = build_file_path_and_rm_old("test_lss_pager", "test_full_lss.db").unwrap();
+        let last_id = {
+            let mut rm = RecordsManager::with_page_provider(LssPager::with_capacity(&file, LSS_PAGE_SIZE, 3), NODE_RECORD_SIZE);
+            let mut last_id = 0;
+            loop {
+                match rm.create(&[1u8; NODE_RECORD_SIZE]) {
+                    Ok(id) => last_id = id,
+                    Err(e) => {
+                        assert!(matches!(e, RecordsManagerError::Full(ref full_file) if *full_file == file));
+                        break;
+                    },
+                }
+            }
+            rm.sync().unwrap();
+            last_id
+        };
+        let mut rm = RecordsManager::with_page_provider(LssPager::with_capacity(&file, LSS_PAGE_SIZE, 3), NODE_RECORD_SIZE);
+        let mut data = [0u8; NODE_RECORD_SIZE];
+        rm.load(last_id, &mut data).unwrap();
+        assert_eq!(data, [1u8; NODE_RECORD_SIZE]);
+    }
+
+    #[test]
+    fn test_unsupported_page_size() {
+        let file = build_file_path_and_rm_old("test_lss_pager", "test_unsupported_page_size.db").unwrap();
+        let mut rm = RecordsManager::with_page_provider(LssPager::new(&file, 2 * LSS_PAGE_SIZE), NODE_RECORD_SIZE);
+        assert!(matches!(rm.create(&[1u8; NODE_RECORD_SIZE]), Err(RecordsManagerError::Incompatible(_, _))));
+        let mut pager = LssPager::new(&file, 2 * LSS_PAGE_SIZE);
+        assert!(matches!(pager.load_page(1), Err(PagerError::Incompatible(_, _))));
+    }
+}
@@ -1,116 +1,644 @@
 use super::super::buf_config::*;
+use super::byte_utils::crc32c;
 use super::io::file_access::*;
-use std::collections::HashMap;
+use super::wal::*;
+use super::super::config::PAGE_CACHE_CAPACITY;
+use std::collections::{HashMap, VecDeque};
+use log::error;
+
+pub mod lss_pager;
 
 pub type PageId = u64;
 
+pub const HEADER_PAGE_ID: PageId = 0;
+
 #[derive(Debug, Clone)]
 pub enum PagerError {
-    FileOverflow,
+    PageNotFound(PageId),
+    /// The page of the file could not be read entirely or does not match its checksum.
+    Corrupted(String, PageId),
+    /// The file could not be read, with the cause reported by the system.
+    Io(String),
+    /// The file was created with a page or record geometry this build cannot use, with the reason.
+    Incompatible(String, String),
+    /// The storage of the file has no room left for another page.
+    Full(String),
+}
+
+pub type PagerResult<T> = std::result::Result<T, PagerError>;
+
+/// Writes the checksum of the page content in the page header, done right before the page goes to disk.
+pub fn seal_page(data: &mut [u8]) {
+    let checksum = crc32c(&data[PAGE_CHECKSUM_SIZE..]);
+    data[..PAGE_CHECKSUM_SIZE].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// Whether the page content matches its checksum, a page that was never written is all zeros and is valid.
+pub fn is_page_valid(data: &[u8]) -> bool {
+    let mut bytes = [0u8; PAGE_CHECKSUM_SIZE];
+    bytes.copy_from_slice(&data[..PAGE_CHECKSUM_SIZE]);
+    let checksum = u32::from_be_bytes(bytes);
+    checksum == crc32c(&data[PAGE_CHECKSUM_SIZE..]) || (checksum == 0 && data.iter().all(|&byte| byte == 0))
+}
+
+pub fn is_page_size_valid(page_size: usize) -> bool {
+    page_size.is_power_of_two() && (MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size)
 }
 
-pub type PagerResult = std::result::Result<PageId, PagerError>;
+const PAGE_SIZE_OFFSET: usize = PAGE_CHECKSUM_SIZE + PAGE_COUNTER_SIZE;
+const FORMAT_MAGIC_OFFSET: usize = PAGE_SIZE_OFFSET + PAGE_SIZE_FIELD_SIZE;
+const FORMAT_VERSION_OFFSET: usize = FORMAT_MAGIC_OFFSET + FORMAT_MAGIC_SIZE;
 
+/// The header page starts with the checksum, the page count, the page size, the format magic and
+/// the format version of the file, the rest belongs to the records manager.
+/// Like the other pages it is dirty once changed and written back only then.
 pub struct HeaderPage {
-    pub data: [u8; PAGE_SIZE],
+    data: Vec<u8>,
+    dirty: bool,
 }
 
 impl  HeaderPage {
-    fn new(data: [u8; PAGE_SIZE]) -> Self {
-        HeaderPage{data: data}
+    fn new(data: Vec<u8>) -> Self {
+        HeaderPage{data: data, dirty: false}
+    }
+
+    pub fn get_data_ref(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn get_data_mut(&mut self) -> &mut [u8] {
+        self.dirty = true;
+        &mut self.data
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn create(page_size: usize) -> Self {
+        let mut data = vec![0u8; page_size];
+        data[PAGE_SIZE_OFFSET..FORMAT_MAGIC_OFFSET].copy_from_slice(&(page_size as u32).to_be_bytes());
+        data[FORMAT_MAGIC_OFFSET..FORMAT_VERSION_OFFSET].copy_from_slice(&FORMAT_MAGIC);
+        data[FORMAT_VERSION_OFFSET..PAGER_HEADER_SIZE].copy_from_slice(&FORMAT_VERSION.to_be_bytes());
+        HeaderPage::new(data)
     }
 
     pub fn get_page_count(&self) -> u64 {
         let mut bytes = [0u8; PAGE_COUNTER_SIZE];
-        bytes.copy_from_slice(&self.data[..PAGE_COUNTER_SIZE]);
+        bytes.copy_from_slice(&self.data[PAGE_CHECKSUM_SIZE..PAGE_CHECKSUM_SIZE + PAGE_COUNTER_SIZE]);
         u64::from_be_bytes(bytes)
     }
 
     fn set_page_count(&mut self, count: u64) {
-        self.data[..PAGE_COUNTER_SIZE].copy_from_slice(&count.to_be_bytes());
+        self.dirty = true;
+        self.data[PAGE_CHECKSUM_SIZE..PAGE_CHECKSUM_SIZE + PAGE_COUNTER_SIZE].copy_from_slice(&count.to_be_bytes());
     }
+
+    pub fn get_page_size(&self) -> usize {
+        read_page_size(&self.data)
+    }
+}
+
+pub fn read_page_size(header: &[u8]) -> usize {
+    let mut bytes = [0u8; PAGE_SIZE_FIELD_SIZE];
+    bytes.copy_from_slice(&header[PAGE_SIZE_OFFSET..FORMAT_MAGIC_OFFSET]);
+    u32::from_be_bytes(bytes) as usize
+}
+
+/// Format version of a header page, `None` when it does not start with the format magic.
+pub fn read_format_version(header: &[u8]) -> Option<u32> {
+    if header[FORMAT_MAGIC_OFFSET..FORMAT_VERSION_OFFSET] != FORMAT_MAGIC {
+        return None;
+    }
+    let mut bytes = [0u8; FORMAT_VERSION_SIZE];
+    bytes.copy_from_slice(&header[FORMAT_VERSION_OFFSET..PAGER_HEADER_SIZE]);
+    Some(u32::from_be_bytes(bytes))
+}
+
+/// Why the header page of the file cannot be used by this build, older formats have to be migrated first.
+pub fn check_header(file: &str, header: &[u8]) -> Option<PagerError> {
+    let reason = match read_format_version(header) {
+        None => String::from("the file has no format version, migrate the database with og migrate"),
+        Some(version) if version < FORMAT_VERSION => format!("format version {} is older than {}, migrate the database with og migrate", version, FORMAT_VERSION),
+        Some(version) if version > FORMAT_VERSION => format!("format version {} is newer than {}", version, FORMAT_VERSION),
+        Some(_) if !is_page_size_valid(read_page_size(header)) => format!("unsupported page size {}", read_page_size(header)),
+        Some(_) => return None,
+    };
+    error!("{} {}", file, reason);
+    Some(PagerError::Incompatible(String::from(file), reason))
 }
 
 pub struct Page<'a> {
     pub id: PageId,
     pub header_page: &'a mut HeaderPage,
-    pub data: &'a mut [u8; PAGE_SIZE],
+    data: &'a mut [u8],
+    dirty: &'a mut bool,
 }
 
 impl <'a> Page<'a> {
-    fn new(id: PageId, header_page: &'a mut HeaderPage, data: &'a mut [u8; PAGE_SIZE]) -> Self {
-        Page{id: id, header_page: header_page, data: data}
+    fn new(id: PageId, header_page: &'a mut HeaderPage, frame: &'a mut Frame) -> Self {
+        Page{id, header_page, data: &mut frame.data, dirty: &mut frame.dirty}
     }
+
+    pub fn get_data_ref(&self) -> &[u8] {
+        self.data
+    }
+
+    /// Any mutable access marks the page dirty so that it is written back on sync.
+    pub fn get_data_mut(&mut self) -> &mut [u8] {
+        *self.dirty = true;
+        self.data
+    }
+}
+
+pub trait PageProvider {
+    fn get_header_page_mut(&mut self) -> &mut HeaderPage;
+    fn get_header_page_ref(&self) -> &HeaderPage;
+    fn get_file(&self) -> &str;
+    /// Page size of the file, read from its header page or chosen when the file was created.
+    fn get_page_size(&self) -> usize;
+    /// The error of a file whose header page cannot be used, every page load fails with it.
+    fn get_header_error(&self) -> Option<PagerError>;
+    /// Fails with `PagerError::Corrupted` when the page read from storage does not match its checksum.
+    fn load_page(&mut self, pid: PageId) -> PagerResult<Page<'_>>;
+    /// Fails with `PagerError::Full` when the storage cannot hold another page.
+    fn append(&mut self) -> PagerResult<Page<'_>>;
+    /// Writes the dirty pages to the file, the pages that could not be written stay dirty.
+    fn sync(&mut self) -> PagerResult<()>;
+    fn discard(&mut self);
+    /// Appends the dirty pages to the write ahead log, they stay dirty until `mark_clean`.
+    fn log_sync(&mut self, wal: &mut WriteAheadLog);
+    /// Called once the write ahead log committed the logged pages.
+    fn mark_clean(&mut self) -> PagerResult<()>;
+    /// Bounds the number of cached pages, the pages above the new capacity are evicted once written.
+    fn set_capacity(&mut self, capacity: usize);
+}
+
+struct Frame {
+    data: Box<[u8]>,
+    dirty: bool,
+    referenced: bool,
 }
 
+impl Frame {
+    fn new(data: Vec<u8>, dirty: bool) -> Self {
+        Frame{data: data.into_boxed_slice(), dirty, referenced: true}
+    }
+}
+
+/// Page cache bounded to `capacity` pages, evicted with the CLOCK policy.
+/// Dirty pages hold uncommitted changes and pinned pages were explicitly requested to stay resident,
+/// neither of them can be evicted: the cache may exceed its budget until the next sync or unpin.
+/// A `Page` mutably borrows the pager so no eviction can happen while it is alive.
 pub struct Pager {
-    records_file: FileAccess,
-    page_cache: HashMap<PageId, [u8; PAGE_SIZE]>,
-    nb_pages: u64,
+    file: String,
+    /// None when the file cannot be opened, the header error tells why.
+    records_file: Option<FileAccess>,
+    page_cache: HashMap<PageId, Frame>,
+    clock: VecDeque<PageId>,
+    pins: HashMap<PageId, usize>,
+    capacity: usize,
+    page_size: usize,
     header_page: HeaderPage,
+    header_error: Option<PagerError>,
 }
 
+/// A short read is reported like a checksum mismatch, it means the file was truncated.
+fn read_page(io: &mut FileAccess, file: &str, pid: PageId, page_size: usize) -> PagerResult<Vec<u8>> {
+    let mut page_data = vec![0u8; page_size];
+    let read = io.read_at(pid * page_size as u64, &mut page_data);
+    if let Err(e) = &read {
+        if e.kind() != std::io::ErrorKind::UnexpectedEof {
+            return Err(PagerError::Io(format!("{}: {}", file, e)));
+        }
+    }
+    if read.is_err() || !is_page_valid(&page_data) {
+        error!("page {} of {} is corrupted", pid, file);
+        return Err(PagerError::Corrupted(String::from(file), pid));
+    }
+    Ok(page_data)
+}
 
-fn load_or_create_header_page(io: &mut FileAccess) -> HeaderPage {
-    let mut header_page_data = [0u8; PAGE_SIZE];
+/// A new file gets `page_size`, an existing one keeps the page size stored in its header page.
+/// A header page that cannot be used is replaced by an empty one along with the error,
+/// the pager then refuses to load any page and never writes the file.
+fn load_or_create_header_page(io: &mut FileAccess, file: &str, page_size: usize) -> (HeaderPage, Option<PagerError>) {
     if io.get_file_len() == 0 {
-        io.write_at(0, &header_page_data);
-    } else {
-        io.read_at(0, &mut header_page_data);
+        let mut header_page = HeaderPage::create(page_size);
+        seal_page(&mut header_page.data);
+        let _ = io.write_at(0, &header_page.data);
+        return (header_page, None);
+    }
+    let mut pager_header = [0u8; PAGER_HEADER_SIZE];
+    if io.read_at(0, &mut pager_header).is_err() {
+        error!("page {} of {} is corrupted", HEADER_PAGE_ID, file);
+        return (HeaderPage::create(page_size), Some(PagerError::Corrupted(String::from(file), HEADER_PAGE_ID)));
+    }
+    if let Some(e) = check_header(file, &pager_header) {
+        return (HeaderPage::create(page_size), Some(e));
+    }
+    let stored_page_size = read_page_size(&pager_header);
+    match read_page(io, file, HEADER_PAGE_ID, stored_page_size) {
+        Ok(data) => (HeaderPage::new(data), None),
+        Err(e) => (HeaderPage::create(stored_page_size), Some(e)),
     }
-    HeaderPage{data: header_page_data}
 }
 
 impl Pager {
-    pub fn new(file: &str) -> Self {
-        let mut file_io = FileAccess::new(file);
-        let header_page = load_or_create_header_page(&mut file_io);
-        Pager { records_file: file_io, page_cache: HashMap::new(), nb_pages: 0u64, header_page: header_page}
+    pub fn new(file: &str, page_size: usize) -> Self {
+        Pager::with_capacity(file, page_size, PAGE_CACHE_CAPACITY)
+    }
+
+    pub fn with_capacity(file: &str, page_size: usize, capacity: usize) -> Self {
+        let (records_file, header_page, header_error) = match FileAccess::new(file) {
+            Ok(mut file_io) => {
+                let (header_page, header_error) = load_or_create_header_page(&mut file_io, file, page_size);
+                (Some(file_io), header_page, header_error)
+            },
+            Err(e) => (None, HeaderPage::create(page_size), Some(PagerError::Io(format!("{}: {}", file, e)))),
+        };
+        Pager { file: String::from(file), records_file, page_cache: HashMap::new(), clock: VecDeque::new(),
+            pins: HashMap::new(), capacity: std::cmp::max(capacity, 1), page_size: header_page.get_page_size(), header_page, header_error}
+    }
+
+    pub fn get_cached_pages_count(&self) -> usize {
+        self.page_cache.len()
+    }
+
+    pub fn is_dirty(&self, pid: PageId) -> bool {
+        self.page_cache.get(&pid).map(|frame| frame.dirty).unwrap_or(false)
+    }
+
+    fn is_evictable(&self, pid: PageId) -> bool {
+        !self.pins.contains_key(&pid) && self.page_cache.get(&pid).map(|frame| !frame.dirty).unwrap_or(true)
+    }
+
+    /// Sweeps the clock until the cache fits in `target` pages, giving referenced pages a second chance.
+    /// Stops after two full turns when only dirty or pinned pages are left.
+    fn evict(&mut self, target: usize) {
+        let mut budget = 2 * self.clock.len();
+        while self.page_cache.len() > target && budget > 0 {
+            budget -= 1;
+            if let Some(pid) = self.clock.pop_front() {
+                if !self.is_evictable(pid) {
+                    self.clock.push_back(pid);
+                    continue;
+                }
+                let referenced = self.page_cache.get_mut(&pid).map(|frame| std::mem::replace(&mut frame.referenced, false));
+                match referenced {
+                    Some(true) => self.clock.push_back(pid),
+                    Some(false) => {
+                        self.page_cache.remove(&pid);
+                    },
+                    None => {},
+                }
+            }
+        }
+    }
+
+    fn insert_frame(&mut self, pid: PageId, frame: Frame) {
+        self.evict(self.capacity - 1);
+        self.page_cache.insert(pid, frame);
+        self.clock.push_back(pid);
+    }
+
+    /// Keeps a page resident until the matching `unpin`, pins are counted.
+    pub fn pin(&mut self, pid: PageId) {
+        *self.pins.entry(pid).or_insert(0) += 1;
+    }
+
+    pub fn unpin(&mut self, pid: PageId) {
+        if let Some(count) = self.pins.get_mut(&pid) {
+            *count -= 1;
+            if *count == 0 {
+                self.pins.remove(&pid);
+            }
+        }
+    }
+    
+    fn sorted_dirty_page_ids(&self) -> Vec<PageId> {
+        let mut pids = self.page_cache.iter().filter(|(_, frame)| frame.dirty).map(|(pid, _)| *pid).collect::<Vec<PageId>>();
+        pids.sort();
+        pids
     }
+}
 
-    pub fn get_header_page_mut(&mut self) -> &mut HeaderPage {
+impl PageProvider for Pager {
+    fn get_header_page_mut(&mut self) -> &mut HeaderPage {
         &mut self.header_page
     }
 
-    pub fn get_header_page_ref(&self) -> &HeaderPage {
+    fn get_header_page_ref(&self) -> &HeaderPage {
         &self.header_page
     }
-    
-    fn read_page_data(&mut self, pid: PageId) -> [u8; PAGE_SIZE] {
-        let mut page_data = [0u8; PAGE_SIZE];
-        let page_begin_pos = pid * PAGE_SIZE as u64;
-        self.records_file.read_at(page_begin_pos, &mut page_data);
-        page_data
-    }
-
-    pub fn load_page(&mut self, pid: PageId) -> Option<Page> {
-        if self.header_page.get_page_count() >= pid {
-            if !self.page_cache.contains_key(&pid) {
-                let page_data = self.read_page_data(pid);
-                self.page_cache.insert(pid, page_data);
-            }
-            Some(Page::new(pid, &mut self.header_page, self.page_cache.get_mut(&pid).unwrap()))
+
+    fn get_file(&self) -> &str {
+        &self.file
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_header_error(&self) -> Option<PagerError> {
+        self.header_error.clone()
+    }
+
+    fn load_page(&mut self, pid: PageId) -> PagerResult<Page<'_>> {
+        if let Some(e) = &self.header_error {
+            return Err(e.clone());
+        }
+        if self.header_page.get_page_count() < pid {
+            return Err(PagerError::PageNotFound(pid));
+        }
+        if let Some(frame) = self.page_cache.get_mut(&pid) {
+            frame.referenced = true;
         } else {
-            None
+            let file = &self.file;
+            let records_file = self.records_file.as_mut().ok_or_else(|| PagerError::Io(format!("{}: the file is not opened", file)))?;
+            let page_data = read_page(records_file, file, pid, self.page_size)?;
+            self.insert_frame(pid, Frame::new(page_data, false));
         }
-        
+        Ok(Page::new(pid, &mut self.header_page, self.page_cache.get_mut(&pid).unwrap()))
     }
 
-    pub fn append(&mut self) -> Page {
+    fn append(&mut self) -> PagerResult<Page<'_>> {
         let next_pid = self.header_page.get_page_count() + 1;
         self.header_page.set_page_count(next_pid);
-        let page_data = [0u8; PAGE_SIZE];
-        self.page_cache.insert(next_pid, page_data);
-        Page::new(next_pid, &mut self.header_page, self.page_cache.get_mut(&next_pid).unwrap())
+        self.insert_frame(next_pid, Frame::new(vec![0u8; self.page_size], true));
+        Ok(Page::new(next_pid, &mut self.header_page, self.page_cache.get_mut(&next_pid).unwrap()))
     }
-    
-    pub fn sync(&mut self) {
-        self.records_file.write_at(0, &self.header_page.data);
-        let mut pids = self.page_cache.keys().cloned().collect::<Vec<PageId>>();
-        pids.sort();
-        for pid in pids {
-            let pos = pid * PAGE_SIZE as u64;
-            self.records_file.write_at(pos, self.page_cache.get(&pid).unwrap());
+
+    /// A page failing to be written stays dirty so that the next sync retries it, the first failure is returned.
+    fn sync(&mut self) -> PagerResult<()> {
+        let dirty_page_ids = self.sorted_dirty_page_ids();
+        let records_file = match self.records_file.as_mut() {
+            Some(records_file) if self.header_error.is_none() => records_file,
+            _ => return Ok(()),
+        };
+        let file = &self.file;
+        let to_error = |file: &str, e: std::io::Error| PagerError::Io(format!("{}: {}", file, e));
+        let mut result = Ok(());
+        if self.header_page.dirty {
+            seal_page(&mut self.header_page.data);
+            result = records_file.write_at(0, &self.header_page.data).map_err(|e| to_error(file, e));
+            self.header_page.dirty = result.is_err();
+        }
+        for pid in dirty_page_ids {
+            let pos = pid * self.page_size as u64;
+            let frame = self.page_cache.get_mut(&pid).unwrap();
+            seal_page(&mut frame.data);
+            if let Err(e) = records_file.write_at(pos, &frame.data[..]) {
+                result = result.and(Err(to_error(file, e)));
+                continue;
+            }
+            frame.dirty = false;
         }
+        result = result.and_then(|_| records_file.sync().map_err(|e| to_error(file, e)));
+        let capacity = self.capacity;
+        self.evict(capacity);
+        result
     }
-}
\ No newline at end of file
+
+    /// Drops the uncommitted pages, clean pages still match the file and stay cached.
+    fn discard(&mut self) {
+        self.page_cache.retain(|_, frame| !frame.dirty);
+        let page_cache = &self.page_cache;
+        self.clock.retain(|pid| page_cache.contains_key(pid));
+        if let Some(records_file) = self.records_file.as_mut() {
+            let (header_page, header_error) = load_or_create_header_page(records_file, &self.file, self.page_size);
+            self.header_page = header_page;
+            self.header_error = header_error;
+        }
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = std::cmp::max(capacity, 1);
+        self.evict(self.capacity);
+    }
+
+    fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        if self.header_error.is_some() {
+            return;
+        }
+        if self.header_page.dirty {
+            seal_page(&mut self.header_page.data);
+            wal.append(&self.file, 0, &self.header_page.data);
+        }
+        for pid in self.sorted_dirty_page_ids() {
+            let pos = pid * self.page_size as u64;
+            let frame = self.page_cache.get_mut(&pid).unwrap();
+            seal_page(&mut frame.data);
+            wal.append(&self.file, pos, &frame.data[..]);
+        }
+    }
+
+    /// The write ahead log checkpointed the logged pages, they can be evicted.
+    fn mark_clean(&mut self) -> PagerResult<()> {
+        self.header_page.dirty = false;
+        for frame in self.page_cache.values_mut() {
+            frame.dirty = false;
+        }
+        let capacity = self.capacity;
+        self.evict(capacity);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_pager {
+    use super::*;
+    use super::super::super::test_utils::*;
+
+    #[test]
+    fn test_bounded_cache() {
+        let file = build_file_path_and_rm_old("test_pager", "test_bounded_cache.db").unwrap();
+        let mut pager = Pager::with_capacity(&file, DEFAULT_PAGE_SIZE, 4);
+        for count in 0..10u8 {
+            pager.append().unwrap().get_data_mut()[PAGE_CHECKSUM_SIZE] = count;
+        }
+        assert_eq!(pager.get_cached_pages_count(), 10);
+        pager.sync().unwrap();
+        assert_eq!(pager.get_cached_pages_count(), 4);
+        for pid in 1..=10 {
+            assert_eq!(pager.load_page(pid).unwrap().get_data_ref()[PAGE_CHECKSUM_SIZE], pid as u8 - 1);
+            assert!(pager.get_cached_pages_count() <= 4);
+            assert!(!pager.is_dirty(pid));
+        }
+    }
+
+    #[test]
+    fn test_file_not_opened() {
+        let dir = build_dir_path_and_rm_old("test_pager_file_not_opened").unwrap();
+        let file = format!("{}/missing/test_file_not_opened.db", dir);
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        assert!(matches!(pager.get_header_error(), Some(PagerError::Io(_))));
+        assert!(matches!(pager.load_page(1), Err(PagerError::Io(_))));
+        pager.discard();
+        assert!(pager.sync().is_ok());
+    }
+
+    #[test]
+    fn test_dirty_pages() {
+        let file = build_file_path_and_rm_old("test_pager", "test_dirty_pages.db").unwrap();
+        let mut pager = Pager::with_capacity(&file, DEFAULT_PAGE_SIZE, 2);
+        for _ in 0..4 {
+            pager.append().unwrap();
+        }
+        pager.sync().unwrap();
+        pager.load_page(1).unwrap().get_data_mut()[PAGE_CHECKSUM_SIZE] = 1;
+        assert!(pager.is_dirty(1));
+        for pid in 2..=4 {
+            pager.load_page(pid).unwrap();
+        }
+        assert!(pager.is_dirty(1));
+        assert_eq!(pager.load_page(1).unwrap().get_data_ref()[PAGE_CHECKSUM_SIZE], 1);
+        pager.discard();
+        assert!(!pager.is_dirty(1));
+        assert_eq!(pager.load_page(1).unwrap().get_data_ref()[PAGE_CHECKSUM_SIZE], 0);
+    }
+
+    #[test]
+    fn test_log_sync_cleans_pages() {
+        let file = build_file_path_and_rm_old("test_pager", "test_log_sync_cleans_pages.db").unwrap();
+        let wal_file = build_file_path_and_rm_old("test_pager", "test_log_sync_cleans_pages_wal.db").unwrap();
+        let mut wal = WriteAheadLog::new(&wal_file).unwrap();
+        let mut pager = Pager::with_capacity(&file, DEFAULT_PAGE_SIZE, 1);
+        for _ in 0..3 {
+            pager.append().unwrap();
+        }
+        pager.log_sync(&mut wal);
+        assert!(pager.is_dirty(3));
+        wal.clear();
+        pager.discard();
+        assert_eq!(pager.get_cached_pages_count(), 0);
+        for _ in 0..3 {
+            pager.append().unwrap();
+        }
+        pager.log_sync(&mut wal);
+        wal.commit().unwrap();
+        pager.mark_clean().unwrap();
+        assert!(!pager.is_dirty(3));
+        assert_eq!(pager.get_cached_pages_count(), 1);
+        assert_eq!(Pager::new(&file, DEFAULT_PAGE_SIZE).get_header_page_ref().get_page_count(), 3);
+    }
+
+    #[test]
+    fn test_log_sync_skips_clean_pager() {
+        let file = build_file_path_and_rm_old("test_pager", "test_log_sync_skips_clean_pager.db").unwrap();
+        let wal_file = build_file_path_and_rm_old("test_pager", "test_log_sync_skips_clean_pager_wal.db").unwrap();
+        let mut wal = WriteAheadLog::new(&wal_file).unwrap();
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        pager.log_sync(&mut wal);
+        assert!(wal.is_empty());
+        pager.append().unwrap();
+        assert!(pager.get_header_page_ref().is_dirty());
+        pager.log_sync(&mut wal);
+        wal.commit().unwrap();
+        pager.mark_clean().unwrap();
+        assert!(!pager.get_header_page_ref().is_dirty());
+        pager.load_page(1).unwrap().get_data_ref();
+        pager.log_sync(&mut wal);
+        assert!(wal.is_empty());
+        pager.get_header_page_mut().get_data_mut()[PAGER_HEADER_SIZE] = 1;
+        pager.log_sync(&mut wal);
+        assert!(!wal.is_empty());
+    }
+
+    #[test]
+    fn test_pinned_pages() {
+        let file = build_file_path_and_rm_old("test_pager", "test_pinned_pages.db").unwrap();
+        let mut pager = Pager::with_capacity(&file, DEFAULT_PAGE_SIZE, 1);
+        for _ in 0..3 {
+            pager.append().unwrap();
+        }
+        pager.sync().unwrap();
+        pager.load_page(1).unwrap();
+        pager.pin(1);
+        pager.load_page(2).unwrap();
+        pager.load_page(3).unwrap();
+        assert!(pager.page_cache.contains_key(&1));
+        pager.unpin(1);
+        pager.load_page(2).unwrap();
+        pager.load_page(3).unwrap();
+        assert!(!pager.page_cache.contains_key(&1));
+    }
+
+    #[test]
+    fn test_corrupted_pages() {
+        let file = build_file_path_and_rm_old("test_pager", "test_corrupted_pages.db").unwrap();
+        {
+            let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+            for count in 0..3u8 {
+                pager.append().unwrap().get_data_mut()[PAGE_CHECKSUM_SIZE] = count;
+            }
+            pager.sync().unwrap();
+        }
+        FileAccess::new(&file).unwrap().write_at(2 * DEFAULT_PAGE_SIZE as u64 + 100, &[1]).unwrap();
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        assert_eq!(pager.load_page(1).unwrap().get_data_ref()[PAGE_CHECKSUM_SIZE], 0);
+        match pager.load_page(2) {
+            Err(PagerError::Corrupted(corrupted_file, pid)) => assert!(corrupted_file == file && pid == 2),
+            _ => panic!("page 2 should be corrupted"),
+        }
+        assert!(matches!(pager.load_page(4), Err(PagerError::PageNotFound(4))));
+
+        let mut records_file = FileAccess::new(&file).unwrap();
+        let mut data = [0u8; DEFAULT_PAGE_SIZE];
+        records_file.read_at(0, &mut data).unwrap();
+        records_file.truncate().unwrap();
+        records_file.write_at(0, &data).unwrap();
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        assert!(matches!(pager.load_page(3), Err(PagerError::Corrupted(_, 3))));
+    }
+
+    #[test]
+    fn test_page_size() {
+        let file = build_file_path_and_rm_old("test_pager", "test_page_size.db").unwrap();
+        {
+            let mut pager = Pager::new(&file, 2 * DEFAULT_PAGE_SIZE);
+            pager.append().unwrap().get_data_mut()[2 * DEFAULT_PAGE_SIZE - 1] = 1;
+            pager.sync().unwrap();
+        }
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        assert_eq!(pager.get_page_size(), 2 * DEFAULT_PAGE_SIZE);
+        assert_eq!(pager.load_page(1).unwrap().get_data_ref()[2 * DEFAULT_PAGE_SIZE - 1], 1);
+
+        let mut header = [0u8; PAGER_HEADER_SIZE];
+        FileAccess::new(&file).unwrap().read_at(0, &mut header).unwrap();
+        header[PAGE_SIZE_OFFSET..FORMAT_MAGIC_OFFSET].copy_from_slice(&1000u32.to_be_bytes());
+        FileAccess::new(&file).unwrap().write_at(0, &header).unwrap();
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        assert!(matches!(pager.load_page(1), Err(PagerError::Incompatible(_, _))));
+        pager.append().unwrap();
+        pager.sync().unwrap();
+        let mut stored = [0u8; PAGER_HEADER_SIZE];
+        FileAccess::new(&file).unwrap().read_at(0, &mut stored).unwrap();
+        assert_eq!(stored, header);
+    }
+
+    #[test]
+    fn test_format_version() {
+        let file = build_file_path_and_rm_old("test_pager", "test_format_version.db").unwrap();
+        {
+            let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+            pager.append().unwrap();
+            pager.sync().unwrap();
+        }
+        let mut header = vec![0u8; DEFAULT_PAGE_SIZE];
+        FileAccess::new(&file).unwrap().read_at(0, &mut header).unwrap();
+        assert_eq!(read_format_version(&header), Some(FORMAT_VERSION));
+
+        header[FORMAT_VERSION_OFFSET..PAGER_HEADER_SIZE].copy_from_slice(&(FORMAT_VERSION + 1).to_be_bytes());
+        seal_page(&mut header);
+        FileAccess::new(&file).unwrap().write_at(0, &header).unwrap();
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        assert!(matches!(pager.load_page(1), Err(PagerError::Incompatible(_, _))));
+
+        header[FORMAT_MAGIC_OFFSET..PAGER_HEADER_SIZE].copy_from_slice(&[0u8; FORMAT_MAGIC_SIZE + FORMAT_VERSION_SIZE]);
+        seal_page(&mut header);
+        FileAccess::new(&file).unwrap().write_at(0, &header).unwrap();
+        let mut pager = Pager::new(&file, DEFAULT_PAGE_SIZE);
+        assert!(matches!(pager.load_page(1), Err(PagerError::Incompatible(_, _))));
+    }
+}
@@ -1,22 +1,23 @@
 use super::store::*;
+use super::store::token_store::TokenStore;
 use super::super::model::*;
+use super::super::error::*;
+use super::records::RecordsManagerResult;
+use super::wal::*;
 
 pub struct PropertiesRespository {
     prop_store: properties_store::PropertiesStore,
     dyn_store: dynamic_store::DynamicStore,
+    keys_store: TokenStore,
 }
 
-fn compute_prop_name_size(prop: &Property) -> Option<usize> {
-    prop.get_name().as_ref().map(|name| name.len())
-}
-
-fn compute_prop_size(prop: &Property) -> Option<usize> {
+fn compute_value_size(prop: &Property) -> Option<usize> {
     prop.get_value().as_ref().map(|value| match value {
         PropertyValue::PString(sval) => sval.len(),
         PropertyValue::PInteger(_) => std::mem::size_of::<i64>(),
         PropertyValue::PFloat(_) => std::mem::size_of::<f64>(),
         PropertyValue::PBool(_) => std::mem::size_of::<bool>(),
-    }).and_then(|vsize| compute_prop_name_size(prop).map(|nsize| nsize + vsize))
+    })
 }
 
 fn map_prop_type(prop: &Property) -> Option<u8> {
@@ -28,69 +29,36 @@ fn map_prop_type(prop: &Property) -> Option<u8> {
     })
 }
 
-fn is_full_inlined(prop: &Property) -> Option<bool> {
-    compute_prop_size(prop).map(|psize| psize < 23)
-}
-
-fn is_key_inlined(prop: &Property) -> Option<bool> {
-    compute_prop_name_size(prop).map(|psize| psize < 23)
+fn value_to_bytes(value: &PropertyValue) -> Vec<u8> {
+    match value {
+        PropertyValue::PString(sval) => sval.clone().into_bytes(),
+        PropertyValue::PInteger(ival) => ival.to_be_bytes().to_vec(),
+        PropertyValue::PFloat(fval) => fval.to_be_bytes().to_vec(),
+        PropertyValue::PBool(bval) => vec![*bval as u8],
+    }
 }
 
-fn make_full_inlined_record(prop: &Property) -> Option<records::PropertyRecord> {
-    is_full_inlined(prop).and_then(|full| {
-        if full {
-            prop.get_name().as_ref().and_then(|name| {
-                let mut block = [0u8; 24];
-                let bytes = name.clone().into_bytes();
-                block[0..bytes.len()].copy_from_slice(&bytes);
-                let skip = name.len() + 1;
-                prop.get_value().as_ref().and_then(|value| {
-                    match value {
-                        PropertyValue::PString(sval) => block[skip..skip + sval.len()].copy_from_slice(&sval.clone().into_bytes()),
-                        PropertyValue::PInteger(ival) => block[skip..skip + std::mem::size_of::<i64>()].copy_from_slice(&ival.to_be_bytes()),
-                        PropertyValue::PFloat(fval) => block[skip..skip + std::mem::size_of::<f64>()].copy_from_slice(&fval.to_be_bytes()),
-                        PropertyValue::PBool(bval) => block[skip + 2] = *bval as u8,
-                    };
-                    map_prop_type(prop).map(|ptype| 
-                        records::PropertyRecord {
-                            in_use: true,
-                            key_inlined: false,
-                            full_inlined: true,
-                            has_next: false,
-                            prop_type: ptype,
-                            key_id: 0,
-                            prop_block: block,
-                            next_prop_id: 0,
-                        })
-                    })
-            })
-        } else {
-            None
-        }
-    })
-    
+fn is_full_inlined(prop: &Property) -> Option<bool> {
+    compute_value_size(prop).map(|vsize| vsize <= 24)
 }
 
 impl PropertiesRespository {
-    pub fn new(props_file: &str, dyn_file: &str) -> Self {
-        PropertiesRespository {prop_store: properties_store::PropertiesStore::new(props_file), dyn_store: dynamic_store::DynamicStore::new(dyn_file)}
+    pub fn new(props_file: &str, dyn_file: &str, keys_file: &str, keys_names_file: &str, page_size: usize) -> Self {
+        PropertiesRespository {prop_store: properties_store::PropertiesStore::new(props_file, page_size), dyn_store: dynamic_store::DynamicStore::new(dyn_file, page_size),
+            keys_store: TokenStore::new(keys_file, keys_names_file, page_size)}
     }
 
-    pub fn create(&mut self, prop: &mut Property) -> Option<()> {
-        let prop_id = make_full_inlined_record(prop)
-            .or_else(|| self.make_key_inlined_record(prop))
-            .or_else(|| self.make_record(prop)).as_mut().map(|r| self.prop_store.create(r))?;
-        prop.set_id(prop_id);
-        Some(())
+    pub fn create(&mut self, prop: &mut Property) -> GraphResult<()> {
+        let record = self.make_record(prop)?;
+        let prop_id = self.prop_store.create(&record)?;
+        prop.set_id(Some(prop_id));
+        Ok(())
     }
 
-    pub fn create_list(&mut self, props: &Vec<Property>) -> Option<u64> {
+    pub fn create_list(&mut self, props: &[Property]) -> GraphResult<u64> {
         let mut vec_records = Vec::new();
         for prop in props {
-            let prop_record =  make_full_inlined_record(prop)
-            .or_else(|| self.make_key_inlined_record(prop))
-            .or_else(|| self.make_record(prop))?;
-            vec_records.push(prop_record);
+            vec_records.push(self.make_record(prop)?);
         }
         vec_records.reverse();
         let mut curr_id = 0;
@@ -99,10 +67,10 @@ impl PropertiesRespository {
             curr_id = self.prop_store.create(pr)?;
 
         }
-        Some(curr_id)
+        Ok(curr_id)
     }
 
-    pub fn retrieve_list(&mut self, prop_id: u64) -> Option<Vec<Property>> {
+    pub fn retrieve_list(&mut self, prop_id: u64) -> GraphResult<Vec<Property>> {
         let mut curr_id = prop_id;
         let mut res = Vec::new();
         while curr_id != 0 {
@@ -112,128 +80,134 @@ impl PropertiesRespository {
             res.push(prop);
             curr_id = pr.next_prop_id;
         }
-        Some(res)
+        Ok(res)
+    }
+
+    pub fn get_key_id(&mut self, name: &str) -> GraphResult<Option<u64>> {
+        Ok(self.keys_store.get_id(name)?)
     }
 
-    fn make_record(&mut self, prop: &Property) -> Option<records::PropertyRecord> {
-        let value_id = prop.get_value().as_ref().and_then(|val| {
-            match val {
-                PropertyValue::PString(sval) => self.dyn_store.save_data(&sval.clone().into_bytes()),
-                PropertyValue::PInteger(ival) => self.dyn_store.save_data(&ival.to_be_bytes()),
-                PropertyValue::PFloat(fval) => self.dyn_store.save_data(&fval.to_be_bytes()),
-                PropertyValue::PBool(bval) => self.dyn_store.save_data(&[*bval as u8]),
+    /// Value of the property named `name` in the list. Records are matched on the key id,
+    /// only the value found is decoded.
+    pub fn find_value(&mut self, prop_id: u64, name: &str) -> GraphResult<Option<PropertyValue>> {
+        let key_id = match self.get_key_id(name)? {
+            Some(key_id) => key_id,
+            None => return Ok(None),
+        };
+        let mut curr_id = prop_id;
+        while curr_id != 0 {
+            let pr = self.prop_store.load(curr_id)?;
+            if pr.key_id == key_id {
+                return Ok(Some(self.make_value(&pr)?));
             }
-        });
-        let key_id = prop.get_name().as_ref().and_then(|key| {
-            self.dyn_store.save_data(&key.clone().into_bytes())
-        });
-        value_id.and_then(|v_id| {
-            key_id.and_then(|key_id| {
-                let mut block = [0u8; 24];
-                let beg = 0;
-                let end = beg + std::mem::size_of::<u64>();
-                block[beg..end].copy_from_slice(&v_id.to_be_bytes());
-                map_prop_type(prop).map(|ptype| 
-                    records::PropertyRecord {
-                        in_use: true,
-                        key_inlined: false,
-                        full_inlined: false,
-                        has_next: false,
-                        prop_type: ptype,
-                        key_id: key_id,
-                        prop_block: block,
-                        next_prop_id: 0,
-                    })
-            })
-        })
+            curr_id = pr.next_prop_id;
+        }
+        Ok(None)
     }
 
-    fn make_key_inlined_record(&mut self, prop: &Property) -> Option<records::PropertyRecord> {
-        is_key_inlined(prop).and_then(|key| {
-            if key {
-                let value_id = prop.get_value().as_ref().and_then(|val| {
-                    match val {
-                        PropertyValue::PString(sval) => self.dyn_store.save_data(&sval.clone().into_bytes()),
-                        PropertyValue::PInteger(ival) => self.dyn_store.save_data(&ival.to_be_bytes()),
-                        PropertyValue::PFloat(fval) => self.dyn_store.save_data(&fval.to_be_bytes()),
-                        PropertyValue::PBool(bval) => self.dyn_store.save_data(&[*bval as u8]),
-                    }
-                });
+    /// Values that fit in the 24 bytes block are inlined, others are written to the dyn store.
+    /// Keys are always stored once in the keys dictionary and referenced by id.
+    fn make_record(&mut self, prop: &Property) -> GraphResult<records::PropertyRecord> {
+        let incomplete = || GraphError::InvalidRequest(String::from("a property needs a name and a value"));
+        let key_id = self.keys_store.get_or_create(prop.get_name().as_ref().ok_or_else(incomplete)?)?;
+        let value = prop.get_value().as_ref().map(value_to_bytes).ok_or_else(incomplete)?;
+        let full_inlined = is_full_inlined(prop).ok_or_else(incomplete)?;
+        let mut block = [0u8; 24];
+        if full_inlined {
+            block[..value.len()].copy_from_slice(&value);
+        } else {
+            let value_id = self.dyn_store.save_data(&value)?;
+            block[..std::mem::size_of::<u64>()].copy_from_slice(&value_id.to_be_bytes());
+        }
+        map_prop_type(prop).map(|ptype|
+            records::PropertyRecord {
+                in_use: true,
+                full_inlined,
+                has_next: false,
+                prop_type: ptype,
+                key_id,
+                prop_block: block,
+                next_prop_id: 0,
+            }).ok_or_else(incomplete)
+    }
 
-                value_id.and_then(|dr_id| {
-                    prop.get_name().as_ref().and_then(|name| {
-                        let mut block = [0u8; 24];
-                        block[..name.len()].copy_from_slice(&name.clone().into_bytes());
-                        let beg = name.len() + 1;
-                        let end = beg + std::mem::size_of::<u64>();
-                        block[beg..end].copy_from_slice(&dr_id.to_be_bytes());
-                        map_prop_type(prop).map(|ptype| 
-                            records::PropertyRecord {
-                                in_use: true,
-                                key_inlined: true,
-                                full_inlined: false,
-                                has_next: false,
-                                prop_type: ptype,
-                                key_id: 0,
-                                prop_block: block,
-                                next_prop_id: 0,
-                            })
-                    })
-                })
-            } else {
-                None
-            }
-        })
-        
+    fn make_value(&mut self, pr: &records::PropertyRecord) -> GraphResult<PropertyValue> {
+        let value = if pr.full_inlined {
+            extract_value(0, pr.prop_type, &pr.prop_block)
+        } else {
+            let data = self.dyn_store.load_data(extract_id(0, &pr.prop_block))?;
+            extract_value(0, pr.prop_type, &data)
+        };
+        value.ok_or_else(|| GraphError::Inconsistent(format!("value of property type {} cannot be decoded", pr.prop_type)))
     }
-    
-    fn make_property(&mut self, pr: &records::PropertyRecord) -> Option<Property> {
+
+    fn make_property(&mut self, pr: &records::PropertyRecord) -> GraphResult<Property> {
         let mut prop = Property::new();
-        if pr.full_inlined {
-            let name_index = extract_string(&pr.prop_block);
-            prop.set_option_name(name_index.1);
-            let key_end = name_index.0;
-            prop.set_value(extract_value(key_end + 1, pr.prop_type, &pr.prop_block));
-        } else if pr.key_inlined {
-            let name_index = extract_string(&pr.prop_block);
-            prop.set_option_name(name_index.1);
-            let value_id = extract_id(name_index.0, &pr.prop_block);
-            let data = self.dyn_store.load_data(value_id)?;
-            prop.set_value(extract_value(0, pr.prop_type, &data));
-        } else {
-            let key = self.dyn_store.load_data(pr.key_id)?;
-            prop.set_option_name(extract_string(&key).1);
-            let value_id = extract_id(0, &pr.prop_block);
-            let data = self.dyn_store.load_data(value_id)?;
-            prop.set_value(extract_value(0, pr.prop_type, &data));
+        prop.set_option_name(self.keys_store.get_name(pr.key_id)?);
+        prop.set_value(Some(self.make_value(pr)?));
+        Ok(prop)
+    }
+
+    pub fn delete_list(&mut self, prop_id: u64) -> GraphResult<()> {
+        let mut curr_id = prop_id;
+        while curr_id != 0 {
+            let pr = self.prop_store.load(curr_id)?;
+            if !pr.full_inlined {
+                self.dyn_store.delete_data(extract_id(0, &pr.prop_block))?;
+            }
+            self.prop_store.delete(curr_id)?;
+            curr_id = pr.next_prop_id;
         }
-        Some(prop)
+        Ok(())
     }
 
-    pub fn load(&mut self, prop_id: u64) -> Option<Property> {
+    pub fn load(&mut self, prop_id: u64) -> GraphResult<Property> {
         let pr = self.prop_store.load(prop_id)?;
         let mut prop = self.make_property(&pr)?;
         prop.set_id(Some(prop_id));
-        Some(prop)
+        Ok(prop)
     }
     
-    pub fn sync(&mut self) {
-        self.prop_store.sync();
-        self.dyn_store.sync();
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.prop_store.sync()?;
+        self.dyn_store.sync()?;
+        self.keys_store.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.prop_store.verify()?;
+        self.dyn_store.verify()?;
+        self.keys_store.verify()
     }
-}
 
-fn extract_string(data: &[u8]) -> (usize, Option<String>) {
-    let mut it = data.iter();
-    let str_end = it.position(|&c| c == b'\0').unwrap_or(data.len());
-    let mut string = Vec::with_capacity(str_end);
-    string.extend_from_slice(&data[0..str_end]);
-    (str_end,  String::from_utf8(string).ok())
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.prop_store.set_cache_capacity(capacity);
+        self.dyn_store.set_cache_capacity(capacity);
+        self.keys_store.set_cache_capacity(capacity);
+    }
+
+    pub fn discard(&mut self) {
+        self.prop_store.discard();
+        self.dyn_store.discard();
+        self.keys_store.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.prop_store.log_sync(wal);
+        self.dyn_store.log_sync(wal);
+        self.keys_store.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.prop_store.mark_clean()?;
+        self.dyn_store.mark_clean()?;
+        self.keys_store.mark_clean()
+    }
 }
 
 fn extract_id(skip: usize, data: &[u8]) -> u64 {
     let mut bytes = [0u8; std::mem::size_of::<u64>()];
-    bytes.copy_from_slice(&data[0..std::mem::size_of::<f64>()]);
+    bytes.copy_from_slice(&data[skip..skip + std::mem::size_of::<u64>()]);
     u64::from_be_bytes(bytes)
 }
 
@@ -246,14 +220,14 @@ fn extract_value(skip: usize, prop_type: u8, data: &[u8]) -> Option<PropertyValu
         String::from_utf8(value).ok().map(|v|PropertyValue::PString(v))
     } else if prop_type == 1 {
         let mut bytes = [0u8; std::mem::size_of::<i64>()];
-        bytes.copy_from_slice(&data[skip..std::mem::size_of::<i64>()]);
+        bytes.copy_from_slice(&data[skip..skip + std::mem::size_of::<i64>()]);
         Some(PropertyValue::PInteger(i64::from_be_bytes(bytes)))
     } else if prop_type == 2 {
         let mut bytes = [0u8; std::mem::size_of::<f64>()];
-        bytes.copy_from_slice(&data[skip..std::mem::size_of::<f64>()]);
+        bytes.copy_from_slice(&data[skip..skip + std::mem::size_of::<f64>()]);
         Some(PropertyValue::PFloat(f64::from_be_bytes(bytes)))
     } else if prop_type == 3 {
-        Some(PropertyValue::PBool(data[skip + 1] > 0))
+        Some(PropertyValue::PBool(data[skip] > 0))
     } else {
         None
     }
@@ -264,15 +238,18 @@ fn extract_value(skip: usize, prop_type: u8, data: &[u8]) -> Option<PropertyValu
 mod test_prop_repo {
     use super::*;
     use super::super::super::test_utils::*;
+    use super::super::super::buf_config::DEFAULT_PAGE_SIZE;
     #[test]
     fn test_save_load_0() {
         let dyn_file = build_file_path_and_rm_old("test_save_load_0", "dyn.db").unwrap();
         let prop_file = build_file_path_and_rm_old("test_save_load_0", "prop.db").unwrap();
-        let mut pr = PropertiesRespository::new(&prop_file, &dyn_file);
+        let keys_file = build_file_path_and_rm_old("test_save_load_0", "keys.db").unwrap();
+        let keys_names_file = build_file_path_and_rm_old("test_save_load_0", "keys-names.db").unwrap();
+        let mut pr = PropertiesRespository::new(&prop_file, &dyn_file, &keys_file, &keys_names_file, DEFAULT_PAGE_SIZE);
         let mut prop = Property::new();
         prop.set_name("qsfsqdf");
         prop.set_value(Some(PropertyValue::PString(String::from("qgkfdgsdf"))));
-        pr.create(&mut prop);
+        pr.create(&mut prop).unwrap();
         let load = pr.load(prop.get_id().unwrap()).unwrap();
         assert_eq!(load.get_name(), prop.get_name());
         assert_eq!(load.get_value(), prop.get_value());
@@ -282,11 +259,13 @@ mod test_prop_repo {
     fn test_save_load_1() {
         let dyn_file = build_file_path_and_rm_old("test_save_load_1", "dyn.db").unwrap();
         let prop_file = build_file_path_and_rm_old("test_save_load_1", "prop.db").unwrap();
-        let mut pr = PropertiesRespository::new(&prop_file, &dyn_file);
+        let keys_file = build_file_path_and_rm_old("test_save_load_1", "keys.db").unwrap();
+        let keys_names_file = build_file_path_and_rm_old("test_save_load_1", "keys-names.db").unwrap();
+        let mut pr = PropertiesRespository::new(&prop_file, &dyn_file, &keys_file, &keys_names_file, DEFAULT_PAGE_SIZE);
         let mut prop = Property::new();
         prop.set_name("qsfsqdfqsdfq");
         prop.set_value(Some(PropertyValue::PString(String::from("qgkfdgsdf"))));
-        pr.create(&mut prop);
+        pr.create(&mut prop).unwrap();
         let load = pr.load(prop.get_id().unwrap()).unwrap();
         assert_eq!(load.get_name(), prop.get_name());
         assert_eq!(load.get_value(), prop.get_value());
@@ -296,13 +275,101 @@ mod test_prop_repo {
     fn test_save_load_2() {
         let dyn_file = build_file_path_and_rm_old("test_save_load_2", "dyn.db").unwrap();
         let props_file = build_file_path_and_rm_old("test_save_load_2", "prop.db").unwrap();
-        let mut pr = PropertiesRespository::new(&props_file, &dyn_file);
+        let keys_file = build_file_path_and_rm_old("test_save_load_2", "keys.db").unwrap();
+        let keys_names_file = build_file_path_and_rm_old("test_save_load_2", "keys-names.db").unwrap();
+        let mut pr = PropertiesRespository::new(&props_file, &dyn_file, &keys_file, &keys_names_file, DEFAULT_PAGE_SIZE);
         let mut prop = Property::new();
         prop.set_name("qsfsqdfqsdfqdhgfdhgdfhgdfhqzerqzerqzregdfqsfdqsfderhryjsrrefqzeqgdsfdfsdrrdsredfsqer");
         prop.set_value(Some(PropertyValue::PString(String::from("qgkfdgsdfqerqzerqzerqzerqzerqzerqzerarthdtrsdqeqtrshsreqsgstreq"))));
-        pr.create(&mut prop);
+        pr.create(&mut prop).unwrap();
         let load = pr.load(prop.get_id().unwrap()).unwrap();
         assert_eq!(load.get_name(), prop.get_name());
         assert_eq!(load.get_value(), prop.get_value());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_delete_list() {
+        let dyn_file = build_file_path_and_rm_old("test_delete_list", "dyn.db").unwrap();
+        let props_file = build_file_path_and_rm_old("test_delete_list", "prop.db").unwrap();
+        let keys_file = build_file_path_and_rm_old("test_delete_list", "keys.db").unwrap();
+        let keys_names_file = build_file_path_and_rm_old("test_delete_list", "keys-names.db").unwrap();
+        let mut pr = PropertiesRespository::new(&props_file, &dyn_file, &keys_file, &keys_names_file, DEFAULT_PAGE_SIZE);
+        let mut props = Vec::new();
+        let mut full = Property::new();
+        full.set_name("name");
+        full.set_value(Some(PropertyValue::PString(String::from("alice"))));
+        props.push(full);
+        let mut long_value = Property::new();
+        long_value.set_name("description");
+        long_value.set_value(Some(PropertyValue::PString(String::from("a rather long description value"))));
+        props.push(long_value);
+        let mut dynamic = Property::new();
+        dynamic.set_name("a_very_long_property_name_stored_in_the_dynamic_store");
+        dynamic.set_value(Some(PropertyValue::PString(String::from("value"))));
+        props.push(dynamic);
+        let id = pr.create_list(&props).unwrap();
+        let loaded = pr.retrieve_list(id).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[1].get_value(), props[1].get_value());
+        assert_eq!(loaded[2].get_name(), props[2].get_name());
+        pr.delete_list(id).unwrap();
+        assert!(pr.retrieve_list(id).is_err());
+        assert!(pr.delete_list(id).is_err());
+    }
+
+    #[test]
+    fn test_scalar_values() {
+        let dyn_file = build_file_path_and_rm_old("test_scalar_values", "dyn.db").unwrap();
+        let props_file = build_file_path_and_rm_old("test_scalar_values", "prop.db").unwrap();
+        let keys_file = build_file_path_and_rm_old("test_scalar_values", "keys.db").unwrap();
+        let keys_names_file = build_file_path_and_rm_old("test_scalar_values", "keys-names.db").unwrap();
+        let mut pr = PropertiesRespository::new(&props_file, &dyn_file, &keys_file, &keys_names_file, DEFAULT_PAGE_SIZE);
+        let mut props = Vec::new();
+        for name in &["age", "key_inlined", "a_dynamic_property_name"] {
+            for value in &[PropertyValue::PInteger(42), PropertyValue::PFloat(4.5), PropertyValue::PBool(true)] {
+                let mut prop = Property::new();
+                prop.set_name(name);
+                prop.set_value(Some(value.clone()));
+                props.push(prop);
+            }
+        }
+        let id = pr.create_list(&props).unwrap();
+        let loaded = pr.retrieve_list(id).unwrap();
+        for (l, p) in loaded.iter().zip(props.iter()) {
+            assert_eq!(l.get_name(), p.get_name());
+            match (l.get_value(), p.get_value()) {
+                (Some(PropertyValue::PFloat(lf)), Some(PropertyValue::PFloat(pf))) => assert_eq!(lf, pf),
+                (lv, pv) => assert_eq!(lv, pv),
+            }
+        }
+    }
+
+    #[test]
+    fn test_shared_keys() {
+        let dyn_file = build_file_path_and_rm_old("test_shared_keys", "dyn.db").unwrap();
+        let props_file = build_file_path_and_rm_old("test_shared_keys", "prop.db").unwrap();
+        let keys_file = build_file_path_and_rm_old("test_shared_keys", "keys.db").unwrap();
+        let keys_names_file = build_file_path_and_rm_old("test_shared_keys", "keys-names.db").unwrap();
+        let (first_id, second_id) = {
+            let mut pr = PropertiesRespository::new(&props_file, &dyn_file, &keys_file, &keys_names_file, DEFAULT_PAGE_SIZE);
+            let mut first = Property::new();
+            first.set_name("name");
+            first.set_value(Some(PropertyValue::PString(String::from("alice"))));
+            pr.create(&mut first).unwrap();
+            let mut second = Property::new();
+            second.set_name("name");
+            second.set_value(Some(PropertyValue::PString(String::from("bob"))));
+            pr.create(&mut second).unwrap();
+            pr.sync().unwrap();
+            (first.get_id().unwrap(), second.get_id().unwrap())
+        };
+        let mut pr = PropertiesRespository::new(&props_file, &dyn_file, &keys_file, &keys_names_file, DEFAULT_PAGE_SIZE);
+        let key_id = pr.get_key_id("name").unwrap().unwrap();
+        assert_eq!(pr.prop_store.load(first_id).unwrap().key_id, key_id);
+        assert_eq!(pr.prop_store.load(second_id).unwrap().key_id, key_id);
+        assert_eq!(pr.load(second_id).unwrap().get_name(), &Some(String::from("name")));
+        assert!(pr.get_key_id("age").unwrap().is_none());
+        assert_eq!(pr.find_value(second_id, "name").unwrap(), Some(PropertyValue::PString(String::from("bob"))));
+        assert_eq!(pr.find_value(second_id, "age").unwrap(), None);
+    }
+}
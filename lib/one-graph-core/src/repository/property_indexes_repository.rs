@@ -0,0 +1,236 @@
+use super::index::{open_index, Index};
+use super::index::key::IndexKey;
+use super::store::token_store::TokenStore;
+use super::records::{RecordsManagerError, RecordsManagerResult};
+use super::wal::*;
+use super::super::model::*;
+use super::super::model::init::IndexKind;
+use super::super::error::*;
+use super::super::config::PAGE_CACHE_CAPACITY;
+use std::collections::HashMap;
+
+pub const DEFINITION_SEPARATOR: char = '\u{1f}';
+
+type IndexDefinition = (ElementKind, String, String);
+
+fn definition_to_token(definition: &IndexDefinition, kind: IndexKind) -> String {
+    format!("{}{sep}{}{sep}{}{sep}{}", definition.0.get_name(), definition.1, definition.2, kind.get_name(), sep = DEFINITION_SEPARATOR)
+}
+
+/// The indexes only tell that one of their nodes could not be loaded.
+fn index_failure() -> GraphError {
+    GraphError::Inconsistent(String::from("a property index node cannot be loaded"))
+}
+
+/// An insert fails when the key does not fit in the index or the index has no room left.
+fn insert_failure() -> GraphError {
+    GraphError::Inconsistent(String::from("a property value cannot be added to its index"))
+}
+
+/// Definitions written before the index kind was stored are B+Trees.
+fn definition_from_token(token: &str) -> Option<(IndexDefinition, IndexKind)> {
+    let mut parts = token.splitn(4, DEFINITION_SEPARATOR);
+    let target = ElementKind::from_name(parts.next()?)?;
+    let definition = (target, String::from(parts.next()?), String::from(parts.next()?));
+    let kind = match parts.next() {
+        Some(name) => IndexKind::from_name(name)?,
+        None => IndexKind::BTree,
+    };
+    Some((definition, kind))
+}
+
+/// User declared indexes on the properties of nodes with a label or relationships with a type.
+/// Definitions are tokens of a catalog store holding the index kind, each index lives in its own file named after its token id.
+/// New indexes get the kind the repository was opened with, existing ones keep theirs.
+/// Indexes only hold the current property values.
+pub struct PropertyIndexesRepository {
+    catalog: TokenStore,
+    index_file_prefix: String,
+    index_kind: IndexKind,
+    definitions: HashMap<IndexDefinition, u64>,
+    indexes: HashMap<u64, Box<dyn Index>>,
+    page_size: usize,
+    cache_capacity: usize,
+    loaded: bool,
+}
+
+impl PropertyIndexesRepository {
+    pub fn new(catalog_file: &str, catalog_names_file: &str, index_file_prefix: &str, page_size: usize, index_kind: IndexKind) -> Self {
+        PropertyIndexesRepository {catalog: TokenStore::new(catalog_file, catalog_names_file, page_size), index_file_prefix: String::from(index_file_prefix),
+            index_kind, definitions: HashMap::new(), indexes: HashMap::new(), page_size, cache_capacity: PAGE_CACHE_CAPACITY, loaded: false}
+    }
+
+    fn open_index(&mut self, index_id: u64, kind: IndexKind) {
+        let file = match kind {
+            IndexKind::BTree => format!("{}{}.db", self.index_file_prefix, index_id),
+            IndexKind::BwTree => format!("{}{}.bw.db", self.index_file_prefix, index_id),
+        };
+        let (page_size, cache_capacity) = (self.page_size, self.cache_capacity);
+        self.indexes.entry(index_id).or_insert_with(|| {
+            let mut index = open_index(kind, &file, page_size);
+            index.set_cache_capacity(cache_capacity);
+            index
+        });
+    }
+
+    fn load_definitions(&mut self) -> GraphResult<()> {
+        if self.loaded {
+            return Ok(());
+        }
+        for (index_id, token) in self.catalog.get_tokens()? {
+            let (definition, kind) = definition_from_token(&token).ok_or_else(|| GraphError::Inconsistent(format!("invalid index definition {}", index_id)))?;
+            self.definitions.insert(definition, index_id);
+            self.open_index(index_id, kind);
+        }
+        self.loaded = true;
+        Ok(())
+    }
+
+    fn get_index_mut(&mut self, target: ElementKind, label: &str, property: &str) -> GraphResult<Option<&mut Box<dyn Index>>> {
+        self.load_definitions()?;
+        let index_id = self.definitions.get(&(target, String::from(label), String::from(property))).copied();
+        Ok(index_id.and_then(move |index_id| self.indexes.get_mut(&index_id)))
+    }
+
+    /// Declares an index, returns false when it already existed.
+    pub fn create_index(&mut self, target: ElementKind, label: &str, property: &str) -> GraphResult<bool> {
+        self.load_definitions()?;
+        let definition = (target, String::from(label), String::from(property));
+        if self.definitions.contains_key(&definition) {
+            return Ok(false);
+        }
+        let kind = self.index_kind;
+        let index_id = self.catalog.get_or_create(&definition_to_token(&definition, kind))?;
+        self.definitions.insert(definition, index_id);
+        self.open_index(index_id, kind);
+        Ok(true)
+    }
+
+    pub fn has_indexes(&mut self) -> GraphResult<bool> {
+        self.load_definitions()?;
+        Ok(!self.definitions.is_empty())
+    }
+
+    pub fn insert(&mut self, target: ElementKind, labels: &[String], props: &[Property], id: u64) -> GraphResult<()> {
+        if !self.has_indexes()? {
+            return Ok(());
+        }
+        for label in labels {
+            for prop in props {
+                if let (Some(name), Some(value)) = (prop.get_name(), prop.get_value()) {
+                    if let Some(index) = self.get_index_mut(target, label, name)? {
+                        index.insert(&IndexKey::from_value(value).encode(), id).ok_or_else(insert_failure)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, target: ElementKind, labels: &[String], props: &[Property], id: u64) -> GraphResult<()> {
+        if !self.has_indexes()? {
+            return Ok(());
+        }
+        for label in labels {
+            for prop in props {
+                if let (Some(name), Some(value)) = (prop.get_name(), prop.get_value()) {
+                    if let Some(index) = self.get_index_mut(target, label, name)? {
+                        index.remove(&IndexKey::from_value(value).encode(), id).ok_or_else(index_failure)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ids having the property equal to `value`, None when the property is not indexed.
+    pub fn search(&mut self, target: ElementKind, label: &str, property: &str, value: &PropertyValue) -> GraphResult<Option<Vec<u64>>> {
+        Ok(self.get_index_mut(target, label, property)?.map(|index| index.search(&IndexKey::from_value(value).encode()).unwrap_or_default()))
+    }
+
+    /// Ids whose property may be in the range, bounds are always included so the caller still checks them.
+    /// None when the property is not indexed or the range is unbounded.
+    pub fn range(&mut self, target: ElementKind, label: &str, range: &PropertyRange) -> GraphResult<Option<Vec<u64>>> {
+        let (lower, upper) = match (range.get_lower(), range.get_upper()) {
+            (Some((lower, _)), Some((upper, _))) => (IndexKey::from_value(lower).encode(), IndexKey::from_value(upper).encode()),
+            (Some((lower, _)), None) => (IndexKey::from_value(lower).encode(), IndexKey::from_value(lower).type_bounds().1),
+            (None, Some((upper, _))) => (IndexKey::from_value(upper).type_bounds().0, IndexKey::from_value(upper).encode()),
+            (None, None) => return Ok(None),
+        };
+        match self.get_index_mut(target, label, range.get_name())? {
+            Some(index) => Ok(Some(index.range(Some(&lower), Some(&upper), true).ok_or_else(index_failure)?.into_iter().map(|(_, id)| id).collect())),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes every entry of the indexes that are not logged and returns their definitions so that they are filled again.
+    pub fn clear_unlogged_indexes(&mut self) -> GraphResult<Vec<(ElementKind, String, String)>> {
+        self.load_definitions()?;
+        let mut res = Vec::new();
+        for (definition, index_id) in &self.definitions {
+            let index = match self.indexes.get_mut(index_id) {
+                Some(index) if !index.is_logged() => index,
+                _ => continue,
+            };
+            for (key, id) in index.range(None, None, true).ok_or_else(index_failure)? {
+                index.remove(&key, id).ok_or_else(index_failure)?;
+            }
+            res.push(definition.clone());
+        }
+        Ok(res)
+    }
+
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.catalog.sync()?;
+        for index in self.indexes.values_mut() {
+            index.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Also opens the declared indexes so that all of their pages are checked.
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.catalog.verify()?;
+        if self.load_definitions().is_err() {
+            return Err(RecordsManagerError::NotFound);
+        }
+        for index in self.indexes.values_mut() {
+            index.verify()?;
+        }
+        Ok(())
+    }
+
+    /// Also applies to the indexes opened later.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache_capacity = capacity;
+        self.catalog.set_cache_capacity(capacity);
+        for index in self.indexes.values_mut() {
+            index.set_cache_capacity(capacity);
+        }
+    }
+
+    pub fn discard(&mut self) {
+        self.catalog.discard();
+        for index in self.indexes.values_mut() {
+            index.discard();
+        }
+        self.definitions.clear();
+        self.indexes.clear();
+        self.loaded = false;
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.catalog.log_sync(wal);
+        for index in self.indexes.values_mut() {
+            index.log_sync(wal);
+        }
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.catalog.mark_clean()?;
+        for index in self.indexes.values_mut() {
+            index.mark_clean()?;
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
 use super::super::buf_config::*;
+use super::super::model::check::Problem;
+use super::super::error::GraphError;
 
 use super::pager::*;
+use super::wal::*;
+use std::collections::HashSet;
+use log::error;
 
 pub type RecordId = u64;
 pub type PageRecordId = usize;
@@ -8,6 +13,39 @@ pub type PageRecordId = usize;
 #[derive(Debug, Clone)]
 pub enum RecordsManagerError {
     NotFound,
+    /// A page of the file, named with its id, failed its checksum.
+    Corrupted(String, PageId),
+    Io(String),
+    /// The file has a page or record geometry this build cannot use, with the reason.
+    Incompatible(String, String),
+    /// The storage of the file has no room left for another record.
+    Full(String),
+}
+
+impl From<PagerError> for RecordsManagerError {
+    fn from(error: PagerError) -> Self {
+        match error {
+            PagerError::Corrupted(file, page_id) => RecordsManagerError::Corrupted(file, page_id),
+            PagerError::Io(cause) => RecordsManagerError::Io(cause),
+            PagerError::Incompatible(file, reason) => RecordsManagerError::Incompatible(file, reason),
+            PagerError::PageNotFound(_) => RecordsManagerError::NotFound,
+            PagerError::Full(file) => RecordsManagerError::Full(file),
+        }
+    }
+}
+
+/// A record missing below the graph layer is one linked from another record,
+/// the callers looking up ids given by the user report them as not found themselves.
+impl From<RecordsManagerError> for GraphError {
+    fn from(error: RecordsManagerError) -> Self {
+        match error {
+            RecordsManagerError::NotFound => GraphError::Inconsistent(String::from("a linked record is missing")),
+            RecordsManagerError::Corrupted(file, page_id) => GraphError::Corrupted(file, page_id),
+            RecordsManagerError::Io(cause) => GraphError::Io(cause),
+            RecordsManagerError::Incompatible(file, reason) => GraphError::Incompatible(file, reason),
+            RecordsManagerError::Full(file) => GraphError::Io(format!("{}: no room left for another record", file)),
+        }
+    }
 }
 
 pub type RecordsManagerResult<T> = std::result::Result<T, RecordsManagerError>;
@@ -21,11 +59,34 @@ struct RecordLocation {
     is_multi_pages_record: bool,
 }
 
-pub struct RecordsManager {
-    pager: Pager,
+pub struct RecordsManager<P: PageProvider = Pager> {
+    pager: P,
     record_size: usize,
     nb_records_per_page: usize,
     page_map: PageMap,
+    layout_error: Option<RecordsManagerError>,
+}
+
+/// Used records in id order, see `RecordsManager::scan`.
+pub struct RecordsScan<'a, P: PageProvider> {
+    records_manager: &'a mut RecordsManager<P>,
+    next_id: RecordId,
+    max_id: RecordId,
+}
+
+impl <'a, P: PageProvider> Iterator for RecordsScan<'a, P> {
+    type Item = (RecordId, Vec<u8>);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next_id <= self.max_id {
+            let id = self.next_id;
+            self.next_id += 1;
+            let mut data = vec![0; self.records_manager.record_size];
+            if self.records_manager.load(id, &mut data).is_ok() {
+                return Some((id, data));
+            }
+        }
+        None
+    }
 }
 
 pub struct HeaderPageWrapper<'a> {
@@ -41,10 +102,10 @@ impl <'a> HeaderPageWrapper<'a> {
     }
 
     fn get_header_slice_ref(&'a self, bounds: Bounds) -> &'a [u8] {
-        &self.header_page.data[bounds.begin..bounds.end]
+        &self.header_page.get_data_ref()[bounds.begin..bounds.end]
     }
     fn get_header_slice_mut(&'a mut self, bounds: Bounds) -> &'a mut [u8] {
-        &mut self.header_page.data[bounds.begin..bounds.end]
+        &mut self.header_page.get_data_mut()[bounds.begin..bounds.end]
     }
     
     pub fn get_header_payload_slice_mut(&'a mut self) -> &'a mut [u8] {
@@ -84,6 +145,24 @@ impl <'a> HeaderPageWrapper<'a> {
         let bounds = self.page_map.header_page_records_version_counter;
         self.get_header_slice_mut(bounds).copy_from_slice(&id.to_be_bytes());
     }
+
+    fn get_header_layout(&self) -> RecordsLayout {
+        let fields = self.get_header_slice_ref(self.page_map.header_page_layout);
+        let field = |index: usize| {
+            let mut bytes = [0u8; LAYOUT_FIELD_SIZE];
+            bytes.copy_from_slice(&fields[index * LAYOUT_FIELD_SIZE..(index + 1) * LAYOUT_FIELD_SIZE]);
+            u32::from_be_bytes(bytes) as usize
+        };
+        RecordsLayout{page_size: self.header_page.get_page_size(), record_size: field(0), nb_records_per_page: field(1), nb_pages_per_record: field(2)}
+    }
+
+    fn set_header_layout(&'a mut self, layout: &RecordsLayout) {
+        let bounds = self.page_map.header_page_layout;
+        let fields = self.get_header_slice_mut(bounds);
+        for (index, value) in [layout.record_size, layout.nb_records_per_page, layout.nb_pages_per_record].iter().enumerate() {
+            fields[index * LAYOUT_FIELD_SIZE..(index + 1) * LAYOUT_FIELD_SIZE].copy_from_slice(&(*value as u32).to_be_bytes());
+        }
+    }
 }
 
 struct RecordPageWrapper<'a> {
@@ -102,10 +181,11 @@ impl <'a> RecordPageWrapper<'a> {
         HeaderPageWrapper::new(&mut self.page.header_page, self.page_map)
     }
     fn has_next_page_record(&self) -> bool {
-        self.page.data[0] & MULTI_PAGE_RECORD_FLAG == 1
+        self.page.get_data_ref()[self.page_map.header_flags.begin] & MULTI_PAGE_RECORD_FLAG != 0
     }
     fn set_page_in_use(&mut self) {
-        self.page.data[0] = self.page.data[0] | IS_FREE_PAGE_FLAG;
+        let flags = self.page_map.header_flags.begin;
+        self.page.get_data_mut()[flags] |= IS_FREE_PAGE_FLAG;
     }
     fn get_free_next_page_ptr(&self) -> PageId {
         let mut bytes = [0u8; NEXT_PAGE_PTR];
@@ -113,14 +193,14 @@ impl <'a> RecordPageWrapper<'a> {
         u64::from_be_bytes(bytes)
     }
     fn set_free_next_page_ptr(&mut self, id: u64) {
-        let bounds = self.page_map.free_list_len;
+        let bounds = self.page_map.next_free_page_ptr;
         self.get_slice_mut(bounds).copy_from_slice(&id.to_be_bytes());
     }
     fn get_slice_ref(&self, bounds: Bounds) -> &[u8] {
-        &self.page.data[bounds.begin..bounds.end]
+        &self.page.get_data_ref()[bounds.begin..bounds.end]
     }
     fn get_slice_mut(&mut self, bounds: Bounds) -> &mut [u8] {
-        &mut self.page.data[bounds.begin..bounds.end]
+        &mut self.page.get_data_mut()[bounds.begin..bounds.end]
     }
     fn get_free_list_len(&self) -> usize {
         let mut bytes = [0u8; FREE_LIST_ITEM_COUNTER_SIZE];
@@ -168,7 +248,7 @@ impl <'a> RecordPageWrapper<'a> {
         free_records.push(page_record_id);
         free_records.sort();
         self.set_free_list_len(free_records.len());
-        let mut bounds = self.page_map.free_list.shift(FREE_LIST_PTR_SIZE);
+        let mut bounds = self.page_map.free_list.sub(0, FREE_LIST_PTR_SIZE);
         for free_rec_id in free_records {
             self.get_slice_mut(bounds).copy_from_slice(&(free_rec_id as u32).to_be_bytes());
             bounds = bounds.shift(FREE_LIST_PTR_SIZE);
@@ -177,6 +257,9 @@ impl <'a> RecordPageWrapper<'a> {
     fn is_page_free_list_empty(&self) -> bool {
         self.get_free_list_len() == 0
     }
+    fn is_record_free(&self, page_record_id: PageRecordId) -> bool {
+        self.get_page_free_list().contains(&page_record_id)
+    }
 }
 
 const MULTI_PAGE_RECORD_FLAG: u8 = 0b1000_0000;
@@ -220,36 +303,57 @@ struct PageMap {
     header_page_free_list_ptr: Bounds,
     header_page_records_counter: Bounds,
     header_page_records_version_counter: Bounds,
+    header_page_layout: Bounds,
     header_page_payload: Bounds,
 }
 
-const fn max_nb_records(record_size: usize) -> usize {
-    (PAGE_SIZE - HEADER_SIZE) / record_size
-}
-
-const fn compute_unused_page_size(record_size: usize) -> usize {
-    (PAGE_SIZE - HEADER_SIZE) % record_size
+/// Geometry of a records file, written in its header page when the file is created
+/// and checked against the record size expected by the store when it is opened.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RecordsLayout {
+    pub page_size: usize,
+    pub record_size: usize,
+    pub nb_records_per_page: usize,
+    pub nb_pages_per_record: usize,
 }
 
 const fn compute_freelist_size(free_list_capacity: usize) -> usize {
     FREE_LIST_PTR_SIZE * free_list_capacity
 }
-const fn compute_nb_pages_per_record(record_size: usize, page_payload_size: usize) -> usize {
-    record_size / page_payload_size
+
+impl RecordsLayout {
+    /// Packs as many records as possible in a page along with their free list,
+    /// a record larger than the page payload spans several pages.
+    pub fn new(page_size: usize, record_size: usize) -> Self {
+        let page_payload_size = page_size - HEADER_SIZE;
+        let mut nb_records_per_page = page_payload_size / record_size;
+        while nb_records_per_page > 0 && page_payload_size - nb_records_per_page * record_size < compute_freelist_size(nb_records_per_page) {
+            nb_records_per_page -= 1;
+        }
+        let nb_pages_per_record = if nb_records_per_page > 0 {
+            0
+        } else {
+            (record_size + page_payload_size - 1) / page_payload_size
+        };
+        RecordsLayout{page_size, record_size, nb_records_per_page, nb_pages_per_record}
+    }
 }
 
-fn compute_page_map(nb_records_per_page: usize, nb_pages_per_record: usize) -> PageMap {
+fn compute_page_map(layout: &RecordsLayout) -> PageMap {
+    let nb_records_per_page = layout.nb_records_per_page;
+    let nb_pages_per_record = layout.nb_pages_per_record;
     let free_list_size = compute_freelist_size(nb_records_per_page);
     //TODO handle all cases
-    let header_flags_bounds = Bounds::new(0, HEADER_FLAGS);
+    let header_flags_bounds = Bounds::new(PAGE_CHECKSUM_SIZE, PAGE_CHECKSUM_SIZE + HEADER_FLAGS);
     let next_free_page_ptr_bounds = header_flags_bounds.shift(NEXT_FREE_PAGE_PTR);
     let free_list_len = next_free_page_ptr_bounds.shift(FREE_LIST_ITEM_COUNTER_SIZE);
     let free_list_bounds = free_list_len.shift(free_list_size);
-    let payload_bounds = Bounds::new(free_list_bounds.end, PAGE_SIZE);
-    let header_page_free_list_ptr_bounds = Bounds::new(PAGE_COUNTER_SIZE, PAGE_COUNTER_SIZE + FIRST_FREE_PAGE_PTR);
+    let payload_bounds = Bounds::new(free_list_bounds.end, layout.page_size);
+    let header_page_free_list_ptr_bounds = Bounds::from_offset_and_len(PAGER_HEADER_SIZE, FIRST_FREE_PAGE_PTR);
     let header_page_records_counter_bounds = header_page_free_list_ptr_bounds.shift(RECORDS_COUNTER_SIZE);
     let header_page_records_version_counter_bounds = header_page_records_counter_bounds.shift(RECORDS_COUNTER_SIZE);
-    let header_page_payload_bounds = Bounds::new(header_page_records_counter_bounds.end, PAGE_SIZE);
+    let header_page_layout_bounds = header_page_records_version_counter_bounds.shift(3 * LAYOUT_FIELD_SIZE);
+    let header_page_payload_bounds = Bounds::new(header_page_layout_bounds.end, layout.page_size);
     PageMap{
         header_flags: header_flags_bounds,
         next_free_page_ptr: next_free_page_ptr_bounds,
@@ -263,6 +367,7 @@ fn compute_page_map(nb_records_per_page: usize, nb_pages_per_record: usize) -> P
         header_page_free_list_ptr: header_page_free_list_ptr_bounds,
         header_page_records_counter: header_page_records_counter_bounds,
         header_page_records_version_counter: header_page_records_version_counter_bounds,
+        header_page_layout: header_page_layout_bounds,
         header_page_payload: header_page_payload_bounds,
     }
 }
@@ -284,14 +389,67 @@ fn copy_buffer_to_payload(payload: &mut [u8], data: &[u8]) {
 }
 
 impl RecordsManager {
-    pub fn new(file: &str, record_size: usize, nb_records_per_page: usize, nb_pages_per_record: usize) -> Self {
-        RecordsManager{pager: Pager::new(file), record_size: record_size, nb_records_per_page: nb_records_per_page, page_map: compute_page_map(nb_records_per_page, nb_pages_per_record)}
+    /// `page_size` is only used when the file is created, an existing file keeps its own page size.
+    pub fn new(file: &str, record_size: usize, page_size: usize) -> Self {
+        RecordsManager::with_page_provider(Pager::new(file, page_size), record_size)
+    }
+
+    pub fn with_cache_capacity(file: &str, record_size: usize, page_size: usize, cache_capacity: usize) -> Self {
+        RecordsManager::with_page_provider(Pager::with_capacity(file, page_size, cache_capacity), record_size)
+    }
+}
+
+impl <P: PageProvider> RecordsManager<P> {
+    /// Operations on a file whose layout does not match `record_size` fail with `RecordsManagerError::Incompatible`.
+    pub fn with_page_provider(pager: P, record_size: usize) -> Self {
+        let layout = RecordsLayout::new(pager.get_page_size(), record_size);
+        let mut records_manager = RecordsManager{pager, record_size, nb_records_per_page: layout.nb_records_per_page,
+            page_map: compute_page_map(&layout), layout_error: None};
+        records_manager.layout_error = match records_manager.pager.get_header_error() {
+            Some(e) => Some(e.into()),
+            None => records_manager.check_layout(&layout),
+        };
+        records_manager
+    }
+
+    /// Sets the layout in the header page of a new file, the next commit writes it with the other pages
+    /// and discarding the changes sets it again. A file with records but no layout predates it.
+    fn check_layout(&mut self, layout: &RecordsLayout) -> Option<RecordsManagerError> {
+        let file = String::from(self.pager.get_file());
+        let is_empty = self.is_empty();
+        let mut header = self.get_header_page_wrapper();
+        let stored = header.get_header_layout();
+        if stored.record_size == 0 && is_empty {
+            header.set_header_layout(layout);
+            return None;
+        }
+        let reason = if stored.record_size == 0 {
+            String::from("the file has no records layout")
+        } else if stored != *layout {
+            format!("records of {} bytes with {} per page, expected {} bytes with {} per page",
+                stored.record_size, stored.nb_records_per_page, layout.record_size, layout.nb_records_per_page)
+        } else {
+            return None;
+        };
+        error!("{} {}", file, reason);
+        Some(RecordsManagerError::Incompatible(file, reason))
+    }
+
+    fn check_error(&self) -> RecordsManagerResult<()> {
+        match &self.layout_error {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_layout(&mut self) -> RecordsLayout {
+        self.get_header_page_wrapper().get_header_layout()
     }
 
     fn compute_location(&self, record_id: u64) -> RecordLocation {
         let record_ptr = record_id - 1;
         let page_payload_size = self.page_map.payload.len();
-        let nb_records_per_page = page_payload_size / self.record_size;
+        let nb_records_per_page = self.nb_records_per_page;
         if self.page_map.is_multi_page_record {
             let nb_pages_per_record = self.record_size / page_payload_size;
             RecordLocation{
@@ -315,13 +473,14 @@ impl RecordsManager {
     }
 
     pub fn load(&mut self, id: RecordId, data: &mut [u8]) -> RecordsManagerResult<()> {
+        self.check_error()?;
         let location = self.compute_location(id);
         if location.is_multi_pages_record {
             let mut page_count: usize = 0;
             let mut has_next_page = true;
             while has_next_page {
                 let payload_bounds = self.page_map.payload;
-                let rpage = self.load_page_wrapper(location.page_id + page_count as u64).ok_or(RecordsManagerError::NotFound)?;
+                let rpage = self.load_page_wrapper(location.page_id + page_count as u64)?;
                 let payload = rpage.get_slice_ref(payload_bounds);
                 copy_payload_to_buffer(&mut data[page_count*payload_bounds.len()..], payload);
                 page_count += 1;
@@ -331,7 +490,10 @@ impl RecordsManager {
         } else {
             let payload_bounds = self.page_map.payload;
             let record_size = self.record_size;
-            let rpage = self.load_page_wrapper(location.page_id).ok_or(RecordsManagerError::NotFound)?;
+            let rpage = self.load_page_wrapper(location.page_id)?;
+            if rpage.is_record_free(location.record_id_in_page) {
+                return Err(RecordsManagerError::NotFound);
+            }
             let payload = rpage.get_slice_ref(payload_bounds);
             data.copy_from_slice(&payload[location.payload_record_address..location.payload_record_address+record_size]);
             Ok(())
@@ -339,33 +501,31 @@ impl RecordsManager {
     }
 
     pub fn save(&mut self, id: RecordId, data: &[u8]) -> RecordsManagerResult<()> {
+        self.check_error()?;
         let location = self.compute_location(id);
         let payload_bounds = self.page_map.payload;
         let nb_pages_per_record = self.page_map.nb_pages_per_record;
         let record_size = self.record_size;
         if location.is_multi_pages_record {
-            let mut wrapper = self.load_page_wrapper(location.page_id).ok_or(RecordsManagerError::NotFound)?;
+            let mut wrapper = self.load_page_wrapper(location.page_id)?;
             let next_free_page_ptr = wrapper.get_free_next_page_ptr();
             wrapper.get_header_page_wrapper().set_header_first_free_page_ptr(next_free_page_ptr);
             for page_count in 0..nb_pages_per_record {
                 copy_buffer_to_payload(wrapper.get_slice_mut(payload_bounds), &data[page_count*payload_bounds.len()..]);
             }
         } else {
-            let mut wrapper = self.load_page_wrapper(location.page_id).ok_or(RecordsManagerError::NotFound)?;
+            let mut wrapper = self.load_page_wrapper(location.page_id)?;
             wrapper.get_slice_mut(payload_bounds.sub(location.record_id_in_page * record_size, record_size)).copy_from_slice(&data);
-            if wrapper.is_page_free_list_empty() {
-                let next_free_page_ptr = wrapper.get_free_next_page_ptr();
-                wrapper.get_header_page_wrapper().set_header_first_free_page_ptr(next_free_page_ptr);
-            }
         }
-        self.increment_records_version_counter();
         Ok(())
     }
 
-    fn increment_records_version_counter(&mut self) {
-        let header = self.pager.get_header_page_mut();
-        let mut wrapper = HeaderPageWrapper::new(header, self.page_map);
-        wrapper.set_header_records_counter(wrapper.get_header_records_version_counter() + 1);
+    pub fn get_version(&mut self) -> u64 {
+        self.get_header_page_wrapper().get_header_records_version_counter()
+    }
+
+    pub fn set_version(&mut self, version: u64) {
+        self.get_header_page_wrapper().set_header_records_version_counter(version);
     }
 
     fn increment_records_counter(&mut self) {
@@ -381,6 +541,7 @@ impl RecordsManager {
     }
 
     pub fn create(&mut self, data: &[u8]) -> RecordsManagerResult<RecordId> {
+        self.check_error()?;
         let record_size = self.record_size;
         let nb_pages_per_record = self.page_map.nb_pages_per_record;
         let nb_records_per_page = self.page_map.nb_records_per_page;
@@ -392,7 +553,7 @@ impl RecordsManager {
             if is_multi_page_record {
                 let mut first = true;
                 for page_count in 0..nb_pages_per_record {
-                    let new_page = self.pager.append();
+                    let new_page = self.pager.append()?;
                     let mut wrapper = RecordPageWrapper::new(new_page, self.page_map);
                     if first {
                         let first_page_id = wrapper.get_id();
@@ -403,7 +564,7 @@ impl RecordsManager {
                     copy_buffer_to_payload(wrapper.get_slice_mut(payload_bounds), &data[page_count*payload_bounds.len()..]);
                 }
             } else {
-                let new_page = self.pager.append();
+                let new_page = self.pager.append()?;
                 let mut wrapper = RecordPageWrapper::new(new_page, self.page_map);
                 wrapper.init_page_free_list();
                 let page_id = wrapper.get_id();
@@ -419,7 +580,7 @@ impl RecordsManager {
             }
         } else {
             if is_multi_page_record {
-                let mut wrapper = self.load_page_wrapper(first_free_page_ptr).ok_or(RecordsManagerError::NotFound)?;
+                let mut wrapper = self.load_page_wrapper(first_free_page_ptr)?;
                 let next_free_page_ptr = wrapper.get_free_next_page_ptr();
                 wrapper.get_header_page_wrapper().set_header_first_free_page_ptr(next_free_page_ptr);
                 let mut first = true;
@@ -431,7 +592,7 @@ impl RecordsManager {
                     copy_buffer_to_payload(wrapper.get_slice_mut(payload_bounds), &data[page_count*payload_bounds.len()..]);
                 }
             } else {
-                let mut wrapper = self.load_page_wrapper(first_free_page_ptr).ok_or(RecordsManagerError::NotFound)?;
+                let mut wrapper = self.load_page_wrapper(first_free_page_ptr)?;
                 let page_record_id = wrapper.pop_free_list_item().ok_or(RecordsManagerError::NotFound)?;
                 record_id = (wrapper.page.id - 1) * nb_records_per_page as u64 + page_record_id as u64;
                 wrapper.get_slice_mut(payload_bounds.sub(page_record_id * record_size, record_size)).copy_from_slice(&data);
@@ -443,22 +604,36 @@ impl RecordsManager {
                 
             }
         }
-        self.increment_records_version_counter();
         self.increment_records_counter();
         Ok(record_id + 1)
     }
 
+    /// Appends pages whose records are all in use until the store holds the record `id`, returns the ids of the appended records.
+    /// Lets a migration write records at the ids they had in another store, the ids it does not write have to be deleted.
+    pub fn extend_to(&mut self, id: RecordId) -> RecordsManagerResult<std::ops::RangeInclusive<RecordId>> {
+        self.check_error()?;
+        if self.page_map.is_multi_page_record {
+            return Err(RecordsManagerError::Incompatible(String::from(self.pager.get_file()), String::from("records span several pages")));
+        }
+        let first_id = self.get_max_record_id() + 1;
+        while self.get_max_record_id() < id {
+            let new_page = self.pager.append()?;
+            let mut wrapper = RecordPageWrapper::new(new_page, self.page_map);
+            wrapper.set_free_list_len(0);
+        }
+        let last_id = self.get_max_record_id();
+        let mut header = self.get_header_page_wrapper();
+        header.set_header_records_counter(header.get_header_records_counter() + last_id + 1 - first_id);
+        Ok(first_id..=last_id)
+    }
+
     fn make_page_wrapper<'a>(&self, page: Page<'a>) -> RecordPageWrapper<'a> {
         RecordPageWrapper::new(page, self.page_map)
     }
 
-    fn load_page_wrapper(&mut self, id: PageId) -> Option<RecordPageWrapper> {
-        let page = self.pager.load_page(id);
-        if let Some(p) = page {
-            Some(RecordPageWrapper::new(p, self.page_map))
-        } else {
-            None
-        }
+    fn load_page_wrapper(&mut self, id: PageId) -> RecordsManagerResult<RecordPageWrapper<'_>> {
+        let page = self.pager.load_page(id)?;
+        Ok(RecordPageWrapper::new(page, self.page_map))
     }
 
     pub fn get_header_page_wrapper(&mut self) -> HeaderPageWrapper {
@@ -466,12 +641,16 @@ impl RecordsManager {
     }
 
     pub fn delete(&mut self, id: RecordId) -> RecordsManagerResult<()> {
-        let free_list_capacity = self.page_map.free_list_capacity;
+        self.check_error()?;
         let loc = self.compute_location(id);
-        let mut rpage = self.load_page_wrapper(loc.page_id).ok_or(RecordsManagerError::NotFound)?;
+        let mut rpage = self.load_page_wrapper(loc.page_id)?;
         let mut append_page_to_free_list = true;
         if !loc.is_multi_pages_record {
-            if rpage.get_free_list_len() != free_list_capacity {
+            if rpage.is_record_free(loc.record_id_in_page) {
+                return Err(RecordsManagerError::NotFound);
+            }
+            //a page with free records is already linked in the free pages list
+            if !rpage.is_page_free_list_empty() {
                 append_page_to_free_list = false;
             }
             rpage.append_free_list_item(loc.record_id_in_page);
@@ -486,7 +665,6 @@ impl RecordsManager {
             rpage.get_header_page_wrapper().set_header_first_free_page_ptr(loc.page_id);
         }
         self.decrement_records_counter();
-        self.increment_records_version_counter();
         Ok(())
     }
 
@@ -494,11 +672,127 @@ impl RecordsManager {
         self.page_map.payload.len()
     }
 
+    pub fn get_max_record_id(&mut self) -> RecordId {
+        let page_count = self.get_header_page_wrapper().header_page.get_page_count();
+        if self.page_map.is_multi_page_record {
+            page_count / self.page_map.nb_pages_per_record as u64
+        } else {
+            page_count * self.nb_records_per_page as u64
+        }
+    }
+
+    /// Iterates the records up to the last allocated page, skipping the free records of single page stores.
+    pub fn scan(&mut self) -> RecordsScan<'_, P> {
+        let max_id = self.get_max_record_id();
+        RecordsScan{records_manager: self, next_id: 1, max_id}
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.pager.set_capacity(capacity);
+    }
+
+    /// Loads every page so that each one read from storage is checked against its checksum.
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.check_error()?;
+        let page_count = self.get_header_page_wrapper().header_page.get_page_count();
+        for page_id in 1..=page_count {
+            self.pager.load_page(page_id)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the page checksums and the free space bookkeeping of single page records stores.
+    /// Only the records counter can be repaired, problems come with whether they were repaired.
+    pub fn check_pages(&mut self, repair: bool) -> Vec<(Problem, bool)> {
+        match &self.layout_error {
+            Some(RecordsManagerError::Incompatible(_, reason)) => return vec![(Problem::UnusableHeader(reason.clone()), false)],
+            Some(_) => return vec![(Problem::CorruptedPage(HEADER_PAGE_ID), false)],
+            None => {},
+        }
+        let mut problems = Vec::new();
+        let page_count = self.get_header_page_wrapper().header_page.get_page_count();
+        let nb_records_per_page = self.page_map.nb_records_per_page;
+        let free_list_capacity = self.page_map.free_list_capacity;
+        let is_multi_page_record = self.page_map.is_multi_page_record;
+        let mut free_pages = HashSet::new();
+        let mut used = 0;
+        for page_id in 1..=page_count {
+            let wrapper = match self.load_page_wrapper(page_id) {
+                Ok(wrapper) => wrapper,
+                Err(_) => {
+                    problems.push((Problem::CorruptedPage(page_id), false));
+                    continue;
+                }
+            };
+            if is_multi_page_record {
+                continue;
+            }
+            if wrapper.get_free_list_len() > free_list_capacity {
+                problems.push((Problem::InvalidFreeList(page_id), false));
+                continue;
+            }
+            let free_list = wrapper.get_page_free_list();
+            let free_slots = free_list.iter().copied().collect::<HashSet<PageRecordId>>();
+            if free_slots.len() != free_list.len() || free_slots.iter().any(|slot| *slot >= nb_records_per_page) {
+                problems.push((Problem::InvalidFreeList(page_id), false));
+                continue;
+            }
+            used += (nb_records_per_page - free_slots.len()) as u64;
+            if !free_slots.is_empty() {
+                free_pages.insert(page_id);
+            }
+        }
+        if is_multi_page_record {
+            return problems;
+        }
+
+        let mut chained = HashSet::new();
+        let mut page_id = self.get_header_page_wrapper().get_header_first_free_page_ptr();
+        while page_id != 0 {
+            if !free_pages.contains(&page_id) || !chained.insert(page_id) {
+                problems.push((Problem::BrokenFreePagesChain(page_id), false));
+                break;
+            }
+            page_id = match self.load_page_wrapper(page_id) {
+                Ok(wrapper) => wrapper.get_free_next_page_ptr(),
+                Err(_) => 0,
+            };
+        }
+        let mut unlinked = free_pages.difference(&chained).copied().collect::<Vec<PageId>>();
+        unlinked.sort();
+        problems.extend(unlinked.into_iter().map(|page_id| (Problem::UnlinkedFreePage(page_id), false)));
+
+        let stored = self.get_header_page_wrapper().get_header_records_counter();
+        if stored != used {
+            if repair {
+                self.get_header_page_wrapper().set_header_records_counter(used);
+            }
+            problems.push((Problem::WrongRecordsCounter{stored, counted: used}, repair));
+        }
+        problems
+    }
+
     pub fn is_empty(&mut self) -> bool {
         self.get_header_page_wrapper().header_page.get_page_count() == 0
     }
 
-    pub fn sync(&mut self) {
-        self.pager.sync();
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        Ok(self.pager.sync()?)
+    }
+
+    pub fn discard(&mut self) {
+        self.pager.discard();
+        if self.layout_error.is_none() {
+            let layout = RecordsLayout::new(self.pager.get_page_size(), self.record_size);
+            self.layout_error = self.check_layout(&layout);
+        }
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.pager.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        Ok(self.pager.mark_clean()?)
     }
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
 use super::super::super::buf_config::*;
 use super::super::records::*;
+use super::super::super::model::check::Problem;
+use super::super::wal::*;
 use super::records::*;
 
 pub struct DynamicStore {
@@ -7,13 +9,13 @@ pub struct DynamicStore {
 }
 
 impl DynamicStore {
-    pub fn new(file: &str) -> Self {
-        DynamicStore {records_manager: RecordsManager::new(file, DYN_RECORD_SIZE, DYN_NB_RECORDS_PER_PAGE, DYN_NB_PAGES_PER_RECORD)}
+    pub fn new(file: &str, page_size: usize) -> Self {
+        DynamicStore {records_manager: RecordsManager::new(file, DYN_RECORD_SIZE, page_size)}
     }
-    pub fn create(&mut self, dr: &DynamicStoreRecord) -> Option<u64> {
-        self.records_manager.create(&dr_to_bytes(dr)).ok()
+    pub fn create(&mut self, dr: &DynamicStoreRecord) -> RecordsManagerResult<u64> {
+        self.records_manager.create(&dr_to_bytes(dr))
     }
-    pub fn save_data(&mut self, data: &[u8]) -> Option<u64> {
+    pub fn save_data(&mut self, data: &[u8]) -> RecordsManagerResult<u64> {
         let mut count = data.len() / 120;
         let rest = data.len() % 120;
         let mut next = 0u64;
@@ -37,11 +39,11 @@ impl DynamicStore {
                 count -= 1;
             }
         }
-        Some(next)
+        Ok(next)
         
     }
 
-    pub fn load_data(&mut self, id: u64) -> Option<Box<[u8]>> {
+    pub fn load_data(&mut self, id: u64) -> RecordsManagerResult<Box<[u8]>> {
         let mut data = Vec::new();
         let mut next = id;
         let mut has_next = true;
@@ -51,16 +53,63 @@ impl DynamicStore {
             has_next = dr.has_next;
             next = dr.next;
         }
-        Some(data.into_boxed_slice())
+        Ok(data.into_boxed_slice())
     }
 
-    pub fn load(&mut self, dr_id: u64) -> Option<DynamicStoreRecord> {
+    pub fn delete_data(&mut self, id: u64) -> RecordsManagerResult<()> {
+        let mut next = id;
+        let mut has_next = true;
+        while has_next {
+            let dr = self.load(next)?;
+            self.delete(next)?;
+            has_next = dr.has_next;
+            next = dr.next;
+        }
+        Ok(())
+    }
+
+    pub fn load(&mut self, dr_id: u64) -> RecordsManagerResult<DynamicStoreRecord> {
         let mut data: [u8; 129] = [0; 129];
-        self.records_manager.load(dr_id, &mut data).ok()?;
-        Some(dr_from_bytes(data))
+        self.records_manager.load(dr_id, &mut data)?;
+        Ok(dr_from_bytes(data))
+    }
+    /// Used dynamic records in id order.
+    pub fn scan(&mut self) -> impl Iterator<Item = (u64, DynamicStoreRecord)> + '_ {
+        self.records_manager.scan().map(|(id, data)| {
+            let mut record = [0u8; DYN_RECORD_SIZE];
+            record.copy_from_slice(&data);
+            (id, dr_from_bytes(record))
+        })
+    }
+    pub fn delete(&mut self, dr_id: u64) -> RecordsManagerResult<()> {
+        self.records_manager.delete(dr_id)
+    }
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.sync()
     }
-    pub fn sync(&mut self) {
-        self.records_manager.sync();
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.records_manager.set_cache_capacity(capacity);
+    }
+
+    pub fn check_pages(&mut self, repair: bool) -> Vec<(Problem, bool)> {
+        self.records_manager.check_pages(repair)
+    }
+
+    pub fn discard(&mut self) {
+        self.records_manager.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.records_manager.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.mark_clean()
     }
 }
 
@@ -71,7 +120,7 @@ mod test_dyn_store {
     #[test]
     fn test_dyn_short() {
         let file = build_file_path_and_rm_old("test_dyn_store", "test_dyn_short.db").unwrap();
-        let mut ds = DynamicStore::new(&file);
+        let mut ds = DynamicStore::new(&file, DEFAULT_PAGE_SIZE);
         let short = b"qsdfqsdfqsdf";
         let id = ds.save_data(short).unwrap();
         let data = ds.load_data(id).unwrap();
@@ -81,7 +130,7 @@ mod test_dyn_store {
     #[test]
     fn test_dyn_long() {
         let file = build_file_path_and_rm_old("test_dyn_store", "test_dyn_long.db").unwrap();
-        let mut ds = DynamicStore::new(&file);
+        let mut ds = DynamicStore::new(&file, DEFAULT_PAGE_SIZE);
         let long = b"qsdfqsdfqsdlkqshdfhljbqlcznzelfnqelincqzlnfqzlnec
         qfqsdfqsdfqsdlkqshdfhljbqlcznzelfnqelincqzlnfqzlnecqfqsdfqsdfqsdlkqsh
         dfhljbqlcznzelfnqelincqzlnfqzlnecqfqsdfqsdfqsdlkqshdfhljbqlcznzelfnqel";
@@ -1,5 +1,7 @@
 use super::super::super::buf_config::*;
 use super::super::records::*;
+use super::super::super::model::check::Problem;
+use super::super::wal::*;
 use super::records::*;
 
 pub struct NodesStore {
@@ -7,22 +9,66 @@ pub struct NodesStore {
 }
 
 impl NodesStore {
-    pub fn new(file: &str) -> Self {
-        NodesStore {records_manager: RecordsManager::new(file, NODE_RECORD_SIZE, NODE_NB_RECORDS_PER_PAGE, NODE_NB_PAGES_PER_RECORD)}
+    pub fn new(file: &str, page_size: usize) -> Self {
+        NodesStore {records_manager: RecordsManager::new(file, NODE_RECORD_SIZE, page_size)}
     }
-    pub fn save(&mut self, id: u64, node: &NodeRecord) -> Option<()> {
-        self.records_manager.save(id, &node.to_bytes()).ok()
+    pub fn save(&mut self, id: u64, node: &NodeRecord) -> RecordsManagerResult<()> {
+        self.records_manager.save(id, &node.to_bytes())
     }
-    pub fn create(&mut self,node: &NodeRecord) -> Option<u64> {
-        self.records_manager.create(&node.to_bytes()).ok()
+    pub fn create(&mut self,node: &NodeRecord) -> RecordsManagerResult<u64> {
+        self.records_manager.create(&node.to_bytes())
     }
-    pub fn load(&mut self, node_id: u64) -> Option<NodeRecord> {
+    pub fn load(&mut self, node_id: u64) -> RecordsManagerResult<NodeRecord> {
         let mut data: [u8; NODE_RECORD_SIZE] = [0; NODE_RECORD_SIZE];
-        self.records_manager.load(node_id, &mut data).ok()?;
-        Some(NodeRecord::from_bytes(data))
+        self.records_manager.load(node_id, &mut data)?;
+        Ok(NodeRecord::from_bytes(data))
     }
-    pub fn sync(&mut self) {
-        self.records_manager.sync();
+    pub fn delete(&mut self, node_id: u64) -> RecordsManagerResult<()> {
+        self.records_manager.delete(node_id)
+    }
+    pub fn extend_to(&mut self, id: u64) -> RecordsManagerResult<std::ops::RangeInclusive<u64>> {
+        self.records_manager.extend_to(id)
+    }
+    /// Used node records in id order, deleted nodes are skipped.
+    pub fn scan(&mut self) -> impl Iterator<Item = (u64, NodeRecord)> + '_ {
+        self.records_manager.scan().map(|(id, data)| {
+            let mut record: [u8; NODE_RECORD_SIZE] = [0; NODE_RECORD_SIZE];
+            record.copy_from_slice(&data);
+            (id, NodeRecord::from_bytes(record))
+        })
+    }
+    pub fn get_version(&mut self) -> u64 {
+        self.records_manager.get_version()
+    }
+    pub fn set_version(&mut self, version: u64) {
+        self.records_manager.set_version(version);
+    }
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.records_manager.set_cache_capacity(capacity);
+    }
+
+    pub fn check_pages(&mut self, repair: bool) -> Vec<(Problem, bool)> {
+        self.records_manager.check_pages(repair)
+    }
+
+    pub fn discard(&mut self) {
+        self.records_manager.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.records_manager.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.mark_clean()
     }
 }
 
@@ -34,16 +80,91 @@ mod test_nodes_store {
     #[test]
     fn test_create_node_0() {
         let file = build_file_path_and_rm_old("test_nodes_store", "test_create_node_0.db").unwrap();
-        let mut store = NodesStore::new(&file);
+        let mut store = NodesStore::new(&file, DEFAULT_PAGE_SIZE);
         let nr = NodeRecord {
             first_inbound_edge: 11287665,
             first_outbound_edge: 87687554,
             next_prop_id: 89089807,
+            labels_id: 5467,
+            version: 0,
+            prev_version: 0,
         };
         let id = store.create(&nr).unwrap();
         let r = store.load(id).unwrap();
         assert_eq!(r.first_inbound_edge, 11287665);
         assert_eq!(r.first_outbound_edge, 87687554);
         assert_eq!(r.next_prop_id, 89089807);
+        assert_eq!(r.labels_id, 5467);
+    }
+
+    #[test]
+    fn test_delete_and_reuse() {
+        let file = build_file_path_and_rm_old("test_nodes_store", "test_delete_and_reuse.db").unwrap();
+        let mut store = NodesStore::new(&file, DEFAULT_PAGE_SIZE);
+        let id0 = store.create(&NodeRecord::new()).unwrap();
+        let id1 = store.create(&NodeRecord::new()).unwrap();
+        store.delete(id0).unwrap();
+        assert!(store.load(id0).is_err());
+        assert!(store.delete(id0).is_err());
+        assert!(store.load(id1).is_ok());
+        assert_eq!(store.create(&NodeRecord::new()).unwrap(), id0);
+    }
+
+    #[test]
+    fn test_scan() {
+        let file = build_file_path_and_rm_old("test_nodes_store", "test_scan.db").unwrap();
+        let mut store = NodesStore::new(&file, DEFAULT_PAGE_SIZE);
+        let mut ids = Vec::new();
+        for labels_id in 0..300 {
+            let mut nr = NodeRecord::new();
+            nr.labels_id = labels_id;
+            ids.push(store.create(&nr).unwrap());
+        }
+        store.delete(ids[0]).unwrap();
+        store.delete(ids[150]).unwrap();
+        let scanned: Vec<(u64, NodeRecord)> = store.scan().collect();
+        assert_eq!(scanned.len(), 298);
+        assert!(scanned.iter().all(|(id, _)| *id != ids[0] && *id != ids[150]));
+        assert!(scanned.iter().all(|(id, nr)| ids[nr.labels_id as usize] == *id));
+    }
+
+    #[test]
+    fn test_page_size_from_file() {
+        let file = build_file_path_and_rm_old("test_nodes_store", "test_page_size_from_file.db").unwrap();
+        let ids = {
+            let mut store = NodesStore::new(&file, 4 * DEFAULT_PAGE_SIZE);
+            let ids = (0..500).map(|labels_id| {
+                let mut nr = NodeRecord::new();
+                nr.labels_id = labels_id;
+                store.create(&nr).unwrap()
+            }).collect::<Vec<u64>>();
+            store.sync().unwrap();
+            ids
+        };
+        let mut store = NodesStore::new(&file, DEFAULT_PAGE_SIZE);
+        let layout = store.records_manager.get_layout();
+        assert_eq!(layout.page_size, 4 * DEFAULT_PAGE_SIZE);
+        assert_eq!(layout.record_size, NODE_RECORD_SIZE);
+        assert_eq!(store.load(ids[499]).unwrap().labels_id, 499);
+        assert_eq!(store.scan().count(), 500);
+
+        let mut other = RecordsManager::new(&file, NODE_RECORD_SIZE + 8, DEFAULT_PAGE_SIZE);
+        let mut data = [0u8; NODE_RECORD_SIZE + 8];
+        assert!(matches!(other.load(ids[0], &mut data), Err(RecordsManagerError::Incompatible(_, _))));
+        assert!(matches!(other.create(&data), Err(RecordsManagerError::Incompatible(_, _))));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_layout_written_on_commit() {
+        let file = build_file_path_and_rm_old("test_nodes_store", "test_layout_written_on_commit.db").unwrap();
+        let mut store = NodesStore::new(&file, DEFAULT_PAGE_SIZE);
+        let mut data = [0u8; NODE_RECORD_SIZE + 8];
+        assert!(RecordsManager::new(&file, NODE_RECORD_SIZE + 8, DEFAULT_PAGE_SIZE).create(&data).is_ok());
+        store.discard();
+        let id = store.create(&NodeRecord::new()).unwrap();
+        store.sync().unwrap();
+        let mut other = RecordsManager::new(&file, NODE_RECORD_SIZE + 8, DEFAULT_PAGE_SIZE);
+        assert!(matches!(other.load(id, &mut data), Err(RecordsManagerError::Incompatible(_, _))));
+        assert_eq!(NodesStore::new(&file, DEFAULT_PAGE_SIZE).records_manager.get_layout().record_size, NODE_RECORD_SIZE);
+    }
+}
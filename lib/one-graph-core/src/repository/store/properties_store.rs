@@ -1,5 +1,7 @@
 use super::super::super::buf_config::*;
 use super::super::records::*;
+use super::super::super::model::check::Problem;
+use super::super::wal::*;
 use super::records::*;
 
 pub struct PropertiesStore {
@@ -7,18 +9,48 @@ pub struct PropertiesStore {
 }
 
 impl PropertiesStore {
-    pub fn new(file: &str) -> Self {
-        PropertiesStore {records_manager: RecordsManager::new(file, PROPERTY_RECORD_SIZE, PROPERTY_NB_RECORDS_PER_PAGE, PROPERTY_NB_PAGES_PER_RECORD)}
+    pub fn new(file: &str, page_size: usize) -> Self {
+        PropertiesStore {records_manager: RecordsManager::new(file, PROPERTY_RECORD_SIZE, page_size)}
     }
-    pub fn create(&mut self, pr: &PropertyRecord) -> Option<u64> {
-        self.records_manager.create(&pr_to_bytes(pr)).ok()
+    pub fn create(&mut self, pr: &PropertyRecord) -> RecordsManagerResult<u64> {
+        self.records_manager.create(&pr_to_bytes(pr))
     }
-    pub fn load(&mut self, pr_id: u64) -> Option<PropertyRecord> {
+    pub fn save(&mut self, pr_id: u64, pr: &PropertyRecord) -> RecordsManagerResult<()> {
+        self.records_manager.save(pr_id, &pr_to_bytes(pr))
+    }
+    pub fn load(&mut self, pr_id: u64) -> RecordsManagerResult<PropertyRecord> {
         let mut data: [u8; 42] = [0; 42];
-        self.records_manager.load(pr_id, &mut data).ok()?;
-        Some(pr_from_bytes(data))
+        self.records_manager.load(pr_id, &mut data)?;
+        Ok(pr_from_bytes(data))
+    }
+    pub fn delete(&mut self, pr_id: u64) -> RecordsManagerResult<()> {
+        self.records_manager.delete(pr_id)
+    }
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.records_manager.set_cache_capacity(capacity);
     }
-    pub fn sync(&mut self) {
-        self.records_manager.sync();
+
+    pub fn check_pages(&mut self, repair: bool) -> Vec<(Problem, bool)> {
+        self.records_manager.check_pages(repair)
+    }
+
+    pub fn discard(&mut self) {
+        self.records_manager.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.records_manager.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.mark_clean()
     }
 }
\ No newline at end of file
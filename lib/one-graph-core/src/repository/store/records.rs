@@ -1,15 +1,26 @@
 use super::super::byte_utils::*;
 use super::super::super::buf_config::*;
 
+/// Properties id of a tombstone, the record a deleted node or relationship keeps until compaction
+/// so that its history stays readable.
+pub const TOMBSTONE: u64 = u64::MAX;
+
 pub struct NodeRecord {
     pub first_outbound_edge: u64,
     pub first_inbound_edge: u64,
     pub next_prop_id: u64,
+    pub labels_id: u64,
+    pub version: u64,
+    pub prev_version: u64,
 }
 
 impl NodeRecord {
     pub fn new() -> Self {
-        NodeRecord{first_outbound_edge: 0, first_inbound_edge: 0, next_prop_id: 0}
+        NodeRecord{first_outbound_edge: 0, first_inbound_edge: 0, next_prop_id: 0, labels_id: 0, version: 0, prev_version: 0}
+    }
+
+    pub fn is_tombstone(&self) -> bool {
+        self.next_prop_id == TOMBSTONE
     }
 
     pub fn to_bytes(&self) -> [u8; NODE_RECORD_SIZE] {
@@ -20,6 +31,12 @@ impl NodeRecord {
         bytes[offset..offset+RELATIONSHIP_ID_SIZE].copy_from_slice(&u64_to_bytes(self.first_inbound_edge));
         offset += RELATIONSHIP_ID_SIZE;
         bytes[offset..offset+PROPERTY_ID_SIZE].copy_from_slice(&u64_to_bytes(self.next_prop_id));
+        offset += PROPERTY_ID_SIZE;
+        bytes[offset..offset+LABELS_ID_SIZE].copy_from_slice(&u64_to_bytes(self.labels_id));
+        offset += LABELS_ID_SIZE;
+        bytes[offset..offset+VERSION_SIZE].copy_from_slice(&u64_to_bytes(self.version));
+        offset += VERSION_SIZE;
+        bytes[offset..offset+VERSION_PTR_SIZE].copy_from_slice(&u64_to_bytes(self.prev_version));
         bytes
     }
 
@@ -30,7 +47,14 @@ impl NodeRecord {
         let in_rel_id = u64_from_bytes(&bytes[offset..offset+RELATIONSHIP_ID_SIZE]);
         offset += RELATIONSHIP_ID_SIZE;
         let prop_id = u64_from_bytes(&bytes[offset..offset+PROPERTY_ID_SIZE]);
-        NodeRecord {first_outbound_edge: out_rel_id, first_inbound_edge: in_rel_id, next_prop_id: prop_id}
+        offset += PROPERTY_ID_SIZE;
+        let labels_id = u64_from_bytes(&bytes[offset..offset+LABELS_ID_SIZE]);
+        offset += LABELS_ID_SIZE;
+        let version = u64_from_bytes(&bytes[offset..offset+VERSION_SIZE]);
+        offset += VERSION_SIZE;
+        let prev_version = u64_from_bytes(&bytes[offset..offset+VERSION_PTR_SIZE]);
+        NodeRecord {first_outbound_edge: out_rel_id, first_inbound_edge: in_rel_id, next_prop_id: prop_id,
+            labels_id, version, prev_version}
     }
 }
 
@@ -47,12 +71,18 @@ pub struct RelationshipRecord {
     pub next_outbound_edge: u64,
     pub next_inbound_edge: u64,
     pub next_prop_id: u64,
+    pub version: u64,
+    pub prev_version: u64,
 }
 
 impl RelationshipRecord {
     pub fn new(first_node: u64, second_node: u64) -> Self {
         RelationshipRecord{source: first_node, target: second_node, relationship_type: 0, next_outbound_edge: 0,
-            next_inbound_edge: 0, next_prop_id: 0}
+            next_inbound_edge: 0, next_prop_id: 0, version: 0, prev_version: 0}
+    }
+
+    pub fn is_tombstone(&self) -> bool {
+        self.next_prop_id == TOMBSTONE
     }
 
     pub fn to_bytes(&self) -> [u8; RELATIONSHIP_RECORD_SIZE] {
@@ -69,6 +99,10 @@ impl RelationshipRecord {
         bytes[offset..offset+RELATIONSHIP_ID_SIZE].clone_from_slice(&u64_to_bytes(self.next_inbound_edge));
         offset += RELATIONSHIP_ID_SIZE;
         bytes[offset..offset+PROPERTY_ID_SIZE].clone_from_slice(&u64_to_bytes(self.next_prop_id));
+        offset += PROPERTY_ID_SIZE;
+        bytes[offset..offset+VERSION_SIZE].clone_from_slice(&u64_to_bytes(self.version));
+        offset += VERSION_SIZE;
+        bytes[offset..offset+VERSION_PTR_SIZE].clone_from_slice(&u64_to_bytes(self.prev_version));
         bytes
     }
     
@@ -85,15 +119,18 @@ impl RelationshipRecord {
         let in_rel = u64_from_bytes(&bytes[offset..offset+RELATIONSHIP_ID_SIZE]);
         offset += RELATIONSHIP_ID_SIZE;
         let p = u64_from_bytes(&bytes[offset..offset+PROPERTY_ID_SIZE]);
+        offset += PROPERTY_ID_SIZE;
+        let v = u64_from_bytes(&bytes[offset..offset+VERSION_SIZE]);
+        offset += VERSION_SIZE;
+        let pv = u64_from_bytes(&bytes[offset..offset+VERSION_PTR_SIZE]);
         RelationshipRecord {source: s, target: t,
             relationship_type: rt, next_outbound_edge: out_rel, next_inbound_edge: in_rel,
-            next_prop_id: p}
+            next_prop_id: p, version: v, prev_version: pv}
     }
 }
 
 pub struct PropertyRecord {
     pub in_use: bool,
-    pub key_inlined: bool,
     pub full_inlined: bool,
     pub has_next: bool,
     pub prop_type: u8,
@@ -140,9 +177,6 @@ pub fn pr_to_bytes(pr: &PropertyRecord) -> [u8; 42] {
     if pr.full_inlined {
         bytes[0] = bytes[0] | 0b0000_0010;
     }
-    if pr.key_inlined {
-        bytes[0] = bytes[0] | 0b0000_0100;
-    }
     if pr.has_next {
         bytes[0] = bytes[0] | 0b0000_1000;
     }
@@ -156,14 +190,13 @@ pub fn pr_to_bytes(pr: &PropertyRecord) -> [u8; 42] {
 pub fn pr_from_bytes(bytes: [u8; 42]) -> PropertyRecord {
     let in_use = bytes[0] & 0b0000_0001 > 0;
     let inlined = bytes[0] & 0b0000_0010 > 0;
-    let key_inlined = bytes[0] & 0b0000_0100 > 0;
     let has_next = bytes[0] & 0b0000_1000 > 0;
     let ptype = bytes[1];
     let key = u64_from_bytes(&bytes[2..10]);
     let mut block = [0u8; 24];
     block.copy_from_slice(&bytes[10..34]);
     let next = u64_from_bytes(&bytes[34..42]);
-    PropertyRecord {in_use: in_use, full_inlined: inlined, key_inlined: key_inlined, has_next: has_next, prop_type: ptype, key_id: key, prop_block: block, next_prop_id: next}
+    PropertyRecord {in_use: in_use, full_inlined: inlined, has_next: has_next, prop_type: ptype, key_id: key, prop_block: block, next_prop_id: next}
 }
 
 
@@ -178,12 +211,15 @@ mod test_records {
     }
     #[test]
     fn test_node_record() {
-        let val = NodeRecord {next_prop_id: 100, first_inbound_edge: 32, first_outbound_edge: 55};
+        let val = NodeRecord {next_prop_id: 100, first_inbound_edge: 32, first_outbound_edge: 55, labels_id: 12, version: 1606000000000, prev_version: 7};
         let bytes = val.to_bytes();
         let nr = NodeRecord::from_bytes(bytes);
         assert_eq!(nr.first_outbound_edge, 55);
         assert_eq!(nr.first_inbound_edge, 32);
         assert_eq!(nr.next_prop_id, 100u64);
+        assert_eq!(nr.labels_id, 12);
+        assert_eq!(nr.version, 1606000000000);
+        assert_eq!(nr.prev_version, 7);
     }
 
     
@@ -191,7 +227,7 @@ mod test_records {
     fn test_relationship_record() {
         let val = RelationshipRecord {source: 2, target: 3,
             next_inbound_edge: 4, next_outbound_edge: 5,
-            relationship_type: 33, next_prop_id: 100};
+            relationship_type: 33, next_prop_id: 100, version: 1606000000000, prev_version: 9};
         let bytes = val.to_bytes();
         let rr = RelationshipRecord::from_bytes(bytes);
         assert_eq!(rr.source, 2);
@@ -200,5 +236,7 @@ mod test_records {
         assert_eq!(rr.next_outbound_edge, 5);
         assert_eq!(rr.relationship_type, 33);
         assert_eq!(rr.next_prop_id, 100);
+        assert_eq!(rr.version, 1606000000000);
+        assert_eq!(rr.prev_version, 9);
     }
 }
\ No newline at end of file
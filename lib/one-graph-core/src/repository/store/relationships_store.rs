@@ -1,28 +1,77 @@
 use super::records::*;
 use super::super::super::buf_config::*;
 use super::super::records::*;
+use super::super::super::model::check::Problem;
+use super::super::wal::*;
 
 pub struct RelationshipsStore {
     records_manager: RecordsManager,
 }
 
 impl RelationshipsStore {
-    pub fn new(file: &str) -> Self {
-        RelationshipsStore {records_manager: RecordsManager::new(file, RELATIONSHIP_RECORD_SIZE, RELATIONSHIP_NB_RECORDS_PER_PAGE, RELATIONSHIP_NB_PAGES_PER_RECORD)}
+    pub fn new(file: &str, page_size: usize) -> Self {
+        RelationshipsStore {records_manager: RecordsManager::new(file, RELATIONSHIP_RECORD_SIZE, page_size)}
     }
-    pub fn save(&mut self, id: u64, rel: &RelationshipRecord) -> Option<()> {
-        self.records_manager.save(id, &rel.to_bytes()).ok()
+    pub fn save(&mut self, id: u64, rel: &RelationshipRecord) -> RecordsManagerResult<()> {
+        self.records_manager.save(id, &rel.to_bytes())
     }
-    pub fn create(&mut self, rel: &RelationshipRecord) -> Option<u64> {
-        self.records_manager.create(&rel.to_bytes()).ok()
+    pub fn create(&mut self, rel: &RelationshipRecord) -> RecordsManagerResult<u64> {
+        self.records_manager.create(&rel.to_bytes())
     }
-    pub fn load(&mut self, rel_id: u64) -> Option<RelationshipRecord> {
+    pub fn load(&mut self, rel_id: u64) -> RecordsManagerResult<RelationshipRecord> {
         let mut data: [u8; RELATIONSHIP_RECORD_SIZE] = [0; RELATIONSHIP_RECORD_SIZE];
-        self.records_manager.load(rel_id, &mut data).ok()?;
-        Some(RelationshipRecord::from_bytes(data))
+        self.records_manager.load(rel_id, &mut data)?;
+        Ok(RelationshipRecord::from_bytes(data))
     }
-    pub fn sync(&mut self) {
-        self.records_manager.sync();
+    pub fn delete(&mut self, rel_id: u64) -> RecordsManagerResult<()> {
+        self.records_manager.delete(rel_id)
+    }
+    pub fn extend_to(&mut self, id: u64) -> RecordsManagerResult<std::ops::RangeInclusive<u64>> {
+        self.records_manager.extend_to(id)
+    }
+    /// Used relationship records in id order.
+    pub fn scan(&mut self) -> impl Iterator<Item = (u64, RelationshipRecord)> + '_ {
+        self.records_manager.scan().map(|(id, data)| {
+            let mut record: [u8; RELATIONSHIP_RECORD_SIZE] = [0; RELATIONSHIP_RECORD_SIZE];
+            record.copy_from_slice(&data);
+            (id, RelationshipRecord::from_bytes(record))
+        })
+    }
+    pub fn get_max_id(&mut self) -> u64 {
+        self.records_manager.get_max_record_id()
+    }
+    pub fn get_version(&mut self) -> u64 {
+        self.records_manager.get_version()
+    }
+    pub fn set_version(&mut self, version: u64) {
+        self.records_manager.set_version(version);
+    }
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.verify()
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.records_manager.set_cache_capacity(capacity);
+    }
+
+    pub fn check_pages(&mut self, repair: bool) -> Vec<(Problem, bool)> {
+        self.records_manager.check_pages(repair)
+    }
+
+    pub fn discard(&mut self) {
+        self.records_manager.discard();
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.records_manager.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.mark_clean()
     }
 }
 
@@ -34,7 +83,7 @@ mod test_relationship_store {
     #[test]
     fn test_create_relationship_0() {
         let file = build_file_path_and_rm_old("test_relationship_store", "test_create_relationship_0.db").unwrap();
-        let mut store = RelationshipsStore::new(&file);
+        let mut store = RelationshipsStore::new(&file, DEFAULT_PAGE_SIZE);
         let rr = RelationshipRecord {
             source: 45465,
             target: 9871321,
@@ -42,6 +91,8 @@ mod test_relationship_store {
             next_outbound_edge: 789545,
             next_inbound_edge: 7895465,
             next_prop_id: 8764564,
+            version: 0,
+            prev_version: 0,
         };
         let id = store.create(&rr).unwrap();
         let r = store.load(id).unwrap();
@@ -52,4 +103,18 @@ mod test_relationship_store {
         assert_eq!(r.next_inbound_edge, 7895465);
         assert_eq!(r.next_prop_id, 8764564);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_relationships_across_pages() {
+        let file = build_file_path_and_rm_old("test_relationship_store", "test_relationships_across_pages.db").unwrap();
+        let mut store = RelationshipsStore::new(&file, DEFAULT_PAGE_SIZE);
+        let ids = (0..150).map(|i| store.create(&RelationshipRecord::new(i, i + 1)).unwrap()).collect::<Vec<u64>>();
+        store.sync().unwrap();
+        for (i, id) in ids.iter().enumerate() {
+            let r = store.load(*id).unwrap();
+            assert_eq!(r.source, i as u64);
+            assert_eq!(r.target, i as u64 + 1);
+        }
+        assert_eq!(store.scan().count(), 150);
+    }
+}
@@ -0,0 +1,152 @@
+use super::super::super::buf_config::*;
+use super::super::byte_utils::*;
+use super::super::records::*;
+use super::super::super::model::check::Problem;
+use super::super::wal::*;
+use super::dynamic_store::DynamicStore;
+use std::collections::HashMap;
+
+/// Maps names (labels, relationship types...) to stable token ids.
+/// Tokens are never deleted and the whole store is loaded in memory on first access.
+pub struct TokenStore {
+    records_manager: RecordsManager,
+    names_store: DynamicStore,
+    ids: HashMap<String, u64>,
+    names: HashMap<u64, String>,
+    loaded: bool,
+}
+
+impl TokenStore {
+    pub fn new(file: &str, names_file: &str, page_size: usize) -> Self {
+        TokenStore {records_manager: RecordsManager::new(file, TOKEN_RECORD_SIZE, page_size),
+            names_store: DynamicStore::new(names_file, page_size), ids: HashMap::new(), names: HashMap::new(), loaded: false}
+    }
+
+    /// Names are written from `&str` so their bytes are valid utf-8.
+    fn load_name(&mut self, name_id: u64) -> RecordsManagerResult<String> {
+        let data = self.names_store.load_data(name_id)?;
+        let end = data.iter().position(|&c| c == b'\0').unwrap_or(data.len());
+        Ok(String::from_utf8_lossy(&data[..end]).into_owned())
+    }
+
+    fn load_tokens(&mut self) -> RecordsManagerResult<()> {
+        if self.loaded {
+            return Ok(());
+        }
+        let mut data = [0u8; TOKEN_RECORD_SIZE];
+        for token_id in 1..=self.records_manager.get_max_record_id() {
+            match self.records_manager.load(token_id, &mut data) {
+                Ok(()) => {
+                    let name = self.load_name(u64_from_bytes(&data))?;
+                    self.ids.insert(name.clone(), token_id);
+                    self.names.insert(token_id, name);
+                },
+                Err(RecordsManagerError::NotFound) => {},
+                Err(e) => return Err(e),
+            }
+        }
+        self.loaded = true;
+        Ok(())
+    }
+
+    pub fn get_id(&mut self, name: &str) -> RecordsManagerResult<Option<u64>> {
+        self.load_tokens()?;
+        Ok(self.ids.get(name).copied())
+    }
+
+    pub fn get_name(&mut self, token_id: u64) -> RecordsManagerResult<Option<String>> {
+        self.load_tokens()?;
+        Ok(self.names.get(&token_id).cloned())
+    }
+
+    pub fn get_tokens(&mut self) -> RecordsManagerResult<Vec<(u64, String)>> {
+        self.load_tokens()?;
+        let mut tokens = self.names.iter().map(|(id, name)| (*id, name.clone())).collect::<Vec<(u64, String)>>();
+        tokens.sort();
+        Ok(tokens)
+    }
+
+    pub fn get_or_create(&mut self, name: &str) -> RecordsManagerResult<u64> {
+        if let Some(token_id) = self.get_id(name)? {
+            return Ok(token_id);
+        }
+        let name_id = self.names_store.save_data(name.as_bytes())?;
+        let token_id = self.records_manager.create(&u64_to_bytes(name_id))?;
+        self.ids.insert(String::from(name), token_id);
+        self.names.insert(token_id, String::from(name));
+        Ok(token_id)
+    }
+
+    pub fn sync(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.sync()?;
+        self.names_store.sync()
+    }
+
+    pub fn verify(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.verify()?;
+        self.names_store.verify()
+    }
+
+    pub fn check_pages(&mut self, repair: bool) -> Vec<(Problem, bool)> {
+        self.records_manager.check_pages(repair)
+    }
+
+    pub fn check_names_pages(&mut self, repair: bool) -> Vec<(Problem, bool)> {
+        self.names_store.check_pages(repair)
+    }
+
+    /// Ids of the tokens in use, with the names record of the tokens whose name cannot be loaded.
+    pub fn check_names(&mut self) -> (Vec<u64>, Vec<(u64, u64)>) {
+        let tokens = self.records_manager.scan().map(|(id, data)| (id, u64_from_bytes(&data))).collect::<Vec<(u64, u64)>>();
+        let missing = tokens.iter().filter(|(_, name_id)| self.load_name(*name_id).is_err()).copied().collect();
+        (tokens.into_iter().map(|(id, _)| id).collect(), missing)
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.records_manager.set_cache_capacity(capacity);
+        self.names_store.set_cache_capacity(capacity);
+    }
+
+    pub fn discard(&mut self) {
+        self.records_manager.discard();
+        self.names_store.discard();
+        self.ids.clear();
+        self.names.clear();
+        self.loaded = false;
+    }
+
+    pub fn log_sync(&mut self, wal: &mut WriteAheadLog) {
+        self.records_manager.log_sync(wal);
+        self.names_store.log_sync(wal);
+    }
+
+    pub fn mark_clean(&mut self) -> RecordsManagerResult<()> {
+        self.records_manager.mark_clean()?;
+        self.names_store.mark_clean()
+    }
+}
+
+#[cfg(test)]
+mod test_token_store {
+    use super::*;
+    use super::super::super::super::test_utils::*;
+
+    #[test]
+    fn test_tokens() {
+        let file = build_file_path_and_rm_old("test_token_store", "test_tokens.db").unwrap();
+        let names_file = build_file_path_and_rm_old("test_token_store", "test_tokens_names.db").unwrap();
+        let (person, city) = {
+            let mut store = TokenStore::new(&file, &names_file, DEFAULT_PAGE_SIZE);
+            let person = store.get_or_create("Person").unwrap();
+            let city = store.get_or_create("City").unwrap();
+            assert_ne!(person, city);
+            assert_eq!(store.get_or_create("Person").unwrap(), person);
+            store.sync().unwrap();
+            (person, city)
+        };
+        let mut store = TokenStore::new(&file, &names_file, DEFAULT_PAGE_SIZE);
+        assert_eq!(store.get_id("Person").unwrap(), Some(person));
+        assert_eq!(store.get_name(city).unwrap(), Some(String::from("City")));
+        assert!(store.get_id("Country").unwrap().is_none());
+    }
+}
@@ -0,0 +1,320 @@
+use super::io::file_access::*;
+use super::super::error::GraphError;
+use super::super::model::init::SyncPolicy;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::{Path, PathBuf};
+use log::warn;
+
+const ENTRY_TAG: u8 = 1;
+const COMMIT_TAG: u8 = 2;
+
+#[derive(Debug, Clone)]
+pub enum WalError {
+    Corrupted,
+    Incomplete,
+    /// The log or a logged file could not be written, with the cause reported by the system.
+    Io(String),
+}
+
+fn io_error(file: &str, error: std::io::Error) -> WalError {
+    WalError::Io(format!("{}: {}", file, error))
+}
+
+impl From<WalError> for GraphError {
+    fn from(error: WalError) -> Self {
+        match error {
+            WalError::Io(cause) => GraphError::Io(cause),
+            _ => GraphError::Inconsistent(String::from("the write-ahead log cannot be decoded")),
+        }
+    }
+}
+
+pub type WalResult<T> = std::result::Result<T, WalError>;
+
+struct LogEntry {
+    file_name: String,
+    pos: u64,
+    data: Vec<u8>,
+}
+
+pub struct WriteAheadLog {
+    log_file: FileAccess,
+    db_dir: PathBuf,
+    entries: Vec<LogEntry>,
+    sync_policy: SyncPolicy,
+    applied: bool,
+}
+
+fn sync_file(file: &mut FileAccess, sync_policy: SyncPolicy) -> std::io::Result<()> {
+    match sync_policy {
+        SyncPolicy::Always => file.sync(),
+        SyncPolicy::Never => Ok(()),
+    }
+}
+
+fn checksum(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in data {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn read_bytes<'a>(data: &'a [u8], offset: &mut usize, len: usize) -> WalResult<&'a [u8]> {
+    if *offset + len > data.len() {
+        return Err(WalError::Incomplete);
+    }
+    let res = &data[*offset..*offset + len];
+    *offset += len;
+    Ok(res)
+}
+
+fn read_u32(data: &[u8], offset: &mut usize) -> WalResult<u32> {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(read_bytes(data, offset, 4)?);
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn read_u64(data: &[u8], offset: &mut usize) -> WalResult<u64> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(read_bytes(data, offset, 8)?);
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn encode_entries(entries: &[LogEntry]) -> Vec<u8> {
+    let mut res = Vec::new();
+    for entry in entries {
+        res.push(ENTRY_TAG);
+        res.extend_from_slice(&(entry.file_name.len() as u32).to_be_bytes());
+        res.extend_from_slice(entry.file_name.as_bytes());
+        res.extend_from_slice(&entry.pos.to_be_bytes());
+        res.extend_from_slice(&(entry.data.len() as u32).to_be_bytes());
+        res.extend_from_slice(&entry.data);
+    }
+    let sum = checksum(&res);
+    res.push(COMMIT_TAG);
+    res.extend_from_slice(&(entries.len() as u64).to_be_bytes());
+    res.extend_from_slice(&sum.to_be_bytes());
+    res
+}
+
+fn decode_entries(data: &[u8]) -> WalResult<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    loop {
+        let entry_begin = offset;
+        let tag = read_bytes(data, &mut offset, 1)?[0];
+        if tag == ENTRY_TAG {
+            let name_len = read_u32(data, &mut offset)? as usize;
+            let name = read_bytes(data, &mut offset, name_len)?;
+            let file_name = String::from_utf8(name.to_vec()).map_err(|_e| WalError::Corrupted)?;
+            let pos = read_u64(data, &mut offset)?;
+            let data_len = read_u32(data, &mut offset)? as usize;
+            let page_data = read_bytes(data, &mut offset, data_len)?.to_vec();
+            entries.push(LogEntry{file_name, pos, data: page_data});
+        } else if tag == COMMIT_TAG {
+            let nb_entries = read_u64(data, &mut offset)? as usize;
+            let sum = read_u64(data, &mut offset)?;
+            if nb_entries != entries.len() || sum != checksum(&data[..entry_begin]) {
+                return Err(WalError::Corrupted);
+            }
+            return Ok(entries);
+        } else {
+            return Err(WalError::Corrupted);
+        }
+    }
+}
+
+impl WriteAheadLog {
+    pub fn new(file: &str) -> WalResult<Self> {
+        WriteAheadLog::with_sync_policy(file, SyncPolicy::Always)
+    }
+
+    /// Fails when the log file cannot be opened or created.
+    pub fn with_sync_policy(file: &str, sync_policy: SyncPolicy) -> WalResult<Self> {
+        let db_dir = Path::new(file).parent().map(PathBuf::from).unwrap_or_default();
+        let log_file = FileAccess::new(file).map_err(|e| io_error("write ahead log", e))?;
+        Ok(WriteAheadLog{log_file, db_dir, entries: Vec::new(), sync_policy, applied: false})
+    }
+
+    pub fn append(&mut self, file: &str, pos: u64, data: &[u8]) {
+        let file_name = Path::new(file).file_name().and_then(|n| n.to_str()).unwrap_or(file);
+        self.entries.push(LogEntry{file_name: String::from(file_name), pos, data: data.to_vec()});
+    }
+
+    /// Drops the entries appended since the last commit, the log file is left as it is.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// No page was appended since the last commit.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Nothing is applied when the log cannot be written. Once it is, a failing checkpoint
+    /// is reported but the transaction is durable: the log is replayed by the next `recover`.
+    /// A commit without pages writes nothing.
+    pub fn commit(&mut self) -> WalResult<()> {
+        self.apply()?;
+        self.end()
+    }
+
+    /// Writes the log and applies it as `commit` does but keeps the log file until `end`,
+    /// the files that are not logged are written in between: a crash before `end` replays the log.
+    pub fn apply(&mut self) -> WalResult<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        self.write_log()?;
+        self.checkpoint()
+    }
+
+    /// Empties the log file once the files that are not logged are written, does nothing when no log was applied.
+    pub fn end(&mut self) -> WalResult<()> {
+        if !self.applied {
+            return Ok(());
+        }
+        let sync_policy = self.sync_policy;
+        self.log_file.truncate().and_then(|_| sync_file(&mut self.log_file, sync_policy)).map_err(|e| io_error("write ahead log", e))?;
+        self.applied = false;
+        Ok(())
+    }
+
+    /// Replays the committed log and returns true, the log file is kept until `end`.
+    /// A log without its commit record was torn by a crash before the commit,
+    /// it is discarded and reported as `Incomplete`. A corrupted log is kept, every recovery fails until it is removed.
+    pub fn recover(&mut self) -> WalResult<bool> {
+        let log_len = self.log_file.get_file_len() as usize;
+        if log_len == 0 {
+            return Ok(false);
+        }
+        let mut data = vec![0u8; log_len];
+        self.log_file.read_at(0, &mut data).map_err(|e| io_error("write ahead log", e))?;
+        match decode_entries(&data) {
+            Ok(entries) => {
+                self.entries = entries;
+                self.applied = true;
+                self.checkpoint()?;
+                Ok(true)
+            },
+            Err(WalError::Incomplete) => {
+                warn!("discarding the uncommitted write ahead log");
+                self.entries.clear();
+                let sync_policy = self.sync_policy;
+                self.log_file.truncate().and_then(|_| sync_file(&mut self.log_file, sync_policy)).map_err(|e| io_error("write ahead log", e))?;
+                Err(WalError::Incomplete)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(super) fn write_log(&mut self) -> WalResult<()> {
+        let data = encode_entries(&self.entries);
+        let sync_policy = self.sync_policy;
+        self.log_file.truncate().and_then(|_| self.log_file.write_at(0, &data)).and_then(|_| sync_file(&mut self.log_file, sync_policy))
+            .map_err(|e| io_error("write ahead log", e))?;
+        self.applied = true;
+        Ok(())
+    }
+
+    /// The entries are kept when a write fails, the log still holds them for the next recovery.
+    fn checkpoint(&mut self) -> WalResult<()> {
+        let mut files = HashMap::new();
+        for entry in &self.entries {
+            let file = match files.entry(entry.file_name.clone()) {
+                Entry::Occupied(file) => file.into_mut(),
+                Entry::Vacant(vacant) => {
+                    let mut path = self.db_dir.clone();
+                    path.push(&entry.file_name);
+                    vacant.insert(FileAccess::new(path.to_str().unwrap_or(&entry.file_name)).map_err(|e| io_error(&entry.file_name, e))?)
+                },
+            };
+            file.write_at(entry.pos, &entry.data).map_err(|e| io_error(&entry.file_name, e))?;
+        }
+        for (file_name, file) in files.iter_mut() {
+            sync_file(file, self.sync_policy).map_err(|e| io_error(file_name, e))?;
+        }
+        self.entries.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_wal {
+    use super::*;
+    use super::super::super::test_utils::*;
+
+    fn read_file(file: &str, pos: u64, len: usize) -> Vec<u8> {
+        let mut fa = FileAccess::new(file).unwrap();
+        let mut data = vec![0u8; len];
+        fa.read_at(pos, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_commit() {
+        let log = build_file_path_and_rm_old("test_wal", "test_commit.wal").unwrap();
+        let file = build_file_path_and_rm_old("test_wal", "test_commit.db").unwrap();
+        let mut wal = WriteAheadLog::new(&log).unwrap();
+        wal.append(&file, 0, &[1, 2, 3]);
+        wal.append(&file, 8, &[4, 5, 6]);
+        wal.commit().unwrap();
+        assert_eq!(read_file(&file, 0, 3), vec![1, 2, 3]);
+        assert_eq!(read_file(&file, 8, 3), vec![4, 5, 6]);
+        assert_eq!(FileAccess::new(&log).unwrap().get_file_len(), 0);
+    }
+
+    #[test]
+    fn test_recover_committed_log() {
+        let log = build_file_path_and_rm_old("test_wal", "test_recover_committed_log.wal").unwrap();
+        let file = build_file_path_and_rm_old("test_wal", "test_recover_committed_log.db").unwrap();
+        {
+            let mut wal = WriteAheadLog::new(&log).unwrap();
+            wal.append(&file, 4, &[7, 8, 9]);
+            wal.write_log().unwrap();
+        }
+        let mut wal = WriteAheadLog::new(&log).unwrap();
+        assert!(matches!(wal.recover(), Ok(true)));
+        assert_eq!(read_file(&file, 4, 3), vec![7, 8, 9]);
+        assert!(FileAccess::new(&log).unwrap().get_file_len() > 0);
+        wal.end().unwrap();
+        assert_eq!(FileAccess::new(&log).unwrap().get_file_len(), 0);
+        assert!(matches!(WriteAheadLog::new(&log).unwrap().recover(), Ok(false)));
+    }
+
+    #[test]
+    fn test_discard_torn_log() {
+        let log = build_file_path_and_rm_old("test_wal", "test_discard_torn_log.wal").unwrap();
+        let file = build_file_path_and_rm_old("test_wal", "test_discard_torn_log.db").unwrap();
+        let data = {
+            let mut wal = WriteAheadLog::new(&log).unwrap();
+            wal.append(&file, 0, &[1, 1, 1, 1]);
+            encode_entries(&wal.entries)
+        };
+        FileAccess::new(&log).unwrap().write_at(0, &data[..data.len() - 1]).unwrap();
+        let mut wal = WriteAheadLog::new(&log).unwrap();
+        assert!(wal.recover().is_err());
+        assert_eq!(FileAccess::new(&file).unwrap().get_file_len(), 0);
+        assert_eq!(FileAccess::new(&log).unwrap().get_file_len(), 0);
+    }
+
+    #[test]
+    fn test_keep_corrupted_log() {
+        let log = build_file_path_and_rm_old("test_wal", "test_keep_corrupted_log.wal").unwrap();
+        let file = build_file_path_and_rm_old("test_wal", "test_keep_corrupted_log.db").unwrap();
+        let mut data = {
+            let mut wal = WriteAheadLog::new(&log).unwrap();
+            wal.append(&file, 0, &[1, 1, 1, 1]);
+            encode_entries(&wal.entries)
+        };
+        data[10] ^= 0xff;
+        FileAccess::new(&log).unwrap().write_at(0, &data).unwrap();
+        assert!(matches!(WriteAheadLog::new(&log).unwrap().recover(), Err(WalError::Corrupted)));
+        assert!(matches!(WriteAheadLog::new(&log).unwrap().recover(), Err(WalError::Corrupted)));
+        assert_eq!(FileAccess::new(&file).unwrap().get_file_len(), 0);
+        assert_eq!(FileAccess::new(&log).unwrap().get_file_len(), data.len() as u64);
+    }
+}
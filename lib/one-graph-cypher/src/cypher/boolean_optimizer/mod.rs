@@ -1,39 +1,60 @@
 use super::super::model::*;
 use super::parser::*;
+use one_graph_core::model::PropertyValue;
 
+/// Collects the comparisons of a conjunction, any other expression makes it unsupported.
 struct BoolExprVisitor {
+    conditions: Vec<(Operator, Vec<String>, Option<PropertyValue>)>,
+    supported: bool,
+}
+
+impl BoolExprVisitor {
+    fn new() -> Self {
+        BoolExprVisitor{conditions: Vec::new(), supported: true}
+    }
 
+    fn set_value(&mut self, value: Option<PropertyValue>) -> AstVisitorResult<bool> {
+        match (self.conditions.last_mut(), value) {
+            (Some(condition), Some(value)) if condition.2.is_none() => condition.2 = Some(value),
+            _ => self.supported = false,
+        }
+        Ok(true)
+    }
 }
 
 impl AstVisitor for BoolExprVisitor {
-    fn enter_create(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+    fn enter_create(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
         Ok(true)
     }
-    fn enter_match(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+    fn enter_match(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
         Ok(true)
     }
-    fn enter_node(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+    fn enter_node(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
         Ok(true)
     }
-    fn enter_relationship(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+    fn enter_relationship(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
         Ok(true)
     }
-    fn enter_property(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+    fn enter_property(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
         Ok(true)
     }
     fn enter_integer_value(&mut self, value: Option<i64>) -> AstVisitorResult<bool> {
-        Ok(true)
+        self.set_value(value.map(PropertyValue::PInteger))
     }
     fn enter_float_value(&mut self, value: Option<f64>) -> AstVisitorResult<bool> {
-        Ok(true)
+        self.set_value(value.map(PropertyValue::PFloat))
     }
     fn enter_string_value(&mut self, value: Option<&str>) -> AstVisitorResult<bool> {
-        Ok(true)
+        self.set_value(value.map(|v| PropertyValue::PString(String::from(v))))
     }
     fn enter_bool_value(&mut self, value: Option<bool>) -> AstVisitorResult<bool> {
-        Ok(true)
+        self.set_value(value.map(PropertyValue::PBool))
     }
     fn enter_identifier(&mut self, key: &str) -> AstVisitorResult<bool> {
+        match self.conditions.last_mut() {
+            Some(condition) => condition.1.push(String::from(key)),
+            None => self.supported = false,
+        }
         Ok(true)
     }
     fn enter_variable(&mut self) -> AstVisitorResult<bool> {
@@ -49,7 +70,8 @@ impl AstVisitor for BoolExprVisitor {
         Ok(true)
     }
     fn enter_function(&mut self) -> AstVisitorResult<bool> {
-        Ok(true)
+        self.supported = false;
+        Ok(false)
     }
     fn enter_function_arg(&mut self) -> AstVisitorResult<bool> {
         Ok(true)
@@ -57,13 +79,51 @@ impl AstVisitor for BoolExprVisitor {
     fn enter_item(&mut self) -> AstVisitorResult<bool> {
         Ok(true)
     }
-    fn enter_where(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+    fn enter_where(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
+        Ok(true)
+    }
+    fn enter_bool_operator(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+        let operator = match node.ast_tag {
+            Some(AstTag::AndOperator) => return Ok(true),
+            Some(AstTag::EqualityOperator) => Operator::Equal,
+            Some(AstTag::LessOperator) => Operator::Inferior,
+            Some(AstTag::LessEqualOperator) => Operator::InferiorOrEqual,
+            Some(AstTag::GreaterOperator) => Operator::Superior,
+            Some(AstTag::GreaterEqualOperator) => Operator::SuperiorOrEqual,
+            _ => {
+                self.supported = false;
+                return Ok(false)
+            }
+        };
+        self.conditions.push((operator, Vec::new(), None));
+        Ok(true)
+    }
+    fn enter_delete(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
+        Ok(true)
+    }
+    fn enter_set(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
+        Ok(true)
+    }
+    fn enter_remove(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
+        Ok(true)
+    }
+    fn enter_constraint(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
         Ok(true)
     }
 }
 
-pub fn extract_mandatory_conditions_from_bool_expr(ast: &Box<dyn Ast>) -> Vec<BoolCondition> {
-    let mut res = Vec::new();
-
-    res
-}
\ No newline at end of file
+/// Comparisons that every match must satisfy, None when the expression is not a conjunction
+/// of `variable.property <operator> literal` comparisons.
+pub fn extract_mandatory_conditions_from_bool_expr(ast: &Box<dyn Ast>) -> Option<Vec<BoolCondition>> {
+    let mut visitor = BoolExprVisitor::new();
+    walk_ast(&mut visitor, ast).ok()?;
+    if !visitor.supported {
+        return None;
+    }
+    visitor.conditions.into_iter().map(|(operator, identifiers, value)| {
+        match identifiers.as_slice() {
+            [variable, property] => Some(BoolCondition{variable: variable.clone(), property: property.clone(), operator, value: value?}),
+            _ => None,
+        }
+    }).collect()
+}
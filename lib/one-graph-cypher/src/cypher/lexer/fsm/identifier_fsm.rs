@@ -6,7 +6,7 @@ pub enum IdentifierState {
         MatchIdentifier(usize),
 }
 
-fn is_valid_id_char(c: char) -> bool {
+pub fn is_valid_id_char(c: char) -> bool {
     c.is_alphabetic() || c == '_'
 }
 
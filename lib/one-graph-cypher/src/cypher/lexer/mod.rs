@@ -18,6 +18,9 @@ pub enum TokenType {
     Match,
     Create,
     Delete,
+    Detach,
+    Set,
+    Remove,
     Where,
     Return,
     OpenParenthesis,
@@ -38,6 +41,10 @@ pub enum TokenType {
     StringType,
     Equals,
     Dot,
+    Less,
+    LessEquals,
+    Greater,
+    GreaterEquals,
 }
 
 
@@ -79,9 +86,17 @@ fn make_token(ttype: TokenType, beg: usize, end: usize, input: &str) -> Option<T
     input.get(beg..end).map(|tok_expr| Token {token_type: ttype, begin: beg, end: end, content: tok_expr})
 }
 
+/// A word keyword followed by an identifier character is the start of an identifier, `settings` is not `set`.
+fn is_inside_word(keyword: &str, input: &str, end: usize) -> bool {
+    let is_word = keyword.chars().last().is_some_and(fsm::identifier_fsm::is_valid_id_char);
+    is_word && input.get(end..).and_then(|rest| rest.chars().next()).is_some_and(fsm::identifier_fsm::is_valid_id_char)
+}
+
 fn run_keyword_fsm<'a>(tok_type: TokenType, keyword: &'static str, input: &'a str, index: usize) -> Option<Token<'a>> {
     let mut kfsm = fsm::keyword_fsm::make_keyword_ignorecase_fsm(keyword);
-    input.get(index..).and_then(|rest| kfsm.run(&rest)).and_then(|size| input.get(index..index + size.0)).map(|tok_expr| Token::new(tok_type, index, index + tok_expr.len(), tok_expr))
+    input.get(index..).and_then(|rest| kfsm.run(&rest)).and_then(|size| input.get(index..index + size.0))
+        .filter(|tok_expr| !is_inside_word(keyword, input, index + tok_expr.len()))
+        .map(|tok_expr| Token::new(tok_type, index, index + tok_expr.len(), tok_expr))
 }
 
 #[derive(Debug, Clone)]
@@ -132,8 +147,12 @@ impl <'a> Lexer<'a> {
                             (TokenType::LeftSourceRel, "-["), (TokenType::RightTargetRel, "]->"),
                             (TokenType::LeftTargetRel, "<-["), (TokenType::RightSourceRel, "]-"),
                             (TokenType::UndirectedRel, "{"), (TokenType::Create, "create"),
+                            (TokenType::Delete, "delete"), (TokenType::Detach, "detach"),
+                            (TokenType::Set, "set"), (TokenType::Remove, "remove"),
                             (TokenType::Comma, ","), (TokenType::Equals, "="),
-                            (TokenType::Pipe, "|"), (TokenType::Minus, "-")],
+                            (TokenType::Pipe, "|"), (TokenType::Minus, "-"),
+                            (TokenType::LessEquals, "<="), (TokenType::GreaterEquals, ">="),
+                            (TokenType::Less, "<"), (TokenType::Greater, ">")],
             input: input, position: 0, line: 0, column: 0, lookahead: 0}
     }
     pub  fn  next_token(&mut self) -> LexerResult<Token<'a>> {
@@ -272,6 +291,30 @@ mod test_lexer {
         }
     }
 
+    #[test]
+    fn test_keyword_prefix() {
+        let mut lexer = Lexer::new("SET n.settings = setup, removed REMOVE n.orders");
+        let tokens = lexer.get_tokens().unwrap();
+        let tokens = tokens.iter().map(|tok| (tok.token_type, tok.content)).collect::<Vec<(TokenType, &str)>>();
+        assert_eq!(tokens, vec![(TokenType::Set, "SET"), (TokenType::Identifier, "n"), (TokenType::Dot, "."), (TokenType::Identifier, "settings"),
+            (TokenType::Equals, "="), (TokenType::Identifier, "setup"), (TokenType::Comma, ","), (TokenType::Identifier, "removed"),
+            (TokenType::Remove, "REMOVE"), (TokenType::Identifier, "n"), (TokenType::Dot, "."), (TokenType::Identifier, "orders")]);
+        assert!(run_keyword_fsm(TokenType::Set, "set", "n.settings", 2).is_none());
+        assert_eq!(run_keyword_fsm(TokenType::Set, "set", "set(", 0).map(|tok| tok.content), Some("set"));
+        assert_eq!(run_keyword_fsm(TokenType::Dot, ".", "n.settings", 1).map(|tok| tok.content), Some("."));
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let mut lexer = Lexer::new("n.age >= 30 AND n.age < 40 MATCH (a)<-[r]-(b)");
+        let tokens = lexer.get_tokens().unwrap();
+        let types = tokens.iter().map(|tok| tok.token_type).collect::<Vec<TokenType>>();
+        assert_eq!(types[..9], [TokenType::Identifier, TokenType::Dot, TokenType::Identifier, TokenType::GreaterEquals, TokenType::Integer,
+            TokenType::And, TokenType::Identifier, TokenType::Dot, TokenType::Identifier]);
+        assert_eq!(types[9], TokenType::Less);
+        assert!(types.contains(&TokenType::LeftTargetRel));
+    }
+
     #[test]
     fn test_run_string_fsm() {
         let mut lexer = Lexer::new("'this is a string' or 'this is another string'");
@@ -0,0 +1,62 @@
+use super::*;
+use super::error::*;
+use super::super::lexer::TokenType;
+
+/// Parses `CONSTRAINT FOR (n:Label) REQUIRE n.property IS UNIQUE | IS NOT NULL`
+/// or `CONSTRAINT FOR ()-[r:TYPE]-() REQUIRE r.property IS NOT NULL` following CREATE.
+pub fn parse_create_constraint(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    parser.require_word("constraint")?;
+    parser.require_word("for")?;
+    let mut constraint_node = make_ast_tag(AstTag::CreateConstraint);
+    parser.require(TokenType::OpenParenthesis)?;
+    let var = if parser.current_token_type_advance(TokenType::CloseParenthesis) {
+        parser.require(TokenType::LeftSourceRel)?;
+        let var = parse_constrained_element(parser, AstTag::RelationshipConstraint, &mut constraint_node)?;
+        parser.require(TokenType::RightSourceRel)?;
+        parser.require(TokenType::OpenParenthesis)?;
+        parser.require(TokenType::CloseParenthesis)?;
+        var
+    } else {
+        let var = parse_constrained_element(parser, AstTag::NodeConstraint, &mut constraint_node)?;
+        parser.require(TokenType::CloseParenthesis)?;
+        var
+    };
+    parser.require_word("require")?;
+    parser.require(TokenType::Identifier)?;
+    if parser.get_tokens()[parser.index - 1].content != var {
+        return Err(ParserError::SyntaxError(parser.index - 1));
+    }
+    parser.require(TokenType::Dot)?;
+    parser.require(TokenType::Identifier)?;
+    let mut property = make_ast_tag(AstTag::ConstraintProperty);
+    property.append(make_ast_token(parser));
+    constraint_node.append(property);
+    parser.require_word("is")?;
+    if parser.current_word_advance("unique") {
+        constraint_node.append(make_ast_tag(AstTag::UniqueConstraint));
+    } else {
+        parser.require_word("not")?;
+        parser.require_word("null")?;
+        constraint_node.append(make_ast_tag(AstTag::ExistsConstraint));
+    }
+    parent_node.append(constraint_node);
+    Ok(())
+}
+
+fn parse_constrained_element(parser: &mut Parser, tag: AstTag, parent_node: &mut Box<AstTagNode>) -> ParserResult<String> {
+    parser.require(TokenType::Identifier)?;
+    let var = parser.get_tokens()[parser.index - 1].content.to_owned();
+    parser.require(TokenType::Colon)?;
+    parser.require(TokenType::Identifier)?;
+    let mut element = make_ast_tag(tag);
+    element.append(make_ast_token(parser));
+    parent_node.append(element);
+    Ok(var)
+}
+
+pub fn parse_show_constraints(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    parser.require_word("show")?;
+    parser.require_word("constraints")?;
+    parent_node.append(make_ast_tag(AstTag::ShowConstraints));
+    Ok(())
+}
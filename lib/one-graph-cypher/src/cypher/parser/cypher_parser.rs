@@ -4,6 +4,10 @@ use super::*;
 use super::pattern_parser_delegate::*;
 use super::return_clause_parser_delegate::*;
 use super::where_clause_parser_delegate::parse_where_clause;
+use super::delete_clause_parser_delegate::parse_delete;
+use super::set_clause_parser_delegate::parse_set;
+use super::remove_clause_parser_delegate::parse_remove;
+use super::constraint_parser_delegate::*;
 
 pub fn parse(parser: &mut Parser) -> ParserResult<Box<dyn Ast>> {
     if parser.get_tokens().len() > 0  {
@@ -11,6 +15,11 @@ pub fn parse(parser: &mut Parser) -> ParserResult<Box<dyn Ast>> {
 
         let tok = &parser.get_tokens()[0];
         match tok.token_type {
+            TokenType::Create if parser.next_word("constraint") => {
+                parser.advance();
+                parse_create_constraint(parser, &mut query_node)?;
+                Ok(query_node)
+            },
             TokenType::Create =>  {
                 parser.advance();
                 let mut create_node = make_ast_tag(AstTag::Create);
@@ -28,10 +37,17 @@ pub fn parse(parser: &mut Parser) -> ParserResult<Box<dyn Ast>> {
                 parse_pattern(parser, &mut match_node)?;
                 query_node.append(match_node);
                 parse_where_clause(parser, &mut query_node)?;
+                parse_set(parser, &mut query_node)?;
+                parse_remove(parser, &mut query_node)?;
+                parse_delete(parser, &mut query_node)?;
                 parse_return(parser, &mut query_node)?;
                 
                 Ok(query_node)
             },
+            TokenType::Identifier if parser.check_word("show") => {
+                parse_show_constraints(parser, &mut query_node)?;
+                Ok(query_node)
+            },
             _ => Err(ParserError::SyntaxError(parser.index))
         }
     } else {
@@ -0,0 +1,23 @@
+use super::*;
+use super::error::*;
+use super::super::lexer::TokenType;
+
+pub fn parse_delete(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    if parser.check(TokenType::Detach) || parser.check(TokenType::Delete) {
+        let detach = parser.current_token_type_advance(TokenType::Detach);
+        parser.require(TokenType::Delete)?;
+        let mut delete_node = make_ast_tag(if detach {AstTag::DetachDelete} else {AstTag::Delete});
+        parse_delete_items(parser, &mut delete_node)?;
+        parent_node.append(delete_node);
+    }
+    Ok(())
+}
+
+fn parse_delete_items(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    parser.require(TokenType::Identifier)?;
+    parent_node.append(make_ast_token(parser));
+    if parser.current_token_type_advance(TokenType::Comma) {
+        parse_delete_items(parser, parent_node)?;
+    }
+    Ok(())
+}
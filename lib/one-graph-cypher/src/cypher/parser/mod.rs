@@ -5,6 +5,10 @@ mod properties_parser_delegate;
 mod common_parser_delegate;
 mod return_clause_parser_delegate;
 mod where_clause_parser_delegate;
+mod delete_clause_parser_delegate;
+mod set_clause_parser_delegate;
+mod remove_clause_parser_delegate;
+mod constraint_parser_delegate;
 pub mod cypher_parser;
 
 use super::lexer::*;
@@ -25,13 +29,30 @@ pub enum AstTag  {
     Query,
     Return,
     Where,
+    Delete,
+    DetachDelete,
+    Set,
+    SetItem,
+    SetProperties,
+    Remove,
     Function,
     FunctionArg,
     Item,
     AndOperator,
     OrOperator,
     EqualityOperator,
+    LessOperator,
+    LessEqualOperator,
+    GreaterOperator,
+    GreaterEqualOperator,
     ItemPropertyIdentifier,
+    CreateConstraint,
+    NodeConstraint,
+    RelationshipConstraint,
+    ConstraintProperty,
+    UniqueConstraint,
+    ExistsConstraint,
+    ShowConstraints,
 }
 
 pub trait AstVisitor {
@@ -53,6 +74,11 @@ pub trait AstVisitor {
     fn enter_function_arg(&mut self) -> AstVisitorResult<bool>;
     fn enter_item(&mut self) -> AstVisitorResult<bool>;
     fn enter_where(&mut self, node: &AstTagNode) -> AstVisitorResult<bool>;
+    fn enter_bool_operator(&mut self, node: &AstTagNode) -> AstVisitorResult<bool>;
+    fn enter_delete(&mut self, node: &AstTagNode) -> AstVisitorResult<bool>;
+    fn enter_set(&mut self, node: &AstTagNode) -> AstVisitorResult<bool>;
+    fn enter_remove(&mut self, node: &AstTagNode) -> AstVisitorResult<bool>;
+    fn enter_constraint(&mut self, node: &AstTagNode) -> AstVisitorResult<bool>;
 }
 
 #[derive(Debug, Clone)]
@@ -145,6 +171,36 @@ impl Ast for AstTagNode {
                     AstTag::Where => {
                         visitor.enter_where(self)
                     },
+                    AstTag::AndOperator |
+                    AstTag::OrOperator |
+                    AstTag::EqualityOperator |
+                    AstTag::LessOperator |
+                    AstTag::LessEqualOperator |
+                    AstTag::GreaterOperator |
+                    AstTag::GreaterEqualOperator => {
+                        visitor.enter_bool_operator(self)
+                    },
+                    AstTag::Delete |
+                    AstTag::DetachDelete => {
+                        visitor.enter_delete(self)
+                    },
+                    AstTag::Set |
+                    AstTag::SetItem |
+                    AstTag::SetProperties => {
+                        visitor.enter_set(self)
+                    },
+                    AstTag::Remove => {
+                        visitor.enter_remove(self)
+                    },
+                    AstTag::CreateConstraint |
+                    AstTag::NodeConstraint |
+                    AstTag::RelationshipConstraint |
+                    AstTag::ConstraintProperty |
+                    AstTag::UniqueConstraint |
+                    AstTag::ExistsConstraint |
+                    AstTag::ShowConstraints => {
+                        visitor.enter_constraint(self)
+                    },
                     _ => {
                         Ok(true)
                     }
@@ -293,6 +349,32 @@ impl <'a> Parser<'a> {
         self.tokens.len() > self.index + 1 && self.tokens[self.index + 1].token_type == token_type
     }
 
+    /// Words that are not lexer keywords come as identifiers, they are matched ignoring case.
+    pub fn check_word(&self, word: &str) -> bool {
+        self.check(TokenType::Identifier) && self.tokens[self.index].content.eq_ignore_ascii_case(word)
+    }
+
+    pub fn next_word(&self, word: &str) -> bool {
+        self.next_token_type(TokenType::Identifier) && self.tokens[self.index + 1].content.eq_ignore_ascii_case(word)
+    }
+
+    pub fn current_word_advance(&mut self, word: &str) -> bool {
+        if self.check_word(word) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn require_word(&mut self, word: &str) -> ParserResult<usize> {
+        if !self.check_word(word) {
+            return Err(ParserError::SyntaxError(self.index));
+        }
+        self.advance();
+        Ok(self.index)
+    }
+
 }
 
 
@@ -327,6 +409,11 @@ mod test_parser {
         run("CREATE (n:Person) RETURN id(n, r, z)");
     }
     #[test]
+    fn test_match_unlabeled() {
+        run("MATCH (n) RETURN n");
+        run("MATCH (n:Person)-[r:FRIEND_OF]->(m {name: 'bob'}) RETURN m");
+    }
+    #[test]
     fn test_create_labels() {
         run("CREATE (n:Person:Friend:Etc)");
     }
@@ -350,5 +437,34 @@ mod test_parser {
     fn test_where_clause_1() {
         run("CREATE (n:Person:Parent {test: 'Hello', case: 4.99}) WHERE id(n) = 112 AND n.test = 'hello' OR n.case = 123.9 RETURN n, id(n)");
     }
-}
 
+    #[test]
+    fn test_where_comparisons() {
+        run("MATCH (n:Person) WHERE n.age > 30 AND n.age <= 40 RETURN n");
+        run("MATCH (n:Person) WHERE n.age >= 30 OR n.name < 'm' RETURN n");
+        let mut parser = Parser::new(Lexer::new("MATCH (n:Person) WHERE n.age").get_tokens().unwrap());
+        assert!(cypher_parser::parse(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_delete() {
+        run("MATCH (n:Person)-[r:FRIEND_OF]->(m:Person) DELETE r");
+        run("MATCH (n:Person) DETACH DELETE n");
+    }
+
+    #[test]
+    fn test_set_remove() {
+        run("MATCH (n:Person) SET n.name = 'bob', n.age = 42 RETURN n");
+        run("MATCH (n:Person)-[r:FRIEND_OF]->(m:Person) SET r = {since: 2020} REMOVE n.age, m.age");
+        run("MATCH (n:Person) SET n.settings = 'dark', n.setup = true REMOVE n.removed RETURN n");
+    }
+
+    #[test]
+    fn test_constraints() {
+        run("CREATE CONSTRAINT FOR (n:Person) REQUIRE n.email IS UNIQUE");
+        run("create constraint for ()-[r:FRIEND_OF]-() require r.since is not null");
+        run("SHOW CONSTRAINTS");
+        let mut parser = Parser::new(Lexer::new("CREATE CONSTRAINT FOR (n:Person) REQUIRE m.email IS UNIQUE").get_tokens().unwrap());
+        assert!(cypher_parser::parse(&mut parser).is_err());
+    }
+}
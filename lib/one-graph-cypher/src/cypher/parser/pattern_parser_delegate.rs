@@ -112,8 +112,9 @@ fn enter_node_def(parser: &mut Parser, mut parent_node: &mut Box<AstTagNode>) ->
     let mut var_node = Box::new(AstTagNode::new_tag(AstTag::Variable));
     enter_identifier(parser, &mut var_node)?;
     parent_node.append(var_node);
-    parser.require(TokenType::Colon)?;
-    enter_labels(parser, &mut parent_node)?;
+    if parser.current_token_type_advance(TokenType::Colon) {
+        enter_labels(parser, parent_node)?;
+    }
 
     enter_properties(parser, parent_node)?;
 
@@ -50,7 +50,7 @@ fn enter_bool_expr(parser: &mut Parser, parent_node: &mut Box<dyn Ast>) -> Parse
     }
 }
 
-fn enter_prop_value(parser: &mut Parser, parent_node: &mut Box<dyn Ast>) -> ParserResult<usize> {
+pub fn enter_prop_value(parser: &mut Parser, parent_node: &mut Box<dyn Ast>) -> ParserResult<usize> {
     match parser.get_current_token_type() {
         TokenType::StringType => {
             enter_string_expr(parser, parent_node)
@@ -0,0 +1,26 @@
+use super::*;
+use super::error::*;
+use super::super::lexer::TokenType;
+
+pub fn parse_remove(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    if parser.current_token_type_advance(TokenType::Remove) {
+        let mut remove_node = make_ast_tag(AstTag::Remove);
+        parse_remove_items(parser, &mut remove_node)?;
+        parent_node.append(remove_node);
+    }
+    Ok(())
+}
+
+fn parse_remove_items(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    parser.require(TokenType::Identifier)?;
+    let mut item_prop = make_ast_tag(AstTag::ItemPropertyIdentifier);
+    item_prop.append(make_ast_token(parser));
+    parser.require(TokenType::Dot)?;
+    parser.require(TokenType::Identifier)?;
+    item_prop.append(make_ast_token(parser));
+    parent_node.append(item_prop);
+    if parser.current_token_type_advance(TokenType::Comma) {
+        parse_remove_items(parser, parent_node)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use super::*;
+use super::error::*;
+use super::super::lexer::TokenType;
+use super::properties_parser_delegate::*;
+
+pub fn parse_set(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    if parser.current_token_type_advance(TokenType::Set) {
+        let mut set_node = make_ast_tag(AstTag::Set);
+        parse_set_items(parser, &mut set_node)?;
+        parent_node.append(set_node);
+    }
+    Ok(())
+}
+
+fn parse_set_items(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
+    parser.require(TokenType::Identifier)?;
+    if parser.check(TokenType::Dot) {
+        let mut item_prop = make_ast_tag(AstTag::ItemPropertyIdentifier);
+        item_prop.append(make_ast_token(parser));
+        parser.advance();
+        parser.require(TokenType::Identifier)?;
+        item_prop.append(make_ast_token(parser));
+        parser.require(TokenType::Equals)?;
+        let mut set_item: Box<dyn Ast> = Box::new(AstTagNode::new_tag(AstTag::SetItem));
+        set_item.append(item_prop);
+        enter_prop_value(parser, &mut set_item)?;
+        parent_node.append(set_item);
+    } else {
+        let mut set_properties = make_ast_tag(AstTag::SetProperties);
+        set_properties.append(make_ast_token(parser));
+        parser.require(TokenType::Equals)?;
+        if !parser.check(TokenType::OpenBrace) {
+            return Err(ParserError::SyntaxError(parser.index));
+        }
+        enter_properties(parser, &mut set_properties)?;
+        parent_node.append(set_properties);
+    }
+    if parser.current_token_type_advance(TokenType::Comma) {
+        parse_set_items(parser, parent_node)?;
+    }
+    Ok(())
+}
@@ -41,6 +41,15 @@ fn parse_boolean_operator(parser: &mut Parser, prev_expr: Box<AstTagNode>) -> Pa
     }
 }
 
+fn parse_comparison_operator(parser: &mut Parser) -> ParserResult<Box<AstTagNode>> {
+    let operators = [(TokenType::Equals, AstTag::EqualityOperator), (TokenType::Less, AstTag::LessOperator),
+        (TokenType::LessEquals, AstTag::LessEqualOperator), (TokenType::Greater, AstTag::GreaterOperator),
+        (TokenType::GreaterEquals, AstTag::GreaterEqualOperator)];
+    let tag = operators.iter().find(|(token_type, _)| parser.check(*token_type)).map(|(_, tag)| *tag).ok_or(ParserError::SyntaxError(parser.index))?;
+    parser.advance();
+    Ok(make_ast_tag(tag))
+}
+
 fn parse_boolean_expression_terminal(parser: &mut Parser, parent_node: &mut Box<AstTagNode>) -> ParserResult<()> {
     match parser.get_current_token_type() {
         TokenType::Integer | TokenType::Float | TokenType::True | TokenType::False | TokenType::StringType => {
@@ -60,6 +69,7 @@ fn parse_boolean_expression_terminal(parser: &mut Parser, parent_node: &mut Box<
                 if parser.check(TokenType::Identifier) {
                     parser.advance();
                     item_prop.append(make_ast_token(parser));
+                    parent_node.append(item_prop);
                 } else {
                     return Err(ParserError::SyntaxError(parser.index))
                 }
@@ -78,22 +88,28 @@ fn parse_boolean_expression(parser: &mut Parser) -> ParserResult<Box<AstTagNode>
     match parser.get_current_token_type() {
         TokenType::Integer => {
             parser.advance();
+            let literal = make_ast_token(parser);
             parser.require(TokenType::Equals)?;
             let mut eqop = make_ast_tag(AstTag::EqualityOperator);
+            eqop.append(literal);
             parse_boolean_expression_terminal(parser, &mut eqop)?;
             parse_boolean_operator(parser, eqop)
         },
         TokenType::Float => {
             parser.advance();
+            let literal = make_ast_token(parser);
             parser.require(TokenType::Equals)?;
             let mut eqop = make_ast_tag(AstTag::EqualityOperator);
+            eqop.append(literal);
             parse_boolean_expression_terminal(parser, &mut eqop)?;
             parse_boolean_operator(parser, eqop)
         },
         TokenType::True | TokenType::False => {
             parser.advance();
+            let literal = make_ast_token(parser);
             parser.require(TokenType::Equals)?;
             let mut eqop = make_ast_tag(AstTag::EqualityOperator);
+            eqop.append(literal);
             parse_boolean_expression_terminal(parser, &mut eqop)?;
             parse_boolean_operator(parser, eqop)
         },
@@ -118,11 +134,10 @@ fn parse_boolean_expression(parser: &mut Parser) -> ParserResult<Box<AstTagNode>
                 if parser.check(TokenType::Identifier) {
                     parser.advance();
                     item_prop.append(make_ast_token(parser));
-                    parser.require(TokenType::Equals)?;
-                    let mut eqop = make_ast_tag(AstTag::EqualityOperator);
-                    eqop.append(item_prop);
-                    parse_boolean_expression_terminal(parser, &mut eqop)?;
-                    return parse_boolean_operator(parser, eqop)
+                    let mut op = parse_comparison_operator(parser)?;
+                    op.append(item_prop);
+                    parse_boolean_expression_terminal(parser, &mut op)?;
+                    return parse_boolean_operator(parser, op)
                 } else {
                     return Err(ParserError::SyntaxError(parser.index))
                 }
@@ -1,21 +1,70 @@
 use super::*;
 use super::super::model::*;
 use super::parser::*;
+use super::parser::error::ParserError;
 use one_graph_core::graph::traits::{GraphContainerTrait};
 use one_graph_core::graph::*;
 use one_graph_core::model::*;
+use one_graph_core::model::constraint::*;
+use one_graph_core::error::*;
 
-pub fn process_cypher_query(query: &str) -> Option<Request> {
+pub fn process_cypher_query(query: &str) -> GraphResult<Request> {
     let mut lexer = lexer::Lexer::new(query);
     match lexer.get_tokens() {
         Ok(tokens) => {
             let mut parser = parser::Parser::new(tokens);
-            let ast = parser::cypher_parser::parse(&mut parser).ok()?;
+            let ast = parser::cypher_parser::parse(&mut parser).map_err(|e| match e {
+                ParserError::SyntaxError(index) => GraphError::InvalidRequest(format!("syntax error around {}", index)),
+                ParserError::EndOfFile => GraphError::InvalidRequest(String::from("unexpected end of query")),
+            })?;
             let mut visitor = CypherAstVisitor::new();
             parser::walk_ast(&mut visitor, &ast);
-            visitor.request
+            let mut request = visitor.request.ok_or_else(|| GraphError::InvalidRequest(String::from("unsupported query")))?;
+            if let Some(where_clause) = &request.where_clause {
+                let conditions = boolean_optimizer::extract_mandatory_conditions_from_bool_expr(&where_clause.expressions)
+                    .ok_or_else(|| GraphError::InvalidRequest(String::from("unsupported where clause")))?;
+                for condition in &conditions {
+                    apply_condition(&mut request.pattern, condition)?;
+                }
+            }
+            Ok(request)
         }
-        Err(value) => None
+        Err(value) => Err(GraphError::InvalidRequest(format!("cannot read the query: {:?}", value)))
+    }
+}
+
+/// Restricts the pattern elements bound to the condition variable, only node properties can be compared to a range.
+fn apply_condition(pattern: &mut PropertyGraph, condition: &BoolCondition) -> GraphResult<()> {
+    let is_bound = |var: &Option<String>| var.as_deref() == Some(condition.variable.as_str());
+    let mut prop = Property::new();
+    prop.set_name(&condition.property);
+    prop.set_value(Some(condition.value.clone()));
+    let range = match condition.operator {
+        Operator::Equal => None,
+        Operator::Inferior => Some(PropertyRange::new(&condition.property, None, Some((condition.value.clone(), false)))),
+        Operator::InferiorOrEqual => Some(PropertyRange::new(&condition.property, None, Some((condition.value.clone(), true)))),
+        Operator::Superior => Some(PropertyRange::new(&condition.property, Some((condition.value.clone(), false)), None)),
+        Operator::SuperiorOrEqual => Some(PropertyRange::new(&condition.property, Some((condition.value.clone(), true)), None)),
+    };
+    let mut bound = false;
+    for node in pattern.get_nodes_mut().iter_mut().filter(|n| is_bound(n.get_var())) {
+        match &range {
+            Some(range) => node.add_property_range(range.clone()),
+            None => set_in_list(node.get_properties_mut(), &prop),
+        }
+        bound = true;
+    }
+    for rel in pattern.get_relationships_mut().iter_mut().filter(|r| is_bound(r.get_var())) {
+        if range.is_some() {
+            return Err(GraphError::InvalidRequest(format!("cannot compare the relationship property {}.{} to a range", condition.variable, condition.property)));
+        }
+        set_in_list(rel.get_properties_mut(), &prop);
+        bound = true;
+    }
+    if bound {
+        Ok(())
+    } else {
+        Err(GraphError::InvalidRequest(format!("unknown variable {} in the where clause", condition.variable)))
     }
 }
 
@@ -35,6 +84,12 @@ enum VisitorState {
     FunctionCall,
     FunctionArg,
     ReturnItem,
+    DeleteItem,
+    SetItem,
+    SetProperties,
+    RemoveItem,
+    ConstraintLabel,
+    ConstraintProperty,
 }
 
 enum IdentifierType {
@@ -78,6 +133,78 @@ impl AstVisitor for CypherAstVisitor {
         }
         Ok(false)
     }
+    fn enter_bool_operator(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
+        Ok(true)
+    }
+    fn enter_delete(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+        if let Some(request) = &mut self.request {
+            request.directive = Directive::DELETE;
+            request.delete_clause = Some(DeleteClause::new(node.ast_tag == Some(AstTag::DetachDelete)));
+            self.state = VisitorState::DeleteItem;
+        }
+        Ok(true)
+    }
+    fn enter_set(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+        if let Some(request) = &mut self.request {
+            match node.ast_tag {
+                Some(AstTag::SetItem) => {
+                    if let Some(set) = &mut request.set_clause {
+                        set.items.push(SetItem::Property(String::new(), Property::new()));
+                    }
+                    self.state = VisitorState::SetItem;
+                },
+                Some(AstTag::SetProperties) => {
+                    if let Some(set) = &mut request.set_clause {
+                        set.items.push(SetItem::Properties(String::new(), Vec::new()));
+                    }
+                    self.state = VisitorState::SetProperties;
+                },
+                _ => {
+                    request.directive = Directive::UPDATE;
+                    request.set_clause = Some(SetClause::new());
+                }
+            }
+        }
+        Ok(true)
+    }
+    fn enter_remove(&mut self, _node: &AstTagNode) -> AstVisitorResult<bool> {
+        if let Some(request) = &mut self.request {
+            request.directive = Directive::UPDATE;
+            request.remove_clause = Some(RemoveClause::new());
+            self.state = VisitorState::RemoveItem;
+        }
+        Ok(true)
+    }
+    fn enter_constraint(&mut self, node: &AstTagNode) -> AstVisitorResult<bool> {
+        match node.ast_tag {
+            Some(AstTag::CreateConstraint) => {
+                let mut request = Request::new(Directive::CONSTRAINT);
+                request.constraint = Some(Constraint::new(ConstraintKind::Exists, ElementKind::Node, "", ""));
+                self.request = Some(request);
+            },
+            Some(AstTag::ShowConstraints) => {
+                self.request = Some(Request::new(Directive::SHOW));
+            },
+            _ => {
+                if let Some(constraint) = self.request.as_mut().and_then(|req| req.constraint.as_mut()) {
+                    match node.ast_tag {
+                        Some(AstTag::NodeConstraint) => {
+                            constraint.target = ElementKind::Node;
+                            self.state = VisitorState::ConstraintLabel;
+                        },
+                        Some(AstTag::RelationshipConstraint) => {
+                            constraint.target = ElementKind::Relationship;
+                            self.state = VisitorState::ConstraintLabel;
+                        },
+                        Some(AstTag::ConstraintProperty) => self.state = VisitorState::ConstraintProperty,
+                        Some(AstTag::UniqueConstraint) => constraint.kind = ConstraintKind::Unique,
+                        _ => constraint.kind = ConstraintKind::Exists,
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
     fn enter_function(&mut self) -> AstVisitorResult<bool> {
         if let Some(request) = &mut self.request {
             if let Some(_) = &mut request.return_clause {
@@ -184,6 +311,11 @@ impl AstVisitor for CypherAstVisitor {
                 })
             }
         }
+        if self.state == VisitorState::SetProperties {
+            if let Some(SetItem::Properties(_, props)) = self.request.as_mut().and_then(|req| last_set_item(req)) {
+                props.push(Property::new());
+            }
+        }
         if self.state == VisitorState::NodeProperty {
             if let Some(node_id) = self.curr_node {
                 self.curr_property_id = self.request.as_mut().map(|req| {
@@ -235,6 +367,10 @@ impl AstVisitor for CypherAstVisitor {
                         }
                     }
                 },
+                VisitorState::SetItem |
+                VisitorState::SetProperties => {
+                    set_update_value(req, value.map(PropertyValue::PInteger));
+                },
                 _ => {}
             }
         }
@@ -277,6 +413,10 @@ impl AstVisitor for CypherAstVisitor {
                         }
                     }
                 },
+                VisitorState::SetItem |
+                VisitorState::SetProperties => {
+                    set_update_value(req, value.map(PropertyValue::PFloat));
+                },
                 _ => {}
             }
         }
@@ -319,6 +459,10 @@ impl AstVisitor for CypherAstVisitor {
                         }
                     }
                 },
+                VisitorState::SetItem |
+                VisitorState::SetProperties => {
+                    set_update_value(req, value.map(|sv| PropertyValue::PString(String::from(sv))));
+                },
                 _ => {}
             }
         }
@@ -361,6 +505,10 @@ impl AstVisitor for CypherAstVisitor {
                         }
                     }
                 },
+                VisitorState::SetItem |
+                VisitorState::SetProperties => {
+                    set_update_value(req, value.map(PropertyValue::PBool));
+                },
                 _ => {}
             }
         }
@@ -496,6 +644,33 @@ impl AstVisitor for CypherAstVisitor {
                             ret.expressions.push(ReturnExpression::Item(String::from(key)));
                         }
                     }
+                },
+                VisitorState::DeleteItem => {
+                    if let Some(delete) = &mut req.delete_clause {
+                        delete.items.push(String::from(key));
+                    }
+                },
+                VisitorState::SetItem |
+                VisitorState::SetProperties => {
+                    set_update_identifier(req, key);
+                },
+                VisitorState::RemoveItem => {
+                    if let Some(remove) = &mut req.remove_clause {
+                        match remove.items.last_mut() {
+                            Some(item) if item.1.is_empty() => item.1 = String::from(key),
+                            _ => remove.items.push((String::from(key), String::new())),
+                        }
+                    }
+                },
+                VisitorState::ConstraintLabel => {
+                    if let Some(constraint) = &mut req.constraint {
+                        constraint.label = String::from(key);
+                    }
+                },
+                VisitorState::ConstraintProperty => {
+                    if let Some(constraint) = &mut req.constraint {
+                        constraint.property = String::from(key);
+                    }
                 }
                 _ => {}
             }
@@ -505,6 +680,42 @@ impl AstVisitor for CypherAstVisitor {
     }
 }
 
+fn last_set_item(req: &mut Request) -> Option<&mut SetItem> {
+    req.set_clause.as_mut().and_then(|set| set.items.last_mut())
+}
+
+fn set_update_identifier(req: &mut Request, key: &str) {
+    match last_set_item(req) {
+        Some(SetItem::Property(var, prop)) => {
+            if var.is_empty() {
+                *var = String::from(key);
+            } else {
+                prop.set_name(key);
+            }
+        },
+        Some(SetItem::Properties(var, props)) => {
+            if var.is_empty() {
+                *var = String::from(key);
+            } else if let Some(prop) = props.last_mut() {
+                prop.set_name(key);
+            }
+        },
+        None => {}
+    }
+}
+
+fn set_update_value(req: &mut Request, value: Option<PropertyValue>) {
+    match last_set_item(req) {
+        Some(SetItem::Property(_, prop)) => prop.set_value(value),
+        Some(SetItem::Properties(_, props)) => {
+            if let Some(prop) = props.last_mut() {
+                prop.set_value(value);
+            }
+        },
+        None => {}
+    }
+}
+
 #[cfg(test)]
 mod test_query_engine {
     use super::*;
@@ -512,7 +723,7 @@ mod test_query_engine {
     #[test]
     fn test_create_0() {
         let request = process_cypher_query("CREATE (n:Person)");
-        if let  Some(req) = request {
+        if let  Ok(req) = request {
             let node = req.pattern.get_node_ref(&NodeIndex::new(0));
             assert_eq!(node.get_var(), &Some(String::from("n")));
             assert_eq!(node.get_labels_ref()[0], String::from("Person"));
@@ -525,7 +736,7 @@ mod test_query_engine {
     #[test]
     fn test_create_1() {
         let request = process_cypher_query("CREATE (n:Person:Parent {test: 'Hello', case: 4.99})");
-        if let  Some(req) = request {
+        if let  Ok(req) = request {
             let node = req.pattern.get_node_ref(&NodeIndex::new(0));
             assert_eq!(node.get_var(), &Some(String::from("n")));
             assert_eq!(node.get_labels_ref()[0], String::from("Person"));
@@ -544,7 +755,7 @@ mod test_query_engine {
     #[test]
     fn test_create_2() {
         let request = process_cypher_query("CREATE (n:Person:Parent)-[r:FRIEND_OF]->(p:Person)");
-        if let  Some(req) = request {
+        if let  Ok(req) = request {
             let node = req.pattern.get_node_ref(&NodeIndex::new(0));
             assert_eq!(node.get_var(), &Some(String::from("n")));
             assert_eq!(node.get_labels_ref()[0], String::from("Person"));
@@ -558,4 +769,60 @@ mod test_query_engine {
         }
         
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_detach_delete() {
+        let request = process_cypher_query("MATCH (n:Person)-[r:FRIEND_OF]->(p:Person) DETACH DELETE n, r");
+        if let Ok(req) = request {
+            let delete = req.delete_clause.unwrap();
+            assert!(delete.detach);
+            assert_eq!(delete.items, vec![String::from("n"), String::from("r")]);
+        } else {
+            panic!("no request found");
+        }
+    }
+
+    #[test]
+    fn test_set_remove() {
+        let request = process_cypher_query("MATCH (n:Person)-[r:FRIEND_OF]->(m:Person) SET n.name = 'bob', r = {since: 2020, close: true} REMOVE m.age");
+        if let Ok(req) = request {
+            let set = req.set_clause.unwrap();
+            assert_eq!(set.items.len(), 2);
+            match &set.items[0] {
+                SetItem::Property(var, prop) => {
+                    assert_eq!(var, "n");
+                    assert_eq!(prop.get_name(), &Some(String::from("name")));
+                    assert_eq!(prop.get_value(), &Some(PropertyValue::PString(String::from("bob"))));
+                },
+                _ => panic!("expected a property item"),
+            }
+            match &set.items[1] {
+                SetItem::Properties(var, props) => {
+                    assert_eq!(var, "r");
+                    assert_eq!(props.len(), 2);
+                    assert_eq!(props[1].get_value(), &Some(PropertyValue::PBool(true)));
+                },
+                _ => panic!("expected a properties item"),
+            }
+            assert_eq!(req.remove_clause.unwrap().items, vec![(String::from("m"), String::from("age"))]);
+        } else {
+            panic!("no request found");
+        }
+    }
+
+    #[test]
+    fn test_where_conditions() {
+        let req = process_cypher_query("MATCH (n:Person)-[r:KNOWS]->(m:Person) WHERE n.age > 30 AND (r.since = 2020 AND m.age <= 40) RETURN n").unwrap();
+        let n = req.pattern.get_node_ref(&NodeIndex::new(0));
+        assert_eq!(n.get_property_ranges_ref().len(), 1);
+        assert!(!n.get_property_ranges_ref()[0].contains(&PropertyValue::PInteger(30)));
+        assert!(n.get_property_ranges_ref()[0].contains(&PropertyValue::PInteger(31)));
+        let m = req.pattern.get_node_ref(&NodeIndex::new(1));
+        assert!(m.get_property_ranges_ref()[0].contains(&PropertyValue::PInteger(40)));
+        let r = req.pattern.get_relationship_ref(&EdgeIndex::new(0));
+        assert_eq!(r.get_properties_ref()[0].get_value(), &Some(PropertyValue::PInteger(2020)));
+        assert!(matches!(process_cypher_query("MATCH (n:Person) WHERE n.age > 30 OR n.age < 10 RETURN n"), Err(GraphError::InvalidRequest(_))));
+        assert!(matches!(process_cypher_query("MATCH (n:Person) WHERE p.age > 30 RETURN n"), Err(GraphError::InvalidRequest(_))));
+        assert!(matches!(process_cypher_query("MATCH (n)-[r:KNOWS]->(m) WHERE r.since > 2000 RETURN n"), Err(GraphError::InvalidRequest(_))));
+    }
+}
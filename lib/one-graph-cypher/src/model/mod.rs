@@ -1,11 +1,16 @@
 use super::cypher::parser::Ast;
-use one_graph_core::model::PropertyGraph;
+use one_graph_core::model::{Property, PropertyGraph, PropertyValue};
+use one_graph_core::model::constraint::Constraint;
 
 
+#[allow(clippy::upper_case_acronyms)]
 pub enum Directive {
     CREATE,
     MATCH,
-    DELETE
+    DELETE,
+    UPDATE,
+    CONSTRAINT,
+    SHOW,
 }
 
 pub struct FunctionCall {
@@ -44,6 +49,43 @@ impl WhereClause {
     }
 }
 
+pub struct DeleteClause {
+    pub detach: bool,
+    pub items: Vec<String>,
+}
+
+impl DeleteClause {
+    pub fn new(detach: bool) -> Self {
+        DeleteClause{detach, items: Vec::new()}
+    }
+}
+
+pub enum SetItem {
+    Property(String, Property),
+    Properties(String, Vec<Property>),
+}
+
+pub struct SetClause {
+    pub items: Vec<SetItem>,
+}
+
+impl SetClause {
+    pub fn new() -> Self {
+        SetClause{items: Vec::new()}
+    }
+}
+
+pub struct RemoveClause {
+    pub items: Vec<(String, String)>,
+}
+
+impl RemoveClause {
+    pub fn new() -> Self {
+        RemoveClause{items: Vec::new()}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Operator {
     Equal,
     Inferior,
@@ -51,10 +93,14 @@ pub enum Operator {
     InferiorOrEqual,
     SuperiorOrEqual,
 }
+
+/// Comparison of the property of a pattern variable with a literal, `n.age > 30`.
+#[derive(Debug, Clone, PartialEq)]
 pub struct BoolCondition {
-    pub first_member: Box<dyn Ast>,
-    pub second_member: Box<dyn Ast>,
+    pub variable: String,
+    pub property: String,
     pub operator: Operator,
+    pub value: PropertyValue,
 }
 
 pub struct Request {
@@ -62,10 +108,14 @@ pub struct Request {
     pub directive: Directive,
     pub return_clause: Option<ReturnClause>,
     pub where_clause: Option<WhereClause>,
+    pub delete_clause: Option<DeleteClause>,
+    pub set_clause: Option<SetClause>,
+    pub remove_clause: Option<RemoveClause>,
+    pub constraint: Option<Constraint>,
 }
 
 impl Request {
     pub fn new(directive: Directive) -> Self {
-        Request {pattern: PropertyGraph::new(), directive: directive, return_clause: None, where_clause: None}
+        Request {pattern: PropertyGraph::new(), directive, return_clause: None, where_clause: None, delete_clause: None, set_clause: None, remove_clause: None, constraint: None}
     }
 }
\ No newline at end of file
@@ -1,10 +1,13 @@
 use one_graph_core::model::init::InitContext;
 use one_graph_core::model::*;
+use one_graph_core::model::constraint::*;
+use one_graph_core::error::*;
 use super::cypher::query_engine::process_cypher_query;
 use one_graph_core::graph_engine::GraphEngine;
 use super::model::*;
 
 use bson::Document;
+use std::collections::HashSet;
 
 
 pub struct GraphStore<'a> {
@@ -17,47 +20,227 @@ impl <'a> GraphStore<'a> {
         GraphStore{ctx: ctx}
     }
 
-    pub fn process_cypher_query(&mut self, query: &str) -> Option<Document> {
+    /// Store opened with the settings of a configuration, see `DbConfig::get_init_context`.
+    pub fn with_context(ctx: InitContext<'a>) -> Self {
+        GraphStore{ctx}
+    }
+
+    pub fn process_cypher_query(&mut self, query: &str) -> GraphResult<Document> {
         let req = process_cypher_query(query)?;
-        let mut graph_engine = GraphEngine::new(&self.ctx);
+        let mut graph_engine = GraphEngine::new(&self.ctx)?;
+        let tx = graph_engine.begin()?;
         match req.directive {
             Directive::CREATE => {
-                let res = graph_engine.create_graph(&req.pattern)?;
-                graph_engine.sync();
-                req.return_clause.and_then(|ret| process_return_clause(&ret, &res))
+                let created = graph_engine.create_graph(&req.pattern);
+                let res = graph_engine.commit_or_rollback(tx, created)?;
+                match req.return_clause {
+                    Some(ret) => process_return_clause(&mut graph_engine, &ret, &res),
+                    None => Ok(Document::new()),
+                }
             },
             Directive::MATCH => {
-                let res = graph_engine.match_pattern(&req.pattern)?;
-                req.return_clause.and_then(|ret| {
-                    let mut doc = Document::new();
-                    let mut counter = 0;
-                    for graph in &res {
-                        doc.insert(counter.to_string(), process_return_clause(&ret, graph)?);
-                        counter += 1;
+                let matched = graph_engine.match_pattern(&req.pattern, None);
+                let res = graph_engine.commit_or_rollback(tx, matched)?;
+                let mut doc = Document::new();
+                if let Some(ret) = req.return_clause {
+                    for (counter, graph) in res.iter().enumerate() {
+                        doc.insert(counter.to_string(), process_return_clause(&mut graph_engine, &ret, graph)?);
                     }
-                    Some(doc)
-                })
+                }
+                Ok(doc)
+            },
+            Directive::UPDATE => {
+                let updated = graph_engine.match_pattern(&req.pattern, None).and_then(|mut matched| {
+                    let mut count = 0;
+                    for graph in &mut matched {
+                        if let Some(set) = &req.set_clause {
+                            count += process_set_clause(&mut graph_engine, set, graph)?;
+                        }
+                        if let Some(remove) = &req.remove_clause {
+                            count += process_remove_clause(&mut graph_engine, remove, graph)?;
+                        }
+                    }
+                    Ok((matched, count))
+                });
+                let (res, count) = graph_engine.commit_or_rollback(tx, updated)?;
+                match req.return_clause {
+                    Some(ret) => {
+                        let mut doc = Document::new();
+                        for (counter, graph) in res.iter().enumerate() {
+                            doc.insert(counter.to_string(), process_return_clause(&mut graph_engine, &ret, graph)?);
+                        }
+                        Ok(doc)
+                    },
+                    None => Ok(doc!{"properties_set": count})
+                }
             },
             Directive::DELETE => {
-                Some(Document::new())
+                let deleted = match &req.delete_clause {
+                    Some(delete) => graph_engine.match_pattern(&req.pattern, None).and_then(|matched| process_delete_clause(&mut graph_engine, delete, &matched)),
+                    None => Err(GraphError::InvalidRequest(String::from("nothing to delete"))),
+                };
+                graph_engine.commit_or_rollback(tx, deleted)
+            },
+            Directive::CONSTRAINT => {
+                let created = match &req.constraint {
+                    Some(constraint) => graph_engine.create_constraint(constraint),
+                    None => Err(GraphError::InvalidRequest(String::from("incomplete constraint"))),
+                };
+                let added = graph_engine.commit_or_rollback(tx, created)?;
+                Ok(doc!{"constraints_added": if added {1i64} else {0i64}})
+            },
+            Directive::SHOW => {
+                let constraints = graph_engine.list_constraints();
+                let res = graph_engine.commit_or_rollback(tx, constraints)?;
+                let mut doc = Document::new();
+                for (counter, constraint) in res.iter().enumerate() {
+                    doc.insert(counter.to_string(), constraint_to_document(constraint));
+                }
+                Ok(doc)
+            }
+        }
+    }
+}
+
+/// Matched and created elements always carry their id.
+fn stored_id(id: Option<u64>) -> GraphResult<u64> {
+    id.ok_or_else(|| GraphError::Inconsistent(String::from("a matched element has no id")))
+}
+
+fn process_delete_clause(graph_engine: &mut GraphEngine, delete_clause: &DeleteClause, matched: &[PropertyGraph]) -> GraphResult<Document> {
+    let mut nodes = HashSet::new();
+    let mut relationships = HashSet::new();
+    for graph in matched {
+        for node in graph.get_nodes() {
+            if let Some(var) = node.get_var() {
+                if delete_clause.items.contains(var) {
+                    nodes.insert(stored_id(node.get_id())?);
+                }
+            }
+        }
+        for relationship in graph.get_relationships() {
+            if let Some(var) = relationship.get_var() {
+                if delete_clause.items.contains(var) {
+                    relationships.insert(stored_id(relationship.get_id())?);
+                }
             }
         }
     }
+    for rel_id in &relationships {
+        graph_engine.delete_relationship(*rel_id)?;
+    }
+    for node_id in &nodes {
+        if delete_clause.detach {
+            graph_engine.detach_delete_node(*node_id)?;
+        } else {
+            graph_engine.delete_node(*node_id)?;
+        }
+    }
+    Ok(doc!{
+        "nodes_deleted": nodes.len() as i64,
+        "relationships_deleted": relationships.len() as i64
+    })
 }
 
-fn process_return_clause(return_clause: &ReturnClause, result: &PropertyGraph) -> Option<Document> {
+fn constraint_to_document(constraint: &Constraint) -> Document {
+    doc!{
+        "kind": constraint.kind.get_name(),
+        "entity": constraint.target.get_name(),
+        "label": constraint.label.clone(),
+        "property": constraint.property.clone()
+    }
+}
+
+fn process_set_clause(graph_engine: &mut GraphEngine, set_clause: &SetClause, graph: &mut PropertyGraph) -> GraphResult<i64> {
+    let mut count = 0;
+    for item in &set_clause.items {
+        let (var, props) = match item {
+            SetItem::Property(var, prop) => (var, std::slice::from_ref(prop)),
+            SetItem::Properties(var, props) => (var, props.as_slice()),
+        };
+        let replace = matches!(item, SetItem::Properties(_, _));
+        for node in graph.get_nodes_mut() {
+            if node.get_var().as_ref() == Some(var) {
+                let node_id = stored_id(node.get_id())?;
+                if replace {
+                    graph_engine.replace_node_properties(node_id, props)?;
+                    node.set_properties(props.to_vec());
+                } else {
+                    graph_engine.set_node_property(node_id, &props[0])?;
+                    set_in_list(node.get_properties_mut(), &props[0]);
+                }
+                count += props.len() as i64;
+            }
+        }
+        for relationship in graph.get_relationships_mut() {
+            if relationship.get_var().as_ref() == Some(var) {
+                let rel_id = stored_id(relationship.get_id())?;
+                if replace {
+                    graph_engine.replace_relationship_properties(rel_id, props)?;
+                    relationship.set_properties(props.to_vec());
+                } else {
+                    graph_engine.set_relationship_property(rel_id, &props[0])?;
+                    set_in_list(relationship.get_properties_mut(), &props[0]);
+                }
+                count += props.len() as i64;
+            }
+        }
+    }
+    Ok(count)
+}
+
+fn process_remove_clause(graph_engine: &mut GraphEngine, remove_clause: &RemoveClause, graph: &mut PropertyGraph) -> GraphResult<i64> {
+    let mut count = 0;
+    for (var, name) in &remove_clause.items {
+        for node in graph.get_nodes_mut() {
+            if node.get_var().as_ref() == Some(var) && graph_engine.remove_node_property(stored_id(node.get_id())?, name)? {
+                node.get_properties_mut().retain(|p| p.get_name().as_ref() != Some(name));
+                count += 1;
+            }
+        }
+        for relationship in graph.get_relationships_mut() {
+            if relationship.get_var().as_ref() == Some(var) && graph_engine.remove_relationship_property(stored_id(relationship.get_id())?, name)? {
+                relationship.get_properties_mut().retain(|p| p.get_name().as_ref() != Some(name));
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+fn process_return_clause(graph_engine: &mut GraphEngine, return_clause: &ReturnClause, result: &PropertyGraph) -> GraphResult<Document> {
     let mut res = Document::new();
     for expr in &return_clause.expressions {
         match expr {
             ReturnExpression::Item(item) => {
-                res.insert(item, evaluate_item(result, item)?);
+                let value = evaluate_item(result, item).ok_or_else(|| GraphError::InvalidRequest(format!("variable {} is not defined", item)))?;
+                res.insert(item, value);
             }
             ReturnExpression::FunctionCall(func_call) => {
-                res.insert(&func_call.name, evaluate_function_call(result, func_call)?);
+                let value = evaluate_function_call(graph_engine, result, func_call)?
+                    .ok_or_else(|| GraphError::InvalidRequest(format!("cannot evaluate {}", func_call.name)))?;
+                res.insert(&func_call.name, value);
             }
         }
     }
-    Some(res)
+    Ok(res)
+}
+
+fn convert_properties(properties: &[Property]) -> Vec<Document> {
+    let mut props = Vec::new();
+    for p in properties {
+        if let (Some(n), Some(v)) = (p.get_name(), p.get_value()) {
+            let mut bprop = Document::new();
+            match v {
+                PropertyValue::PBool(v) => bprop.insert(n, v),
+                PropertyValue::PFloat(f) => bprop.insert(n, f),
+                PropertyValue::PInteger(i) => bprop.insert(n, i),
+                PropertyValue::PString(s) => bprop.insert(n, s),
+            };
+            props.push(bprop);
+        }
+    }
+    props
 }
 
 fn evaluate_item(result: &PropertyGraph, item: &str) -> Option<Document> {
@@ -65,28 +248,10 @@ fn evaluate_item(result: &PropertyGraph, item: &str) -> Option<Document> {
         if let Some(var) = node.get_var() {
             if var == item {
 
-                let mut props = Vec::new();
-                for p in node.get_properties_ref() {
-                    
-                    let name = p.get_name();
-                    let value = p.get_value();
-                    if let Some(n) = name {
-                        if let Some(v) = value {
-                            let mut bprop = Document::new();
-                            match v {
-                                PropertyValue::PBool(v) => bprop.insert(n, v),
-                                PropertyValue::PFloat(f) => bprop.insert(n, f),
-                                PropertyValue::PInteger(i) => bprop.insert(n, i),
-                                PropertyValue::PString(s) => bprop.insert(n, s),
-                            };
-                            props.push(bprop);
-                        }
-                        
-                    }
-                }
                 return Some(doc!{
                     "id": node.get_id()?,
-                    "properties": props
+                    "labels": node.get_labels_ref().clone(),
+                    "properties": convert_properties(node.get_properties_ref())
                 });
             }
         }
@@ -96,28 +261,10 @@ fn evaluate_item(result: &PropertyGraph, item: &str) -> Option<Document> {
         if let Some(var) = relationship.get_var() {
             if var == item {
 
-                let mut props = Vec::new();
-                for p in relationship.get_properties_ref() {
-                    
-                    let name = p.get_name();
-                    let value = p.get_value();
-                    if let Some(n) = name {
-                        if let Some(v) = value {
-                            let mut bprop = Document::new();
-                            match v {
-                                PropertyValue::PBool(v) => bprop.insert(n, v),
-                                PropertyValue::PFloat(f) => bprop.insert(n, f),
-                                PropertyValue::PInteger(i) => bprop.insert(n, i),
-                                PropertyValue::PString(s) => bprop.insert(n, s),
-                            };
-                            props.push(bprop);
-                        }
-                        
-                    }
-                }
                 return Some(doc!{
                     "id": relationship.get_id()?,
-                    "properties": props
+                    "labels": relationship.get_labels_ref().clone(),
+                    "properties": convert_properties(relationship.get_properties_ref())
                 });
             }
         }
@@ -127,17 +274,62 @@ fn evaluate_item(result: &PropertyGraph, item: &str) -> Option<Document> {
     
 }
 
-fn evaluate_function_call(result: &PropertyGraph, func_call: &FunctionCall) -> Option<Document> {
+/// None when the function is unknown or its argument is not a node or relationship of the result.
+fn evaluate_function_call(graph_engine: &mut GraphEngine, result: &PropertyGraph, func_call: &FunctionCall) -> GraphResult<Option<Document>> {
     if func_call.name == "id" {
         for node in result.get_nodes() {
             if let Some(var) = node.get_var() {
                 if func_call.args.contains(var) {
-                    return Some(doc!{
-                        var: node.get_id()?
+                    return Ok(Some(doc!{
+                        var: stored_id(node.get_id())?
+                    }));
+                }
+            }
+        }
+    } else if func_call.name == "history" {
+        return evaluate_history(graph_engine, result, func_call);
+    }
+    Ok(None)
+}
+
+fn evaluate_history(graph_engine: &mut GraphEngine, result: &PropertyGraph, func_call: &FunctionCall) -> GraphResult<Option<Document>> {
+    for node in result.get_nodes() {
+        if let Some(var) = node.get_var() {
+            if func_call.args.contains(var) {
+                let mut versions = Vec::new();
+                for nv in graph_engine.node_history(stored_id(node.get_id())?, None, None)? {
+                    versions.push(doc!{
+                        "version": nv.version,
+                        "properties": convert_properties(nv.node.get_properties_ref()),
+                        "labels": nv.node.get_labels_ref().clone(),
+                        "outbound": nv.outbound_relationships,
+                        "inbound": nv.inbound_relationships,
                     });
                 }
+                return Ok(Some(doc!{
+                    var: versions
+                }));
             }
         }
     }
-    None
-}
\ No newline at end of file
+    for relationship in result.get_relationships() {
+        if let Some(var) = relationship.get_var() {
+            if func_call.args.contains(var) {
+                let mut versions = Vec::new();
+                for rv in graph_engine.relationship_history(stored_id(relationship.get_id())?, None, None)? {
+                    versions.push(doc!{
+                        "version": rv.version,
+                        "properties": convert_properties(rv.relationship.get_properties_ref()),
+                        "labels": rv.relationship.get_labels_ref().clone(),
+                        "source": rv.source,
+                        "target": rv.target,
+                    });
+                }
+                return Ok(Some(doc!{
+                    var: versions
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
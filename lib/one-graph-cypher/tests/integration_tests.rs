@@ -2,6 +2,7 @@ extern crate one_graph_core;
 
 use one_graph_cypher::store::GraphStore;
 use one_graph_core::test_utils::*;
+use one_graph_core::error::GraphError;
 
 #[test]
 fn create_graph() {
@@ -11,4 +12,113 @@ fn create_graph() {
     println!("{}", res);
     let mres = store.process_cypher_query("MATCH (n:Person:Parent)-[r:FRIEND_OF]->(p:Person) RETURN n, r, p").unwrap();
     println!("{}", mres);
-}
\ No newline at end of file
+    let labels = mres.get_document("0").unwrap().get_document("n").unwrap().get_array("labels").unwrap();
+    assert_eq!(labels.len(), 2);
+}
+#[test]
+fn history_function() {
+    let db_dir = build_dir_path_and_rm_old("history_function").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    store.process_cypher_query("CREATE (n:Person {name: 'alice'}) RETURN id(n)").unwrap();
+    let res = store.process_cypher_query("MATCH (n:Person) RETURN history(n)").unwrap();
+    let versions = res.get_document("0").unwrap().get_document("history").unwrap().get_array("n").unwrap();
+    assert_eq!(versions.len(), 1);
+}
+#[test]
+fn delete_graph() {
+    let db_dir = build_dir_path_and_rm_old("delete_graph").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    store.process_cypher_query("CREATE (n:Person {name: 'alice'})-[r:FRIEND_OF]->(p:Person {name: 'bob'}) RETURN id(n)").unwrap();
+    assert!(matches!(store.process_cypher_query("MATCH (n:Person)-[r:FRIEND_OF]->(p:Person) DELETE n"), Err(GraphError::NodeHasRelationships(_))));
+    let res = store.process_cypher_query("MATCH (n:Person)-[r:FRIEND_OF]->(p:Person) DELETE r").unwrap();
+    assert_eq!(res.get_i64("relationships_deleted").unwrap(), 1);
+    let mres = store.process_cypher_query("MATCH (n:Person) RETURN n").unwrap();
+    assert_eq!(mres.len(), 2);
+    store.process_cypher_query("CREATE (n:Person {name: 'carol'})-[r:FRIEND_OF]->(p:Person {name: 'dave'}) RETURN id(n)").unwrap();
+    let res = store.process_cypher_query("MATCH (n:Person)-[r:FRIEND_OF]->(p:Person) DETACH DELETE n").unwrap();
+    assert_eq!(res.get_i64("nodes_deleted").unwrap(), 1);
+    let res = store.process_cypher_query("MATCH (n:Person) DELETE n").unwrap();
+    assert_eq!(res.get_i64("nodes_deleted").unwrap(), 3);
+    assert!(store.process_cypher_query("MATCH (n:Person) RETURN n").unwrap().is_empty());
+}
+#[test]
+fn match_relationship_type() {
+    let db_dir = build_dir_path_and_rm_old("match_relationship_type").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    store.process_cypher_query("CREATE (n:Person {name: 'alice'})-[r:KNOWS]->(p:Person {name: 'bob'}) RETURN id(n)").unwrap();
+    store.process_cypher_query("CREATE (n:Person {name: 'carol'})-[r:LIKES]->(p:Person {name: 'dave'}) RETURN id(n)").unwrap();
+    let mres = store.process_cypher_query("MATCH (n:Person)-[r:KNOWS]->(p:Person) RETURN r").unwrap();
+    assert_eq!(mres.len(), 1);
+    let labels = mres.get_document("0").unwrap().get_document("r").unwrap().get_array("labels").unwrap();
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].as_str(), Some("KNOWS"));
+}
+#[test]
+fn update_properties() {
+    let db_dir = build_dir_path_and_rm_old("update_properties").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    store.process_cypher_query("CREATE (n:Person {name: 'alice'})-[r:FRIEND_OF]->(p:Person {name: 'bob'}) RETURN id(n)").unwrap();
+    let res = store.process_cypher_query("MATCH (n:Person {name: 'alice'}) SET n.age = 42, n.name = 'alicia' RETURN n").unwrap();
+    let props = res.get_document("0").unwrap().get_document("n").unwrap().get_array("properties").unwrap();
+    assert_eq!(props.len(), 2);
+    let res = store.process_cypher_query("MATCH (n:Person {name: 'alicia'})-[r:FRIEND_OF]->(p:Person) SET r = {since: 2020} REMOVE n.age").unwrap();
+    assert_eq!(res.get_i64("properties_set").unwrap(), 2);
+    let mres = store.process_cypher_query("MATCH (n:Person {name: 'alicia'})-[r:FRIEND_OF]->(p:Person) RETURN n, r").unwrap();
+    let n = mres.get_document("0").unwrap().get_document("n").unwrap();
+    assert_eq!(n.get_array("properties").unwrap().len(), 1);
+    let r = mres.get_document("0").unwrap().get_document("r").unwrap();
+    assert_eq!(r.get_array("properties").unwrap()[0].as_document().unwrap().get_i64("since").unwrap(), 2020);
+}
+#[test]
+fn constraints() {
+    let db_dir = build_dir_path_and_rm_old("constraints").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    store.process_cypher_query("CREATE (n:Person {email: 'alice'}) RETURN id(n)").unwrap();
+    let res = store.process_cypher_query("CREATE CONSTRAINT FOR (n:Person) REQUIRE n.email IS UNIQUE").unwrap();
+    assert_eq!(res.get_i64("constraints_added").unwrap(), 1);
+    let res = store.process_cypher_query("CREATE CONSTRAINT FOR (n:Person) REQUIRE n.email IS UNIQUE").unwrap();
+    assert_eq!(res.get_i64("constraints_added").unwrap(), 0);
+    store.process_cypher_query("CREATE CONSTRAINT FOR ()-[r:FRIEND_OF]-() REQUIRE r.since IS NOT NULL").unwrap();
+    let res = store.process_cypher_query("SHOW CONSTRAINTS").unwrap();
+    assert_eq!(res.len(), 2);
+    let constraints: Vec<_> = res.values().map(|c| c.as_document().unwrap()).collect();
+    assert!(constraints.iter().any(|c| c.get_str("kind").unwrap() == "unique" && c.get_str("property").unwrap() == "email"));
+    assert!(constraints.iter().any(|c| c.get_str("entity").unwrap() == "relationship" && c.get_str("label").unwrap() == "FRIEND_OF"));
+
+    assert!(matches!(store.process_cypher_query("CREATE (n:Person {email: 'alice'}) RETURN id(n)"), Err(GraphError::Constraint(_))));
+    assert!(matches!(store.process_cypher_query("CREATE (n:Person {email: 'bob'})-[r:FRIEND_OF]->(p:Person {email: 'carol'}) RETURN id(n)"), Err(GraphError::Constraint(_))));
+    store.process_cypher_query("CREATE (n:Person {email: 'bob'}) RETURN id(n)").unwrap();
+    store.process_cypher_query("CREATE (n:Person {email: 'carol'}) RETURN id(n)").unwrap();
+    assert!(matches!(store.process_cypher_query("MATCH (n:Person {email: 'bob'}) SET n.email = 'alice' RETURN n"), Err(GraphError::Constraint(_))));
+    assert_eq!(store.process_cypher_query("MATCH (n:Person) RETURN n").unwrap().len(), 3);
+}
+#[test]
+fn match_unlabeled_nodes() {
+    let db_dir = build_dir_path_and_rm_old("match_unlabeled_nodes").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    store.process_cypher_query("CREATE (n:Person {name: 'alice'})-[r:LIVES_IN]->(p {name: 'paris'}) RETURN id(n)").unwrap();
+    assert_eq!(store.process_cypher_query("MATCH (n) RETURN n").unwrap().len(), 2);
+    let res = store.process_cypher_query("MATCH (n:Person)-[r:LIVES_IN]->(p) RETURN p").unwrap();
+    assert_eq!(res.len(), 1);
+    assert!(res.get_document("0").unwrap().get_document("p").unwrap().get_array("labels").unwrap().is_empty());
+}
+#[test]
+fn where_clause() {
+    let db_dir = build_dir_path_and_rm_old("where_clause").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    for age in &[20, 35, 50] {
+        store.process_cypher_query(&format!("CREATE (n:Person {{age: {}}}) RETURN id(n)", age)).unwrap();
+    }
+    assert_eq!(store.process_cypher_query("MATCH (n:Person) WHERE n.age > 30 RETURN n").unwrap().len(), 2);
+    assert_eq!(store.process_cypher_query("MATCH (n:Person) WHERE n.age >= 35 AND n.age < 50 RETURN n").unwrap().len(), 1);
+    assert_eq!(store.process_cypher_query("MATCH (n:Person) WHERE n.age = 20 RETURN n").unwrap().len(), 1);
+}
+#[test]
+fn invalid_requests() {
+    let db_dir = build_dir_path_and_rm_old("invalid_requests").unwrap();
+    let mut store = GraphStore::new(&db_dir);
+    assert!(matches!(store.process_cypher_query("MATCH (n:Person RETURN n"), Err(GraphError::InvalidRequest(_))));
+    store.process_cypher_query("CREATE (n:Person {name: 'alice'}) RETURN id(n)").unwrap();
+    assert!(matches!(store.process_cypher_query("MATCH (n:Person) RETURN m"), Err(GraphError::InvalidRequest(_))));
+    assert!(store.process_cypher_query("CREATE (n:Person {name: 'bob'})").unwrap().is_empty());
+}
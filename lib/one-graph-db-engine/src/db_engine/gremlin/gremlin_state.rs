@@ -72,8 +72,23 @@ impl State for EndState {
     }
 }
 
+pub struct HistoryRequest {
+    pub pattern_index: usize,
+    pub node_index: NodeIndex,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+pub struct PropertyUpdateRequest {
+    pub pattern_index: usize,
+    pub node_index: NodeIndex,
+    pub property: Property,
+}
+
 pub struct StateContext {
     pub patterns: Vec<PropertyGraph>,
+    pub history_requests: Vec<HistoryRequest>,
+    pub property_updates: Vec<PropertyUpdateRequest>,
     pub node_index: Option<NodeIndex>,
     pub relationship_index: Option<EdgeIndex>,
     pub previous_step: GStep,
@@ -83,7 +98,7 @@ pub struct StateContext {
 
 impl StateContext {
     pub fn new() -> Self {
-        StateContext{patterns: Vec::new(), node_index: None, relationship_index: None, previous_step: GStep::Empty,
+        StateContext{patterns: Vec::new(), history_requests: Vec::new(), property_updates: Vec::new(), node_index: None, relationship_index: None, previous_step: GStep::Empty,
              node_aliases: HashMap::new(), add_edge_label: None}
     }
 }
@@ -0,0 +1,64 @@
+use super::gremlin_state::{State, StateContext};
+use super::match_out_edge_state::MatchOutEdgeState;
+use super::super::utils::prop_value_from_gremlin_value;
+use one_graph_core::graph::traits::GraphContainerTrait;
+use one_graph_core::model::{set_in_list, Property, PropertyRange};
+use one_graph_gremlin::gremlin::*;
+use super::gremlin_state::*;
+
+/// Filters the matched vertex on a property, a comparison becomes a range the property indexes can serve.
+pub struct HasState {
+    name: String,
+    predicate: Predicate,
+}
+
+impl HasState {
+    pub fn new(name: &str, predicate: &Predicate) -> Self {
+        HasState{name: String::from(name), predicate: predicate.clone()}
+    }
+}
+
+impl State for HasState {
+    fn handle_step(&self, _step: &GStep, context: &mut StateContext) -> Result<(), StateError> {
+        match &context.previous_step {
+            GStep::V(_) | GStep::Has(_, _) => {
+                let node_index = context.node_index.ok_or(StateError::Invalid)?;
+                let pattern = context.patterns.last_mut().ok_or(StateError::Invalid)?;
+                let node = pattern.get_node_mut(&node_index);
+                let bound = |value: &GValue, inclusive: bool| Some((prop_value_from_gremlin_value(value), inclusive));
+                match &self.predicate {
+                    Predicate::Value(value) => {
+                        let mut prop = Property::new();
+                        prop.set_name(&self.name);
+                        prop.set_value(Some(prop_value_from_gremlin_value(value)));
+                        set_in_list(node.get_properties_mut(), &prop);
+                    },
+                    Predicate::Gt(value) => node.add_property_range(PropertyRange::new(&self.name, bound(value, false), None)),
+                    Predicate::Gte(value) => node.add_property_range(PropertyRange::new(&self.name, bound(value, true), None)),
+                    Predicate::Lt(value) => node.add_property_range(PropertyRange::new(&self.name, None, bound(value, false))),
+                    Predicate::Lte(value) => node.add_property_range(PropertyRange::new(&self.name, None, bound(value, true))),
+                    Predicate::Within(_) => return Err(StateError::Invalid),
+                }
+                Ok(())
+            },
+            _ => Err(StateError::Invalid),
+        }
+    }
+
+    fn create_state(&self, step: &GStep, _context: &mut StateContext) -> Result<Box<dyn State>, StateError> {
+        match step {
+            GStep::Has(name, predicate) => {
+                Ok(Box::new(HasState::new(name, predicate)))
+            }
+            GStep::OutE(labels) => {
+                Ok(Box::new(MatchOutEdgeState::new(labels)))
+            }
+            GStep::Empty => {
+                Ok(Box::new(EndState::new()))
+            }
+            _ => {
+                Err(StateError::Invalid)
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use super::gremlin_state::{State, StateContext};
+use one_graph_gremlin::gremlin::*;
+use super::gremlin_state::*;
+use std::convert::TryFrom;
+
+pub struct HistoryState {
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+impl HistoryState {
+    pub fn new(from: &Option<GValue>, to: &Option<GValue>) -> Self {
+        let from = from.as_ref().and_then(|v| u64::try_from(v.clone()).ok());
+        let to = to.as_ref().and_then(|v| u64::try_from(v.clone()).ok());
+        HistoryState{from, to}
+    }
+}
+
+impl State for HistoryState {
+    fn handle_step(&self, _step: &GStep, context: &mut StateContext) -> Result<(), StateError> {
+        let node_index = context.node_index.ok_or(StateError::Invalid)?;
+        if context.patterns.is_empty() {
+            return Err(StateError::Invalid);
+        }
+        let pattern_index = context.patterns.len() - 1;
+        context.history_requests.push(HistoryRequest{pattern_index, node_index, from: self.from, to: self.to});
+        Ok(())
+    }
+
+    fn create_state(&self, step: &GStep, _context: &mut StateContext) -> Result<Box<dyn State>, StateError> {
+        match step {
+            GStep::Empty => {
+                Ok(Box::new(EndState::new()))
+            }
+            _ => {
+                Err(StateError::Invalid)
+            }
+        }
+    }
+}
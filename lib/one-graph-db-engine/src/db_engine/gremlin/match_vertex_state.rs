@@ -6,6 +6,9 @@ use super::gremlin_state::*;
 use super::match_out_edge_state::MatchOutEdgeState;
 use super::match_state::MatchState;
 use super::add_edge_state::AddEdgeState;
+use super::history_state::HistoryState;
+use super::set_property_state::SetPropertyState;
+use super::has_state::HasState;
 use std::convert::TryFrom;
 use super::super::utils::*;
 
@@ -82,6 +85,15 @@ impl State for MatchVertexState {
             GStep::AddE(label) => {
                 Ok(Box::new(AddEdgeState::new(label)))
             }
+            GStep::History(from, to) => {
+                Ok(Box::new(HistoryState::new(from, to)))
+            }
+            GStep::SetProperty(name, value) => {
+                Ok(Box::new(SetPropertyState::new(name, value)))
+            }
+            GStep::Has(name, predicate) => {
+                Ok(Box::new(HasState::new(name, predicate)))
+            }
             _ => {
                 Err(StateError::Invalid)
             }
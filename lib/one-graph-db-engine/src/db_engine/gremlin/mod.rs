@@ -6,4 +6,6 @@ pub mod match_state;
 pub mod from_state;
 pub mod add_vertex_state;
 pub mod set_property_state;
+pub mod history_state;
+pub mod has_state;
 pub mod gremlin_state;
\ No newline at end of file
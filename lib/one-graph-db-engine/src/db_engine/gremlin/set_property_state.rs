@@ -29,6 +29,17 @@ impl State for SetPropertyState {
                 }
                 
             },
+            GStep::V(_vid) => {
+                let node_index = context.node_index.ok_or(StateError::Invalid)?;
+                if context.patterns.is_empty() {
+                    return Err(StateError::Invalid);
+                }
+                let pattern_index = context.patterns.len() - 1;
+                let mut property = Property::new();
+                property.set_name(&self.name);
+                property.set_value(Some(prop_value_from_gremlin_value(&self.value)));
+                context.property_updates.push(PropertyUpdateRequest{pattern_index, node_index, property});
+            },
             GStep::From(_alias) => {
                 let pattern = context.patterns.last_mut().ok_or(StateError::Invalid)?;
                 if let Some(rid) = &context.relationship_index {
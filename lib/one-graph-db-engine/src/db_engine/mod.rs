@@ -1,20 +1,25 @@
-use one_graph_core::model::PropertyGraph;
+use one_graph_core::model::{set_in_list, PropertyGraph};
+use one_graph_core::graph::traits::GraphContainerTrait;
 use one_graph_gremlin::gremlin::*;
 use one_graph_core::graph_engine::GraphEngine;
 use one_graph_core::model::init::InitContext;
+use one_graph_core::error::*;
 
 use self::gremlin::gremlin_state::*;
 use self::utils::ResultGraph;
 use self::utils::convert_graph_to_gremlin_response;
 use self::utils::get_request_scenario;
 use self::utils::Scenario;
+pub use self::utils::build_error_response;
 
 mod gremlin;
 mod utils;
 
 
-pub struct GraphDatabaseEngine<'a> {
-    conf: InitContext<'a>,
+/// Serves the requests with a single graph engine opened for the lifetime of the database engine,
+/// its write ahead log is only recovered when it is opened.
+pub struct GraphDatabaseEngine {
+    graph_engine: GraphEngine,
 }
 
 fn iterate_gremlin_steps(steps: &Vec<GStep>, mut gremlin_state: GremlinStateMachine) -> Option<GremlinStateMachine> {
@@ -36,39 +41,170 @@ fn iterate_gremlin_steps(steps: &Vec<GStep>, mut gremlin_state: GremlinStateMach
     Some(gremlin_state)
 }
 
-impl <'a> GraphDatabaseEngine<'a> {
-    pub fn new(ctx: InitContext<'a>) -> Self {
-        GraphDatabaseEngine{conf: ctx}
+fn process_patterns(graph_engine: &mut GraphEngine, patterns: Vec<PropertyGraph>) -> GraphResult<Vec<ResultGraph>> {
+    let mut matched_graphs = Vec::new();
+    for pattern in patterns {
+        let result_graphs = match get_request_scenario(&pattern) {
+            Scenario::CreateOnly => {
+                let created = graph_engine.create_graph(&pattern)?;
+                ResultGraph{ scenario: Scenario::CreateOnly, patterns: vec![created], history: None }
+            }
+            Scenario::MatchAndCreate => {
+                let matched = graph_engine.match_pattern_and_create(&pattern)?;
+                ResultGraph{ scenario: Scenario::MatchAndCreate, patterns: matched, history: None }
+            }
+            Scenario::MatchOnly => {
+                let matched = graph_engine.match_pattern(&pattern, None)?;
+                ResultGraph{ scenario: Scenario::MatchOnly, patterns: matched, history: None }
+            }
+            Scenario::Unknown => {ResultGraph{ scenario: Scenario::Unknown, patterns: vec![], history: None }}
+        };
+        matched_graphs.push(result_graphs);
+    }
+    Ok(matched_graphs)
+}
+
+fn unknown_pattern() -> GraphError {
+    GraphError::InvalidRequest(String::from("the step refers to an unknown traversal"))
+}
+
+fn unmatched_vertex() -> GraphError {
+    GraphError::Inconsistent(String::from("a matched vertex has no id"))
+}
+
+fn process_history_requests(graph_engine: &mut GraphEngine, requests: &[HistoryRequest], matched_graphs: &mut [ResultGraph]) -> GraphResult<()> {
+    for request in requests {
+        let result_graph = matched_graphs.get_mut(request.pattern_index).ok_or_else(unknown_pattern)?;
+        let mut history = Vec::new();
+        for graph in &result_graph.patterns {
+            let node_id = graph.get_node_ref(&request.node_index).get_id().ok_or_else(unmatched_vertex)?;
+            history.append(&mut graph_engine.node_history(node_id, request.from, request.to)?);
+        }
+        result_graph.history = Some(history);
+    }
+    Ok(())
+}
+
+fn process_property_updates(graph_engine: &mut GraphEngine, requests: &[PropertyUpdateRequest], matched_graphs: &mut [ResultGraph]) -> GraphResult<()> {
+    for request in requests {
+        let result_graph = matched_graphs.get_mut(request.pattern_index).ok_or_else(unknown_pattern)?;
+        for graph in &mut result_graph.patterns {
+            let node = graph.get_node_mut(&request.node_index);
+            graph_engine.set_node_property(node.get_id().ok_or_else(unmatched_vertex)?, &request.property)?;
+            set_in_list(node.get_properties_mut(), &request.property);
+        }
+    }
+    Ok(())
+}
+
+impl GraphDatabaseEngine {
+    /// Opens the database, fails when its write ahead log cannot be recovered.
+    pub fn new(ctx: &InitContext) -> GraphResult<Self> {
+        Ok(GraphDatabaseEngine{graph_engine: GraphEngine::new(ctx)?})
     }
 
-    pub fn handle_gremlin_request(&mut self, gremlin: &GremlinRequest) -> Option<GremlinResponse> {
+    /// Runs the traversal in its own transaction, `build_error_response` turns a failure into the reply to the client.
+    pub fn handle_gremlin_request(&mut self, gremlin: &GremlinRequest) -> GraphResult<GremlinResponse> {
         let mut gremlin_state = GremlinStateMachine::new();
-        gremlin_state = iterate_gremlin_steps(&gremlin.steps, gremlin_state)?;
+        gremlin_state = iterate_gremlin_steps(&gremlin.steps, gremlin_state)
+            .ok_or_else(|| GraphError::InvalidRequest(String::from("unsupported traversal")))?;
         let ctx = gremlin_state.context;
-        let mut graph_engine = GraphEngine::new(&self.conf);
-        let mut matched_graphs = Vec::new();
-        for pattern in ctx.patterns {
-            let result_graphs = match get_request_scenario(&pattern) {
-                Scenario::CreateOnly => {
-                    let created = graph_engine.create_graph(&pattern)?;
-                    graph_engine.sync();
-                    ResultGraph{ scenario: Scenario::CreateOnly, patterns: vec![created] }
-                }
-                Scenario::MatchAndCreate => {
-                    let matched = graph_engine.match_pattern_and_create(&pattern)?;
-                    graph_engine.sync();
-                    ResultGraph{ scenario: Scenario::MatchAndCreate, patterns: matched }
-                }
-                Scenario::MatchOnly => {
-                    let matched = graph_engine.match_pattern(&pattern)?;
-                    graph_engine.sync();
-                    ResultGraph{ scenario: Scenario::MatchOnly, patterns: matched }
-                }
-                Scenario::Unknown => {ResultGraph{ scenario: Scenario::Unknown, patterns: vec![] }}
-            };
-            matched_graphs.push(result_graphs);
-        }
-        return convert_graph_to_gremlin_response(&matched_graphs, &gremlin.request_id);
+        let graph_engine = &mut self.graph_engine;
+        let tx = graph_engine.begin()?;
+        let history_requests = ctx.history_requests;
+        let property_updates = ctx.property_updates;
+        let matched_graphs = process_patterns(graph_engine, ctx.patterns).and_then(|mut graphs| {
+            process_property_updates(graph_engine, &property_updates, &mut graphs)?;
+            process_history_requests(graph_engine, &history_requests, &mut graphs)?;
+            Ok(graphs)
+        });
+        let matched_graphs = graph_engine.commit_or_rollback(tx, matched_graphs)?;
+        convert_graph_to_gremlin_response(&matched_graphs, &gremlin.request_id).ok_or_else(unmatched_vertex)
+    }
+
+    /// Flushes the stores and the index files, the server calls it once no request is handled anymore.
+    pub fn sync(&mut self) -> GraphResult<()> {
+        self.graph_engine.sync()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use one_graph_core::model::*;
+    use one_graph_core::test_utils::build_dir_path_and_rm_old;
+
+    fn build_name_property(name: &str) -> Property {
+        let mut prop = Property::new();
+        prop.set_name("name");
+        prop.set_value(Some(PropertyValue::PString(String::from(name))));
+        prop
+    }
+
+    fn history_request(node_id: u64, from: Option<u64>) -> GremlinRequest {
+        let id = GValue::Integer(GInteger::I64(GInt64(node_id as i64)));
+        let from = from.map(|version| GValue::Integer(GInteger::I64(GInt64(version as i64))));
+        GremlinRequest{request_id: String::from("1"), steps: vec![GStep::V(Some(GValueOrVertex::Value(id))), GStep::History(from, None)]}
     }
 
+    fn get_names(response: &GremlinResponse) -> Vec<GValue> {
+        response.result.data.values.iter().map(|traverser| match &traverser.value {
+            GItem::VertexVersion(vv) => vv.properties[0].1.clone(),
+            _ => panic!("expected a vertex version"),
+        }).collect()
+    }
+
+    #[test]
+    fn test_gremlin_history() {
+        let dir = build_dir_path_and_rm_old("test_gremlin_history").unwrap();
+        let ctx = InitContext::new(&dir);
+        let (node_id, v2) = {
+            let mut graph_engine = GraphEngine::new(&ctx).unwrap();
+            let mut node = Node::new();
+            node.get_properties_mut().push(build_name_property("alice"));
+            let node_id = graph_engine.create_node(&node).unwrap().get_id().unwrap();
+            graph_engine.set_node_property(node_id, &build_name_property("bob")).unwrap();
+            (node_id, graph_engine.get_last_version())
+        };
+
+        let mut db_engine = GraphDatabaseEngine::new(&ctx).unwrap();
+        let response = db_engine.handle_gremlin_request(&history_request(node_id, None)).unwrap();
+        assert_eq!(get_names(&response), vec![GValue::String(String::from("alice")), GValue::String(String::from("bob"))]);
+
+        let response = db_engine.handle_gremlin_request(&history_request(node_id, Some(v2))).unwrap();
+        assert_eq!(get_names(&response), vec![GValue::String(String::from("bob"))]);
+    }
+
+    #[test]
+    fn test_gremlin_has_range() {
+        let dir = build_dir_path_and_rm_old("test_gremlin_has_range").unwrap();
+        let ctx = InitContext::new(&dir);
+        {
+            let mut graph_engine = GraphEngine::new(&ctx).unwrap();
+            for age in &[20, 35, 50] {
+                let mut node = Node::new();
+                let mut prop = Property::new();
+                prop.set_name("age");
+                prop.set_value(Some(PropertyValue::PInteger(*age)));
+                node.get_properties_mut().push(prop);
+                graph_engine.create_node(&node).unwrap();
+            }
+            graph_engine.sync().unwrap();
+        }
+        let age = |value: i64| GValue::Integer(GInteger::I64(GInt64(value)));
+        let has_request = |predicates: Vec<Predicate>| {
+            let mut steps = vec![GStep::V(None)];
+            steps.extend(predicates.into_iter().map(|predicate| GStep::Has(String::from("age"), predicate)));
+            GremlinRequest{request_id: String::from("1"), steps}
+        };
+        let mut db_engine = GraphDatabaseEngine::new(&ctx).unwrap();
+        let response = db_engine.handle_gremlin_request(&has_request(vec![Predicate::Gt(age(30))])).unwrap();
+        assert_eq!(response.result.data.values.len(), 2);
+        let response = db_engine.handle_gremlin_request(&has_request(vec![Predicate::Gte(age(35)), Predicate::Lt(age(50))])).unwrap();
+        assert_eq!(response.result.data.values.len(), 1);
+        let response = db_engine.handle_gremlin_request(&has_request(vec![Predicate::Value(age(20))])).unwrap();
+        assert_eq!(response.result.data.values.len(), 1);
+        let within = Predicate::Within(GList{values: vec![age(20)]});
+        assert!(matches!(db_engine.handle_gremlin_request(&has_request(vec![within])), Err(GraphError::InvalidRequest(_))));
+    }
 }
@@ -1,4 +1,5 @@
-use one_graph_core::{graph::{EdgeIndex, NodeIndex, traits::{GraphContainerTrait, GraphTrait}}, model::{Node, PropertyGraph, PropertyValue, Status}};
+use one_graph_core::{graph::{EdgeIndex, NodeIndex, traits::{GraphContainerTrait, GraphTrait}}, model::{Node, NodeVersion, PropertyGraph, PropertyValue, Status}};
+use one_graph_core::error::GraphError;
 
 use super::{gremlin::gremlin_state::StateContext};
 
@@ -36,6 +37,23 @@ pub fn prop_value_from_gremlin_value(gval: &GValue) -> PropertyValue {
     }
 }
 
+pub fn gremlin_value_from_prop_value(pval: &PropertyValue) -> GValue {
+    match pval {
+        PropertyValue::PString(sval) => {
+            GValue::String(sval.clone())
+        }
+        PropertyValue::PBool(bval) => {
+            GValue::Bool(*bval)
+        }
+        PropertyValue::PInteger(ival) => {
+            GValue::Integer(GInteger::I64(GInt64(*ival)))
+        }
+        PropertyValue::PFloat(fval) => {
+            GValue::Double(GDouble(*fval))
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Scenario {
     CreateOnly,
@@ -83,14 +101,37 @@ fn build_vertex_from_node(n: &Node) -> Option<GVertex> {
 }
 
 
+fn build_vertex_version(nv: &NodeVersion) -> Option<GVertexVersion> {
+    let mut properties = Vec::new();
+    for p in nv.node.get_properties_ref() {
+        if let (Some(name), Some(value)) = (p.get_name(), p.get_value()) {
+            properties.push((name.clone(), gremlin_value_from_prop_value(value)));
+        }
+    }
+    let mut out_e = GList::new();
+    out_e.values.extend(nv.outbound_relationships.iter().map(|id| GInt64(*id as i64)));
+    let mut in_e = GList::new();
+    in_e.values.extend(nv.inbound_relationships.iter().map(|id| GInt64(*id as i64)));
+    Some(GVertexVersion{id: GValue::Integer(GInteger::I64(GInt64(nv.node.get_id()? as i64))),
+        label: nv.node.get_labels_ref().join(":"), version: GInt64(nv.version as i64), properties, out_e, in_e})
+}
+
 pub struct ResultGraph {
     pub scenario: Scenario,
     pub patterns: Vec<PropertyGraph>,
+    pub history: Option<Vec<NodeVersion>>,
 }
 
 pub fn convert_graph_to_gremlin_response(graphs: &Vec<ResultGraph>, request_id: &str) -> Option<GremlinResponse> {
     let mut res = GResult::new();
     for result_graph in graphs {
+        if let Some(history) = &result_graph.history {
+            for nv in history {
+                let traverser = GTraverser{bulk: GInt64(1), value: GItem::VertexVersion(build_vertex_version(nv)?)};
+                res.data.values.push(traverser);
+            }
+            continue;
+        }
         let graphs = &result_graph.patterns;
         for graph in graphs {
             for n in graph.get_nodes() {
@@ -125,4 +166,49 @@ pub fn convert_graph_to_gremlin_response(graphs: &Vec<ResultGraph>, request_id:
     
     let attrs = GMap::new();
     Some(GremlinResponse{request_id: String::from(request_id), status: GStatus{message: String::from(""), code: 200, attributes: attrs}, result: res})
-}
\ No newline at end of file
+}
+/// Gremlin server status codes: 499 for a request the engine cannot run, 597 when
+/// the traversal fails on the data and 500 when the database itself fails.
+fn gremlin_status_code(error: &GraphError) -> i32 {
+    match error {
+        GraphError::InvalidRequest(_) => 499,
+        GraphError::NodeNotFound(_) | GraphError::RelationshipNotFound(_) |
+        GraphError::NodeHasRelationships(_) | GraphError::Constraint(_) => 597,
+        GraphError::Io(_) | GraphError::Corrupted(_, _) | GraphError::Incompatible(_, _) | GraphError::Inconsistent(_) | GraphError::Transaction(_) => 500,
+    }
+}
+
+pub fn build_error_response(error: &GraphError, request_id: &str) -> GremlinResponse {
+    GremlinResponse{request_id: String::from(request_id),
+        status: GStatus{message: error.to_string(), code: gremlin_status_code(error), attributes: GMap::new()},
+        result: GResult::new()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_request_status() {
+        let error = GraphError::InvalidRequest(String::from("unsupported step"));
+        let response = build_error_response(&error, "1");
+        assert_eq!(response.status.code, 499);
+        assert_eq!(response.status.message, error.to_string());
+        assert_eq!(response.request_id, "1");
+        assert!(response.result.data.values.is_empty());
+    }
+
+    #[test]
+    fn test_traversal_failure_status() {
+        assert_eq!(build_error_response(&GraphError::NodeNotFound(1), "1").status.code, 597);
+        assert_eq!(gremlin_status_code(&GraphError::RelationshipNotFound(2)), 597);
+        assert_eq!(gremlin_status_code(&GraphError::NodeHasRelationships(1)), 597);
+    }
+
+    #[test]
+    fn test_database_failure_status() {
+        assert_eq!(build_error_response(&GraphError::Io(String::from("disk full")), "1").status.code, 500);
+        assert_eq!(gremlin_status_code(&GraphError::Corrupted(String::from("nodes.db"), 1)), 500);
+        assert_eq!(gremlin_status_code(&GraphError::Inconsistent(String::from("dangling relationship"))), 500);
+    }
+}
@@ -19,6 +19,7 @@ pub enum GStep {
     Match(Vec<Vec<GStep>>),
     SetProperty(String, GValue),
     SetDynProperty(String, Vec<GStep>),
+    History(Option<GValue>, Option<GValue>),
     Empty,
 }
 
@@ -118,7 +119,7 @@ impl ToJson for GDouble {
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct GInt64(pub i64);
 
-impl GInt64 {
+impl ToJson for GInt64 {
     fn to_json(&self) -> serde_json::Value {
         json!({
             "@type": "g:Int64",
@@ -192,6 +193,10 @@ impl ToJson for GEdge {
 pub enum Predicate {
     Value(GValue),
     Within(GList<GValue>),
+    Gt(GValue),
+    Gte(GValue),
+    Lt(GValue),
+    Lte(GValue),
 }
 
 pub struct GremlinRequest {
@@ -254,9 +259,43 @@ pub struct GVertex {
     pub label: String,
 }
 
+pub struct GVertexVersion {
+    pub id: GValue,
+    pub label: String,
+    pub version: GInt64,
+    pub properties: Vec<(String, GValue)>,
+    pub out_e: GList<GInt64>,
+    pub in_e: GList<GInt64>,
+}
+
+impl ToJson for GVertexVersion {
+    fn to_json(&self) -> serde_json::Value {
+        let mut properties = Vec::new();
+        for (name, value) in &self.properties {
+            properties.push(json!(name));
+            properties.push(value.to_json());
+        }
+        json!({
+            "@type": "g:Map",
+            "@value": [
+                "id", self.id.to_json(),
+                "label", self.label,
+                "version", self.version.to_json(),
+                "properties", {
+                    "@type": "g:Map",
+                    "@value": properties
+                },
+                "outE", self.out_e.to_json(),
+                "inE", self.in_e.to_json(),
+            ]
+        })
+    }
+}
+
 pub enum GItem {
     Vertex(GVertex),
     Edge(GEdge),
+    VertexVersion(GVertexVersion),
 }
 
 
@@ -288,6 +327,9 @@ impl ToJson for GItem {
             GItem::Edge(e) => {
                 e.to_json()
             }
+            GItem::VertexVersion(v) => {
+                v.to_json()
+            }
         }
     }
 }
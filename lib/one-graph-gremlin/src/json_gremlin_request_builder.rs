@@ -71,6 +71,9 @@ fn build_gremlin_step(step: &Value) -> Option<GStep> {
       "property" => {
           set_property_step(elts)?
       }
+      "history" => {
+          history_step(elts)?
+      }
       _ => {
           GStep::Empty
       }
@@ -88,6 +91,18 @@ fn set_property_step(json_step: &Vec<Value>) -> Option<GStep> {
   }  
 }
 
+fn history_step(json_step: &[Value]) -> Option<GStep> {
+  let from = match json_step.get(1) {
+    Some(value) => Some(build_gremlin_value(value)?),
+    None => None,
+  };
+  let to = match json_step.get(2) {
+    Some(value) => Some(build_gremlin_value(value)?),
+    None => None,
+  };
+  Some(GStep::History(from, to))
+}
+
 fn match_step(json_step: &Vec<Value>) -> Option<GStep> {
   let mut bytecodes = Vec::new();
   for bc in &json_step[1..] {
@@ -128,7 +143,7 @@ fn build_value_or_vertex(elt: &Value) -> Option<GValueOrVertex> {
         let vertex = build_vertex(obj_map)?;
         Some(GValueOrVertex::Vertex(vertex))
       } else {
-        None
+        Some(GValueOrVertex::Value(build_gremlin_value(elt)?))
       }
     }
     _ => {
@@ -180,6 +195,11 @@ fn build_predicate(json_predicate: &Value) -> Option<Predicate> {
                 "within" => {
                     return build_within_predicate(p)
                 },
+                "eq" => Some(Predicate::Value(build_gremlin_value(p.get("value")?)?)),
+                "gt" => Some(Predicate::Gt(build_gremlin_value(p.get("value")?)?)),
+                "gte" => Some(Predicate::Gte(build_gremlin_value(p.get("value")?)?)),
+                "lt" => Some(Predicate::Lt(build_gremlin_value(p.get("value")?)?)),
+                "lte" => Some(Predicate::Lte(build_gremlin_value(p.get("value")?)?)),
                 _ => return None
             }
         },
@@ -320,6 +340,26 @@ mod test_gremlin_json {
         }
     }
 
+    #[test]
+    fn test_build_comparison_predicate() {
+        let json = r#"
+        {
+            "@type": "g:P",
+            "@value": {
+              "predicate": "gte",
+              "value": {
+                "@type": "g:Int32",
+                "@value": 30
+              }
+            }
+          }
+        "#;
+        let value: Value = serde_json::from_str(json).expect("json predicate");
+        assert_eq!(build_predicate(&value), Some(Predicate::Gte(GValue::Integer(GInteger::I32(GInt32(30))))));
+        let value: Value = serde_json::from_str(r#"{"@type": "g:P", "@value": {"predicate": "between", "value": "a"}}"#).expect("json predicate");
+        assert!(build_predicate(&value).is_none());
+    }
+
     #[test]
     fn test_build_has_str_predicate() {
         let json = r#"
@@ -597,6 +637,31 @@ mod test_gremlin_json {
       assert_eq!("e9ec71b5-7c44-4d9e-b1c9-f1268d64e2d4", g.request_id);
     }
 
+    #[test]
+    fn test_history_step() {
+      let json = r#"{"requestId":"4f1c1a2e-6a3b-4e55-9d0e-2d0f8a1b7c11","op":"bytecode","processor":"traversal","args":{"@type":"g:Map","@value":["gremlin",{"@type":"g:Bytecode","@value":{"step":[["V",{"@type":"g:Int64","@value":1}],["history",{"@type":"g:Int64","@value":10},{"@type":"g:Int64","@value":20}]]}},"aliases",{"@type":"g:Map","@value":["g","g"]}]}}"#;
+      let value: Value = serde_json::from_str(json).expect("json gremlin request");
+      let g = build_gremlin_request_from_json(&value).expect("gremlin request");
+      match &g.steps[1] {
+        GStep::History(from, to) => {
+          assert_eq!(Some(GValue::Integer(GInteger::I64(GInt64(10)))), *from);
+          assert_eq!(Some(GValue::Integer(GInteger::I64(GInt64(20)))), *to);
+        },
+        _ => panic!("expected history step"),
+      }
+    }
+
+    #[test]
+    fn test_v_step_with_typed_id() {
+      let json = r#"{"requestId":"0b6f3c52-1d7e-4f0a-8a44-5c2b9e6d3f10","op":"bytecode","processor":"traversal","args":{"@type":"g:Map","@value":["gremlin",{"@type":"g:Bytecode","@value":{"step":[["V",{"@type":"g:Int64","@value":1}]]}},"aliases",{"@type":"g:Map","@value":["g","g"]}]}}"#;
+      let value: Value = serde_json::from_str(json).expect("json gremlin request");
+      let g = build_gremlin_request_from_json(&value).expect("gremlin request");
+      match &g.steps[0] {
+        GStep::V(Some(GValueOrVertex::Value(id))) => assert_eq!(GValue::Integer(GInteger::I64(GInt64(1))), *id),
+        _ => panic!("expected a V step with an id"),
+      }
+    }
+
     #[test]
     fn test_add_vertex() {
       let json = r#"{"requestId":"b3a2c6a8-0982-4414-b07f-41ec49009861","op":"bytecode","processor":"traversal","args":{"@type":"g:Map","@value":["gremlin",{"@type":"g:Bytecode","@value":{"step":[["addV","person"],["property","name","marko"],["none"]]}},"aliases",{"@type":"g:Map","@value":["g","g"]}]}}"#;
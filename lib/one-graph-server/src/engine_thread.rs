@@ -0,0 +1,86 @@
+use one_graph_core::error::{GraphError, GraphResult};
+use one_graph_core::model::init::InitContext;
+use one_graph_db_engine::db_engine::GraphDatabaseEngine;
+use serde_json::Value;
+use std::thread;
+use tokio::sync::{mpsc, oneshot};
+use super::json_gremlin_request_handler::*;
+
+/// Requests waiting for the engine thread, the connections wait for their reply without blocking the runtime.
+const PENDING_REQUESTS: usize = 64;
+
+struct GremlinJob {
+    request: Value,
+    reply: oneshot::Sender<Value>,
+}
+
+/// Sends the requests of a connection to the engine thread.
+#[derive(Clone)]
+pub struct EngineHandle {
+    jobs: mpsc::Sender<GremlinJob>,
+}
+
+impl EngineHandle {
+    /// Reply to the JSON request, `None` when the engine thread stopped.
+    pub async fn handle_request(&self, request: Value) -> Option<Value> {
+        let (reply, receiver) = oneshot::channel();
+        self.jobs.send(GremlinJob{request, reply}).await.ok()?;
+        receiver.await.ok()
+    }
+}
+
+/// Opens the database on a thread of its own which then handles the requests one at a time,
+/// the graph engine is not `Send` so it can't be shared by the tasks of the connections.
+/// Once every handle is dropped the thread handles the requests left, flushes the stores and returns the result of the flush.
+pub async fn start_engine_thread(ctx: InitContext<'static>) -> GraphResult<(EngineHandle, thread::JoinHandle<GraphResult<()>>)> {
+    let (jobs, mut receiver) = mpsc::channel::<GremlinJob>(PENDING_REQUESTS);
+    let (opened_sender, opened) = oneshot::channel();
+    let engine_thread = thread::spawn(move || {
+        let mut graph_engine = match GraphDatabaseEngine::new(&ctx) {
+            Ok(graph_engine) => {
+                let _ = opened_sender.send(Ok(()));
+                graph_engine
+            },
+            Err(e) => {
+                let _ = opened_sender.send(Err(e.clone()));
+                return Err(e);
+            },
+        };
+        while let Some(job) = receiver.blocking_recv() {
+            let _ = job.reply.send(handle_gremlin_json_request(&mut graph_engine, &job.request));
+        }
+        graph_engine.sync()
+    });
+    match opened.await {
+        Ok(Ok(())) => Ok((EngineHandle{jobs}, engine_thread)),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(GraphError::Inconsistent(String::from("the engine thread panicked while opening the database"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use one_graph_core::test_utils::build_dir_path_and_rm_old;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_engine_thread_flushes_once_the_handles_are_dropped() {
+        let dir = build_dir_path_and_rm_old("test_engine_thread").unwrap();
+        let (graph_engine, engine_thread) = start_engine_thread(InitContext::new(&dir)).await.unwrap();
+        let connection = graph_engine.clone();
+        let reply = connection.handle_request(json!({"requestId": "1"})).await.unwrap();
+        assert_eq!(reply["requestId"], "1");
+        assert_eq!(reply["status"]["code"], 499);
+        drop(graph_engine);
+        drop(connection);
+        assert!(tokio::task::spawn_blocking(move || engine_thread.join()).await.unwrap().unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_engine_thread_reports_the_open_failure() {
+        let dir = build_dir_path_and_rm_old("test_engine_thread_open_failure").unwrap();
+        let res = start_engine_thread(InitContext::new(&format!("{}/missing", dir))).await;
+        assert!(matches!(res, Err(GraphError::Io(_))));
+    }
+}
@@ -1,12 +1,18 @@
 use one_graph_gremlin::json_gremlin_request_builder::*;
 use one_graph_gremlin::gremlin::*;
-use one_graph_db_engine::db_engine::GraphDatabaseEngine;
-use std::sync::RwLock;
-use std::sync::Arc;
+use one_graph_core::error::GraphError;
+use one_graph_db_engine::db_engine::{GraphDatabaseEngine, build_error_response};
 use serde_json::Value;
 
-pub fn handle_gremlin_json_request<'a>(graph_engine: Arc<RwLock<GraphDatabaseEngine<'a>>>, value: &Value) -> Option<Value> {
-    let gremlin_request = build_gremlin_request_from_json(value)?;
-    let res = graph_engine.write().unwrap().handle_gremlin_request(&gremlin_request)?;
-    Some(res.to_json())
-}
\ No newline at end of file
+/// Always answers, a failing request gets an error status instead of closing the connection.
+pub fn handle_gremlin_json_request(graph_engine: &mut GraphDatabaseEngine, value: &Value) -> Value {
+    let res = match build_gremlin_request_from_json(value) {
+        Some(gremlin_request) => graph_engine.handle_gremlin_request(&gremlin_request)
+            .unwrap_or_else(|e| build_error_response(&e, &gremlin_request.request_id)),
+        None => {
+            let request_id = value["requestId"].as_str().unwrap_or("");
+            build_error_response(&GraphError::InvalidRequest(String::from("cannot parse the gremlin request")), request_id)
+        }
+    };
+    res.to_json()
+}
@@ -12,26 +12,32 @@ extern crate serde_json;
 use futures_util::{
     SinkExt, StreamExt,
 };
+use futures_util::stream::FuturesUnordered;
 use tungstenite::Message;
-use std::sync::RwLock;
-use std::sync::Arc;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::protocol::frame::coding::CloseCode;
 use log::*;
 use std::net::SocketAddr;
 use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tokio::task::{spawn_blocking, JoinHandle};
+use tokio::time::timeout;
 use tokio_tungstenite::{accept_async, tungstenite::Error};
 use simple_logger::SimpleLogger;
 use serde_json::Value;
 use std::result::Result;
-use self::json_gremlin_request_handler::*;
+use self::engine_thread::*;
 mod result;
 mod json_gremlin_request_handler;
+mod engine_thread;
 
 use self::result::ServerError;
-use one_graph_core::model::init::InitContext;
-use one_graph_db_engine::db_engine::GraphDatabaseEngine;
+use one_graph_core::model::db_config::DbConfig;
 
-async fn accept_connection<'a>(peer: SocketAddr, graph_engine: Arc<RwLock<GraphDatabaseEngine<'a>>>, stream: TcpStream) {
-    if let Err(e) = handle_connection(peer, graph_engine, stream).await {
+async fn accept_connection(peer: SocketAddr, graph_engine: EngineHandle, stream: TcpStream, shutdown: watch::Receiver<bool>) {
+    if let Err(e) = handle_connection(peer, graph_engine, stream, shutdown).await {
         match e {
             ServerError::WebsocketError(te) => match te {
                 Error::ConnectionClosed | Error::Protocol(_) | Error::Utf8 => (),
@@ -39,31 +45,46 @@ async fn accept_connection<'a>(peer: SocketAddr, graph_engine: Arc<RwLock<GraphD
             },
             ServerError::ParsingError(err_msg) => error!("Parsing error: {}", err_msg),
             ServerError::HeaderError => error!("wrong header"),
-            ServerError::GremlinError => error!("parsing gremlin request"),
+            ServerError::EngineStopped => error!("the engine thread stopped"),
         }
         
     }
 }
 
+/// Sends the close frame of a server going away and reads until the client acknowledges it.
+async fn close_session<S, R>(ws_sender: &mut S, ws_receiver: &mut R) -> Result<(), ServerError>
+    where S: SinkExt<Message, Error = Error> + Unpin, R: StreamExt<Item = Result<Message, Error>> + Unpin {
+    let close_frame = CloseFrame{code: CloseCode::Away, reason: "the server is shutting down".into()};
+    ws_sender.send(Message::Close(Some(close_frame))).await.map_err(ServerError::WebsocketError)?;
+    while let Some(msg) = ws_receiver.next().await {
+        msg.map_err(ServerError::WebsocketError)?;
+    }
+    Ok(())
+}
 
-async fn handle_connection<'a>(peer: SocketAddr, graph_engine: Arc<RwLock<GraphDatabaseEngine<'a>>>, stream: TcpStream) -> Result<(), ServerError> {
+/// A request being handled is always answered, the session is closed when the server shuts down.
+async fn handle_connection(peer: SocketAddr, graph_engine: EngineHandle, stream: TcpStream, mut shutdown: watch::Receiver<bool>) -> Result<(), ServerError> {
     let ws_stream = accept_async(stream).await.expect("Failed to accept");
     info!("New WebSocket connection: {}", peer);
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
-    let mut msg_fut = ws_receiver.next();
     loop {
-        match msg_fut.await {
+        let next_msg = tokio::select! {
+            msg = ws_receiver.next() => msg,
+            _ = shutdown.changed() => {
+                info!("Closing WebSocket connection: {}", peer);
+                return close_session(&mut ws_sender, &mut ws_receiver).await;
+            }
+        };
+        match next_msg {
             Some(msg) => {
                 let msg = msg.map_err(ServerError::WebsocketError)?;
                 if msg.is_binary() {
                     let text_msg = msg.to_text().map_err(ServerError::WebsocketError)?;
                     let json_msg = text_msg.strip_prefix("!application/vnd.gremlin-v3.0+json").ok_or(ServerError::HeaderError)?;
                     let v: Value = serde_json::from_str(json_msg).map_err(|err| ServerError::ParsingError(err.to_string()))?;
-                    let gremlin_reply = handle_gremlin_json_request(graph_engine.clone(), &v).ok_or(ServerError::GremlinError)?;
+                    let gremlin_reply = graph_engine.handle_request(v).await.ok_or(ServerError::EngineStopped)?;
                     let res_msg = serde_json::to_string(&gremlin_reply).map_err(|err| ServerError::ParsingError(err.to_string()))?;
-                    let mut with_prefix = String::from("application/vnd.gremlin-v3.0+json");
-                    with_prefix.push_str(&res_msg);
                     debug!("response msg: {}", res_msg);
                     let response = Message::Text(res_msg);
                     ws_sender.send(response).await.map_err(ServerError::WebsocketError)?;
@@ -71,7 +92,6 @@ async fn handle_connection<'a>(peer: SocketAddr, graph_engine: Arc<RwLock<GraphD
                 else if msg.is_close() {
                     break;
                 }
-                msg_fut = ws_receiver.next(); // Receive next WebSocket message.
             }
             None => break, // WebSocket stream terminated.
         }
@@ -80,18 +100,75 @@ async fn handle_connection<'a>(peer: SocketAddr, graph_engine: Arc<RwLock<GraphD
     Ok(())
 }
 
+/// Completes on SIGINT or SIGTERM.
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res,
+        _ = terminate.recv() => Ok(()),
+    }
+}
 
+/// Completes on Ctrl-C.
+#[cfg(not(unix))]
+async fn shutdown_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
 
-pub async fn run_server(addr: &str, conf: InitContext<'static>) {
-    SimpleLogger::new().init().unwrap();
-    let graph_engine = Arc::new(RwLock::new(GraphDatabaseEngine::new(conf)));
-    let listener = TcpListener::bind(&addr).await.expect("Can't listen");
+/// Serves the database of a validated configuration, its data directory is created when missing.
+/// On SIGINT or SIGTERM the server stops accepting connections, closes the sessions once their request is answered,
+/// waits for them up to the shutdown timeout, aborts the ones left and flushes the stores before returning.
+/// Returns an error when the data directory can't be created, the database can't be opened, the address can't be listened
+/// or the final flush fails.
+pub async fn run_server(config: DbConfig) -> std::io::Result<()> {
+    SimpleLogger::new().with_level(config.get_log_level()).init().unwrap();
+    std::fs::create_dir_all(&config.data_dir)?;
+    let (graph_engine, engine_thread) = start_engine_thread(config.get_init_context()).await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let addr = config.listen_address.as_str();
+    let listener = TcpListener::bind(addr).await?;
     info!("Listening on: {}", addr);
 
-    while let Ok((stream, _)) = listener.accept().await {
-        let peer = stream.peer_addr().expect("connected streams should have a peer address");
-        info!("Peer address: {}", peer);
-        tokio::spawn(accept_connection(peer, graph_engine.clone(), stream));
+    let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+    let mut connections: FuturesUnordered<JoinHandle<()>> = FuturesUnordered::new();
+    let signal = shutdown_signal();
+    tokio::pin!(signal);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    info!("Peer address: {}", peer);
+                    connections.push(tokio::spawn(accept_connection(peer, graph_engine.clone(), stream, shutdown_receiver.clone())));
+                },
+                Err(e) => error!("Can't accept a connection: {}", e),
+            },
+            Some(_) = connections.next(), if !connections.is_empty() => {},
+            res = &mut signal => {
+                if let Err(e) = res {
+                    error!("Can't listen to the shutdown signals: {}", e);
+                }
+                break;
+            }
+        }
     }
-}
 
+    info!("Shutting down");
+    drop(listener);
+    let _ = shutdown_sender.send(true);
+    if timeout(config.get_shutdown_timeout(), async { while connections.next().await.is_some() {} }).await.is_err() {
+        warn!("Connections still open after {:?}, they are aborted", config.get_shutdown_timeout());
+        for connection in connections.iter() {
+            connection.abort();
+        }
+        while connections.next().await.is_some() {}
+    }
+    // The engine thread flushes the stores once the last handle is dropped and the requests left are answered.
+    drop(graph_engine);
+    let synced = spawn_blocking(move || engine_thread.join()).await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .map_err(|_| std::io::Error::other("the engine thread panicked"))?;
+    synced.map_err(|e| std::io::Error::other(e.to_string()))?;
+    info!("Stores flushed");
+    Ok(())
+}
@@ -1,7 +1,7 @@
 #[derive(Debug)]
 pub enum ServerError {
     HeaderError,
+    EngineStopped,
     ParsingError(String),
-    GremlinError,
     WebsocketError(tungstenite::Error),
 }
\ No newline at end of file
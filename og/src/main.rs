@@ -1,11 +1,353 @@
 extern crate one_graph_server;
 extern crate tokio;
-use one_graph_core::model::init::InitContext;
-use one_graph_core::test_utils::*;
+use one_graph_core::graph_engine::{check_database, export_database, import_database, migrate_database, GraphEngine};
+use one_graph_core::model::db_config::DbConfig;
+use one_graph_core::model::dump::GraphDump;
+use one_graph_cypher::store::GraphStore;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const USAGE: &str = "usage: og <command> [options]
+
+commands:
+  serve [--address <ip:port>]  serves the database over WebSocket
+  import <dump file>           imports a JSON dump in a single transaction
+  export [<dump file>]         writes a JSON dump, to the standard output without file
+  check [--repair]             checks the consistency of the stores
+  migrate [--target <dir>]     upgrades the store files, a copy in the target directory when one is given
+  stats                        prints the counts of nodes, relationships, labels and constraints
+  shell                        runs the Cypher queries read from the standard input, one per line
+
+options of all the commands:
+  --config <file>              TOML configuration file, OG_ environment variables override it
+  --data-dir <dir>             database directory, overrides the configuration
+
+exit codes: 0 on success, 1 when the command failed, 2 on invalid arguments or configuration";
+
+/// The command succeeded.
+const EXIT_SUCCESS: i32 = 0;
+/// The command ran and failed, or found problems it did not repair.
+const EXIT_FAILURE: i32 = 1;
+/// Invalid arguments or configuration, nothing was run.
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Default)]
+struct Options {
+    config: Option<String>,
+    data_dir: Option<String>,
+    address: Option<String>,
+    target: Option<String>,
+    repair: bool,
+    args: Vec<String>,
+}
+
+fn usage_error(msg: &str) -> i32 {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    EXIT_USAGE
+}
+
+/// Parses the options of the command, `--address`, `--target` and `--repair` only belong to their command.
+fn parse_options(command: &str, args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().ok_or_else(|| format!("missing value of {}", arg));
+        match arg.as_str() {
+            "--config" => options.config = Some(value()?),
+            "--data-dir" => options.data_dir = Some(value()?),
+            "--address" if command == "serve" => options.address = Some(value()?),
+            "--target" if command == "migrate" => options.target = Some(value()?),
+            "--repair" if command == "check" => options.repair = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {} of og {}", arg, command)),
+            _ => options.args.push(arg.clone()),
+        }
+    }
+    let max_args = match command {
+        "import" | "export" => 1,
+        _ => 0,
+    };
+    if options.args.len() > max_args {
+        return Err(format!("unexpected argument {} of og {}", options.args[max_args], command));
+    }
+    Ok(options)
+}
+
+/// Settings from the defaults, the configuration file, the environment and then the flags.
+fn load_config(options: &Options) -> Result<DbConfig, String> {
+    let mut config = match &options.config {
+        Some(file) => DbConfig::load(file).map_err(|e| e.to_string())?,
+        None => {
+            let mut config = DbConfig::default();
+            config.apply_overrides(std::env::vars()).map_err(|e| e.to_string())?;
+            config
+        }
+    };
+    if let Some(data_dir) = &options.data_dir {
+        config.data_dir = data_dir.clone();
+    }
+    if let Some(address) = &options.address {
+        config.listen_address = address.clone();
+    }
+    config.validate().map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+/// The commands that write create the database, the others need an existing one.
+fn prepare_data_dir(command: &str, config: &DbConfig) -> Result<(), String> {
+    match command {
+        "serve" => Ok(()),
+        "import" | "shell" => std::fs::create_dir_all(&config.data_dir).map_err(|e| format!("can't create {}: {}", config.data_dir, e)),
+        _ if Path::new(&config.data_dir).is_dir() => Ok(()),
+        _ => Err(format!("no database in {}", config.data_dir)),
+    }
+}
+
+fn check(config: &DbConfig, repair: bool) -> i32 {
+    let report = match check_database(&config.get_init_context(), repair) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("check failed: {}", e);
+            return EXIT_FAILURE;
+        },
+    };
+    for finding in &report.findings {
+        println!("{}", finding);
+    }
+    let unrepaired = report.get_unrepaired().len();
+    println!("{} problems found, {} left", report.findings.len(), unrepaired);
+    if unrepaired > 0 {EXIT_FAILURE} else {EXIT_SUCCESS}
+}
+
+fn migrate(config: &DbConfig, target_dir: Option<&str>) -> i32 {
+    match migrate_database(&config.get_init_context(), target_dir) {
+        Ok(report) => {
+            for file in &report.files {
+                println!("{}", file);
+            }
+            println!("{} files migrated", report.get_migrated().len());
+            EXIT_SUCCESS
+        },
+        Err(e) => {
+            eprintln!("migration failed: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn import(config: &DbConfig, file: &str) -> i32 {
+    let graph_dump = std::fs::read_to_string(file).map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str::<GraphDump>(&text).map_err(|e| e.to_string()));
+    let graph_dump = match graph_dump {
+        Ok(graph_dump) => graph_dump,
+        Err(e) => {
+            eprintln!("can't read {}: {}", file, e);
+            return EXIT_FAILURE;
+        }
+    };
+    match import_database(&config.get_init_context(), &graph_dump) {
+        Ok(()) => {
+            println!("{} nodes and {} relationships imported", graph_dump.nodes.len(), graph_dump.relationships.len());
+            EXIT_SUCCESS
+        },
+        Err(e) => {
+            eprintln!("import failed: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn export(config: &DbConfig, file: Option<&str>) -> i32 {
+    let text = export_database(&config.get_init_context()).map_err(|e| e.to_string())
+        .and_then(|graph_dump| serde_json::to_string_pretty(&graph_dump).map_err(|e| e.to_string()));
+    let written = text.and_then(|text| match file {
+        Some(file) => std::fs::write(file, text).map_err(|e| format!("{}: {}", file, e)),
+        None => writeln!(std::io::stdout(), "{}", text).map_err(|e| e.to_string()),
+    });
+    match written {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            eprintln!("export failed: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn get_dir_size(dir: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let md = entry?.metadata()?;
+        if md.is_file() {
+            size += md.len();
+        }
+    }
+    Ok(size)
+}
+
+fn stats(config: &DbConfig) -> i32 {
+    let stats = match GraphEngine::new(&config.get_init_context()).and_then(|mut engine| engine.get_stats()) {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("can't read the database: {}", e);
+            return EXIT_FAILURE;
+        }
+    };
+    println!("nodes: {}", stats.nodes);
+    for (label, count) in &stats.labels {
+        println!("  :{} {}", label, count);
+    }
+    println!("relationships: {}", stats.relationships);
+    for (rel_type, count) in &stats.relationship_types {
+        println!("  :{} {}", rel_type, count);
+    }
+    println!("constraints: {}", stats.constraints);
+    match get_dir_size(Path::new(&config.data_dir)) {
+        Ok(size) => println!("disk size: {} bytes", size),
+        Err(e) => println!("disk size: unknown, {}", e),
+    }
+    EXIT_SUCCESS
+}
+
+/// Reads the queries until the end of the input, exits with 1 when a query failed.
+fn shell(config: &DbConfig) -> i32 {
+    let mut store = GraphStore::with_context(config.get_init_context());
+    let mut exit_code = EXIT_SUCCESS;
+    let stdin = std::io::stdin();
+    print!("og> ");
+    let _ = std::io::stdout().flush();
+    for line in stdin.lock().lines() {
+        let query = match line {
+            Ok(query) => query,
+            Err(e) => {
+                eprintln!("can't read the input: {}", e);
+                return EXIT_FAILURE;
+            }
+        };
+        match query.trim() {
+            "" => {},
+            ":quit" | ":exit" => break,
+            query => match store.process_cypher_query(query) {
+                Ok(doc) => println!("{}", bson::Bson::Document(doc).into_relaxed_extjson()),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit_code = EXIT_FAILURE;
+                }
+            },
+        }
+        print!("og> ");
+        let _ = std::io::stdout().flush();
+    }
+    println!();
+    exit_code
+}
+
+async fn run(args: &[String]) -> i32 {
+    let command = match args.first() {
+        Some(command) if command == "--help" || command == "help" => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        },
+        Some(command) => command.as_str(),
+        None => return usage_error("missing command"),
+    };
+    if !["serve", "import", "export", "check", "migrate", "stats", "shell"].contains(&command) {
+        return usage_error(&format!("unknown command {}", command));
+    }
+    let options = match parse_options(command, &args[1..]) {
+        Ok(options) => options,
+        Err(e) => return usage_error(&e),
+    };
+    let config = match load_config(&options) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+    if let Err(e) = prepare_data_dir(command, &config) {
+        eprintln!("{}", e);
+        return EXIT_FAILURE;
+    }
+    match command {
+        "serve" => match one_graph_server::run_server(config).await {
+            Ok(()) => EXIT_SUCCESS,
+            Err(e) => {
+                eprintln!("server failed: {}", e);
+                EXIT_FAILURE
+            }
+        },
+        "import" => match options.args.first() {
+            Some(file) => import(&config, file),
+            None => usage_error("missing dump file of og import"),
+        },
+        "export" => export(&config, options.args.first().map(|file| file.as_str())),
+        "check" => check(&config, options.repair),
+        "migrate" => migrate(&config, options.target.as_deref()),
+        "stats" => stats(&config),
+        _ => shell(&config),
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    let main_dir = build_dir_path_and_rm_old("test_main").unwrap();
-    let conf = InitContext::new(&main_dir);
-    one_graph_server::run_server("127.0.0.1:8182", conf).await;
-}
\ No newline at end of file
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let exit_code = run(&args).await;
+    std::process::exit(exit_code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use one_graph_core::model::Node;
+    use one_graph_core::test_utils::build_dir_path_and_rm_old;
+
+    fn to_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse_options("serve", &to_args(&["--data-dir", "db", "--address", "0.0.0.0:8182"])).unwrap();
+        assert_eq!(options.data_dir.as_deref(), Some("db"));
+        assert_eq!(options.address.as_deref(), Some("0.0.0.0:8182"));
+        assert!(parse_options("check", &to_args(&["--repair"])).unwrap().repair);
+        assert_eq!(parse_options("migrate", &to_args(&["--target", "copy"])).unwrap().target.as_deref(), Some("copy"));
+        assert_eq!(parse_options("import", &to_args(&["dump.json"])).unwrap().args, to_args(&["dump.json"]));
+
+        assert_eq!(parse_options("stats", &to_args(&["--repair"])).err().unwrap(), "unknown option --repair of og stats");
+        assert_eq!(parse_options("check", &to_args(&["--config"])).err().unwrap(), "missing value of --config");
+        assert_eq!(parse_options("export", &to_args(&["a.json", "b.json"])).err().unwrap(), "unexpected argument b.json of og export");
+        assert!(parse_options("stats", &to_args(&["extra"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_usage_exit_codes() {
+        assert_eq!(run(&[]).await, EXIT_USAGE);
+        assert_eq!(run(&to_args(&["drop"])).await, EXIT_USAGE);
+        assert_eq!(run(&to_args(&["stats", "--target", "copy"])).await, EXIT_USAGE);
+        assert_eq!(run(&to_args(&["import"])).await, EXIT_USAGE);
+        assert_eq!(run(&to_args(&["stats", "--config", "missing_og_config.toml"])).await, EXIT_USAGE);
+        assert_eq!(run(&to_args(&["help"])).await, EXIT_SUCCESS);
+    }
+
+    #[tokio::test]
+    async fn test_command_exit_codes() {
+        let dir = build_dir_path_and_rm_old("test_og_command_exit_codes").unwrap();
+        let missing_dir = format!("{}/missing", dir);
+        assert_eq!(run(&to_args(&["stats", "--data-dir", &missing_dir])).await, EXIT_FAILURE);
+        let missing_dump = format!("{}/missing.json", dir);
+        assert_eq!(run(&to_args(&["import", &missing_dump, "--data-dir", &dir])).await, EXIT_FAILURE);
+
+        {
+            let mut engine = GraphEngine::new(&one_graph_core::model::init::InitContext::new(&dir)).unwrap();
+            engine.create_node(&Node::new()).unwrap();
+            engine.sync().unwrap();
+        }
+        assert_eq!(run(&to_args(&["stats", "--data-dir", &dir])).await, EXIT_SUCCESS);
+        assert_eq!(run(&to_args(&["check", "--data-dir", &dir])).await, EXIT_SUCCESS);
+        let dump = format!("{}/dump.json", dir);
+        assert_eq!(run(&to_args(&["export", &dump, "--data-dir", &dir])).await, EXIT_SUCCESS);
+        let copy_dir = format!("{}/copy", dir);
+        assert_eq!(run(&to_args(&["import", &dump, "--data-dir", &copy_dir])).await, EXIT_SUCCESS);
+        assert_eq!(run(&to_args(&["stats", "--data-dir", &copy_dir])).await, EXIT_SUCCESS);
+    }
+}